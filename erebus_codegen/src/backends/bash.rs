@@ -0,0 +1,1307 @@
+//! Bash backend.
+//!
+//! Bash has no expressions that produce values, so the program is lowered with
+//! [`LowerOptions::flatten_operands`]. Every operand is then a literal or a variable.
+//!
+//! Functions and helpers return their value by assigning it to `__ret`.
+//! Numbers are stored as strings. Operations on `int`s use `$(( ))`, everything else
+//! goes through `awk`, because `$(( ))` only supports integers.
+//!
+//! Arrays are bash arrays that are referred to by the name of their binding,
+//! so they can't be nested or passed to and returned from functions.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{
+    ir::{
+        BinOp, Builtin, Enum, Expr, Function, Global, Item, LowerOptions, Method, Program, Stmt,
+        Struct, Type, UnaryOp,
+    },
+    CodeWriter, Diagnostic, IrBackend, Output, OutputFile,
+};
+
+const RESERVED: &[&str] = &[
+    // keywords
+    "case",
+    "coproc",
+    "do",
+    "done",
+    "elif",
+    "else",
+    "esac",
+    "fi",
+    "for",
+    "function",
+    "if",
+    "in",
+    "select",
+    "then",
+    "time",
+    "until",
+    "while",
+    // commands used by the generated code
+    "awk",
+    "declare",
+    "false",
+    "local",
+    "printf",
+    "read",
+    "readonly",
+    "return",
+    "set",
+    "true",
+    // variables with a special meaning
+    "BASH_SOURCE",
+    "HOME",
+    "IFS",
+    "LINENO",
+    "OLDPWD",
+    "PATH",
+    "PPID",
+    "PWD",
+    "RANDOM",
+    "SECONDS",
+    "SHELL",
+    "UID",
+    // helpers
+    "__array",
+    "__at",
+    "__set",
+    "__bits",
+    "__calc",
+    "__int",
+    "__compare",
+    "__not",
+    "__ret",
+];
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Helper {
+    Bits,
+    Calc,
+    Int,
+    Compare,
+    Not,
+    At,
+    Set,
+}
+
+impl Helper {
+    fn dependencies(self) -> &'static [Helper] {
+        match self {
+            Self::Bits | Self::Calc | Self::Compare | Self::Not | Self::At => &[],
+            Self::Int => &[Self::Calc],
+            Self::Set => &[Self::At],
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            Self::Bits => {
+                r#"# Applies the bitwise operator `$2` to `$1` and `$3` converted to 32 bit integers.
+__bits() {
+    local a b
+    read -r a b <<< "$(awk -v a="$1" -v b="$3" 'function truncated(x) {
+        if (sprintf("%g", x) ~ /nan|inf/) return 0
+        return int(x) % 4294967296
+    }
+    BEGIN { printf "%.0f %.0f\n", truncated(a), truncated(b) }')"
+    a=$(( (a & 0xFFFFFFFF) - ((a & 0x80000000) << 1) ))
+    b=$(( (b & 0xFFFFFFFF) - ((b & 0x80000000) << 1) ))
+    case $2 in
+        '|') __ret=$(( a | b )) ;;
+        '^') __ret=$(( a ^ b )) ;;
+        '&') __ret=$(( a & b )) ;;
+        '<<')
+            __ret=$(( a << (b & 31) ))
+            __ret=$(( (__ret & 0xFFFFFFFF) - ((__ret & 0x80000000) << 1) ))
+            ;;
+        '>>') __ret=$(( a >> (b & 31) )) ;;
+    esac
+}"#
+            }
+            Self::Calc => {
+                r#"# Applies the operator `$2` to the floating point numbers `$1` and `$3`.
+# Numbers are written the way `print` writes them.
+__calc() {
+    __ret=$(awk -v a="$1" -v op="$2" -v b="$3" '
+    function zeros(n, s) {
+        for (s = ""; n > 0; n--) s = s "0"
+        return s
+    }
+    function str(x, s, precision, sign, digits, point) {
+        s = sprintf("%g", x)
+        if (s ~ /nan/) return "NaN"
+        if (s ~ /inf/) return s ~ /^-/ ? "-inf" : "inf"
+        if (x == 0) return "0"
+        for (precision = 0; precision < 17; precision++) {
+            s = sprintf("%." precision "e", x)
+            if (s + 0 == x) break
+        }
+        if (x < 0) sign = "-"
+        sub(/^-/, "", s)
+        # the position of the decimal point after the first digit
+        point = substr(s, index(s, "e") + 1) + 1
+        digits = substr(s, 1, index(s, "e") - 1)
+        sub(/\./, "", digits)
+        sub(/0+$/, "", digits)
+        if (length(digits) <= point && point <= 21) return sign digits zeros(point - length(digits))
+        if (0 < point && point <= 21) return sign substr(digits, 1, point) "." substr(digits, point + 1)
+        if (-6 < point && point <= 0) return sign "0." zeros(-point) digits
+        s = substr(digits, 1, 1) (length(digits) > 1 ? "." substr(digits, 2) : "")
+        return sign s (point > 1 ? "e+" : "e-") (point > 1 ? point - 1 : 1 - point)
+    }
+    BEGIN {
+        if (op == "+") print str(a + b)
+        else if (op == "-") print str(a - b)
+        else if (op == "*") print str(a * b)
+        else if (op == "/") {
+            if (b != 0) print str(a / b)
+            else if (a == 0 || str(a) == "NaN") print "NaN"
+            else print (a < 0) == (sprintf("%g", b) ~ /^-/) ? "inf" : "-inf"
+        }
+        else if (op == "%") print b == 0 ? "NaN" : str(a % b)
+        else if (op == "==") print (a == b ? "true" : "false")
+        else if (op == "!=") print (a != b ? "true" : "false")
+        else if (op == "<") print (a < b ? "true" : "false")
+        else if (op == "<=") print (a <= b ? "true" : "false")
+        else if (op == ">") print (a > b ? "true" : "false")
+        else if (op == ">=") print (a >= b ? "true" : "false")
+    }')
+}"#
+            }
+            Self::Int => {
+                r#"# Applies the operator `$2` to the integers `$1` and `$3` without starting `awk`,
+# unless the result could differ from the one of `__calc`.
+__int() {
+    local pattern='^(0|-?[1-9][0-9]{0,8})$'
+    if [[ $1 =~ $pattern && $3 =~ $pattern ]]; then
+        case $2 in
+            '+') __ret=$(( $1 + $3 )); return ;;
+            '-') __ret=$(( $1 - $3 )); return ;;
+            '*')
+                __ret=$(( $1 * $3 ))
+                (( __ret > -(1 << 53) && __ret < 1 << 53 )) && return
+                ;;
+            '/') (( $3 != 0 )) && (( $1 % $3 == 0 )) && __ret=$(( $1 / $3 )) && return ;;
+            '%') (( $3 != 0 )) && __ret=$(( $1 % $3 )) && return ;;
+            *)
+                (( $1 $2 $3 )) && __ret=true || __ret=false
+                return
+                ;;
+        esac
+    fi
+    __calc "$@"
+}"#
+            }
+            Self::Compare => {
+                r#"# Compares the strings `$1` and `$3` with the operator `$2`.
+__compare() {
+    local LC_ALL=C
+    case $2 in
+        '==') [[ $1 == "$3" ]] ;;
+        '!=') [[ $1 != "$3" ]] ;;
+        '<') [[ $1 < "$3" ]] ;;
+        '<=') [[ ! $1 > "$3" ]] ;;
+        '>') [[ $1 > "$3" ]] ;;
+        '>=') [[ ! $1 < "$3" ]] ;;
+    esac && __ret=true || __ret=false
+}"#
+            }
+            Self::Not => {
+                r#"__not() {
+    if [[ $1 == true ]]; then
+        __ret=false
+    else
+        __ret=true
+    fi
+}"#
+            }
+            Self::At => {
+                r#"# Reads the element `$2` of the array called `$1`.
+__at() {
+    local -n __array="$1"
+    if [[ ! $2 =~ ^[0-9]+$ ]] || (( $2 >= ${#__array[@]} )); then
+        printf 'index %s is out of bounds for length %s\n' "$2" "${#__array[@]}" >&2
+        exit 1
+    fi
+    __ret="${__array[$2]}"
+}"#
+            }
+            Self::Set => {
+                r#"# Changes the element `$2` of the array called `$1` to `$3`.
+__set() {
+    __at "$1" "$2"
+    local -n __array="$1"
+    __array[$2]="$3"
+}"#
+            }
+        }
+    }
+}
+
+pub struct BashBackend;
+
+impl IrBackend for BashBackend {
+    fn name(&self) -> &str {
+        "bash"
+    }
+
+    fn lower_options(&self) -> LowerOptions<'_> {
+        LowerOptions {
+            reserved: RESERVED,
+            flatten_operands: true,
+            dictionary_passing: true,
+            ..LowerOptions::default()
+        }
+    }
+
+    fn emit(&self, module: &str, program: &Program) -> Output {
+        let mut diagnostics: Vec<_> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Struct(r#struct) => Some(Diagnostic::warning(format!(
+                    "struct `{}` has no representation in bash",
+                    r#struct.name
+                ))),
+                _ => None,
+            })
+            .collect();
+
+        let (content, emit_diagnostics) = BashEmitter::new().emit(program);
+        diagnostics.extend(emit_diagnostics);
+
+        Output {
+            files: vec![OutputFile {
+                path: format!("{module}.sh").into(),
+                content,
+            }],
+            diagnostics,
+        }
+    }
+}
+
+/// How the value of an expression becomes available.
+enum Value {
+    /// The expression can be used as a word directly.
+    Word(String),
+    /// The command has to run first, the value is in `__ret` afterwards.
+    Command(String),
+    /// The command has to run first, it doesn't produce a value.
+    Unit(String),
+}
+
+struct BashEmitter {
+    writer: CodeWriter,
+    helpers: BTreeSet<Helper>,
+    /// The bindings that hold an array.
+    arrays: HashSet<String>,
+    /// The enums with a variant that has fields, by the variants that have them.
+    /// Values of them have no representation, a variant is stored as its name.
+    variants_with_fields: HashMap<String, HashSet<String>>,
+    /// The labels of the loops around the current statement.
+    loops: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl BashEmitter {
+    fn new() -> Self {
+        Self {
+            writer: CodeWriter::new("    "),
+            helpers: BTreeSet::new(),
+            arrays: HashSet::new(),
+            variants_with_fields: HashMap::new(),
+            loops: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn emit(mut self, program: &Program) -> (String, Vec<Diagnostic>) {
+        for item in &program.items {
+            match item {
+                Item::Struct(r#struct) => self.r#struct(r#struct),
+                Item::Enum(r#enum) => self.r#enum(r#enum),
+                Item::Fn(function) => self.function(function),
+                Item::Global(global) => self.global(global),
+            }
+        }
+
+        let has_main = program.items.iter().any(|item| match item {
+            Item::Fn(function) => function.name == "main" && function.params.is_empty(),
+            _ => false,
+        });
+        if has_main {
+            self.writer.blank_line();
+            // only run main if this script isn't sourced
+            self.writer
+                .line("if [[ \"${BASH_SOURCE[0]}\" == \"$0\" ]]; then");
+            self.writer.indent();
+            self.writer.line("main");
+            self.writer.dedent();
+            self.writer.line("fi");
+        }
+
+        let mut header = CodeWriter::new("    ");
+        header.line("#!/usr/bin/env bash");
+        header.line("set -eu");
+
+        let mut helpers = BTreeSet::new();
+        for helper in &self.helpers {
+            helpers.insert(*helper);
+            helpers.extend(helper.dependencies());
+        }
+        for helper in helpers {
+            header.blank_line();
+            for line in helper.source().lines() {
+                header.line(line);
+            }
+        }
+        header.blank_line();
+
+        (header.finish() + &self.writer.finish(), self.diagnostics)
+    }
+
+    fn r#struct(&mut self, r#struct: &Struct) {
+        let fields: Vec<_> = r#struct
+            .fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+
+        self.writer.blank_line();
+        self.writer.line(format!(
+            "# struct {} {{ {} }}",
+            r#struct.name,
+            fields.join(", ")
+        ));
+        self.methods(&r#struct.name, &r#struct.methods);
+    }
+
+    /// A variant is stored as its name.
+    fn r#enum(&mut self, r#enum: &Enum) {
+        let with_fields: HashSet<_> = r#enum
+            .variants
+            .iter()
+            .filter(|variant| !variant.fields.is_empty())
+            .map(|variant| variant.name.clone())
+            .collect();
+        if !with_fields.is_empty() {
+            self.variants_with_fields
+                .insert(r#enum.name.clone(), with_fields);
+        }
+
+        let variants: Vec<_> = r#enum
+            .variants
+            .iter()
+            .map(|variant| variant.name.as_str())
+            .collect();
+
+        self.writer.blank_line();
+        self.writer.line(format!(
+            "# enum {} {{ {} }}",
+            r#enum.name,
+            variants.join(", ")
+        ));
+        self.methods(&r#enum.name, &r#enum.methods);
+    }
+
+    /// Methods are functions called `Type.method`, the receiver is passed as the first argument.
+    fn methods(&mut self, r#type: &str, methods: &[Method]) {
+        for method in methods {
+            self.function_named(
+                &format!("{type}.{}", method.function.name),
+                &method.function,
+                method.has_receiver,
+            );
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.function_named(&function.name, function, false);
+    }
+
+    fn function_named(&mut self, name: &str, function: &Function, has_receiver: bool) {
+        let is_array = |r#type: &Type| matches!(r#type, Type::Array(_));
+        if function.params.iter().any(|param| is_array(&param.r#type))
+            || is_array(&function.return_type)
+        {
+            self.diagnostics.push(Diagnostic::error(format!(
+                "`{name}` takes or returns an array, which can't be passed around in bash"
+            )));
+        }
+
+        self.writer.blank_line();
+        self.writer.line(format!("{name}() {{"));
+        self.writer.indent();
+
+        let params: Vec<_> = has_receiver
+            .then_some("self")
+            .into_iter()
+            .chain(function.params.iter().map(|param| param.name.as_str()))
+            .enumerate()
+            .map(|(i, param)| format!("{param}=\"${}\"", i + 1))
+            .collect();
+        if !params.is_empty() {
+            self.writer.line(format!("local {}", params.join(" ")));
+        }
+        self.block(&function.body, true);
+
+        self.writer.dedent();
+        self.writer.line("}");
+    }
+
+    fn global(&mut self, global: &Global) {
+        self.writer.blank_line();
+        self.block(&global.setup, false);
+        self.assign(false, global.is_mut, &global.name, &global.value);
+    }
+
+    fn block(&mut self, statements: &[Stmt], is_local: bool) {
+        for statement in statements {
+            match statement {
+                Stmt::Let {
+                    is_mut,
+                    name,
+                    value,
+                } => self.assign(is_local, *is_mut, name, value),
+                Stmt::Assign { name, value } => {
+                    let word = self.assigned_word(name, value);
+                    self.writer.line(format!("{name}={word}"));
+                }
+                Stmt::AssignField { .. } => self.diagnostics.push(Diagnostic::error(
+                    "fields have no representation in bash, they can't be assigned to",
+                )),
+                Stmt::AssignIndex {
+                    target: Expr::Var(name),
+                    index,
+                    value,
+                } => {
+                    self.helpers.insert(Helper::Set);
+                    self.writer
+                        .line(format!("__set {name} {} {}", word(index), word(value)));
+                }
+                Stmt::AssignIndex { .. } => self.diagnostics.push(Diagnostic::error(
+                    "only arrays that are stored in a binding can be assigned to in bash",
+                )),
+                Stmt::If {
+                    condition,
+                    then,
+                    r#else,
+                } => {
+                    self.writer
+                        .line(format!("if [[ {} == true ]]; then", word(condition)));
+                    self.indented_block(then, is_local);
+
+                    // `else { if ... }` is written as a single `else if`
+                    let mut r#else = r#else;
+                    while let [Stmt::If {
+                        condition,
+                        then,
+                        r#else: next,
+                    }] = r#else.as_slice()
+                    {
+                        self.writer
+                            .line(format!("elif [[ {} == true ]]; then", word(condition)));
+                        self.indented_block(then, is_local);
+                        r#else = next;
+                    }
+                    if !r#else.is_empty() {
+                        self.writer.line("else");
+                        self.indented_block(r#else, is_local);
+                    }
+                    self.writer.line("fi");
+                }
+                Stmt::Loop {
+                    label,
+                    condition,
+                    body,
+                } => {
+                    let condition = match condition {
+                        Some(condition) => format!("[[ {} == true ]]", word(condition)),
+                        None => "true".to_owned(),
+                    };
+                    self.r#loop(label, format!("while {condition}"), body, is_local);
+                }
+                // arithmetic only works with integers, just like ranges in erebus
+                Stmt::ForRange {
+                    label,
+                    binding,
+                    start,
+                    end,
+                    is_inclusive,
+                    body,
+                } => {
+                    if is_local {
+                        self.writer.line(format!("local {binding}"));
+                    }
+                    let comparison = if *is_inclusive { "<=" } else { "<" };
+                    self.r#loop(
+                        label,
+                        format!(
+                            "for (({binding} = {}; {binding} {comparison} {}; {binding}++))",
+                            word(start),
+                            word(end)
+                        ),
+                        body,
+                        is_local,
+                    );
+                }
+                // collections are arrays that are referred to by their name
+                Stmt::ForEach {
+                    label,
+                    binding,
+                    iterable,
+                    body,
+                } => {
+                    let Expr::Var(iterable) = iterable else {
+                        unreachable!("operands are flattened, found {iterable:?}")
+                    };
+                    if is_local {
+                        self.writer.line(format!("local {binding}"));
+                    }
+                    self.r#loop(
+                        label,
+                        format!("for {binding} in \"${{{iterable}[@]}}\""),
+                        body,
+                        is_local,
+                    );
+                }
+                Stmt::Break { label } => {
+                    let levels = self.jump_levels(label);
+                    self.writer.line(format!("break{levels}"));
+                }
+                Stmt::Continue { label } => {
+                    let levels = self.jump_levels(label);
+                    self.writer.line(format!("continue{levels}"));
+                }
+                Stmt::Expr(value) => match self.value(value) {
+                    Value::Word(word) => self.writer.line(format!(": {word}")),
+                    Value::Command(command) | Value::Unit(command) => self.writer.line(command),
+                },
+                Stmt::Return(value) => {
+                    match self.value(value) {
+                        Value::Word(word) => self.writer.line(format!("__ret={word}")),
+                        Value::Command(command) => self.writer.line(command),
+                        Value::Unit(command) => {
+                            self.writer.line(command);
+                            self.writer.line("__ret='()'");
+                        }
+                    }
+                    self.writer.line("return");
+                }
+            }
+        }
+    }
+
+    fn r#loop(&mut self, label: &str, header: String, body: &[Stmt], is_local: bool) {
+        self.writer.line(format!("{header}; do"));
+        self.loops.push(label.to_owned());
+        self.indented_block(body, is_local);
+        self.loops.pop();
+        self.writer.line("done");
+    }
+
+    /// Bash counts the loops to leave instead of naming them.
+    fn jump_levels(&self, label: &str) -> String {
+        let position = self
+            .loops
+            .iter()
+            .rposition(|loop_label| loop_label == label)
+            .expect("jumps are inside of their loop");
+
+        match self.loops.len() - position {
+            1 => String::new(),
+            levels => format!(" {levels}"),
+        }
+    }
+
+    fn indented_block(&mut self, statements: &[Stmt], is_local: bool) {
+        self.writer.indent();
+        if statements.is_empty() {
+            self.writer.line(":");
+        }
+        self.block(statements, is_local);
+        self.writer.dedent();
+    }
+
+    fn assign(&mut self, is_local: bool, is_mut: bool, name: &str, value: &Expr) {
+        // a binding inside of a loop is declared again in every iteration
+        let is_mut = is_mut || !self.loops.is_empty();
+        let declaration = match (is_local, is_mut) {
+            (true, true) => "local ",
+            (true, false) => "local -r ",
+            (false, true) => "",
+            (false, false) => "readonly ",
+        };
+
+        let word = self.assigned_word(name, value);
+        self.writer.line(format!("{declaration}{name}={word}"));
+    }
+
+    /// Like [`Self::value_word`], but arrays can only be assigned to the binding `name`.
+    ///
+    /// An array that is stored in another binding is copied, bash can't share it anyway.
+    fn assigned_word(&mut self, name: &str, value: &Expr) -> String {
+        let value = match value {
+            Expr::Copy(value) => value,
+            value => value,
+        };
+        match value {
+            Expr::Array(elements) => {
+                self.arrays.insert(name.to_owned());
+                let words: Vec<_> = elements.iter().map(word).collect();
+                format!("({})", words.join(" "))
+            }
+            Expr::Var(array) if self.arrays.contains(array) => {
+                self.arrays.insert(name.to_owned());
+                format!("(\"${{{array}[@]}}\")")
+            }
+            value => {
+                self.arrays.remove(name);
+                self.value_word(value)
+            }
+        }
+    }
+
+    /// Runs the commands needed by `value` and returns a word that refers to it.
+    fn value_word(&mut self, value: &Expr) -> String {
+        match self.value(value) {
+            Value::Word(word) => word,
+            Value::Command(command) => {
+                self.writer.line(command);
+                "\"$__ret\"".to_owned()
+            }
+            Value::Unit(command) => {
+                self.writer.line(command);
+                "'()'".to_owned()
+            }
+        }
+    }
+
+    /// Reports a variant with fields, which would lose them.
+    fn check_variant(&mut self, r#enum: &str, variant: &str, usage: &str) {
+        if self
+            .variants_with_fields
+            .get(r#enum)
+            .is_some_and(|variants| variants.contains(variant))
+        {
+            self.diagnostics.push(Diagnostic::error(format!(
+                "the fields of `{enum}::{variant}` have no representation in bash, it can't be {usage}"
+            )));
+        }
+    }
+
+    fn value(&mut self, expr: &Expr) -> Value {
+        match expr {
+            Expr::Unit | Expr::Bool(_) | Expr::Num(_) | Expr::Str(_) | Expr::Var(_) => {
+                Value::Word(word(expr))
+            }
+            // words are copied anyway
+            Expr::Copy(value) => self.value(value),
+            Expr::Variant {
+                r#enum, variant, ..
+            } => {
+                self.check_variant(r#enum, variant, "constructed");
+                Value::Word(quote(variant))
+            }
+            Expr::Struct { name, .. } => {
+                self.diagnostics.push(Diagnostic::error(format!(
+                    "struct `{name}` has no representation in bash, it can't be constructed"
+                )));
+                Value::Word("''".to_owned())
+            }
+            Expr::Tuple(_) => {
+                self.diagnostics.push(Diagnostic::error(
+                    "tuples have no representation in bash, they can't be constructed",
+                ));
+                Value::Word("''".to_owned())
+            }
+            Expr::Array(_) => {
+                self.diagnostics.push(Diagnostic::error(
+                    "arrays can only be stored in a binding in bash, they can't be passed around",
+                ));
+                Value::Word("''".to_owned())
+            }
+            Expr::Closure(_) => {
+                self.diagnostics.push(Diagnostic::error(
+                    "closures have no representation in bash, they can't be constructed",
+                ));
+                Value::Word("''".to_owned())
+            }
+            Expr::Index { value, index } => match &**value {
+                Expr::Var(name) => {
+                    self.helpers.insert(Helper::At);
+                    Value::Command(format!("__at {name} {}", word(index)))
+                }
+                _ => {
+                    self.diagnostics.push(Diagnostic::error(
+                        "only arrays that are stored in a binding can be indexed in bash",
+                    ));
+                    Value::Word("''".to_owned())
+                }
+            },
+            Expr::Field { .. } | Expr::TupleField { .. } => {
+                self.diagnostics.push(Diagnostic::error(
+                    "fields have no representation in bash, they can't be read",
+                ));
+                Value::Word("''".to_owned())
+            }
+            Expr::IsVariant {
+                value,
+                r#enum,
+                variant,
+            } => {
+                self.check_variant(r#enum, variant, "matched");
+                self.helpers.insert(Helper::Compare);
+                Value::Command(format!("__compare {} == {}", word(value), quote(variant)))
+            }
+
+            Expr::Unary { op, operand } => {
+                let operand = word(operand);
+                match op {
+                    UnaryOp::Neg => {
+                        self.helpers.insert(Helper::Calc);
+                        Value::Command(format!("__calc 0 - {operand}"))
+                    }
+                    UnaryOp::Not => {
+                        self.helpers.insert(Helper::Not);
+                        Value::Command(format!("__not {operand}"))
+                    }
+                }
+            }
+            Expr::Bin {
+                op,
+                left,
+                right,
+                operand_type,
+            } => {
+                let (left, right) = (word(left), word(right));
+                let op_str = match op {
+                    BinOp::Or | BinOp::And => {
+                        unreachable!("short circuiting operators are lowered to `if`s")
+                    }
+                    BinOp::BitOr | BinOp::BitXor | BinOp::BitAnd | BinOp::Shl | BinOp::Shr => {
+                        let op = match op {
+                            BinOp::BitOr => "'|'",
+                            BinOp::BitXor => "^",
+                            BinOp::BitAnd => "'&'",
+                            BinOp::Shl => "'<<'",
+                            BinOp::Shr => "'>>'",
+                            _ => unreachable!(),
+                        };
+                        self.helpers.insert(Helper::Bits);
+                        return Value::Command(format!("__bits {left} {op} {right}"));
+                    }
+                    BinOp::Equals => "==",
+                    BinOp::NotEquals => "'!='",
+                    BinOp::Less => "'<'",
+                    BinOp::LessEquals => "'<='",
+                    BinOp::Greater => "'>'",
+                    BinOp::GreaterEquals => "'>='",
+                    BinOp::Add => "+",
+                    BinOp::Sub => "-",
+                    BinOp::Mul => "'*'",
+                    BinOp::Div => "/",
+                    BinOp::Rem => "%",
+                };
+
+                // numbers go through `awk`, everything else is compared as a string
+                let is_number = match operand_type {
+                    Some(Type::Named(name)) if self.variants_with_fields.contains_key(name) => {
+                        self.diagnostics.push(Diagnostic::error(format!(
+                            "values of enum `{name}` can't be compared in bash, the fields of its variants have no representation"
+                        )));
+                        return Value::Word("''".to_owned());
+                    }
+                    Some(Type::Named(name)) => matches!(name.as_str(), "int" | "float"),
+                    Some(Type::Tuple(types)) if types.is_empty() => false,
+                    Some(r#type) => {
+                        self.diagnostics.push(Diagnostic::error(format!(
+                            "values of type `{type}` can't be used with `{}` in bash",
+                            op_str.trim_matches('\'')
+                        )));
+                        return Value::Word("''".to_owned());
+                    }
+                    None => {
+                        self.diagnostics.push(Diagnostic::error(format!(
+                            "the type of the operands of `{}` has to be known in bash",
+                            op_str.trim_matches('\'')
+                        )));
+                        return Value::Word("''".to_owned());
+                    }
+                };
+                match op {
+                    // `awk` is only needed if a number can have a fractional part
+                    _ if is_number && operand_type == &Some(Type::Named("int".to_owned())) => {
+                        self.helpers.insert(Helper::Int);
+                        Value::Command(format!("__int {left} {op_str} {right}"))
+                    }
+                    _ if is_number => {
+                        self.helpers.insert(Helper::Calc);
+                        Value::Command(format!("__calc {left} {op_str} {right}"))
+                    }
+                    // adjacent words are concatenated
+                    BinOp::Add => Value::Word(format!("{left}{right}")),
+                    _ => {
+                        self.helpers.insert(Helper::Compare);
+                        Value::Command(format!("__compare {left} {op_str} {right}"))
+                    }
+                }
+            }
+            Expr::Call { callee, args } => Value::Command(command(callee, args)),
+            Expr::AssociatedCall {
+                r#type,
+                function,
+                args,
+            } => Value::Command(command(&format!("{type}.{function}"), args)),
+            // the implementation is the name of a type, whose methods are prefixed with it
+            Expr::TraitCall {
+                implementation,
+                method,
+                args,
+            } => Value::Command(command(&format!("{}.{method}", word(implementation)), args)),
+            // the type of the receiver isn't known, so the function can't be picked
+            Expr::MethodCall { .. } => {
+                self.diagnostics.push(Diagnostic::error(
+                    "methods can only be called in bash if the type of the receiver is known",
+                ));
+                Value::Word("''".to_owned())
+            }
+            Expr::Builtin {
+                builtin: Builtin::Print,
+                args,
+            } => {
+                if args
+                    .iter()
+                    .any(|arg| matches!(arg, Expr::Var(name) if self.arrays.contains(name)))
+                {
+                    self.diagnostics.push(Diagnostic::error(
+                        "arrays can't be printed in bash, it doesn't know whether their elements are strings",
+                    ));
+                }
+                let format = vec!["%s"; args.len()].join(" ");
+                Value::Unit(command(&format!("printf '{format}\\n'"), args))
+            }
+        }
+    }
+}
+
+fn command(name: &str, args: &[Expr]) -> String {
+    let mut command = name.to_owned();
+    for arg in args {
+        command.push(' ');
+        command.push_str(&word(arg));
+    }
+    command
+}
+
+/// Turns an atomic expression into a shell word.
+fn word(expr: &Expr) -> String {
+    match expr {
+        Expr::Unit => "'()'".to_owned(),
+        Expr::Bool(bool) => bool.to_string(),
+        Expr::Num(num) => number(*num),
+        Expr::Str(str) => quote(str),
+        Expr::Var(name) => format!("\"${name}\""),
+        Expr::Variant {
+            variant, fields, ..
+        } if fields.is_empty() => quote(variant),
+        expr => unreachable!("operands are flattened, found {expr:?}"),
+    }
+}
+
+/// Writes a number the way [`Builtin::Print`] does, like `str` in `__calc`.
+fn number(num: f64) -> String {
+    if num.is_nan() {
+        return "NaN".to_owned();
+    } else if num.is_infinite() {
+        return if num > 0.0 { "inf" } else { "-inf" }.to_owned();
+    } else if num == 0.0 {
+        return "0".to_owned();
+    }
+
+    // `{:e}` has the fewest digits that read back as the same number
+    let scientific = format!("{:e}", num.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("`{:e}` writes an exponent");
+    let digits = mantissa.replace('.', "");
+    // the position of the decimal point after the first digit
+    let point = exponent.parse::<i32>().expect("the exponent is an integer") + 1;
+    let length = digits.len() as i32;
+
+    let sign = if num < 0.0 { "-" } else { "" };
+    let zeros = |count: i32| "0".repeat(count as usize);
+    if length <= point && point <= 21 {
+        format!("{sign}{digits}{}", zeros(point - length))
+    } else if 0 < point && point <= 21 {
+        let (whole, fraction) = digits.split_at(point as usize);
+        format!("{sign}{whole}.{fraction}")
+    } else if -6 < point && point <= 0 {
+        format!("{sign}0.{}{digits}", zeros(-point))
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let separator = if rest.is_empty() { "" } else { "." };
+        format!("{sign}{first}{separator}{rest}e{:+}", point - 1)
+    }
+}
+
+/// Single quotes `string`, nothing is special inside of them except `'`.
+fn quote(string: &str) -> String {
+    format!("'{}'", string.replace('\'', r"'\''"))
+}
+
+#[test]
+fn test_bash_emit() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &BashBackend,
+        "enum Shape { Empty, Circle }
+         fn sub(a: int, b: int) -> int { a - b * 2 }
+         let mut then = sub(sub(3, 1), 1);
+         let quoted = \"it's\" + \"!\";
+         let shapes = print(Shape::Empty, Shape::Circle);",
+    );
+
+    assert_eq!(
+        code,
+        r#"#!/usr/bin/env bash
+set -eu
+
+# Applies the operator `$2` to the floating point numbers `$1` and `$3`.
+# Numbers are written the way `print` writes them.
+__calc() {
+    __ret=$(awk -v a="$1" -v op="$2" -v b="$3" '
+    function zeros(n, s) {
+        for (s = ""; n > 0; n--) s = s "0"
+        return s
+    }
+    function str(x, s, precision, sign, digits, point) {
+        s = sprintf("%g", x)
+        if (s ~ /nan/) return "NaN"
+        if (s ~ /inf/) return s ~ /^-/ ? "-inf" : "inf"
+        if (x == 0) return "0"
+        for (precision = 0; precision < 17; precision++) {
+            s = sprintf("%." precision "e", x)
+            if (s + 0 == x) break
+        }
+        if (x < 0) sign = "-"
+        sub(/^-/, "", s)
+        # the position of the decimal point after the first digit
+        point = substr(s, index(s, "e") + 1) + 1
+        digits = substr(s, 1, index(s, "e") - 1)
+        sub(/\./, "", digits)
+        sub(/0+$/, "", digits)
+        if (length(digits) <= point && point <= 21) return sign digits zeros(point - length(digits))
+        if (0 < point && point <= 21) return sign substr(digits, 1, point) "." substr(digits, point + 1)
+        if (-6 < point && point <= 0) return sign "0." zeros(-point) digits
+        s = substr(digits, 1, 1) (length(digits) > 1 ? "." substr(digits, 2) : "")
+        return sign s (point > 1 ? "e+" : "e-") (point > 1 ? point - 1 : 1 - point)
+    }
+    BEGIN {
+        if (op == "+") print str(a + b)
+        else if (op == "-") print str(a - b)
+        else if (op == "*") print str(a * b)
+        else if (op == "/") {
+            if (b != 0) print str(a / b)
+            else if (a == 0 || str(a) == "NaN") print "NaN"
+            else print (a < 0) == (sprintf("%g", b) ~ /^-/) ? "inf" : "-inf"
+        }
+        else if (op == "%") print b == 0 ? "NaN" : str(a % b)
+        else if (op == "==") print (a == b ? "true" : "false")
+        else if (op == "!=") print (a != b ? "true" : "false")
+        else if (op == "<") print (a < b ? "true" : "false")
+        else if (op == "<=") print (a <= b ? "true" : "false")
+        else if (op == ">") print (a > b ? "true" : "false")
+        else if (op == ">=") print (a >= b ? "true" : "false")
+    }')
+}
+
+# Applies the operator `$2` to the integers `$1` and `$3` without starting `awk`,
+# unless the result could differ from the one of `__calc`.
+__int() {
+    local pattern='^(0|-?[1-9][0-9]{0,8})$'
+    if [[ $1 =~ $pattern && $3 =~ $pattern ]]; then
+        case $2 in
+            '+') __ret=$(( $1 + $3 )); return ;;
+            '-') __ret=$(( $1 - $3 )); return ;;
+            '*')
+                __ret=$(( $1 * $3 ))
+                (( __ret > -(1 << 53) && __ret < 1 << 53 )) && return
+                ;;
+            '/') (( $3 != 0 )) && (( $1 % $3 == 0 )) && __ret=$(( $1 / $3 )) && return ;;
+            '%') (( $3 != 0 )) && __ret=$(( $1 % $3 )) && return ;;
+            *)
+                (( $1 $2 $3 )) && __ret=true || __ret=false
+                return
+                ;;
+        esac
+    fi
+    __calc "$@"
+}
+
+# enum Shape { Empty, Circle }
+
+sub() {
+    local a="$1" b="$2"
+    __int "$b" '*' 2
+    local -r _t0="$__ret"
+    __int "$a" - "$_t0"
+    return
+}
+
+sub 3 1
+readonly _t0="$__ret"
+sub "$_t0" 1
+then_1="$__ret"
+
+readonly quoted='it'\''s''!'
+
+printf '%s %s\n' 'Empty' 'Circle'
+readonly shapes='()'
+"#
+    )
+}
+
+#[test]
+fn test_bash_operators() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &BashBackend,
+        "fn half(x: int) -> int { x / 2 }
+         fn main() -> () {
+             print(1 / 0, -1 / 0, 7 % 0, 0.1 + 0.2, 1.1 + 2.2, 8 / 4 / 2, half(7) == 3.5);
+             print(1 << 31, 1 << 32, -7 >> 1, 0 - 1 >> 31, half(11) & 7, half(-11) | 0);
+             print(-(1 - 4) * 2, -(-1.5), 7 % 3 != 1, -7 % 3, (6 & 3 | 8 ^ 1) << 2 >> 1);
+             print(
+                 !(1 == 2) == (3 == 3),
+                 2 < 3 && 3 <= 2 || 4 >= 4,
+                 1 == 2 && { let a = print(\"skipped\"); 1 == 1 },
+                 1 == 2 || { let a = 5; a > 4 }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("bash", "sh", &code) {
+        assert_eq!(output, "inf -inf NaN 0.30000000000000004 3.3000000000000003 1 true\n-2147483648 1 -4 -1 5 -5\n6 1.5 false -1 22\ntrue true false true\n");
+    }
+}
+
+#[test]
+fn test_bash_strings() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &BashBackend,
+        "fn main() -> () {
+             let one = \"1\";
+             let joined = \"a\" + \"'b\";
+             print(one + \"2\", one == \"1.0\", 1 == 1.0, \"b\" > \"a\", joined);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("bash", "sh", &code) {
+        assert_eq!(output, "12 false true true a'b\n");
+    }
+}
+
+#[test]
+fn test_bash_control_flow() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &BashBackend,
+        "fn first_over(n: int, limit: int) -> int {
+             for i in 0..n { if i * i > limit { return i; } }
+             0 - 1
+         }
+         fn main() -> () {
+             print(first_over(10, 20), first_over(3, 20), { let x = 3; x + 4 });
+             print(
+                 if 1 > 2 { \"a\" } else if 2 > 1 { let b = \"b\"; b } else { \"c\" },
+                 'outer: loop {
+                     while 2 > 1 { let x = if 3 > 2 { break 'outer 30 } else { 1 }; x }
+                 },
+                 'counting: loop {
+                     loop { let x = if 1 > 2 { continue 'counting } else { break 'counting 7 }; x }
+                 },
+                 match 2 { 1 => \"one\", n if n > 1 => \"many\", _ => \"none\" }
+             );
+             for i in 1..=2 { print(\"i\", i) }
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("bash", "sh", &code) {
+        assert_eq!(output, "5 -1 7\nb 30 7 many\ni 1\ni 2\n");
+    }
+}
+
+#[test]
+fn test_bash_enums() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &BashBackend,
+        "enum Shape { Empty, Circle }
+         fn kind(shape: Shape) -> String {
+             match shape { Shape::Empty => \"empty\", Shape::Circle => \"circle\" }
+         }
+         impl Shape { fn unit() -> Self { Self::Empty } }
+         fn main() -> () {
+             print(kind(Shape::Circle), kind(Shape::unit()));
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("bash", "sh", &code) {
+        assert_eq!(output, "circle empty\n");
+    }
+}
+
+#[test]
+fn test_bash_arrays() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &BashBackend,
+        "fn main() -> () {
+             let mut xs = [1, 2];
+             let mut n = 3;
+             n *= 2;
+             xs[1] += n;
+             for x in xs { print(\"x\", x) }
+             for x in xs { n += x; () }
+             print(n, [10, 20, 30][2 - 1]);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("bash", "sh", &code) {
+        assert_eq!(output, "x 1\nx 8\n15 20\n");
+    }
+}
+
+#[test]
+fn test_bash_generics() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &BashBackend,
+        "enum Shape { Empty, Circle }
+         fn kind(shape: Shape) -> String {
+             match shape { Shape::Empty => \"empty\", Shape::Circle => \"circle\" }
+         }
+         fn id<T>(x: T) -> T { x }
+         trait Describe { fn describe(self) -> String; }
+         impl Describe for Shape { fn describe(self) -> String { kind(self) } }
+         fn twice<T: Describe>(x: T) -> String { x.describe() + x.describe() }
+         fn both(x: dyn Describe, y: dyn Describe) -> String { let z = y; x.describe() + z.describe() }
+         fn main() -> () {
+             print(id(5), twice(Shape::Empty), Shape::Circle.describe(), both(Shape::Empty, Shape::Circle));
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("bash", "sh", &code) {
+        assert_eq!(output, "5 emptyempty circle emptycircle\n");
+    }
+}
+
+#[test]
+fn test_bash_integers() {
+    use crate::writer::compile_source;
+
+    // `awk` takes over whenever shell arithmetic would give a different result
+    let code = compile_source(
+        &BashBackend,
+        "fn fib(n: int) -> int { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+         fn main() -> () {
+             let x: int = 1.5;
+             print(fib(15), -7 % 3, 6 / 3, 7 / 2, 1 / 0, 5 % 0, x + 1, 2 <= 2);
+             print(123456789 * 987654321, 1234567890 + 1);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("bash", "sh", &code) {
+        assert_eq!(
+            output,
+            "610 -1 2 3.5 inf NaN 2.5 true\n121932631112635260 1234567891\n"
+        );
+    }
+}
+
+#[test]
+fn test_bash_copies() {
+    use crate::writer::compile_source;
+
+    // changing a copy is never seen through the binding it was copied from
+    let code = compile_source(
+        &BashBackend,
+        "fn main() -> () {
+             let xs = [1, 2];
+             let mut ys = xs;
+             ys[0] = 9;
+             let zs = ys;
+             ys[1] = 8;
+             print(xs[0], ys[0], zs[1], ys[1]);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("bash", "sh", &code) {
+        assert_eq!(output, "1 9 2 8\n");
+    }
+}
+
+#[test]
+fn test_bash_print() {
+    use crate::writer::compile_source;
+
+    // the format of `print` is the same on every backend
+    let code = compile_source(
+        &BashBackend,
+        "enum Shape { Empty, Circle(int) }
+         fn main() -> () {
+             print(0.000001, 1 / 10000000, 0 - 25 / 1000000000, 12345678901234567);
+             print(100000000000 * 10000000000, 0.1 + 0.2, -2.5, 100, 1 / 3, 0 / 0, -1 / 0);
+             print(Shape::Empty, \"top\", 1 > 0, ());
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("bash", "sh", &code) {
+        assert_eq!(
+            output,
+            "0.000001 1e-7 -2.5e-8 12345678901234568\n1e+21 0.30000000000000004 -2.5 100 0.3333333333333333 NaN -inf\nEmpty top true ()\n"
+        );
+    }
+}
+
+#[test]
+fn test_bash_variant_fields() {
+    use erebus_parser::{Ast, Parsable};
+
+    use crate::Backend;
+
+    let ast = Ast::parse(
+        "enum Shape { Empty, Circle(int) }
+         fn main() -> () {
+             let empty = Shape::Empty;
+             print(Shape::Circle(1), empty == Shape::Empty);
+             match empty { Shape::Circle(_) => 1, _ => 0 };
+         }",
+    )
+    .unwrap();
+    let messages: Vec<_> = BashBackend
+        .compile("test", &ast)
+        .diagnostics
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "the fields of `Shape::Circle` have no representation in bash, it can't be constructed",
+            "values of enum `Shape` can't be compared in bash, the fields of its variants have no representation",
+            "the fields of `Shape::Circle` have no representation in bash, it can't be matched",
+        ]
+    );
+}
@@ -0,0 +1,227 @@
+//! Backend that runs a separate executable, selected with `--emit external:<path>`.
+//!
+//! # Protocol
+//!
+//! The executable receives a single JSON object on stdin and has to answer with a
+//! single JSON object on stdout. Both carry the [`PROTOCOL_VERSION`], which gets
+//! incremented on every incompatible change.
+//!
+//! The request contains the name of the module and the lowered program.
+//! Its layout mirrors [`crate::ir::Program`], with enum variants in snake case:
+//!
+//! ```json
+//! { "version": 2, "module": "main", "program": { "items": [{ "fn": { ... } }] } }
+//! ```
+//!
+//! The response lists the files to write, relative to the output directory,
+//! and diagnostics with a severity of either `error` or `warning`:
+//!
+//! ```json
+//! {
+//!     "version": 2,
+//!     "files": [{ "path": "main.txt", "content": "..." }],
+//!     "diagnostics": [{ "severity": "warning", "message": "..." }]
+//! }
+//! ```
+//!
+//! # Changes
+//!
+//! Version 2 covers everything the language gained since version 1:
+//!
+//! - enums, the `methods` of structs and enums and the `generics` of functions and structs
+//! - the `param`, `applied`, `array` and `dyn` types
+//! - every statement besides `let` and `return`
+//! - every expression besides `num`, `str`, `var`, `bin`, `call` and `builtin`,
+//!   like `trait_call`, and the `operand_type` of `bin`
+//! - every operator besides `equals`, `add`, `sub`, `mul` and `div`
+
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ir::Program, Diagnostic, IrBackend, Output};
+
+pub const PROTOCOL_VERSION: u32 = 2;
+
+const NAME_PREFIX: &str = "external:";
+
+#[derive(Serialize)]
+struct Request<'a> {
+    version: u32,
+    module: &'a str,
+    program: &'a Program,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    version: u32,
+    #[serde(flatten)]
+    output: Output,
+}
+
+pub struct ExternalBackend {
+    name: String,
+    path: PathBuf,
+}
+
+impl ExternalBackend {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+
+        Self {
+            name: format!("{NAME_PREFIX}{}", path.display()),
+            path,
+        }
+    }
+
+    /// Parses names of the form `external:<path>`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        name.strip_prefix(NAME_PREFIX).map(Self::new)
+    }
+
+    fn run(&self, request: &Request) -> Result<Output, String> {
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("failed to start: {err}"))?;
+
+        let request = serde_json::to_vec(request).expect("the ir can always be serialized");
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // the response is read while the request is written,
+        // both could be larger than the buffer of the pipe
+        let writer = thread::spawn(move || {
+            // a closed stdin is reported through the exit status
+            let _ = stdin.write_all(&request);
+        });
+
+        let output = child
+            .wait_with_output()
+            .map_err(|err| format!("failed to run: {err}"))?;
+        writer.join().expect("writing the request doesn't panic");
+        if !output.status.success() {
+            return Err(format!(
+                "exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            ));
+        }
+
+        let response: Response = serde_json::from_slice(&output.stdout)
+            .map_err(|err| format!("sent an invalid response: {err}"))?;
+        if response.version != PROTOCOL_VERSION {
+            return Err(format!(
+                "uses protocol version {}, but version {PROTOCOL_VERSION} is required",
+                response.version
+            ));
+        }
+
+        Ok(response.output)
+    }
+}
+
+impl IrBackend for ExternalBackend {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn emit(&self, module: &str, program: &Program) -> Output {
+        let request = Request {
+            version: PROTOCOL_VERSION,
+            module,
+            program,
+        };
+
+        self.run(&request).unwrap_or_else(|err| Output {
+            files: Vec::new(),
+            diagnostics: vec![Diagnostic::error(format!(
+                "external backend \"{}\" {err}",
+                self.path.display()
+            ))],
+        })
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn test_external_backend() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use erebus_parser::{Ast, Parsable};
+
+    use crate::{Backend, OutputFile, Severity};
+
+    let dir = std::env::temp_dir().join(format!("erebus_external_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    // the response is written before the request is read
+    let stub = |name: &str, response: &str| {
+        let path = dir.join(name);
+        fs::write(
+            &path,
+            format!("#!/bin/sh\nprintf '%s' '{response}'\ncat > \"$0.request\"\n"),
+        )
+        .unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        ExternalBackend::from_name(&format!("external:{}", path.display())).unwrap()
+    };
+    let ast = Ast::parse("fn main() -> int { 1 }").unwrap();
+
+    let backend = stub(
+        "valid",
+        r#"{"version": 2, "files": [{"path": "out.txt", "content": "hi"}],
+            "diagnostics": [{"severity": "warning", "message": "stub"}]}"#,
+    );
+    let output = backend.compile("test", &ast);
+    assert_eq!(
+        output.files,
+        [OutputFile {
+            path: "out.txt".into(),
+            content: "hi".to_owned()
+        }]
+    );
+    assert_eq!(output.diagnostics[0].severity, Severity::Warning);
+
+    let request: serde_json::Value =
+        serde_json::from_slice(&fs::read(dir.join("valid.request")).unwrap()).unwrap();
+    assert_eq!(request["version"], PROTOCOL_VERSION);
+    assert_eq!(request["module"], "test");
+    assert_eq!(request["program"]["items"][0]["fn"]["name"], "main");
+    assert_eq!(
+        request["program"]["items"][0]["fn"]["body"][0]["return"]["num"],
+        1.0
+    );
+
+    // neither fits into the buffer of a pipe
+    let content = "x".repeat(100_000);
+    let large = stub(
+        "large",
+        &format!(
+            r#"{{"version": 2, "files": [{{"path": "out.txt", "content": "{content}"}}], "diagnostics": []}}"#
+        ),
+    );
+    let source: String = (0..2_000)
+        .map(|i| format!("fn f{i}() -> int {{ {i} }}\n"))
+        .collect();
+    let output = large.compile("test", &Ast::parse(&source).unwrap());
+    assert_eq!(output.files[0].content, content);
+    assert!(fs::metadata(dir.join("large.request")).unwrap().len() > 100_000);
+
+    let outdated = stub(
+        "outdated",
+        r#"{"version": 1, "files": [], "diagnostics": []}"#,
+    );
+    assert!(outdated.compile("test", &ast).has_errors());
+
+    let missing = ExternalBackend::new(dir.join("missing"));
+    assert!(missing.compile("test", &ast).has_errors());
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,1157 @@
+//! JavaScript (ES2020) backend emitting an ES module.
+
+use crate::{
+    ir::{
+        BinOp, Builtin, Closure, Enum, Expr, FieldValue, Function, Global, Item, Jumps,
+        LowerOptions, Method, Program, Stmt, Struct, Type, UnaryOp,
+    },
+    quote, CodeWriter, IrBackend, Output, OutputFile,
+};
+
+const RESERVED: &[&str] = &[
+    // keywords
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+    // globals used by the generated code
+    "__at",
+    "__copy",
+    "__equals",
+    "__set",
+    "__str",
+    "__variants",
+    "Array",
+    "Number",
+    "Object",
+    "RangeError",
+    "String",
+    "arguments",
+    "console",
+    "constructor",
+    "eval",
+    "Infinity",
+    "NaN",
+    "pathToFileURL",
+    "process",
+    "undefined",
+];
+
+/// Reads an element of an array, which would be `undefined` if the index is out of bounds.
+const AT_HELPER: &str = "function __at(values, index) {
+    if (!Number.isInteger(index) || index < 0 || index >= values.length) {
+        throw new RangeError(`index ${index} is out of bounds for length ${values.length}`);
+    }
+    return values[index];
+}";
+
+const SET_HELPER: &str = "function __set(values, index, value) {
+    __at(values, index);
+    values[index] = value;
+}";
+
+/// Implements [`Expr::Copy`], objects keep their class.
+const COPY_HELPER: &str = "function __copy(value) {
+    if (Array.isArray(value)) {
+        return value.map(__copy);
+    }
+    if (typeof value !== \"object\") {
+        return value;
+    }
+    const copied = Object.create(Object.getPrototypeOf(value));
+    for (const key of Object.keys(value)) {
+        copied[key] = __copy(value[key]);
+    }
+    return copied;
+}";
+
+/// Compares objects by their class and fields, `===` only checks whether they are the same.
+const EQUALS_HELPER: &str = "function __equals(a, b) {
+    if (a === b) {
+        return true;
+    }
+    if (typeof a !== \"object\" || typeof b !== \"object\" || a.constructor !== b.constructor) {
+        return false;
+    }
+    const keys = Object.keys(a);
+    return keys.length === Object.keys(b).length && keys.every((key) => __equals(a[key], b[key]));
+}";
+
+/// Formats a value the way [`Builtin::Print`] writes it, `nested` values quote their strings.
+///
+/// The fields of a variant are looked up in the `__variants` of its enum, in declaration order.
+const STR_HELPER: &str = "function __str(value, nested = false) {
+    switch (typeof value) {
+        case \"undefined\":
+            return \"()\";
+        case \"number\":
+            if (value !== value) {
+                return \"NaN\";
+            } else if (value === Infinity) {
+                return \"inf\";
+            } else if (value === -Infinity) {
+                return \"-inf\";
+            }
+            return String(value);
+        case \"string\":
+            return nested ? `\"${value.replace(/[\\\\\"]/g, \"\\\\$&\")}\"` : value;
+        case \"function\":
+            return \"<fn>\";
+        case \"object\":
+            break;
+        default:
+            return String(value);
+    }
+    if (Array.isArray(value)) {
+        return `[${value.map((element) => __str(element, true)).join(\", \")}]`;
+    }
+    const variants = value.constructor.__variants;
+    const name = variants ? value.tag : value.constructor.name;
+    const fields = variants ? variants[value.tag] : Object.keys(value);
+    const texts = fields.map((field) => __str(value[field], true));
+    if (fields.length === 0) {
+        return name;
+    } else if (fields[0] === \"_0\") {
+        return `${name}(${texts.join(\", \")})`;
+    }
+    return `${name} { ${fields.map((field, i) => `${field}: ${texts[i]}`).join(\", \")} }`;
+}";
+
+pub struct JsBackend;
+
+impl IrBackend for JsBackend {
+    fn name(&self) -> &str {
+        "js"
+    }
+
+    fn lower_options(&self) -> LowerOptions<'_> {
+        LowerOptions {
+            reserved: RESERVED,
+            receiver: "this",
+            ..LowerOptions::default()
+        }
+    }
+
+    fn emit(&self, module: &str, program: &Program) -> Output {
+        Output {
+            files: vec![OutputFile {
+                path: format!("{module}.mjs").into(),
+                content: JsEmitter::new().emit(program),
+            }],
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+struct JsEmitter {
+    writer: CodeWriter,
+    uses_at_helper: bool,
+    uses_set_helper: bool,
+    uses_copy_helper: bool,
+    uses_equals_helper: bool,
+    uses_str_helper: bool,
+    /// The labels of the loops around the current statement.
+    loops: Vec<String>,
+}
+
+impl JsEmitter {
+    fn new() -> Self {
+        Self {
+            writer: CodeWriter::new("    "),
+            uses_at_helper: false,
+            uses_set_helper: false,
+            uses_copy_helper: false,
+            uses_equals_helper: false,
+            uses_str_helper: false,
+            loops: Vec::new(),
+        }
+    }
+
+    fn emit(mut self, program: &Program) -> String {
+        let has_main = program.items.iter().any(|item| match item {
+            Item::Fn(function) => function.name == "main" && function.params.is_empty(),
+            _ => false,
+        });
+        for item in &program.items {
+            match item {
+                Item::Struct(r#struct) => self.r#struct(r#struct),
+                Item::Enum(r#enum) => self.r#enum(r#enum),
+                Item::Fn(function) => self.function(function),
+                Item::Global(global) => self.global(global),
+            }
+        }
+
+        if has_main {
+            self.writer.blank_line();
+            // only run main if this module is executed directly
+            self.writer
+                .line("if (import.meta.url === pathToFileURL(process.argv[1]).href) {");
+            self.writer.indent();
+            self.writer.line("main();");
+            self.writer.dedent();
+            self.writer.line("}");
+        }
+
+        // whether the helpers are needed is only known after emitting everything
+        let mut header = CodeWriter::new("    ");
+        if has_main {
+            header.line("import { pathToFileURL } from \"node:url\";");
+        }
+        let helpers = [
+            (self.uses_at_helper, AT_HELPER),
+            (self.uses_set_helper, SET_HELPER),
+            (self.uses_copy_helper, COPY_HELPER),
+            (self.uses_equals_helper, EQUALS_HELPER),
+            (self.uses_str_helper, STR_HELPER),
+        ];
+        for (is_used, helper) in helpers {
+            if is_used {
+                header.blank_line();
+                for line in helper.lines() {
+                    header.line(line);
+                }
+            }
+        }
+        header.blank_line();
+
+        header.finish() + &self.writer.finish()
+    }
+
+    fn r#struct(&mut self, r#struct: &Struct) {
+        let fields: Vec<_> = r#struct
+            .fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+
+        self.writer.blank_line();
+        self.writer.line(format!(
+            "{}class {} {{",
+            export(r#struct.is_pub),
+            r#struct.name
+        ));
+        self.writer.indent();
+
+        self.writer
+            .line(format!("constructor({}) {{", fields.join(", ")));
+        self.writer.indent();
+        for field in fields {
+            self.writer.line(format!("this.{field} = {field};"));
+        }
+        self.writer.dedent();
+        self.writer.line("}");
+        self.methods(&r#struct.methods);
+
+        self.writer.dedent();
+        self.writer.line("}");
+    }
+
+    /// Every variant is an instance of the same class that stores its name in `tag`,
+    /// `__variants` has the names of the fields of each one.
+    fn r#enum(&mut self, r#enum: &Enum) {
+        self.writer.blank_line();
+        self.writer
+            .line(format!("{}class {} {{", export(r#enum.is_pub), r#enum.name));
+        self.writer.indent();
+
+        let variants: Vec<_> = r#enum
+            .variants
+            .iter()
+            .map(|variant| {
+                let fields: Vec<_> = variant
+                    .fields
+                    .iter()
+                    .map(|field| quote(&field.name))
+                    .collect();
+                format!("{}: [{}]", quote(&variant.name), fields.join(", "))
+            })
+            .collect();
+        self.writer.line(format!(
+            "static __variants = {{ {} }};",
+            variants.join(", ")
+        ));
+        self.writer.blank_line();
+        self.writer.line("constructor(tag, fields) {");
+        self.writer.indent();
+        self.writer.line("this.tag = tag;");
+        self.writer.line("Object.assign(this, fields);");
+        self.writer.dedent();
+        self.writer.line("}");
+        self.methods(&r#enum.methods);
+
+        self.writer.dedent();
+        self.writer.line("}");
+    }
+
+    /// Methods without a receiver are `static`, `self` is `this` in the others.
+    fn methods(&mut self, methods: &[Method]) {
+        for method in methods {
+            let modifier = if method.has_receiver { "" } else { "static " };
+
+            self.writer.blank_line();
+            self.writer.line(format!(
+                "{modifier}{}({}) {{",
+                method.function.name,
+                params_list(&method.function)
+            ));
+            self.body(&method.function);
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.writer.blank_line();
+        self.writer.line(format!(
+            "{}function {}({}) {{",
+            export(function.is_pub),
+            function.name,
+            params_list(function),
+        ));
+        self.body(function);
+    }
+
+    fn body(&mut self, function: &Function) {
+        self.writer.indent();
+        self.block(&function.body);
+        self.writer.dedent();
+        self.writer.line("}");
+    }
+
+    fn global(&mut self, global: &Global) {
+        self.writer.blank_line();
+        self.block(&global.setup);
+
+        let binding = format!(
+            "{}{} {}",
+            export(global.is_pub),
+            declaration(global.is_mut),
+            global.name,
+        );
+        if let Expr::Closure(closure) = &global.value {
+            return self.closure(&binding, closure);
+        }
+        let value = self.expr(&global.value);
+        self.writer.line(format!("{binding} = {value};"));
+    }
+
+    /// An arrow function, which keeps the receiver of the surrounding method.
+    fn closure(&mut self, binding: &str, closure: &Closure) {
+        self.writer
+            .line(format!("{binding} = ({}) => {{", closure.params.join(", ")));
+        self.writer.indent();
+        self.block(&closure.body);
+        self.writer.dedent();
+        self.writer.line("};");
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            match statement {
+                Stmt::Let {
+                    is_mut,
+                    name,
+                    value: Expr::Closure(closure),
+                } => self.closure(&format!("{} {name}", declaration(*is_mut)), closure),
+                Stmt::Let {
+                    is_mut,
+                    name,
+                    value,
+                } => {
+                    let value = self.expr(value);
+                    self.writer
+                        .line(format!("{} {name} = {value};", declaration(*is_mut)))
+                }
+                Stmt::Assign { name, value } => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("{name} = {value};"))
+                }
+                Stmt::AssignField {
+                    target,
+                    name,
+                    value,
+                } => {
+                    let target = self.unary_operand(target);
+                    let value = self.expr(value);
+                    self.writer.line(format!("{target}.{name} = {value};"))
+                }
+                Stmt::AssignIndex {
+                    target,
+                    index,
+                    value,
+                } => {
+                    self.uses_at_helper = true;
+                    self.uses_set_helper = true;
+                    let target = self.expr(target);
+                    let index = self.expr(index);
+                    let value = self.expr(value);
+                    self.writer
+                        .line(format!("__set({target}, {index}, {value});"))
+                }
+                Stmt::If {
+                    condition,
+                    then,
+                    r#else,
+                } => {
+                    let condition = self.expr(condition);
+                    self.writer.line(format!("if ({condition}) {{"));
+                    self.writer.indent();
+                    self.block(then);
+                    self.writer.dedent();
+
+                    // `else { if ... }` is written as a single `else if`
+                    let mut r#else = r#else;
+                    while let [Stmt::If {
+                        condition,
+                        then,
+                        r#else: next,
+                    }] = r#else.as_slice()
+                    {
+                        let condition = self.expr(condition);
+                        self.writer.line(format!("}} else if ({condition}) {{"));
+                        self.writer.indent();
+                        self.block(then);
+                        self.writer.dedent();
+                        r#else = next;
+                    }
+                    if !r#else.is_empty() {
+                        self.writer.line("} else {");
+                        self.writer.indent();
+                        self.block(r#else);
+                        self.writer.dedent();
+                    }
+                    self.writer.line("}");
+                }
+                Stmt::Loop {
+                    label,
+                    condition,
+                    body,
+                } => {
+                    let condition = condition
+                        .as_ref()
+                        .map_or_else(|| "true".to_owned(), |condition| self.expr(condition));
+                    self.r#loop(label, format!("while ({condition})"), body);
+                }
+                Stmt::ForRange {
+                    label,
+                    binding,
+                    start,
+                    end,
+                    is_inclusive,
+                    body,
+                } => {
+                    let comparison = if *is_inclusive { "<=" } else { "<" };
+                    let (start, end) = (self.expr(start), self.expr(end));
+                    self.r#loop(
+                        label,
+                        format!(
+                            "for (let {binding} = {start}; {binding} {comparison} {end}; {binding}++)"
+                        ),
+                        body,
+                    );
+                }
+                Stmt::ForEach {
+                    label,
+                    binding,
+                    iterable,
+                    body,
+                } => {
+                    let iterable = self.expr(iterable);
+                    self.r#loop(label, format!("for (const {binding} of {iterable})"), body);
+                }
+                Stmt::Break { label } => {
+                    let target = self.jump_target(label);
+                    self.writer.line(format!("break{target};"))
+                }
+                Stmt::Continue { label } => {
+                    let target = self.jump_target(label);
+                    self.writer.line(format!("continue{target};"))
+                }
+                Stmt::Expr(value) => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("{value};"))
+                }
+                Stmt::Return(value) => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("return {value};"))
+                }
+            }
+        }
+    }
+
+    fn r#loop(&mut self, label: &str, header: String, body: &[Stmt]) {
+        let jumps = Jumps::to(label, body);
+        let prefix = match jumps.nested_break || jumps.nested_continue {
+            true => format!("{label}: "),
+            false => String::new(),
+        };
+
+        self.writer.line(format!("{prefix}{header} {{"));
+        self.writer.indent();
+        self.loops.push(label.to_owned());
+        self.block(body);
+        self.loops.pop();
+        self.writer.dedent();
+        self.writer.line("}");
+    }
+
+    /// Only jumps out of nested loops need a label.
+    fn jump_target(&self, label: &str) -> String {
+        match self.loops.last() {
+            Some(innermost) if innermost == label => String::new(),
+            _ => format!(" {label}"),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> String {
+        match expr {
+            Expr::Unit => "undefined".to_owned(),
+            Expr::Bool(bool) => bool.to_string(),
+            Expr::Num(num) => format!("{num:?}"),
+            Expr::Str(str) => quote(str),
+            Expr::Var(name) => name.clone(),
+
+            Expr::Unary { op, operand } => {
+                let op_str = match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::Not => "!",
+                };
+                format!("{op_str}{}", self.unary_operand(operand))
+            }
+            // `===` would compare objects by their identity
+            Expr::Bin {
+                op: op @ (BinOp::Equals | BinOp::NotEquals),
+                left,
+                right,
+                operand_type,
+            } if !operand_type.as_ref().is_some_and(Type::is_primitive) => {
+                self.uses_equals_helper = true;
+                let negation = if *op == BinOp::NotEquals { "!" } else { "" };
+                format!(
+                    "{negation}__equals({}, {})",
+                    self.expr(left),
+                    self.expr(right)
+                )
+            }
+            Expr::Bin {
+                op, left, right, ..
+            } => {
+                let op_str = match op {
+                    BinOp::Or => "||",
+                    BinOp::And => "&&",
+                    BinOp::Equals => "===",
+                    BinOp::NotEquals => "!==",
+                    BinOp::Less => "<",
+                    BinOp::LessEquals => "<=",
+                    BinOp::Greater => ">",
+                    BinOp::GreaterEquals => ">=",
+                    BinOp::BitOr => "|",
+                    BinOp::BitXor => "^",
+                    BinOp::BitAnd => "&",
+                    BinOp::Shl => "<<",
+                    BinOp::Shr => ">>",
+                    BinOp::Add => "+",
+                    BinOp::Sub => "-",
+                    BinOp::Mul => "*",
+                    BinOp::Div => "/",
+                    // `%` truncates like erebus
+                    BinOp::Rem => "%",
+                };
+                format!(
+                    "{} {op_str} {}",
+                    self.operand(*op, left, false),
+                    self.operand(*op, right, true)
+                )
+            }
+            Expr::Call { callee, args } => format!("{callee}({})", self.args_list(args)),
+            Expr::MethodCall {
+                receiver,
+                method,
+                args,
+            } => format!(
+                "{}.{method}({})",
+                self.unary_operand(receiver),
+                self.args_list(args)
+            ),
+            Expr::AssociatedCall {
+                r#type,
+                function,
+                args,
+            } => format!("{type}.{function}({})", self.args_list(args)),
+            // methods are looked up on the receiver instead
+            Expr::TraitCall { .. } => unreachable!("only lowered with `dictionary_passing`"),
+            // a format string keeps `%` in the arguments from being replaced
+            Expr::Builtin {
+                builtin: Builtin::Print,
+                args,
+            } => {
+                let format = quote(&vec!["%s"; args.len()].join(" "));
+                let args: String = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Expr::Str(_) => format!(", {}", self.expr(arg)),
+                        arg => {
+                            self.uses_str_helper = true;
+                            format!(", __str({})", self.expr(arg))
+                        }
+                    })
+                    .collect();
+                format!("console.log({format}{args})")
+            }
+            Expr::Tuple(elements) | Expr::Array(elements) => {
+                format!("[{}]", self.args_list(elements))
+            }
+            Expr::Copy(value) => {
+                self.uses_copy_helper = true;
+                format!("__copy({})", self.expr(value))
+            }
+            Expr::Closure(_) => unreachable!("closures are only bound by a `let`"),
+            // the constructor takes the fields in declaration order
+            Expr::Struct { name, fields } => format!("new {name}({})", self.values_list(fields)),
+            Expr::Variant {
+                r#enum,
+                variant,
+                fields,
+            } if fields.is_empty() => format!("new {enum}({})", quote(variant)),
+            Expr::Variant {
+                r#enum,
+                variant,
+                fields,
+            } => format!(
+                "new {enum}({}, {{ {} }})",
+                quote(variant),
+                self.fields_list(fields)
+            ),
+            Expr::Field { value, name } => format!("{}.{name}", self.unary_operand(value)),
+            Expr::TupleField { value, index } => format!("{}[{index}]", self.unary_operand(value)),
+            Expr::Index { value, index } => {
+                self.uses_at_helper = true;
+                format!("__at({}, {})", self.expr(value), self.expr(index))
+            }
+            Expr::IsVariant { value, variant, .. } => {
+                format!("{}.tag === {}", self.unary_operand(value), quote(variant))
+            }
+        }
+    }
+
+    fn operand(&mut self, parent: BinOp, operand: &Expr, is_right: bool) -> String {
+        let code = self.expr(operand);
+
+        match bin_op(operand) {
+            Some(op)
+                if precedence(op) < precedence(parent)
+                    || precedence(op) == precedence(parent) && is_right =>
+            {
+                format!("({code})")
+            }
+            _ => code,
+        }
+    }
+
+    fn unary_operand(&mut self, operand: &Expr) -> String {
+        match operand {
+            // nested operators are parenthesized so `-(-x)` doesn't become `--x`
+            Expr::Unary { .. } | Expr::Bin { .. } | Expr::IsVariant { .. } => {
+                format!("({})", self.expr(operand))
+            }
+            _ => self.expr(operand),
+        }
+    }
+
+    fn args_list(&mut self, args: &[Expr]) -> String {
+        args.iter()
+            .map(|arg| self.expr(arg))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn values_list(&mut self, fields: &[FieldValue]) -> String {
+        fields
+            .iter()
+            .map(|field| self.expr(&field.value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn fields_list(&mut self, fields: &[FieldValue]) -> String {
+        fields
+            .iter()
+            .map(|field| format!("{}: {}", field.name, self.expr(&field.value)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn export(is_pub: bool) -> &'static str {
+    if is_pub {
+        "export "
+    } else {
+        ""
+    }
+}
+
+fn declaration(is_mut: bool) -> &'static str {
+    if is_mut {
+        "let"
+    } else {
+        "const"
+    }
+}
+
+/// Unlike in erebus, the bitwise operators bind weaker than comparisons in js.
+fn precedence(op: BinOp) -> u8 {
+    match op {
+        BinOp::Or => 1,
+        BinOp::And => 2,
+        BinOp::BitOr => 3,
+        BinOp::BitXor => 4,
+        BinOp::BitAnd => 5,
+        BinOp::Equals | BinOp::NotEquals => 6,
+        BinOp::Less | BinOp::LessEquals | BinOp::Greater | BinOp::GreaterEquals => 7,
+        BinOp::Shl | BinOp::Shr => 8,
+        BinOp::Add | BinOp::Sub => 9,
+        BinOp::Mul | BinOp::Div | BinOp::Rem => 10,
+    }
+}
+
+fn params_list(function: &Function) -> String {
+    function
+        .params
+        .iter()
+        .map(|param| param.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The operator `expr` is emitted with, if it is a binary operation.
+fn bin_op(expr: &Expr) -> Option<BinOp> {
+    match expr {
+        Expr::Bin { op, .. } => Some(*op),
+        Expr::IsVariant { .. } => Some(BinOp::Equals),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_js_emit() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &JsBackend,
+        "pub struct Point { x: int, class: String }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         pub fn sub(a: int, b: int) -> int { let mut c = a; c - b }
+         let this = { let a = 2; a };
+         let shapes = print(Shape::Empty, Shape::Circle(1), Shape::Rect { height: 2, width: 3 });
+         let origin = Point { class: \"o\", x: 0 };
+         let class = origin.class;",
+    );
+
+    assert_eq!(
+        code,
+        "function __str(value, nested = false) {
+    switch (typeof value) {
+        case \"undefined\":
+            return \"()\";
+        case \"number\":
+            if (value !== value) {
+                return \"NaN\";
+            } else if (value === Infinity) {
+                return \"inf\";
+            } else if (value === -Infinity) {
+                return \"-inf\";
+            }
+            return String(value);
+        case \"string\":
+            return nested ? `\"${value.replace(/[\\\\\"]/g, \"\\\\$&\")}\"` : value;
+        case \"function\":
+            return \"<fn>\";
+        case \"object\":
+            break;
+        default:
+            return String(value);
+    }
+    if (Array.isArray(value)) {
+        return `[${value.map((element) => __str(element, true)).join(\", \")}]`;
+    }
+    const variants = value.constructor.__variants;
+    const name = variants ? value.tag : value.constructor.name;
+    const fields = variants ? variants[value.tag] : Object.keys(value);
+    const texts = fields.map((field) => __str(value[field], true));
+    if (fields.length === 0) {
+        return name;
+    } else if (fields[0] === \"_0\") {
+        return `${name}(${texts.join(\", \")})`;
+    }
+    return `${name} { ${fields.map((field, i) => `${field}: ${texts[i]}`).join(\", \")} }`;
+}
+
+export class Point {
+    constructor(x, class_) {
+        this.x = x;
+        this.class_ = class_;
+    }
+}
+
+class Shape {
+    static __variants = { \"Empty\": [], \"Circle\": [\"_0\"], \"Rect\": [\"width\", \"height\"] };
+
+    constructor(tag, fields) {
+        this.tag = tag;
+        Object.assign(this, fields);
+    }
+}
+
+export function sub(a, b) {
+    let c = a;
+    return c - b;
+}
+
+const a = 2.0;
+const this_1 = a;
+
+const shapes = console.log(\"%s %s %s\", __str(new Shape(\"Empty\")), __str(new Shape(\"Circle\", { _0: 1.0 })), __str(new Shape(\"Rect\", { height: 2.0, width: 3.0 })));
+
+const origin = new Point(0.0, \"o\");
+
+const class_1 = origin.class_;
+"
+    )
+}
+
+#[test]
+fn test_js_operators() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &JsBackend,
+        "fn half(x: int) -> int { x / 2 }
+         fn main() -> () {
+             print(1 / 0, -1 / 0, 7 % 0, 0.1 + 0.2, 8 / 4 / 2, half(7) == 3.5);
+             print(1 << 31, 1 << 32, -7 >> 1, 0 - 1 >> 31, half(11) & 7, half(-11) | 0);
+             print(-(1 - 4) * 2, -(-1.5), 7 % 3 != 1, -7 % 3, (6 & 3 | 8 ^ 1) << 2 >> 1);
+             print(
+                 !(1 == 2) == (3 == 3),
+                 2 < 3 && 3 <= 2 || 4 >= 4,
+                 1 == 2 && { let a = print(\"skipped\"); 1 == 1 },
+                 1 == 2 || { let a = 5; a > 4 }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "inf -inf NaN 0.30000000000000004 1 true\n-2147483648 1 -4 -1 5 -5\n6 1.5 false -1 22\ntrue true false true\n");
+    }
+}
+
+#[test]
+fn test_js_equality() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &JsBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn main() -> () {
+             print(
+                 Shape::Circle(1) == Shape::Circle(1),
+                 Shape::Empty != Shape::Empty,
+                 Point::new(1, 2) == Point::new(1, 3),
+                 (1, [2, 3]) == (1, [2, 3]),
+                 Shape::Rect { width: 1, height: 2 } == Shape::Rect { height: 2, width: 1 }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "true false false true true\n");
+    }
+}
+
+#[test]
+fn test_js_control_flow() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &JsBackend,
+        "fn first_over(n: int, limit: int) -> int {
+             for i in 0..n { if i * i > limit { return i; } }
+             0 - 1
+         }
+         fn main() -> () {
+             print(first_over(10, 20), first_over(3, 20), { let x = 3; x + 4 });
+             print(
+                 if 1 > 2 { \"a\" } else if 2 > 1 { let b = \"b\"; b } else { \"c\" },
+                 'outer: loop {
+                     while 2 > 1 { let x = if 3 > 2 { break 'outer 30 } else { 1 }; x }
+                 },
+                 'counting: loop {
+                     loop { let x = if 1 > 2 { continue 'counting } else { break 'counting 7 }; x }
+                 },
+                 match 2 { 1 => \"one\", n if n > 1 => \"many\", _ => \"none\" }
+             );
+             for i in 1..=2 { print(\"i\", i) }
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "5 -1 7\nb 30 7 many\ni 1\ni 2\n");
+    }
+}
+
+#[test]
+fn test_js_structs() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &JsBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         fn moved(p: Point) -> int { let mut q = p; q.x = q.x + 1; q.x * q.y }
+         fn main() -> () {
+             print(
+                 moved(Point { y: 3, x: 1 }),
+                 Point::new(1, 2).shift(2).dot(Point::new(1, 1)),
+                 Point::dot(Point::new(2, 0), Point::new(3, 0)),
+                 { let Point { x, y } = Point::new(3, 4); x * y }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "6 5 6 12\n");
+    }
+}
+
+#[test]
+fn test_js_enums() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &JsBackend,
+        "enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn area(shape: Shape) -> int {
+             match shape {
+                 Shape::Empty => 0,
+                 Shape::Circle(r) if r < 0 => 0,
+                 Shape::Circle(r) => r * r * 3,
+                 Shape::Rect { width, height } => width * height,
+             }
+         }
+         impl Shape { fn doubled(self) -> int { area(self) * 2 } }
+         fn main() -> () {
+             print(area(Shape::Circle(2)), area(Shape::Circle(-1)), area(Shape::Rect { width: 2, height: 3 }));
+             print(area(Shape::Empty), Shape::Circle(1).doubled());
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "12 0 6\n0 6\n");
+    }
+}
+
+#[test]
+fn test_js_tuples_and_arrays() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &JsBackend,
+        "fn swap(pair: (int, int)) -> (int, int) { let (a, b) = pair; (b, a) }
+         fn main() -> () {
+             print(swap((1, 2)).0, { let xs = [[1], [2, 3]]; xs[1][0 + 1] }, [10, 20, 30][1]);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "2 3 20\n");
+    }
+}
+
+#[test]
+fn test_js_closures() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &JsBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         fn apply(f: fn(int) -> int, x: int) -> int { f(x) }
+         fn counted(n: int) -> int {
+             let mut count = 0;
+             let tick = |by| { count += by; count };
+             let mut xs = [0, 0];
+             for i in 0..n { xs[i % 2] += tick(i); () }
+             xs[0] * 100 + xs[1]
+         }
+         fn main() -> () {
+             print(
+                 { let k = 10; let add = |a: int, b| a + b + k; apply(|x| add(x, x), 3) },
+                 Point::new(3, 4).scaled(),
+                 counted(4)
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "16 12 307\n");
+    }
+}
+
+#[test]
+fn test_js_generics() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &JsBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn id<T>(x: T) -> T { x }
+         struct Pair<A, B> { first: A, second: B }
+         fn first<A, B>(pair: Pair<A, B>) -> A { pair.first }
+         trait Area { fn area(self) -> int; }
+         impl Area for Point { fn area(self) -> int { self.x * self.y } }
+         impl Area for Shape {
+             fn area(self) -> int { match self { Shape::Circle(r) => r * r * 3, _ => 0 } }
+         }
+         fn total<T: Area>(a: T, b: dyn Area) -> int { a.area() + b.area() }
+         fn main() -> () {
+             print(id(first(Pair { first: 8, second: \"b\" })), total(Point::new(2, 3), Shape::Circle(1)));
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "8 9\n");
+    }
+}
+
+#[test]
+fn test_js_names() {
+    use crate::writer::compile_source;
+
+    // names of the runtime that the program uses are renamed
+    let code = compile_source(
+        &JsBackend,
+        "let Number = 1;
+         let RangeError = 2;
+         fn main() -> () {
+             print([10, 20, 30][Number + RangeError - 2]);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "20\n");
+    }
+}
+
+#[test]
+fn test_js_copies() {
+    use crate::writer::compile_source;
+
+    // changing a copy is never seen through the binding it was copied from
+    let code = compile_source(
+        &JsBackend,
+        "struct P { x: int, ys: [int] }
+         fn main() -> () {
+             let xs = [1, 2];
+             let mut ys = xs;
+             ys[0] = 9;
+             let mut p = P { x: 1, ys };
+             let q = p;
+             p.x = 5;
+             p.ys[0] = 6;
+             print(xs[0], ys[0], q.x, q.ys[0], p.x, p.ys[0]);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(output, "1 9 1 9 5 6\n");
+    }
+}
+
+#[test]
+fn test_js_print() {
+    use crate::writer::compile_source;
+
+    // the format of `print` is the same on every backend
+    let code = compile_source(
+        &JsBackend,
+        "struct Point { x: int, name: String }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn main() -> () {
+             print(0.000001, 1 / 10000000, 0 - 25 / 1000000000, 12345678901234567);
+             print(100000000000 * 10000000000, 0.1 + 0.2, -2.5, 100, 1 / 3, 0 / 0, -1 / 0);
+             print(Point { name: \"a\\\"b\", x: 1 }, Shape::Empty, Shape::Circle(2));
+             print(Shape::Rect { height: 3, width: 2 }, \"top\", 1 > 0, ());
+             print([[1, 2]], (1, \"a\", ()), || 1);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("node", "mjs", &code) {
+        assert_eq!(
+            output,
+            "0.000001 1e-7 -2.5e-8 12345678901234568\n1e+21 0.30000000000000004 -2.5 100 0.3333333333333333 NaN -inf\nPoint { x: 1, name: \"a\\\"b\" } Empty Circle(2)\nRect { width: 2, height: 3 } top true ()\n[[1, 2]] [1, \"a\", ()] <fn>\n"
+        );
+    }
+}
@@ -0,0 +1,1290 @@
+//! Lua 5.4 backend emitting a module.
+
+use crate::{
+    ir::{
+        BinOp, Builtin, Closure, Enum, Expr, Function, Global, Item, Jumps, LowerOptions, Method,
+        Program, Stmt, Struct, Type, TypedName, UnaryOp,
+    },
+    quote, CodeWriter, IrBackend, Output, OutputFile,
+};
+
+const RESERVED: &[&str] = &[
+    // keywords
+    "and",
+    "break",
+    "do",
+    "else",
+    "elseif",
+    "end",
+    "false",
+    "for",
+    "function",
+    "goto",
+    "if",
+    "in",
+    "local",
+    "nil",
+    "not",
+    "or",
+    "repeat",
+    "return",
+    "then",
+    "true",
+    "until",
+    "while",
+    // globals used by the generated code
+    "_ENV",
+    "_G",
+    "__add",
+    "__at",
+    "__copy",
+    "__equals",
+    "__fields",
+    "__int32",
+    "__name",
+    "__number",
+    "__set",
+    "__str",
+    "__unit",
+    "__variants",
+    "debug",
+    "error",
+    "getmetatable",
+    "ipairs",
+    "math",
+    "pairs",
+    "pcall",
+    "print",
+    "setmetatable",
+    "string",
+    "table",
+    "tonumber",
+    "tostring",
+    "type",
+];
+
+/// The unit value, a table can't hold `nil` as an element.
+const UNIT_HELPER: &str = "local __unit = {}";
+
+/// Adds numbers and concatenates strings, used if the operands of `+` could be either.
+const ADD_HELPER: &str = "local function __add(a, b)
+    if type(a) == \"string\" then
+        return a .. b
+    end
+    return a + b
+end";
+
+/// Reads an element of an array, which starts at 1 in the table.
+const AT_HELPER: &str = "local function __at(values, index)
+    if index % 1 ~= 0 or index < 0 or index >= #values then
+        error(\"index \" .. tostring(index) .. \" is out of bounds for length \" .. #values, 2)
+    end
+    return values[index + 1]
+end";
+
+const SET_HELPER: &str = "local function __set(values, index, value)
+    __at(values, index)
+    values[index + 1] = value
+end";
+
+/// Implements [`Expr::Copy`], tables keep their metatable.
+const COPY_HELPER: &str = "local function __copy(value)
+    if type(value) ~= \"table\" or value == __unit then
+        return value
+    end
+    local copied = {}
+    for key, element in pairs(value) do
+        copied[key] = __copy(element)
+    end
+    return setmetatable(copied, getmetatable(value))
+end";
+
+/// Compares tables by their metatable and fields, `==` only checks whether they are the same.
+const EQUALS_HELPER: &str = "local function __equals(a, b)
+    if a == b then
+        return true
+    elseif type(a) ~= \"table\" or type(b) ~= \"table\" or getmetatable(a) ~= getmetatable(b) then
+        return false
+    end
+    for key, value in pairs(a) do
+        if not __equals(value, b[key]) then
+            return false
+        end
+    end
+    for key in pairs(b) do
+        if a[key] == nil then
+            return false
+        end
+    end
+    return true
+end";
+
+/// Converts a number to the 32 bit integer the bitwise [`BinOp`]s work on.
+const INT32_HELPER: &str = "local function __int32(value)
+    if math.type(value) == \"float\" then
+        if value ~= value or value == math.huge or value == -math.huge then
+            return 0
+        end
+        value = math.fmod(value, 2^32)
+        value = value < 0 and math.ceil(value) or math.floor(value)
+    end
+    return (value & 0xFFFFFFFF) - ((value & 0x80000000) << 1)
+end";
+
+/// Formats a value the way [`Builtin::Print`] writes it, `nested` values quote their strings.
+///
+/// `tostring` would only keep 14 digits of a float. The fields of a struct are in the `__fields`
+/// of its metatable, the ones of a variant in the `__variants` of its enum.
+const STR_HELPER: &str = "local function __number(value)
+    if value ~= value then
+        return \"NaN\"
+    elseif value == math.huge then
+        return \"inf\"
+    elseif value == -math.huge then
+        return \"-inf\"
+    elseif value == 0 then
+        return \"0\"
+    end
+    local text
+    for precision = 0, 16 do
+        text = string.format(\"%.\" .. precision .. \"e\", value)
+        if tonumber(text) == value then
+            break
+        end
+    end
+    local sign, first, rest, exponent = text:match(\"^(-?)(%d)%.?(%d*)e([-+]%d+)$\")
+    local digits = (first .. rest):gsub(\"0+$\", \"\")
+    -- the position of the decimal point after the first digit
+    local point = tonumber(exponent) + 1
+    if #digits <= point and point <= 21 then
+        return sign .. digits .. string.rep(\"0\", point - #digits)
+    elseif 0 < point and point <= 21 then
+        return sign .. digits:sub(1, point) .. \".\" .. digits:sub(point + 1)
+    elseif -6 < point and point <= 0 then
+        return sign .. \"0.\" .. string.rep(\"0\", -point) .. digits
+    end
+    local mantissa = digits:sub(1, 1) .. (#digits > 1 and \".\" .. digits:sub(2) or \"\")
+    return sign .. mantissa .. (point > 1 and \"e+\" or \"e-\") .. string.format(\"%d\", math.abs(point - 1))
+end
+
+local function __str(value, nested)
+    if value == __unit then
+        return \"()\"
+    elseif type(value) == \"number\" then
+        return __number(value)
+    elseif type(value) == \"string\" then
+        return nested and \"\\\"\" .. value:gsub(\"[\\\\\\\"]\", \"\\\\%0\") .. \"\\\"\" or value
+    elseif type(value) == \"function\" then
+        return \"<fn>\"
+    elseif type(value) ~= \"table\" then
+        return tostring(value)
+    end
+    local texts = {}
+    local class = getmetatable(value)
+    if class == nil then
+        for i, element in ipairs(value) do
+            texts[i] = __str(element, true)
+        end
+        return \"[\" .. table.concat(texts, \", \") .. \"]\"
+    end
+    local name, fields = class.__name, class.__fields
+    if class.__variants then
+        name, fields = value.tag, class.__variants[value.tag]
+    end
+    for i, field in ipairs(fields) do
+        texts[i] = __str(value[field], true)
+    end
+    if #fields == 0 then
+        return name
+    elseif fields[1] == \"_0\" then
+        return name .. \"(\" .. table.concat(texts, \", \") .. \")\"
+    end
+    for i, field in ipairs(fields) do
+        texts[i] = field .. \": \" .. texts[i]
+    end
+    return name .. \" { \" .. table.concat(texts, \", \") .. \" }\"
+end";
+
+pub struct LuaBackend;
+
+impl IrBackend for LuaBackend {
+    fn name(&self) -> &str {
+        "lua"
+    }
+
+    fn lower_options(&self) -> LowerOptions<'_> {
+        LowerOptions {
+            reserved: RESERVED,
+            ..LowerOptions::default()
+        }
+    }
+
+    fn emit(&self, module: &str, program: &Program) -> Output {
+        Output {
+            files: vec![OutputFile {
+                path: format!("{module}.lua").into(),
+                content: LuaEmitter::new().emit(program),
+            }],
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+struct LuaEmitter {
+    writer: CodeWriter,
+    uses_unit_helper: bool,
+    uses_add_helper: bool,
+    uses_at_helper: bool,
+    uses_set_helper: bool,
+    uses_copy_helper: bool,
+    uses_equals_helper: bool,
+    uses_int32_helper: bool,
+    uses_str_helper: bool,
+    /// The labels of the loops around the current statement.
+    loops: Vec<String>,
+}
+
+impl LuaEmitter {
+    fn new() -> Self {
+        Self {
+            writer: CodeWriter::new("    "),
+            uses_unit_helper: false,
+            uses_add_helper: false,
+            uses_at_helper: false,
+            uses_set_helper: false,
+            uses_copy_helper: false,
+            uses_equals_helper: false,
+            uses_int32_helper: false,
+            uses_str_helper: false,
+            loops: Vec::new(),
+        }
+    }
+
+    fn emit(mut self, program: &Program) -> String {
+        for item in &program.items {
+            match item {
+                Item::Struct(r#struct) => self.r#struct(r#struct),
+                Item::Enum(r#enum) => self.r#enum(r#enum),
+                Item::Fn(function) => self.function(function),
+                Item::Global(global) => self.global(global),
+            }
+        }
+
+        let has_main = program.items.iter().any(|item| match item {
+            Item::Fn(function) => function.name == "main" && function.params.is_empty(),
+            _ => false,
+        });
+        if has_main {
+            self.writer.blank_line();
+            // there is no caller at level 4 if this file is run directly instead of `require`d
+            self.writer.line("if not pcall(debug.getlocal, 4, 1) then");
+            self.writer.indent();
+            self.writer.line("main()");
+            self.writer.dedent();
+            self.writer.line("end");
+        }
+
+        self.writer.blank_line();
+        let exports: Vec<_> = program
+            .items
+            .iter()
+            .filter(|item| item_is_pub(item))
+            .map(item_name)
+            .collect();
+        if exports.is_empty() {
+            self.writer.line("return {}");
+        } else {
+            self.writer.line("return {");
+            self.writer.indent();
+            for name in exports {
+                self.writer.line(format!("{name} = {name},"));
+            }
+            self.writer.dedent();
+            self.writer.line("}");
+        }
+
+        let mut header = CodeWriter::new("    ");
+        let names: Vec<_> = program.items.iter().map(item_name).collect();
+        if !names.is_empty() {
+            // declaring every item upfront allows them to refer to each other in any order
+            header.line(format!("local {}", names.join(", ")));
+        }
+        // the unit value is compared against by other helpers
+        let uses_unit_helper =
+            self.uses_unit_helper || self.uses_copy_helper || self.uses_str_helper;
+        let helpers = [
+            (uses_unit_helper, UNIT_HELPER),
+            (self.uses_add_helper, ADD_HELPER),
+            (self.uses_at_helper, AT_HELPER),
+            (self.uses_set_helper, SET_HELPER),
+            (self.uses_copy_helper, COPY_HELPER),
+            (self.uses_equals_helper, EQUALS_HELPER),
+            (self.uses_int32_helper, INT32_HELPER),
+            (self.uses_str_helper, STR_HELPER),
+        ];
+        for (is_used, helper) in helpers {
+            if is_used {
+                header.blank_line();
+                for line in helper.lines() {
+                    header.line(line);
+                }
+            }
+        }
+        header.blank_line();
+
+        header.finish() + &self.writer.finish()
+    }
+
+    /// Every instance is a table with the struct as its metatable,
+    /// which has the names of its fields in `__fields`.
+    fn r#struct(&mut self, r#struct: &Struct) {
+        let name = &r#struct.name;
+        let fields = field_names(&r#struct.fields);
+        self.metatable(
+            name,
+            &[
+                format!("{name}.__name = {}", quote(name)),
+                format!("{name}.__fields = {fields}"),
+            ],
+            &r#struct.methods,
+        );
+    }
+
+    /// Every variant is a table with the enum as its metatable that stores its name in `tag`,
+    /// `__variants` has the names of the fields of each one.
+    fn r#enum(&mut self, r#enum: &Enum) {
+        let name = &r#enum.name;
+        let variants: Vec<_> = r#enum
+            .variants
+            .iter()
+            .map(|variant| {
+                format!(
+                    "[{}] = {}",
+                    quote(&variant.name),
+                    field_names(&variant.fields)
+                )
+            })
+            .collect();
+        self.metatable(
+            name,
+            &[format!("{name}.__variants = {{ {} }}", variants.join(", "))],
+            &r#enum.methods,
+        );
+    }
+
+    /// Methods with a receiver are called with `:`, which passes it as `self`.
+    fn metatable(&mut self, name: &str, lines: &[String], methods: &[Method]) {
+        self.writer.blank_line();
+        self.writer.line(format!("{name} = {{}}"));
+        self.writer.line(format!("{name}.__index = {name}"));
+        for line in lines {
+            self.writer.line(line);
+        }
+
+        for method in methods {
+            let separator = if method.has_receiver { ':' } else { '.' };
+            self.writer.blank_line();
+            self.function_body(
+                &format!("{name}{separator}{}", method.function.name),
+                &method.function,
+            );
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.writer.blank_line();
+        self.function_body(&function.name, function);
+    }
+
+    fn function_body(&mut self, name: &str, function: &Function) {
+        let params: Vec<_> = function
+            .params
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect();
+
+        self.writer
+            .line(format!("function {name}({})", params.join(", ")));
+
+        self.writer.indent();
+        self.block(&function.body);
+        self.writer.dedent();
+        self.writer.line("end");
+    }
+
+    /// The captures of a closure are upvalues.
+    fn closure(&mut self, is_local: bool, name: &str, closure: &Closure) {
+        let declaration = if is_local { "local " } else { "" };
+        self.writer.line(format!(
+            "{declaration}function {name}({})",
+            closure.params.join(", ")
+        ));
+        self.writer.indent();
+        self.block(&closure.body);
+        self.writer.dedent();
+        self.writer.line("end");
+    }
+
+    fn global(&mut self, global: &Global) {
+        self.writer.blank_line();
+        self.block(&global.setup);
+        if let Expr::Closure(closure) = &global.value {
+            return self.closure(false, &global.name, closure);
+        }
+        let value = self.expr(&global.value);
+        self.writer.line(format!("{} = {value}", global.name));
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        for (i, statement) in statements.iter().enumerate() {
+            match statement {
+                Stmt::Let {
+                    name,
+                    value: Expr::Closure(closure),
+                    ..
+                } => self.closure(true, name, closure),
+                Stmt::Let {
+                    is_mut,
+                    name,
+                    value,
+                } => {
+                    let attribute = if *is_mut { "" } else { " <const>" };
+                    let value = self.expr(value);
+                    self.writer
+                        .line(format!("local {name}{attribute} = {value}"))
+                }
+                Stmt::Assign { name, value } => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("{name} = {value}"))
+                }
+                Stmt::AssignField {
+                    target,
+                    name,
+                    value,
+                } => {
+                    let target = self.unary_operand(target);
+                    let value = self.expr(value);
+                    self.writer.line(format!("{target}.{name} = {value}"))
+                }
+                Stmt::AssignIndex {
+                    target,
+                    index,
+                    value,
+                } => {
+                    self.uses_at_helper = true;
+                    self.uses_set_helper = true;
+                    let target = self.expr(target);
+                    let index = self.expr(index);
+                    let value = self.expr(value);
+                    self.writer
+                        .line(format!("__set({target}, {index}, {value})"))
+                }
+                Stmt::If {
+                    condition,
+                    then,
+                    r#else,
+                } => {
+                    let condition = self.expr(condition);
+                    self.writer.line(format!("if {condition} then"));
+                    self.writer.indent();
+                    self.block(then);
+                    self.writer.dedent();
+
+                    // `else { if ... }` is written as a single `else if`
+                    let mut r#else = r#else;
+                    while let [Stmt::If {
+                        condition,
+                        then,
+                        r#else: next,
+                    }] = r#else.as_slice()
+                    {
+                        let condition = self.expr(condition);
+                        self.writer.line(format!("elseif {condition} then"));
+                        self.writer.indent();
+                        self.block(then);
+                        self.writer.dedent();
+                        r#else = next;
+                    }
+                    if !r#else.is_empty() {
+                        self.writer.line("else");
+                        self.writer.indent();
+                        self.block(r#else);
+                        self.writer.dedent();
+                    }
+                    self.writer.line("end");
+                }
+                Stmt::Loop {
+                    label,
+                    condition,
+                    body,
+                } => {
+                    let condition = match condition {
+                        Some(condition) => self.expr(condition),
+                        None => "true".to_owned(),
+                    };
+                    self.r#loop(label, format!("while {condition} do"), body);
+                }
+                Stmt::ForRange {
+                    label,
+                    binding,
+                    start,
+                    end,
+                    is_inclusive,
+                    body,
+                } => {
+                    let start = self.expr(start);
+                    // the numeric `for` includes its limit
+                    let end = match (end, is_inclusive) {
+                        (end, true) => self.expr(end),
+                        (Expr::Num(num), false) => format!("{:?}", num - 1.0),
+                        (end, false) => format!("{} - 1", self.expr(end)),
+                    };
+                    self.r#loop(label, format!("for {binding} = {start}, {end} do"), body);
+                }
+                Stmt::ForEach {
+                    label,
+                    binding,
+                    iterable,
+                    body,
+                } => {
+                    let iterable = self.expr(iterable);
+                    self.r#loop(
+                        label,
+                        format!("for _, {binding} in ipairs({iterable}) do"),
+                        body,
+                    );
+                }
+                Stmt::Break { label } => match self.loops.last() {
+                    Some(innermost) if innermost == label => self.writer.line("break"),
+                    _ => self.writer.line(format!("goto {label}_break")),
+                },
+                Stmt::Continue { label } => self.writer.line(format!("goto {label}_continue")),
+                // calls are the only expressions that can be statements
+                Stmt::Expr(
+                    value @ (Expr::Call { .. }
+                    | Expr::MethodCall { .. }
+                    | Expr::AssociatedCall { .. }
+                    | Expr::Builtin { .. }),
+                ) => {
+                    let value = self.expr(value);
+                    // a parenthesized receiver would continue the previous line
+                    match value.starts_with('(') {
+                        true => self.writer.line(format!(";{value}")),
+                        false => self.writer.line(value),
+                    }
+                }
+                Stmt::Expr(value) => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("local _ = {value}"))
+                }
+                // `return` has to be the last statement of a block
+                Stmt::Return(value) if i + 1 < statements.len() => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("do return {value} end"))
+                }
+                Stmt::Return(value) => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("return {value}"))
+                }
+            }
+        }
+    }
+
+    /// Lua only has `break` for the innermost loop, other jumps use `goto`.
+    fn r#loop(&mut self, label: &str, header: String, body: &[Stmt]) {
+        let jumps = Jumps::to(label, body);
+
+        self.writer.line(header);
+        self.writer.indent();
+        self.loops.push(label.to_owned());
+        self.block(body);
+        self.loops.pop();
+        if jumps.r#continue {
+            self.writer.line(format!("::{label}_continue::"));
+        }
+        self.writer.dedent();
+        self.writer.line("end");
+        if jumps.nested_break {
+            self.writer.line(format!("::{label}_break::"));
+        }
+    }
+}
+
+fn item_name(item: &Item) -> &str {
+    match item {
+        Item::Fn(function) => &function.name,
+        Item::Struct(r#struct) => &r#struct.name,
+        Item::Enum(r#enum) => &r#enum.name,
+        Item::Global(global) => &global.name,
+    }
+}
+
+/// A table with the names of `fields` in order.
+fn field_names(fields: &[TypedName]) -> String {
+    let names: Vec<_> = fields.iter().map(|field| quote(&field.name)).collect();
+    match names.is_empty() {
+        true => "{}".to_owned(),
+        false => format!("{{ {} }}", names.join(", ")),
+    }
+}
+
+fn item_is_pub(item: &Item) -> bool {
+    match item {
+        Item::Fn(function) => function.is_pub,
+        Item::Struct(r#struct) => r#struct.is_pub,
+        Item::Enum(r#enum) => r#enum.is_pub,
+        Item::Global(global) => global.is_pub,
+    }
+}
+
+/// Whether `expr` always evaluates to a number.
+fn is_numeric(expr: &Expr) -> bool {
+    match expr {
+        Expr::Num(_) => true,
+        Expr::Unary {
+            op: UnaryOp::Neg, ..
+        } => true,
+        Expr::Bin {
+            op:
+                BinOp::BitOr
+                | BinOp::BitXor
+                | BinOp::BitAnd
+                | BinOp::Shl
+                | BinOp::Shr
+                | BinOp::Sub
+                | BinOp::Mul
+                | BinOp::Div
+                | BinOp::Rem,
+            ..
+        } => true,
+        Expr::Bin {
+            op: BinOp::Add,
+            left,
+            right,
+            ..
+        } => is_numeric(left) || is_numeric(right),
+        _ => false,
+    }
+}
+
+fn precedence(op: BinOp) -> u8 {
+    match op {
+        BinOp::Or => 1,
+        BinOp::And => 2,
+        BinOp::Equals
+        | BinOp::NotEquals
+        | BinOp::Less
+        | BinOp::LessEquals
+        | BinOp::Greater
+        | BinOp::GreaterEquals => 3,
+        BinOp::BitOr => 4,
+        BinOp::BitXor => 5,
+        BinOp::BitAnd => 6,
+        BinOp::Shl | BinOp::Shr => 7,
+        BinOp::Add | BinOp::Sub => 8,
+        BinOp::Mul | BinOp::Div | BinOp::Rem => 9,
+    }
+}
+
+impl LuaEmitter {
+    fn expr(&mut self, expr: &Expr) -> String {
+        match expr {
+            // float literals keep every number a float, just like in erebus
+            Expr::Unit => {
+                self.uses_unit_helper = true;
+                "__unit".to_owned()
+            }
+            Expr::Bool(bool) => bool.to_string(),
+            Expr::Num(num) => format!("{num:?}"),
+            Expr::Str(str) => quote(str),
+            Expr::Var(name) => name.clone(),
+
+            Expr::Unary { op, operand } => {
+                let op_str = match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::Not => "not ",
+                };
+                format!("{op_str}{}", self.unary_operand(operand))
+            }
+            Expr::Bin {
+                op: BinOp::Add,
+                left,
+                right,
+                ..
+            } if !is_numeric(left) && !is_numeric(right) => {
+                self.uses_add_helper = true;
+                format!("__add({}, {})", self.expr(left), self.expr(right))
+            }
+            // `%` would round the quotient towards negative infinity
+            Expr::Bin {
+                op: BinOp::Rem,
+                left,
+                right,
+                ..
+            } => format!("math.fmod({}, {})", self.expr(left), self.expr(right)),
+            // `==` would compare tables by their identity
+            Expr::Bin {
+                op: op @ (BinOp::Equals | BinOp::NotEquals),
+                left,
+                right,
+                operand_type,
+            } if !operand_type.as_ref().is_some_and(Type::is_primitive) => {
+                self.uses_equals_helper = true;
+                let negation = if *op == BinOp::NotEquals { "not " } else { "" };
+                format!(
+                    "{negation}__equals({}, {})",
+                    self.expr(left),
+                    self.expr(right)
+                )
+            }
+            // the bitwise operators don't accept floats and work on 64 bit integers,
+            // adding `0.0` turns the result back into a float
+            Expr::Bin {
+                op: op @ (BinOp::BitOr | BinOp::BitXor | BinOp::BitAnd | BinOp::Shl | BinOp::Shr),
+                left,
+                right,
+                ..
+            } => {
+                self.uses_int32_helper = true;
+                let (left, right) = (self.expr(left), self.expr(right));
+                match op {
+                    BinOp::Shl => {
+                        format!("__int32(__int32({left}) << (__int32({right}) & 31)) + 0.0")
+                    }
+                    // `>>` would shift in zeros instead of keeping the sign
+                    BinOp::Shr => {
+                        format!("__int32({left}) // (1 << (__int32({right}) & 31)) + 0.0")
+                    }
+                    BinOp::BitOr => format!("(__int32({left}) | __int32({right})) + 0.0"),
+                    BinOp::BitXor => format!("(__int32({left}) ~ __int32({right})) + 0.0"),
+                    _ => format!("(__int32({left}) & __int32({right})) + 0.0"),
+                }
+            }
+            Expr::Bin {
+                op, left, right, ..
+            } => {
+                let op_str = match op {
+                    BinOp::Or => "or",
+                    BinOp::And => "and",
+                    BinOp::Equals => "==",
+                    BinOp::NotEquals => "~=",
+                    BinOp::Less => "<",
+                    BinOp::LessEquals => "<=",
+                    BinOp::Greater => ">",
+                    BinOp::GreaterEquals => ">=",
+                    BinOp::BitOr | BinOp::BitXor | BinOp::BitAnd | BinOp::Shl | BinOp::Shr => {
+                        unreachable!("bitwise operators are emitted with `__int32`")
+                    }
+                    BinOp::Add => "+",
+                    BinOp::Sub => "-",
+                    BinOp::Mul => "*",
+                    BinOp::Div => "/",
+                    BinOp::Rem => unreachable!("`%` is emitted as `math.fmod`"),
+                };
+                format!(
+                    "{} {op_str} {}",
+                    self.operand(*op, left, false),
+                    self.operand(*op, right, true)
+                )
+            }
+            Expr::Call { callee, args } => format!("{callee}({})", self.args_list(args)),
+            Expr::MethodCall {
+                receiver,
+                method,
+                args,
+            } => format!(
+                "{}:{method}({})",
+                self.unary_operand(receiver),
+                self.args_list(args)
+            ),
+            Expr::AssociatedCall {
+                r#type,
+                function,
+                args,
+            } => format!("{type}.{function}({})", self.args_list(args)),
+            // methods are looked up on the receiver instead
+            Expr::TraitCall { .. } => unreachable!("only lowered with `dictionary_passing`"),
+            Expr::Builtin {
+                builtin: Builtin::Print,
+                args,
+            } => {
+                // `print` would separate its arguments with tabs
+                let args: Vec<_> = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Expr::Str(_) => self.expr(arg),
+                        arg => {
+                            self.uses_str_helper = true;
+                            format!("__str({})", self.expr(arg))
+                        }
+                    })
+                    .collect();
+                match args.is_empty() {
+                    true => "print()".to_owned(),
+                    false => format!("print({})", args.join(" .. \" \" .. ")),
+                }
+            }
+            // `new` takes the fields in declaration order
+            Expr::Tuple(elements) | Expr::Array(elements) => {
+                format!("{{ {} }}", self.args_list(elements))
+            }
+            Expr::Copy(value) => {
+                self.uses_copy_helper = true;
+                format!("__copy({})", self.expr(value))
+            }
+            Expr::Closure(_) => unreachable!("closures are only bound by a `let`"),
+            Expr::Struct { name, fields } => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|field| format!("{} = {}", field.name, self.expr(&field.value)))
+                    .collect();
+                format!("setmetatable({{ {} }}, {name})", fields.join(", "))
+            }
+            Expr::Variant {
+                r#enum,
+                variant,
+                fields,
+            } => {
+                let fields: String = fields
+                    .iter()
+                    .map(|field| format!(", {} = {}", field.name, self.expr(&field.value)))
+                    .collect();
+                format!(
+                    "setmetatable({{ tag = {}{fields} }}, {enum})",
+                    quote(variant)
+                )
+            }
+            Expr::Field { value, name } => format!("{}.{name}", self.unary_operand(value)),
+            // tables start at 1
+            Expr::TupleField { value, index } => {
+                format!("{}[{}]", self.unary_operand(value), index + 1)
+            }
+            Expr::Index { value, index } => {
+                self.uses_at_helper = true;
+                format!("__at({}, {})", self.expr(value), self.expr(index))
+            }
+            Expr::IsVariant { value, variant, .. } => {
+                format!("{}.tag == {}", self.unary_operand(value), quote(variant))
+            }
+        }
+    }
+
+    fn operand(&mut self, parent: BinOp, operand: &Expr, is_right: bool) -> String {
+        let code = self.expr(operand);
+
+        let op = match operand {
+            Expr::Bin { op, .. } => *op,
+            Expr::IsVariant { .. } => BinOp::Equals,
+            _ => return code,
+        };
+        if precedence(op) < precedence(parent) || precedence(op) == precedence(parent) && is_right {
+            format!("({code})")
+        } else {
+            code
+        }
+    }
+
+    fn unary_operand(&mut self, operand: &Expr) -> String {
+        let code = self.expr(operand);
+
+        match operand {
+            // nested operators are parenthesized so `-(-x)` doesn't become the comment `--x`
+            Expr::Unary { .. } | Expr::Bin { .. } | Expr::IsVariant { .. } => format!("({code})"),
+            // a table constructor can't be indexed directly
+            Expr::Tuple(_) | Expr::Array(_) => format!("({code})"),
+            _ => code,
+        }
+    }
+
+    fn args_list(&mut self, args: &[Expr]) -> String {
+        let args: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+        args.join(", ")
+    }
+}
+
+#[test]
+fn test_lua_emit() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &LuaBackend,
+        "pub struct Point { x: int, end: String }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn even(n: int) -> int { odd(n - 1) }
+         pub fn odd(n: int) -> int { even(n) / 2 }
+         let local = { let mut a = \"a\"; a + a };
+         let shape = Shape::Rect { height: 2, width: 3 };
+         let origin = Point { end: \"o\", x: 0 };
+         let tail = origin.end;
+         impl Point { fn at(x: int) -> Self { Self { x, end: \"e\" } } fn far(self, d: int) -> int { self.x + d } }
+         let far = Point::at(2).far(1);",
+    );
+
+    assert_eq!(
+        code,
+        "local Point, Shape, even, odd, local_1, shape, origin, tail, far
+
+local function __add(a, b)
+    if type(a) == \"string\" then
+        return a .. b
+    end
+    return a + b
+end
+
+Point = {}
+Point.__index = Point
+Point.__name = \"Point\"
+Point.__fields = { \"x\", \"end_\" }
+
+function Point.at(x)
+    return setmetatable({ x = x, end_ = \"e\" }, Point)
+end
+
+function Point:far(d)
+    return __add(self.x, d)
+end
+
+Shape = {}
+Shape.__index = Shape
+Shape.__variants = { [\"Empty\"] = {}, [\"Circle\"] = { \"_0\" }, [\"Rect\"] = { \"width\", \"height\" } }
+
+function even(n)
+    return odd(n - 1.0)
+end
+
+function odd(n)
+    return even(n) / 2.0
+end
+
+local a = \"a\"
+local_1 = __add(a, a)
+
+shape = setmetatable({ tag = \"Rect\", height = 2.0, width = 3.0 }, Shape)
+
+origin = setmetatable({ x = 0.0, end_ = \"o\" }, Point)
+
+tail = origin.end_
+
+far = Point.at(2.0):far(1.0)
+
+return {
+    Point = Point,
+    odd = odd,
+}
+"
+    )
+}
+
+#[test]
+fn test_lua_operators() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &LuaBackend,
+        "fn half(x: int) -> int { x / 2 }
+         fn main() -> () {
+             print(1 / 0, -1 / 0, 7 % 0, 0.1 + 0.2, 8 / 4 / 2, half(7) == 3.5);
+             print(1 << 31, 1 << 32, -7 >> 1, 0 - 1 >> 31, half(11) & 7, half(-11) | 0);
+             print(-(1 - 4) * 2, -(-1.5), 7 % 3 != 1, -7 % 3, (6 & 3 | 8 ^ 1) << 2 >> 1);
+             print(
+                 !(1 == 2) == (3 == 3),
+                 2 < 3 && 3 <= 2 || 4 >= 4,
+                 1 == 2 && { let a = print(\"skipped\"); 1 == 1 },
+                 1 == 2 || { let a = 5; a > 4 }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "inf -inf NaN 0.30000000000000004 1 true\n-2147483648 1 -4 -1 5 -5\n6 1.5 false -1 22\ntrue true false true\n");
+    }
+}
+
+#[test]
+fn test_lua_equality() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &LuaBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn main() -> () {
+             print(
+                 Shape::Circle(1) == Shape::Circle(1),
+                 Shape::Empty != Shape::Empty,
+                 Point::new(1, 2) == Point::new(1, 3),
+                 (1, [2, 3]) == (1, [2, 3]),
+                 Shape::Rect { width: 1, height: 2 } == Shape::Rect { height: 2, width: 1 }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "true false false true true\n");
+    }
+}
+
+#[test]
+fn test_lua_control_flow() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &LuaBackend,
+        "fn first_over(n: int, limit: int) -> int {
+             for i in 0..n { if i * i > limit { return i; } }
+             0 - 1
+         }
+         fn main() -> () {
+             print(first_over(10, 20), first_over(3, 20), { let x = 3; x + 4 });
+             print(
+                 if 1 > 2 { \"a\" } else if 2 > 1 { let b = \"b\"; b } else { \"c\" },
+                 'outer: loop {
+                     while 2 > 1 { let x = if 3 > 2 { break 'outer 30 } else { 1 }; x }
+                 },
+                 'counting: loop {
+                     loop { let x = if 1 > 2 { continue 'counting } else { break 'counting 7 }; x }
+                 },
+                 match 2 { 1 => \"one\", n if n > 1 => \"many\", _ => \"none\" }
+             );
+             for i in 1..=2 { print(\"i\", i) }
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "5 -1 7\nb 30 7 many\ni 1\ni 2\n");
+    }
+}
+
+#[test]
+fn test_lua_structs() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &LuaBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         fn moved(p: Point) -> int { let mut q = p; q.x = q.x + 1; q.x * q.y }
+         fn main() -> () {
+             print(
+                 moved(Point { y: 3, x: 1 }),
+                 Point::new(1, 2).shift(2).dot(Point::new(1, 1)),
+                 Point::dot(Point::new(2, 0), Point::new(3, 0)),
+                 { let Point { x, y } = Point::new(3, 4); x * y }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "6 5 6 12\n");
+    }
+}
+
+#[test]
+fn test_lua_enums() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &LuaBackend,
+        "enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn area(shape: Shape) -> int {
+             match shape {
+                 Shape::Empty => 0,
+                 Shape::Circle(r) if r < 0 => 0,
+                 Shape::Circle(r) => r * r * 3,
+                 Shape::Rect { width, height } => width * height,
+             }
+         }
+         impl Shape { fn doubled(self) -> int { area(self) * 2 } }
+         fn main() -> () {
+             print(area(Shape::Circle(2)), area(Shape::Circle(-1)), area(Shape::Rect { width: 2, height: 3 }));
+             print(area(Shape::Empty), Shape::Circle(1).doubled());
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "12 0 6\n0 6\n");
+    }
+}
+
+#[test]
+fn test_lua_tuples_and_arrays() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &LuaBackend,
+        "fn swap(pair: (int, int)) -> (int, int) { let (a, b) = pair; (b, a) }
+         fn main() -> () {
+             print(swap((1, 2)).0, { let xs = [[1], [2, 3]]; xs[1][0 + 1] }, [10, 20, 30][1]);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "2 3 20\n");
+    }
+}
+
+#[test]
+fn test_lua_closures() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &LuaBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         fn apply(f: fn(int) -> int, x: int) -> int { f(x) }
+         fn counted(n: int) -> int {
+             let mut count = 0;
+             let tick = |by| { count += by; count };
+             let mut xs = [0, 0];
+             for i in 0..n { xs[i % 2] += tick(i); () }
+             xs[0] * 100 + xs[1]
+         }
+         fn main() -> () {
+             print(
+                 { let k = 10; let add = |a: int, b| a + b + k; apply(|x| add(x, x), 3) },
+                 Point::new(3, 4).scaled(),
+                 counted(4)
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "16 12 307\n");
+    }
+}
+
+#[test]
+fn test_lua_generics() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &LuaBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn id<T>(x: T) -> T { x }
+         struct Pair<A, B> { first: A, second: B }
+         fn first<A, B>(pair: Pair<A, B>) -> A { pair.first }
+         trait Area { fn area(self) -> int; }
+         impl Area for Point { fn area(self) -> int { self.x * self.y } }
+         impl Area for Shape {
+             fn area(self) -> int { match self { Shape::Circle(r) => r * r * 3, _ => 0 } }
+         }
+         fn total<T: Area>(a: T, b: dyn Area) -> int { a.area() + b.area() }
+         fn main() -> () {
+             print(id(first(Pair { first: 8, second: \"b\" })), total(Point::new(2, 3), Shape::Circle(1)));
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "8 9\n");
+    }
+}
+
+#[test]
+fn test_lua_names() {
+    use crate::writer::compile_source;
+
+    // names of the runtime that the program uses are renamed
+    let code = compile_source(
+        &LuaBackend,
+        "let ipairs = 1;
+         fn main() -> () {
+             print({ let mut n = ipairs; for x in [1, 2, 3] { n += x; () } n }, 4 / 2);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "7 2\n");
+    }
+}
+
+#[test]
+fn test_lua_copies() {
+    use crate::writer::compile_source;
+
+    // changing a copy is never seen through the binding it was copied from
+    let code = compile_source(
+        &LuaBackend,
+        "struct P { x: int, ys: [int] }
+         fn main() -> () {
+             let xs = [1, 2];
+             let mut ys = xs;
+             ys[0] = 9;
+             let mut p = P { x: 1, ys };
+             let q = p;
+             p.x = 5;
+             p.ys[0] = 6;
+             print(xs[0], ys[0], q.x, q.ys[0], p.x, p.ys[0]);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "1 9 1 9 5 6\n");
+    }
+}
+
+#[test]
+fn test_lua_print() {
+    use crate::writer::compile_source;
+
+    // the format of `print` is the same on every backend
+    let code = compile_source(
+        &LuaBackend,
+        "struct Point { x: int, name: String }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn main() -> () {
+             print(0.000001, 1 / 10000000, 0 - 25 / 1000000000, 12345678901234567);
+             print(100000000000 * 10000000000, 0.1 + 0.2, -2.5, 100, 1 / 3, 0 / 0, -1 / 0);
+             print(Point { name: \"a\\\"b\", x: 1 }, Shape::Empty, Shape::Circle(2));
+             print(Shape::Rect { height: 3, width: 2 }, \"top\", 1 > 0, ());
+             print([[1, 2]], (1, \"a\", ()), || 1);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(
+            output,
+            "0.000001 1e-7 -2.5e-8 12345678901234568\n1e+21 0.30000000000000004 -2.5 100 0.3333333333333333 NaN -inf\nPoint { x: 1, name: \"a\\\"b\" } Empty Circle(2)\nRect { width: 2, height: 3 } top true ()\n[[1, 2]] [1, \"a\", ()] <fn>\n"
+        );
+    }
+}
+
+#[test]
+fn test_lua_unit() {
+    use crate::writer::compile_source;
+
+    // `nil` would leave holes in the array
+    let code = compile_source(
+        &LuaBackend,
+        "fn main() -> () {
+             let xs = [(), ()];
+             print(xs[1], xs == [(), ()], xs[0] == ());
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("lua", "lua", &code) {
+        assert_eq!(output, "() true true\n");
+    }
+}
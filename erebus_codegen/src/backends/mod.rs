@@ -0,0 +1,34 @@
+mod bash;
+mod external;
+mod js;
+mod lua;
+mod python;
+
+use erebus_parser::Ast;
+
+pub use bash::BashBackend;
+pub use external::ExternalBackend;
+pub use js::JsBackend;
+pub use lua::LuaBackend;
+pub use python::PythonBackend;
+
+use crate::{Backend, Output, OutputFile};
+
+/// Outputs the parsed [`Ast`], useful for debugging the parser.
+pub struct AstBackend;
+
+impl Backend for AstBackend {
+    fn name(&self) -> &str {
+        "ast"
+    }
+
+    fn compile(&self, module: &str, ast: &Ast) -> Output {
+        Output {
+            files: vec![OutputFile {
+                path: format!("{module}.ast").into(),
+                content: format!("Ast: {ast:#?}\n"),
+            }],
+            diagnostics: Vec::new(),
+        }
+    }
+}
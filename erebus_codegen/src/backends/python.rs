@@ -0,0 +1,1410 @@
+//! Python 3 backend.
+
+use std::collections::HashSet;
+
+use crate::{
+    ir::{
+        is_assigned, BinOp, Builtin, Closure, Enum, Expr, FieldValue, Function, Global, Item,
+        LowerOptions, Method, Program, Stmt, Struct, Type, TypedName, UnaryOp,
+    },
+    quote, CodeWriter, IrBackend, Output, OutputFile,
+};
+
+const RESERVED: &[&str] = &[
+    // keywords
+    "False",
+    "None",
+    "True",
+    "and",
+    "as",
+    "assert",
+    "async",
+    "await",
+    "break",
+    "class",
+    "continue",
+    "def",
+    "del",
+    "elif",
+    "else",
+    "except",
+    "finally",
+    "for",
+    "from",
+    "global",
+    "if",
+    "import",
+    "in",
+    "is",
+    "lambda",
+    "nonlocal",
+    "not",
+    "or",
+    "pass",
+    "raise",
+    "return",
+    "try",
+    "while",
+    "with",
+    "yield",
+    // names used by the generated code
+    "_at",
+    "_copy",
+    "_div",
+    "_int32",
+    "_number",
+    "_rem",
+    "_set",
+    "_str",
+    "IndexError",
+    "abs",
+    "annotations",
+    "bool",
+    "callable",
+    "dataclass",
+    "float",
+    "getattr",
+    "hasattr",
+    "int",
+    "isinstance",
+    "len",
+    "list",
+    "math",
+    "object",
+    "print",
+    "range",
+    "repr",
+    "setattr",
+    "str",
+    "tuple",
+    "type",
+];
+
+const FUTURE_IMPORT: &str = "from __future__ import annotations";
+
+/// Reads an element of a list, negative indices would count from the end otherwise.
+///
+/// A leading `__` would be mangled inside of classes.
+const AT_HELPER: &str = "def _at(values, index):
+    if index % 1 != 0 or not 0 <= index < len(values):
+        raise IndexError(f\"index {index} is out of bounds for length {len(values)}\")
+    return values[int(index)]";
+
+const SET_HELPER: &str = "def _set(values, index, value):
+    _at(values, index)
+    values[int(index)] = value";
+
+/// Implements [`Expr::Copy`], the elements of a list are copied one by one
+/// so that the copy doesn't share any of them.
+const COPY_HELPER: &str = "def _copy(value):
+    if isinstance(value, list):
+        return [_copy(element) for element in value]
+    if isinstance(value, tuple):
+        return tuple(_copy(element) for element in value)
+    if hasattr(value, \"__dataclass_fields__\"):
+        copied = object.__new__(type(value))
+        for name in value.__dataclass_fields__:
+            setattr(copied, name, _copy(getattr(value, name)))
+        return copied
+    return value";
+
+/// Divides like a float would in the other targets, instead of raising `ZeroDivisionError`.
+const DIV_HELPER: &str = "def _div(a, b):
+    if b != 0:
+        return a / b
+    if a == 0 or a != a:
+        return math.nan
+    return math.copysign(math.inf, a) * math.copysign(1, b)";
+
+/// `math.fmod` would raise `ValueError` instead of returning `nan`.
+const REM_HELPER: &str = "def _rem(a, b):
+    if b == 0 or a in (math.inf, -math.inf):
+        return math.nan
+    return math.fmod(a, b)";
+
+/// Converts a number to the 32 bit integer the bitwise [`BinOp`]s work on.
+const INT32_HELPER: &str = "def _int32(value):
+    if value != value or value in (math.inf, -math.inf):
+        return 0
+    value = int(value)
+    return (value & 0xFFFFFFFF) - ((value & 0x80000000) << 1)";
+
+/// Formats a value the way [`Builtin::Print`] writes it, `nested` values quote their strings.
+///
+/// `repr` has the fewest digits already, but uses an exponent from `1e16` on and below `1e-4`.
+const STR_HELPER: &str = "def _str(value, nested=False):
+    if value is None:
+        return \"()\"
+    if isinstance(value, bool):
+        return \"true\" if value else \"false\"
+    if isinstance(value, (int, float)):
+        return _number(float(value))
+    if isinstance(value, str):
+        if not nested:
+            return value
+        return '\"' + value.replace('\\\\', '\\\\\\\\').replace('\"', '\\\\\"') + '\"'
+    if isinstance(value, (list, tuple)):
+        return \"[\" + \", \".join(_str(element, True) for element in value) + \"]\"
+    if callable(value):
+        return \"<fn>\"
+    name = type(value).__name__
+    fields = [(field, _str(getattr(value, field), True)) for field in value.__dataclass_fields__]
+    if not fields:
+        return name
+    if fields[0][0] == \"_0\":
+        return name + \"(\" + \", \".join(field for _, field in fields) + \")\"
+    return name + \" { \" + \", \".join(f\"{field}: {text}\" for field, text in fields) + \" }\"
+
+
+def _number(value):
+    if value != value:
+        return \"NaN\"
+    if value in (math.inf, -math.inf):
+        return \"inf\" if value > 0 else \"-inf\"
+    if value == 0:
+        return \"0\"
+    sign = \"-\" if value < 0 else \"\"
+    mantissa, _, exponent = repr(abs(value)).partition(\"e\")
+    whole, _, fraction = mantissa.partition(\".\")
+    digits = (whole + fraction).rstrip(\"0\")
+    # the position of the decimal point after the first digit that isn't a zero
+    point = len(whole) + int(exponent or 0) - (len(digits) - len(digits.lstrip(\"0\")))
+    digits = digits.lstrip(\"0\")
+    if len(digits) <= point <= 21:
+        return sign + digits + \"0\" * (point - len(digits))
+    if 0 < point <= 21:
+        return sign + digits[:point] + \".\" + digits[point:]
+    if -6 < point <= 0:
+        return sign + \"0.\" + \"0\" * -point + digits
+    exponent = point - 1
+    mantissa = digits[0] + (\".\" + digits[1:] if len(digits) > 1 else \"\")
+    return sign + mantissa + (\"e+\" if exponent > 0 else \"e-\") + str(abs(exponent))";
+
+pub struct PythonBackend;
+
+impl IrBackend for PythonBackend {
+    fn name(&self) -> &str {
+        "python"
+    }
+
+    fn lower_options(&self) -> LowerOptions<'_> {
+        LowerOptions {
+            reserved: RESERVED,
+            labeled_jumps: false,
+            ..LowerOptions::default()
+        }
+    }
+
+    fn emit(&self, module: &str, program: &Program) -> Output {
+        Output {
+            files: vec![OutputFile {
+                path: format!("{module}.py").into(),
+                content: PythonEmitter::new().emit(program),
+            }],
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+struct PythonEmitter {
+    writer: CodeWriter,
+    uses_math: bool,
+    uses_at_helper: bool,
+    uses_set_helper: bool,
+    uses_copy_helper: bool,
+    uses_div_helper: bool,
+    uses_rem_helper: bool,
+    uses_int32_helper: bool,
+    uses_str_helper: bool,
+    /// Names of the globals, which have to be declared in functions that assign to them.
+    globals: Vec<String>,
+    /// The amount of functions around the current statement.
+    depth: usize,
+    /// The bindings of the current item that are stored in a cell, see [`cells`].
+    cells: HashSet<String>,
+}
+
+impl PythonEmitter {
+    fn new() -> Self {
+        Self {
+            writer: CodeWriter::new("    "),
+            uses_math: false,
+            uses_at_helper: false,
+            uses_set_helper: false,
+            uses_copy_helper: false,
+            uses_div_helper: false,
+            uses_rem_helper: false,
+            uses_int32_helper: false,
+            uses_str_helper: false,
+            globals: Vec::new(),
+            depth: 0,
+            cells: HashSet::new(),
+        }
+    }
+
+    fn emit(mut self, program: &Program) -> String {
+        self.writer.line(FUTURE_IMPORT);
+        if program
+            .items
+            .iter()
+            .any(|item| matches!(item, Item::Struct(_) | Item::Enum(_)))
+        {
+            self.writer.line("from dataclasses import dataclass");
+        }
+
+        self.globals = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Global(global) => Some(global.name.clone()),
+                _ => None,
+            })
+            .collect();
+
+        for item in &program.items {
+            match item {
+                Item::Struct(r#struct) => self.r#struct(r#struct),
+                Item::Enum(r#enum) => self.r#enum(r#enum),
+                Item::Fn(function) => self.function(function),
+                Item::Global(global) => self.global(global),
+            }
+        }
+
+        let has_main = program.items.iter().any(|item| match item {
+            Item::Fn(function) => function.name == "main" && function.params.is_empty(),
+            _ => false,
+        });
+        if has_main {
+            self.writer.blank_line();
+            self.writer.blank_line();
+            self.writer.line("if __name__ == \"__main__\":");
+            self.writer.indent();
+            self.writer.line("main()");
+            self.writer.dedent();
+        }
+
+        let mut code = self.writer.finish();
+        // whether the helper and `math` are needed is only known after emitting everything
+        let helpers: Vec<_> = [
+            (self.uses_at_helper, AT_HELPER),
+            (self.uses_set_helper, SET_HELPER),
+            (self.uses_copy_helper, COPY_HELPER),
+            (self.uses_div_helper, DIV_HELPER),
+            (self.uses_rem_helper, REM_HELPER),
+            (self.uses_int32_helper, INT32_HELPER),
+            (self.uses_str_helper, STR_HELPER),
+        ]
+        .into_iter()
+        .filter_map(|(is_used, helper)| is_used.then_some(helper))
+        .collect();
+        if !helpers.is_empty() {
+            // the imports end at the first blank line
+            let imports_end = code.find("\n\n").map_or(code.len(), |end| end + 1);
+            code.insert_str(imports_end, &format!("\n\n{}\n", helpers.join("\n\n\n")));
+        }
+        if self.uses_math {
+            code.replacen(FUTURE_IMPORT, &format!("{FUTURE_IMPORT}\nimport math"), 1)
+        } else {
+            code
+        }
+    }
+
+    fn r#struct(&mut self, r#struct: &Struct) {
+        self.writer.blank_line();
+        self.writer.blank_line();
+        self.dataclass(&r#struct.name, &r#struct.fields, &r#struct.methods);
+    }
+
+    /// Every variant is a dataclass nested inside of the enum class.
+    ///
+    /// The methods are defined on the enum class and copied into every variant.
+    fn r#enum(&mut self, r#enum: &Enum) {
+        self.writer.blank_line();
+        self.writer.blank_line();
+        self.writer.line(format!("class {}:", r#enum.name));
+
+        self.writer.indent();
+        if r#enum.variants.is_empty() && r#enum.methods.is_empty() {
+            self.writer.line("pass");
+        }
+        for (i, variant) in r#enum.variants.iter().enumerate() {
+            if i > 0 {
+                self.writer.blank_line();
+            }
+            self.dataclass(&variant.name, &variant.fields, &[]);
+        }
+        self.methods(&r#enum.methods);
+        self.writer.dedent();
+
+        let shared: Vec<_> = r#enum
+            .methods
+            .iter()
+            .filter(|method| method.has_receiver)
+            .map(|method| &method.function.name)
+            .collect();
+        if !shared.is_empty() && !r#enum.variants.is_empty() {
+            self.writer.blank_line();
+            self.writer.blank_line();
+        }
+        for method in shared {
+            let targets: String = r#enum
+                .variants
+                .iter()
+                .map(|variant| format!("{}.{}.{method} = ", r#enum.name, variant.name))
+                .collect();
+            self.writer
+                .line(format!("{targets}{}.{method}", r#enum.name));
+        }
+    }
+
+    fn dataclass(&mut self, name: &str, fields: &[TypedName], methods: &[Method]) {
+        self.writer.line("@dataclass");
+        self.writer.line(format!("class {name}:"));
+
+        self.writer.indent();
+        if fields.is_empty() && methods.is_empty() {
+            self.writer.line("pass");
+        }
+        for field in fields {
+            self.writer
+                .line(format!("{}: {}", field.name, annotation(&field.r#type)));
+        }
+        self.methods(methods);
+        self.writer.dedent();
+    }
+
+    /// Methods are separated by a single blank line inside of their class.
+    fn methods(&mut self, methods: &[Method]) {
+        for method in methods {
+            self.writer.blank_line();
+            if !method.has_receiver {
+                self.writer.line("@staticmethod");
+            }
+            self.def(&method.function, method.has_receiver);
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.writer.blank_line();
+        self.writer.blank_line();
+        self.def(function, false);
+    }
+
+    fn def(&mut self, function: &Function, has_receiver: bool) {
+        let params: Vec<_> = has_receiver
+            .then(|| "self".to_owned())
+            .into_iter()
+            .chain(
+                function
+                    .params
+                    .iter()
+                    .map(|param| format!("{}: {}", param.name, annotation(&param.r#type))),
+            )
+            .collect();
+
+        self.writer.line(format!(
+            "def {}({}) -> {}:",
+            function.name,
+            params.join(", "),
+            annotation(&function.return_type)
+        ));
+
+        self.writer.indent();
+        self.cells = cells(&function.body);
+        self.declarations(&function.body, &[]);
+        self.depth += 1;
+        self.block(&function.body);
+        self.depth -= 1;
+        self.writer.dedent();
+    }
+
+    /// A nested function, the captures it assigns to are declared `nonlocal`.
+    fn closure(&mut self, name: &str, closure: &Closure) {
+        // a closure would see later values of the bindings it captures, like the binding
+        // of a loop, so the ones that can't change and cells are stored in default arguments
+        let params: Vec<_> = closure
+            .params
+            .iter()
+            .cloned()
+            .chain(
+                closure
+                    .captures
+                    .iter()
+                    .filter(|capture| !capture.is_mut || self.cells.contains(&capture.name))
+                    .map(|capture| format!("{0}={0}", capture.name)),
+            )
+            .collect();
+        self.writer
+            .line(format!("def {name}({}):", params.join(", ")));
+        self.writer.indent();
+
+        let assigned: Vec<_> = closure
+            .assigned_captures()
+            .map(|capture| capture.name.as_str())
+            .filter(|name| !self.cells.contains(*name))
+            .collect();
+        self.declarations(&closure.body, &assigned);
+        self.depth += 1;
+        self.block(&closure.body);
+        self.depth -= 1;
+
+        self.writer.dedent();
+    }
+
+    /// Declares the bindings from outside of a function that its `body` assigns to,
+    /// `captures` are the ones of a closure.
+    ///
+    /// The captures of a closure at the module level are globals as well.
+    fn declarations(&mut self, body: &[Stmt], captures: &[&str]) {
+        let mut globals: Vec<_> = self
+            .globals
+            .iter()
+            .filter(|name| is_assigned(name, body))
+            .cloned()
+            .collect();
+        let mut nonlocals: Vec<_> = captures.iter().map(|name| name.to_string()).collect();
+        if self.depth == 0 {
+            globals.append(&mut nonlocals);
+        }
+
+        for (keyword, names) in [("global", globals), ("nonlocal", nonlocals)] {
+            if !names.is_empty() {
+                self.writer.line(format!("{keyword} {}", names.join(", ")));
+            }
+        }
+    }
+
+    fn global(&mut self, global: &Global) {
+        self.writer.blank_line();
+        self.cells = cells(&global.setup);
+        self.block(&global.setup);
+
+        if let Expr::Closure(closure) = &global.value {
+            self.cells = cells(&closure.body);
+            return self.closure(&global.name, closure);
+        }
+        let value = self.expr(&global.value);
+        match &global.r#type {
+            Some(r#type) => {
+                self.writer
+                    .line(format!("{}: {} = {value}", global.name, annotation(r#type)))
+            }
+            None => self.writer.line(format!("{} = {value}", global.name)),
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            match statement {
+                Stmt::Let {
+                    name,
+                    value: Expr::Closure(closure),
+                    ..
+                } => {
+                    self.closure(name, closure);
+                    if self.cells.contains(name) {
+                        self.writer.line(format!("{name} = [{name}]"));
+                    }
+                }
+                Stmt::Let { name, value, .. } if self.cells.contains(name) => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("{name} = [{value}]"))
+                }
+                Stmt::Assign { name, value } if self.cells.contains(name) => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("{name}[0] = {value}"))
+                }
+                Stmt::Let { name, value, .. } | Stmt::Assign { name, value } => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("{name} = {value}"))
+                }
+                Stmt::AssignField {
+                    target,
+                    name,
+                    value,
+                } => {
+                    let target = self.unary_operand(target);
+                    let value = self.expr(value);
+                    self.writer.line(format!("{target}.{name} = {value}"))
+                }
+                Stmt::AssignIndex {
+                    target,
+                    index,
+                    value,
+                } => {
+                    self.uses_at_helper = true;
+                    self.uses_set_helper = true;
+                    let target = self.expr(target);
+                    let index = self.expr(index);
+                    let value = self.expr(value);
+                    self.writer
+                        .line(format!("_set({target}, {index}, {value})"))
+                }
+                Stmt::If {
+                    condition,
+                    then,
+                    r#else,
+                } => {
+                    let condition = self.expr(condition);
+                    self.writer.line(format!("if {condition}:"));
+                    self.indented_block(then);
+
+                    // `else { if ... }` is written as a single `else if`
+                    let mut r#else = r#else;
+                    while let [Stmt::If {
+                        condition,
+                        then,
+                        r#else: next,
+                    }] = r#else.as_slice()
+                    {
+                        let condition = self.expr(condition);
+                        self.writer.line(format!("elif {condition}:"));
+                        self.indented_block(then);
+                        r#else = next;
+                    }
+                    if !r#else.is_empty() {
+                        self.writer.line("else:");
+                        self.indented_block(r#else);
+                    }
+                }
+                Stmt::Loop {
+                    condition, body, ..
+                } => {
+                    let condition = match condition {
+                        Some(condition) => self.expr(condition),
+                        None => "True".to_owned(),
+                    };
+                    self.writer.line(format!("while {condition}:"));
+                    self.indented_block(body);
+                }
+                Stmt::ForRange {
+                    binding,
+                    start,
+                    end,
+                    is_inclusive,
+                    body,
+                    ..
+                } => {
+                    let start = self.range_bound(start, 0);
+                    let end = self.range_bound(end, *is_inclusive as i64);
+                    self.writer
+                        .line(format!("for {binding} in range({start}, {end}):"));
+                    self.indented_block(body);
+                }
+                Stmt::ForEach {
+                    binding,
+                    iterable,
+                    body,
+                    ..
+                } => {
+                    let iterable = self.expr(iterable);
+                    self.writer.line(format!("for {binding} in {iterable}:"));
+                    self.indented_block(body);
+                }
+                // jumps always target the innermost loop without `labeled_jumps`
+                Stmt::Break { .. } => self.writer.line("break"),
+                Stmt::Continue { .. } => self.writer.line("continue"),
+                Stmt::Expr(value) => {
+                    let value = self.expr(value);
+                    self.writer.line(value)
+                }
+                Stmt::Return(value) => {
+                    let value = self.expr(value);
+                    self.writer.line(format!("return {value}"))
+                }
+            }
+        }
+    }
+
+    /// `range` only accepts ints, so the numbers it yields are ints as well.
+    fn range_bound(&mut self, bound: &Expr, offset: i64) -> String {
+        let bound = match bound {
+            Expr::Num(num) if num.fract() == 0.0 => return (*num as i64 + offset).to_string(),
+            bound => format!("int({})", self.expr(bound)),
+        };
+
+        match offset {
+            0 => bound,
+            offset => format!("{bound} + {offset}"),
+        }
+    }
+
+    fn indented_block(&mut self, statements: &[Stmt]) {
+        self.writer.indent();
+        if statements.is_empty() {
+            self.writer.line("pass");
+        }
+        self.block(statements);
+        self.writer.dedent();
+    }
+}
+
+/// The `mut` bindings declared inside of a loop that a closure captures.
+///
+/// Python has one scope per function, so the closures of every iteration would share them.
+/// They are stored in a list instead, a cell that is created again in every iteration.
+fn cells(statements: &[Stmt]) -> HashSet<String> {
+    let mut declared = HashSet::new();
+    let mut captured = HashSet::new();
+    find_cells(statements, false, &mut declared, &mut captured);
+
+    declared.intersection(&captured).cloned().collect()
+}
+
+/// Finds the `mut` bindings declared in a loop and the ones captured by closures.
+fn find_cells(
+    statements: &[Stmt],
+    is_in_loop: bool,
+    declared: &mut HashSet<String>,
+    captured: &mut HashSet<String>,
+) {
+    for statement in statements {
+        match statement {
+            Stmt::Let {
+                is_mut,
+                name,
+                value,
+            } => {
+                if *is_mut && is_in_loop {
+                    declared.insert(name.clone());
+                }
+                if let Expr::Closure(closure) = value {
+                    captured.extend(
+                        closure
+                            .captures
+                            .iter()
+                            .filter(|capture| capture.is_mut)
+                            .map(|capture| capture.name.clone()),
+                    );
+                    // every call has its own scope
+                    find_cells(&closure.body, false, declared, captured);
+                }
+            }
+            Stmt::If { then, r#else, .. } => {
+                find_cells(then, is_in_loop, declared, captured);
+                find_cells(r#else, is_in_loop, declared, captured);
+            }
+            Stmt::Loop { body, .. } | Stmt::ForRange { body, .. } | Stmt::ForEach { body, .. } => {
+                find_cells(body, true, declared, captured);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn annotation(r#type: &Type) -> String {
+    match r#type {
+        Type::Named(name) => match name.as_str() {
+            // all numbers are floats at runtime
+            "int" | "float" => "float".to_owned(),
+            "String" => "str".to_owned(),
+            name => name.to_owned(),
+        },
+        // annotations aren't evaluated, so the parameters and traits don't need to be declared
+        Type::Param(name) | Type::Dyn(name) => name.clone(),
+        Type::Applied { name, args } => format!(
+            "{name}[{}]",
+            args.iter().map(annotation).collect::<Vec<_>>().join(", ")
+        ),
+        Type::Fn {
+            params,
+            return_type,
+        } => format!(
+            "Callable[[{}], {}]",
+            params.iter().map(annotation).collect::<Vec<_>>().join(", "),
+            annotation(return_type)
+        ),
+        Type::Tuple(types) if types.is_empty() => "None".to_owned(),
+        Type::Tuple(types) => format!(
+            "tuple[{}]",
+            types.iter().map(annotation).collect::<Vec<_>>().join(", ")
+        ),
+        Type::Array(element) => format!("list[{}]", annotation(element)),
+    }
+}
+
+fn precedence(op: BinOp) -> u8 {
+    match op {
+        BinOp::Or => 1,
+        BinOp::And => 2,
+        // `not` binds tighter than `and` but weaker than comparisons
+        BinOp::Equals
+        | BinOp::NotEquals
+        | BinOp::Less
+        | BinOp::LessEquals
+        | BinOp::Greater
+        | BinOp::GreaterEquals => 4,
+        BinOp::BitOr => 5,
+        BinOp::BitXor => 6,
+        BinOp::BitAnd => 7,
+        BinOp::Shl | BinOp::Shr => 8,
+        BinOp::Add | BinOp::Sub => 9,
+        BinOp::Mul | BinOp::Div | BinOp::Rem => 10,
+    }
+}
+
+fn is_comparison(op: BinOp) -> bool {
+    precedence(op) == precedence(BinOp::Equals)
+}
+
+fn is_bitwise(op: BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::BitOr | BinOp::BitXor | BinOp::BitAnd | BinOp::Shl | BinOp::Shr
+    )
+}
+
+impl PythonEmitter {
+    fn expr(&mut self, expr: &Expr) -> String {
+        match expr {
+            Expr::Unit => "None".to_owned(),
+            Expr::Bool(true) => "True".to_owned(),
+            Expr::Bool(false) => "False".to_owned(),
+            Expr::Num(num) => format!("{num:?}"),
+            Expr::Str(str) => quote(str),
+            Expr::Var(name) if self.cells.contains(name) => format!("{name}[0]"),
+            Expr::Var(name) => name.clone(),
+
+            Expr::Unary { op, operand } => {
+                let op_str = match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::Not => "not ",
+                };
+                format!("{op_str}{}", self.unary_operand(operand))
+            }
+            // `/` would raise on a division by zero
+            Expr::Bin {
+                op: BinOp::Div,
+                left,
+                right,
+                ..
+            } => {
+                self.uses_math = true;
+                self.uses_div_helper = true;
+                format!("_div({}, {})", self.expr(left), self.expr(right))
+            }
+            // `%` would round the quotient towards negative infinity
+            Expr::Bin {
+                op: BinOp::Rem,
+                left,
+                right,
+                ..
+            } => {
+                self.uses_math = true;
+                self.uses_rem_helper = true;
+                format!("_rem({}, {})", self.expr(left), self.expr(right))
+            }
+            // the bitwise operators don't accept floats and work on unbounded integers
+            Expr::Bin {
+                op, left, right, ..
+            } if is_bitwise(*op) => {
+                self.uses_math = true;
+                self.uses_int32_helper = true;
+                let (left, right) = (self.expr(left), self.expr(right));
+                match op {
+                    BinOp::Shl => format!("_int32(_int32({left}) << (_int32({right}) & 31))"),
+                    BinOp::Shr => format!("_int32({left}) >> (_int32({right}) & 31)"),
+                    op => format!("_int32({left}) {} _int32({right})", bin_op(*op)),
+                }
+            }
+            Expr::Bin {
+                op, left, right, ..
+            } => format!(
+                "{} {} {}",
+                self.operand(*op, left, false),
+                bin_op(*op),
+                self.operand(*op, right, true)
+            ),
+            Expr::Call { callee, args } => format!("{callee}({})", self.args_list(args)),
+            Expr::MethodCall {
+                receiver,
+                method,
+                args,
+            } => format!(
+                "{}.{method}({})",
+                self.unary_operand(receiver),
+                self.args_list(args)
+            ),
+            Expr::AssociatedCall {
+                r#type,
+                function,
+                args,
+            } => format!("{type}.{function}({})", self.args_list(args)),
+            // methods are looked up on the receiver instead
+            Expr::TraitCall { .. } => unreachable!("only lowered with `dictionary_passing`"),
+            Expr::Builtin {
+                builtin: Builtin::Print,
+                args,
+            } => {
+                let args: Vec<_> = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Expr::Str(_) => self.expr(arg),
+                        arg => {
+                            self.uses_math = true;
+                            self.uses_str_helper = true;
+                            format!("_str({})", self.expr(arg))
+                        }
+                    })
+                    .collect();
+                format!("print({})", args.join(", "))
+            }
+            // a single element needs a trailing comma to not be a grouping
+            Expr::Tuple(elements) if elements.len() == 1 => {
+                format!("({},)", self.expr(&elements[0]))
+            }
+            Expr::Tuple(elements) => format!("({})", self.args_list(elements)),
+            Expr::Array(elements) => format!("[{}]", self.args_list(elements)),
+            Expr::Copy(value) => {
+                self.uses_copy_helper = true;
+                format!("_copy({})", self.expr(value))
+            }
+            Expr::Closure(_) => unreachable!("closures are only bound by a `let`"),
+            Expr::Struct { name, fields } => format!("{name}({})", self.fields_list(fields)),
+            Expr::Variant {
+                r#enum,
+                variant,
+                fields,
+            } => format!("{enum}.{variant}({})", self.fields_list(fields)),
+            Expr::Field { value, name } => format!("{}.{name}", self.unary_operand(value)),
+            Expr::TupleField { value, index } => {
+                format!("{}[{index}]", self.unary_operand(value))
+            }
+            Expr::Index { value, index } => {
+                self.uses_at_helper = true;
+                format!("_at({}, {})", self.expr(value), self.expr(index))
+            }
+            Expr::IsVariant {
+                value,
+                r#enum,
+                variant,
+            } => format!("isinstance({}, {enum}.{variant})", self.expr(value)),
+        }
+    }
+
+    fn operand(&mut self, parent: BinOp, operand: &Expr, is_right: bool) -> String {
+        let code = self.expr(operand);
+
+        match operand {
+            Expr::Bin { op, .. }
+                if precedence(*op) < precedence(parent)
+                    || precedence(*op) == precedence(parent)
+                        // comparisons chain in python
+                        && (is_right || is_comparison(parent)) =>
+            {
+                format!("({code})")
+            }
+            Expr::Unary {
+                op: UnaryOp::Not, ..
+            } if precedence(parent) > precedence(BinOp::And) => format!("({code})"),
+            _ => code,
+        }
+    }
+
+    fn unary_operand(&mut self, operand: &Expr) -> String {
+        match operand {
+            Expr::Unary { .. } | Expr::Bin { .. } => format!("({})", self.expr(operand)),
+            _ => self.expr(operand),
+        }
+    }
+
+    fn args_list(&mut self, args: &[Expr]) -> String {
+        let args: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+        args.join(", ")
+    }
+
+    /// The fields of tuple variants are passed by position, everything else by name.
+    fn fields_list(&mut self, fields: &[FieldValue]) -> String {
+        let is_tuple = fields
+            .iter()
+            .enumerate()
+            .all(|(i, field)| field.name == format!("_{i}"));
+
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|field| match is_tuple {
+                true => self.expr(&field.value),
+                false => format!("{}={}", field.name, self.expr(&field.value)),
+            })
+            .collect();
+        fields.join(", ")
+    }
+}
+
+fn bin_op(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Or => "or",
+        BinOp::And => "and",
+        BinOp::Equals => "==",
+        BinOp::NotEquals => "!=",
+        BinOp::Less => "<",
+        BinOp::LessEquals => "<=",
+        BinOp::Greater => ">",
+        BinOp::GreaterEquals => ">=",
+        BinOp::BitOr => "|",
+        BinOp::BitXor => "^",
+        BinOp::BitAnd => "&",
+        BinOp::Shl => "<<",
+        BinOp::Shr => ">>",
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Rem => "%",
+    }
+}
+
+#[test]
+fn test_python_emit() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &PythonBackend,
+        "struct Point { x: int, y: String }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn sub(a: int, b: int) -> int { a - b }
+         pub let from = sub(3, 1) * { let a = 2; a };
+         let shapes = print(Shape::Empty, Shape::Circle(1), Shape::Rect { height: 2, width: 3 });
+         let origin = Point { y: \"o\", x: 0 };
+         let y = origin.y;",
+    );
+
+    assert_eq!(
+        code,
+        "from __future__ import annotations
+import math
+from dataclasses import dataclass
+
+
+def _str(value, nested=False):
+    if value is None:
+        return \"()\"
+    if isinstance(value, bool):
+        return \"true\" if value else \"false\"
+    if isinstance(value, (int, float)):
+        return _number(float(value))
+    if isinstance(value, str):
+        if not nested:
+            return value
+        return '\"' + value.replace('\\\\', '\\\\\\\\').replace('\"', '\\\\\"') + '\"'
+    if isinstance(value, (list, tuple)):
+        return \"[\" + \", \".join(_str(element, True) for element in value) + \"]\"
+    if callable(value):
+        return \"<fn>\"
+    name = type(value).__name__
+    fields = [(field, _str(getattr(value, field), True)) for field in value.__dataclass_fields__]
+    if not fields:
+        return name
+    if fields[0][0] == \"_0\":
+        return name + \"(\" + \", \".join(field for _, field in fields) + \")\"
+    return name + \" { \" + \", \".join(f\"{field}: {text}\" for field, text in fields) + \" }\"
+
+
+def _number(value):
+    if value != value:
+        return \"NaN\"
+    if value in (math.inf, -math.inf):
+        return \"inf\" if value > 0 else \"-inf\"
+    if value == 0:
+        return \"0\"
+    sign = \"-\" if value < 0 else \"\"
+    mantissa, _, exponent = repr(abs(value)).partition(\"e\")
+    whole, _, fraction = mantissa.partition(\".\")
+    digits = (whole + fraction).rstrip(\"0\")
+    # the position of the decimal point after the first digit that isn't a zero
+    point = len(whole) + int(exponent or 0) - (len(digits) - len(digits.lstrip(\"0\")))
+    digits = digits.lstrip(\"0\")
+    if len(digits) <= point <= 21:
+        return sign + digits + \"0\" * (point - len(digits))
+    if 0 < point <= 21:
+        return sign + digits[:point] + \".\" + digits[point:]
+    if -6 < point <= 0:
+        return sign + \"0.\" + \"0\" * -point + digits
+    exponent = point - 1
+    mantissa = digits[0] + (\".\" + digits[1:] if len(digits) > 1 else \"\")
+    return sign + mantissa + (\"e+\" if exponent > 0 else \"e-\") + str(abs(exponent))
+
+
+@dataclass
+class Point:
+    x: float
+    y: str
+
+
+class Shape:
+    @dataclass
+    class Empty:
+        pass
+
+    @dataclass
+    class Circle:
+        _0: float
+
+    @dataclass
+    class Rect:
+        width: float
+        height: float
+
+
+def sub(a: float, b: float) -> float:
+    return a - b
+
+_t0 = sub(3.0, 1.0)
+a = 2.0
+from_1 = _t0 * a
+
+shapes = print(_str(Shape.Empty()), _str(Shape.Circle(1.0)), _str(Shape.Rect(height=2.0, width=3.0)))
+
+origin = Point(x=0.0, y=\"o\")
+
+y = origin.y
+"
+    )
+}
+
+#[test]
+fn test_python_operators() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &PythonBackend,
+        "fn half(x: int) -> int { x / 2 }
+         fn main() -> () {
+             print(1 / 0, -1 / 0, 7 % 0, 0.1 + 0.2, 8 / 4 / 2, half(7) == 3.5);
+             print(1 << 31, 1 << 32, -7 >> 1, 0 - 1 >> 31, half(11) & 7, half(-11) | 0);
+             print(-(1 - 4) * 2, -(-1.5), 7 % 3 != 1, -7 % 3, (6 & 3 | 8 ^ 1) << 2 >> 1);
+             print(
+                 !(1 == 2) == (3 == 3),
+                 2 < 3 && 3 <= 2 || 4 >= 4,
+                 1 == 2 && { let a = print(\"skipped\"); 1 == 1 },
+                 1 == 2 || { let a = 5; a > 4 }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "inf -inf NaN 0.30000000000000004 1 true\n-2147483648 1 -4 -1 5 -5\n6 1.5 false -1 22\ntrue true false true\n");
+    }
+}
+
+#[test]
+fn test_python_equality() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &PythonBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn main() -> () {
+             print(
+                 Shape::Circle(1) == Shape::Circle(1),
+                 Shape::Empty != Shape::Empty,
+                 Point::new(1, 2) == Point::new(1, 3),
+                 (1, [2, 3]) == (1, [2, 3]),
+                 Shape::Rect { width: 1, height: 2 } == Shape::Rect { height: 2, width: 1 }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "true false false true true\n");
+    }
+}
+
+#[test]
+fn test_python_control_flow() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &PythonBackend,
+        "fn first_over(n: int, limit: int) -> int {
+             for i in 0..n { if i * i > limit { return i; } }
+             0 - 1
+         }
+         fn main() -> () {
+             print(first_over(10, 20), first_over(3, 20), { let x = 3; x + 4 });
+             print(
+                 if 1 > 2 { \"a\" } else if 2 > 1 { let b = \"b\"; b } else { \"c\" },
+                 'outer: loop {
+                     while 2 > 1 { let x = if 3 > 2 { break 'outer 30 } else { 1 }; x }
+                 },
+                 'counting: loop {
+                     loop { let x = if 1 > 2 { continue 'counting } else { break 'counting 7 }; x }
+                 },
+                 match 2 { 1 => \"one\", n if n > 1 => \"many\", _ => \"none\" }
+             );
+             for i in 1..=2 { print(\"i\", i) }
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "5 -1 7\nb 30 7 many\ni 1\ni 2\n");
+    }
+}
+
+#[test]
+fn test_python_structs() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &PythonBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         fn moved(p: Point) -> int { let mut q = p; q.x = q.x + 1; q.x * q.y }
+         fn main() -> () {
+             print(
+                 moved(Point { y: 3, x: 1 }),
+                 Point::new(1, 2).shift(2).dot(Point::new(1, 1)),
+                 Point::dot(Point::new(2, 0), Point::new(3, 0)),
+                 { let Point { x, y } = Point::new(3, 4); x * y }
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "6 5 6 12\n");
+    }
+}
+
+#[test]
+fn test_python_enums() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &PythonBackend,
+        "enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn area(shape: Shape) -> int {
+             match shape {
+                 Shape::Empty => 0,
+                 Shape::Circle(r) if r < 0 => 0,
+                 Shape::Circle(r) => r * r * 3,
+                 Shape::Rect { width, height } => width * height,
+             }
+         }
+         impl Shape { fn doubled(self) -> int { area(self) * 2 } }
+         fn main() -> () {
+             print(area(Shape::Circle(2)), area(Shape::Circle(-1)), area(Shape::Rect { width: 2, height: 3 }));
+             print(area(Shape::Empty), Shape::Circle(1).doubled());
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "12 0 6\n0 6\n");
+    }
+}
+
+#[test]
+fn test_python_tuples_and_arrays() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &PythonBackend,
+        "fn swap(pair: (int, int)) -> (int, int) { let (a, b) = pair; (b, a) }
+         fn main() -> () {
+             print(swap((1, 2)).0, { let xs = [[1], [2, 3]]; xs[1][0 + 1] }, [10, 20, 30][1]);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "2 3 20\n");
+    }
+}
+
+#[test]
+fn test_python_closures() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &PythonBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         fn apply(f: fn(int) -> int, x: int) -> int { f(x) }
+         fn counted(n: int) -> int {
+             let mut count = 0;
+             let tick = |by| { count += by; count };
+             let mut xs = [0, 0];
+             for i in 0..n { xs[i % 2] += tick(i); () }
+             xs[0] * 100 + xs[1]
+         }
+         fn main() -> () {
+             print(
+                 { let k = 10; let add = |a: int, b| a + b + k; apply(|x| add(x, x), 3) },
+                 Point::new(3, 4).scaled(),
+                 counted(4)
+             );
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "16 12 307\n");
+    }
+}
+
+#[test]
+fn test_python_generics() {
+    use crate::writer::compile_source;
+
+    let code = compile_source(
+        &PythonBackend,
+        "struct Point { x: int, y: int }
+         impl Point {
+             fn new(x: int, y: int) -> Self { Self { x, y } }
+             fn dot(self, other: Point) -> int { self.x * other.x + self.y * other.y }
+             fn shift(mut self, by: int) -> Self { self.x = self.x + by; self }
+             fn scaled(self) -> int { let by = |v| v * self.y; by(self.x) }
+         }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn id<T>(x: T) -> T { x }
+         struct Pair<A, B> { first: A, second: B }
+         fn first<A, B>(pair: Pair<A, B>) -> A { pair.first }
+         trait Area { fn area(self) -> int; }
+         impl Area for Point { fn area(self) -> int { self.x * self.y } }
+         impl Area for Shape {
+             fn area(self) -> int { match self { Shape::Circle(r) => r * r * 3, _ => 0 } }
+         }
+         fn total<T: Area>(a: T, b: dyn Area) -> int { a.area() + b.area() }
+         fn main() -> () {
+             print(id(first(Pair { first: 8, second: \"b\" })), total(Point::new(2, 3), Shape::Circle(1)));
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "8 9\n");
+    }
+}
+
+#[test]
+fn test_python_names() {
+    use crate::writer::compile_source;
+
+    // names of the runtime that the program uses are renamed
+    let code = compile_source(
+        &PythonBackend,
+        "let len = 1;
+         let IndexError = 2;
+         fn main() -> () {
+             let range = 2;
+             let mut n = 0;
+             for i in 1..=range { n += i; () }
+             print([10, 20, 30][len + IndexError - 2], n);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "20 3\n");
+    }
+}
+
+#[test]
+fn test_python_closure_cells() {
+    use crate::writer::compile_source;
+
+    // every closure sees the value of `i` in its own iteration
+    let code = compile_source(
+        &PythonBackend,
+        "fn main() -> () {
+             let mut fs = [|| 0, || 0, || 0];
+             for i in 0..3 { let f = || i; fs[i] = f; }
+             let (a, b, c) = (fs[0], fs[1], fs[2]);
+             print(a() * 100 + b() * 10 + c());
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "12\n");
+    }
+}
+
+#[test]
+fn test_python_copies() {
+    use crate::writer::compile_source;
+
+    // changing a copy is never seen through the binding it was copied from
+    let code = compile_source(
+        &PythonBackend,
+        "struct P { x: int, ys: [int] }
+         fn main() -> () {
+             let xs = [1, 2];
+             let mut ys = xs;
+             ys[0] = 9;
+             let mut p = P { x: 1, ys };
+             let q = p;
+             p.x = 5;
+             p.ys[0] = 6;
+             print(xs[0], ys[0], q.x, q.ys[0], p.x, p.ys[0]);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "1 9 1 9 5 6\n");
+    }
+}
+
+#[test]
+fn test_python_print() {
+    use crate::writer::compile_source;
+
+    // the format of `print` is the same on every backend
+    let code = compile_source(
+        &PythonBackend,
+        "struct Point { x: int, name: String }
+         enum Shape { Empty, Circle(int), Rect { width: int, height: int } }
+         fn main() -> () {
+             print(0.000001, 1 / 10000000, 0 - 25 / 1000000000, 12345678901234567);
+             print(100000000000 * 10000000000, 0.1 + 0.2, -2.5, 100, 1 / 3, 0 / 0, -1 / 0);
+             print(Point { name: \"a\\\"b\", x: 1 }, Shape::Empty, Shape::Circle(2));
+             print(Shape::Rect { height: 3, width: 2 }, \"top\", 1 > 0, ());
+             print([[1, 2]], (1, \"a\", ()), || 1);
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(
+            output,
+            "0.000001 1e-7 -2.5e-8 12345678901234568\n1e+21 0.30000000000000004 -2.5 100 0.3333333333333333 NaN -inf\nPoint { x: 1, name: \"a\\\"b\" } Empty Circle(2)\nRect { width: 2, height: 3 } top true ()\n[[1, 2]] [1, \"a\", ()] <fn>\n"
+        );
+    }
+}
+
+#[test]
+fn test_python_loop_cells() {
+    use crate::writer::compile_source;
+
+    // every iteration has its own `n`, which its closures share
+    let code = compile_source(
+        &PythonBackend,
+        "fn main() -> () {
+             let mut fs = [|| 0, || 0, || 0, || 0];
+             for i in 0..2 {
+                 let mut n = i;
+                 let inc = || { n += 10; n };
+                 let get = || n;
+                 fs[i * 2] = inc;
+                 fs[i * 2 + 1] = get;
+                 n += 1;
+             }
+             let (a, b, c, d) = (fs[0], fs[1], fs[2], fs[3]);
+             print(a(), b(), c(), d(), a());
+         }",
+    );
+
+    if let Some(output) = crate::writer::run_with("python3", "py", &code) {
+        assert_eq!(output, "11 11 12 12 21\n");
+    }
+}
@@ -0,0 +1,4468 @@
+mod pattern;
+mod types;
+
+use std::collections::{HashMap, HashSet};
+
+use erebus_parser::{
+    Assign, Ast, BinExpr, Break, Closure, CodeScope, CompoundOp, Continue, Else, EnumDef,
+    Expression, FieldAccess, FnCall, FnDef, For, GenericParams, Ident, If, ImplBlock, Label, Let,
+    MethodCall, Path, Pattern, RawTopLevelStatement, Return, Span, Spanned, Statement, StructDef,
+    StructLiteral, TraitDef, TupleIndex, TypeLiteral, UnaryExpr, VariantFields,
+};
+
+use crate::{Diagnostic, Severity};
+
+use pattern::bound_names;
+use types::{Bindings, Bounds, Dispatch, Operands, Signature};
+
+use super::{
+    BinOp, Builtin, Capture, Enum, Expr, FieldValue, Function, Global, Item, Method, Program, Stmt,
+    Struct, Type, TypedName, UnaryOp, Variant,
+};
+
+pub struct LowerOptions<'a> {
+    /// Names that can't be used as identifiers in the target language.
+    pub reserved: &'a [&'a str],
+    /// Store every operand that isn't [atomic](Expr::is_atomic) in a temporary.
+    ///
+    /// This is required for targets without nested expressions.
+    pub flatten_operands: bool,
+    /// Whether `break` and `continue` can refer to loops other than the innermost one.
+    ///
+    /// Otherwise they set a flag that is checked after every nested loop.
+    pub labeled_jumps: bool,
+    /// Resolve methods when lowering instead of looking them up on the receiver at runtime.
+    ///
+    /// A function passes the types that implement the traits bounding its generic parameters
+    /// as extra arguments after its parameters, followed by the type behind every `dyn` parameter.
+    /// A `let` of a `dyn` value declares another local for its type.
+    /// This is required for targets whose values don't carry their methods.
+    pub dictionary_passing: bool,
+    /// The name of `self` inside of methods, it is never used by other bindings.
+    pub receiver: &'a str,
+}
+
+/// Fits targets with nested expressions and labeled jumps whose values carry their methods.
+impl Default for LowerOptions<'_> {
+    fn default() -> Self {
+        Self {
+            reserved: &[],
+            flatten_operands: false,
+            labeled_jumps: true,
+            dictionary_passing: false,
+            receiver: "self",
+        }
+    }
+}
+
+/// Lowers the [`Ast`] into a [`Program`].
+///
+/// Items are reordered so that structs and enums come first, followed by functions and
+/// then globals in source order. This allows globals to call any function.
+/// The functions of `impl` blocks become [`Method`]s of their type,
+/// including the ones that implement a trait. Traits themselves only exist while lowering.
+///
+/// Fails if the program is invalid in a way the parser can't detect,
+/// like a `break` outside of a loop. Otherwise the warnings are returned with the program.
+pub fn lower(
+    ast: &Ast,
+    options: &LowerOptions,
+) -> Result<(Program, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let mut lowerer = Lowerer::new(options);
+
+    // source name -> where it is defined, items share one namespace
+    let mut definitions = HashMap::new();
+    for statement in &ast.statements {
+        let name = match &statement.inner {
+            RawTopLevelStatement::Let(r#let) => {
+                let names = bound_names(&r#let.pattern.node);
+                if names.is_empty() {
+                    lowerer.diagnostics.push(
+                        Diagnostic::error("a top level `let` has to bind a name")
+                            .with_span(r#let.pattern.span),
+                    );
+                }
+                for name in names {
+                    lowerer.define(&mut definitions, name.as_str(), r#let.pattern.span);
+                    let target_name = lowerer.fresh_name(name.as_str());
+                    if r#let.is_mut {
+                        lowerer.mutable.insert(target_name.clone());
+                    }
+                    lowerer
+                        .globals
+                        .insert(name.as_str().to_owned(), target_name);
+                }
+                continue;
+            }
+            RawTopLevelStatement::FnDef(fn_def) => &fn_def.name,
+            RawTopLevelStatement::StructDef(struct_def) => {
+                // fields can refer to structs that are declared later
+                lowerer.struct_generics.insert(
+                    struct_def.name.as_str().to_owned(),
+                    generic_names(&struct_def.generics),
+                );
+                &struct_def.name
+            }
+            RawTopLevelStatement::EnumDef(enum_def) => &enum_def.name,
+            // traits have their own namespace and aren't part of the program
+            RawTopLevelStatement::TraitDef(_) => continue,
+            // the functions of an impl block live in the namespace of their type
+            RawTopLevelStatement::ImplBlock(_) => continue,
+        };
+        lowerer.define(&mut definitions, name.as_str(), name.span);
+        let target_name = lowerer.fresh_name(name.as_str());
+        lowerer
+            .globals
+            .insert(name.as_str().to_owned(), target_name);
+    }
+    let global_names: HashSet<String> = lowerer.taken.clone();
+
+    // bounds and `impl` blocks can refer to traits that are declared later
+    for statement in &ast.statements {
+        if let RawTopLevelStatement::TraitDef(trait_def) = &statement.inner {
+            lowerer.declare_trait(trait_def);
+        }
+    }
+
+    let mut types = Vec::new();
+    // source name -> position in `types`
+    let mut type_positions = HashMap::new();
+    let mut methods: HashMap<&str, Vec<Method>> = HashMap::new();
+    let mut functions = Vec::new();
+    let mut globals = Vec::new();
+
+    // the variants have to be known before any function can construct them
+    for statement in &ast.statements {
+        let is_pub = statement.is_pub;
+
+        match &statement.inner {
+            RawTopLevelStatement::StructDef(struct_def) => {
+                type_positions.insert(struct_def.name.as_str(), types.len());
+                types.push(Item::Struct(lowerer.lower_struct(is_pub, struct_def)))
+            }
+            RawTopLevelStatement::EnumDef(enum_def) => {
+                type_positions.insert(enum_def.name.as_str(), types.len());
+                types.push(Item::Enum(lowerer.lower_enum(is_pub, enum_def)))
+            }
+            RawTopLevelStatement::FnDef(_)
+            | RawTopLevelStatement::Let(_)
+            | RawTopLevelStatement::TraitDef(_)
+            | RawTopLevelStatement::ImplBlock(_) => {}
+        }
+    }
+
+    // methods can be called before their impl block
+    for statement in &ast.statements {
+        if let RawTopLevelStatement::ImplBlock(block) = &statement.inner {
+            lowerer.declare_impl(statement.is_pub, statement.span, block);
+        }
+    }
+
+    // calls are checked against functions that are declared later as well
+    let mut signatures = Vec::new();
+    for statement in &ast.statements {
+        if let RawTopLevelStatement::FnDef(fn_def) = &statement.inner {
+            let signature = lowerer.lower_signature(fn_def);
+            lowerer
+                .fns
+                .entry(fn_def.name.as_str().to_owned())
+                .or_insert_with(|| signature.clone());
+            signatures.push(signature);
+        }
+    }
+    let mut signatures = signatures.into_iter();
+
+    for statement in &ast.statements {
+        let is_pub = statement.is_pub;
+
+        match &statement.inner {
+            RawTopLevelStatement::StructDef(_)
+            | RawTopLevelStatement::EnumDef(_)
+            | RawTopLevelStatement::TraitDef(_) => {}
+            RawTopLevelStatement::FnDef(fn_def) => {
+                // the module level names stay valid, every function has its own locals
+                let module_names = std::mem::replace(&mut lowerer.taken, global_names.clone());
+                let name = lowerer.globals[fn_def.name.as_str()].clone();
+                let signature = signatures.next().expect("every function has a signature");
+                functions.push(Item::Fn(lowerer.lower_fn(is_pub, name, fn_def, signature)));
+                lowerer.taken = module_names;
+            }
+            RawTopLevelStatement::ImplBlock(block) => {
+                let r#type = block.r#type.as_str();
+                lowerer.self_type = Some(r#type.to_owned());
+                lowerer.generics = lowerer.type_generics(r#type);
+                lowerer.bounds = lowerer
+                    .struct_bounds
+                    .get(r#type)
+                    .cloned()
+                    .unwrap_or_default();
+
+                for fn_def in &block.fns {
+                    let name = fn_def.inner.name.as_str();
+                    // the block of an unknown type and redefinitions are left out
+                    let Some(info) = lowerer.impls.get(r#type).and_then(|fns| fns.get(name)) else {
+                        continue;
+                    };
+                    if info.span != fn_def.inner.name.span {
+                        continue;
+                    }
+                    let name = info.name.clone();
+                    let signature = info.signature.clone();
+
+                    let module_names = std::mem::replace(&mut lowerer.taken, global_names.clone());
+                    let function = lowerer.lower_fn(fn_def.is_pub, name, &fn_def.inner, signature);
+                    methods.entry(r#type).or_default().push(Method {
+                        has_receiver: fn_def.inner.receiver.is_some(),
+                        function,
+                    });
+                    lowerer.taken = module_names;
+                }
+
+                lowerer.self_type = None;
+                lowerer.generics = Vec::new();
+                lowerer.bounds = Bounds::new();
+            }
+            RawTopLevelStatement::Let(r#let) => match &r#let.pattern.node {
+                Pattern::Binding(name) => {
+                    let global = lowerer.lower_global(is_pub, name, r#let);
+                    globals.push(Item::Global(global))
+                }
+                _ => {
+                    let pattern_globals = lowerer.lower_global_pattern(is_pub, r#let);
+                    globals.extend(pattern_globals.into_iter().map(Item::Global))
+                }
+            },
+        }
+    }
+
+    for (r#type, methods) in methods {
+        match &mut types[type_positions[r#type]] {
+            Item::Struct(r#struct) => r#struct.methods = methods,
+            Item::Enum(r#enum) => r#enum.methods = methods,
+            Item::Fn(_) | Item::Global(_) => unreachable!("only types have methods"),
+        }
+    }
+
+    if lowerer
+        .diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        return Err(lowerer.diagnostics);
+    }
+
+    let program = Program {
+        items: types.into_iter().chain(functions).chain(globals).collect(),
+    };
+    Ok((program, lowerer.diagnostics))
+}
+
+/// A loop that is currently being lowered.
+struct LoopContext {
+    /// The label in the source code.
+    source_label: Option<String>,
+    label: String,
+    /// Receives the value of a `break`, only a `loop` that is used as an expression has one.
+    result: Option<String>,
+    /// Only a `loop` can be left with a value.
+    breaks_with_value: bool,
+
+    /// Set by a `break` or `continue` inside of a nested loop that needs to be
+    /// forwarded to this loop, if `labeled_jumps` is disabled.
+    break_flag: Option<String>,
+    continue_flag: Option<String>,
+    /// Flags of outer loops that have to be checked after this loop ends.
+    forwarded: Vec<Forwarded>,
+}
+
+/// A closure that is currently being lowered.
+struct ClosureContext {
+    /// The amount of scopes around the closure.
+    depth: usize,
+    /// Target names of the locals from outside of the closure it uses, in order of first use.
+    captures: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+enum LoopKind<'a> {
+    Loop,
+    While(&'a Spanned<Expression>),
+    For(&'a For),
+}
+
+/// Everything about a loop that is known before its body is lowered.
+enum LoopHeader {
+    Loop(Option<Expr>),
+    ForRange {
+        binding: String,
+        start: Expr,
+        end: Expr,
+        is_inclusive: bool,
+    },
+    ForEach {
+        binding: String,
+        iterable: Expr,
+    },
+}
+
+/// What is needed to construct the variants of an enum.
+struct EnumInfo {
+    /// The target name of the enum.
+    name: String,
+    /// In declaration order.
+    variants: Vec<VariantInfo>,
+}
+
+#[derive(Clone)]
+struct VariantInfo {
+    /// The position of the variant in its enum.
+    index: usize,
+    source_name: String,
+    /// The target name of the variant.
+    name: String,
+    shape: VariantShape,
+}
+
+impl VariantInfo {
+    fn field_count(&self) -> usize {
+        match &self.shape {
+            VariantShape::Unit => 0,
+            VariantShape::Tuple(count) => *count,
+            VariantShape::Struct(names) => names.len(),
+        }
+    }
+}
+
+/// What is needed to construct and match on a struct.
+struct StructInfo {
+    /// Source name -> target name of every field, in declaration order.
+    fields: Vec<(String, String)>,
+    /// The declared type of every field, in declaration order.
+    types: Vec<Type>,
+}
+
+/// A function of an `impl` block.
+#[derive(Clone)]
+struct MethodInfo {
+    /// The target name, which is unique within the type.
+    name: String,
+    has_receiver: bool,
+    signature: Signature,
+    /// Where the function is named, to tell it apart from redefinitions.
+    span: Span,
+}
+
+#[derive(Clone)]
+enum VariantShape {
+    Unit,
+    /// The number of fields.
+    Tuple(usize),
+    /// Source name -> target name of every field, in declaration order.
+    Struct(Vec<(String, String)>),
+}
+
+#[derive(Clone, PartialEq)]
+enum Forwarded {
+    Break { flag: String, target: String },
+    Continue { flag: String, target: String },
+}
+
+struct Lowerer<'a> {
+    options: &'a LowerOptions<'a>,
+
+    /// Source name -> target name of every top level item.
+    globals: HashMap<String, String>,
+    /// Source name -> every enum that has been lowered so far.
+    enums: HashMap<String, EnumInfo>,
+    /// Source name -> every struct that has been lowered so far.
+    structs: HashMap<String, StructInfo>,
+    /// Source name -> the generic parameters of every struct.
+    struct_generics: HashMap<String, Vec<String>>,
+    /// Source name -> the bounds on the generic parameters of every struct.
+    struct_bounds: HashMap<String, Bounds>,
+    /// Source name -> signature of every top level function.
+    fns: HashMap<String, Signature>,
+    /// Source name of a type -> source name -> every function of its `impl` blocks.
+    impls: HashMap<String, HashMap<String, MethodInfo>>,
+    /// Source name of a trait -> source name -> every function it declares.
+    traits: HashMap<String, HashMap<String, MethodInfo>>,
+    /// Source name of a type -> every trait it implements.
+    implementations: HashMap<String, HashSet<String>>,
+    /// The source name of the type whose `impl` block is being lowered, which `Self` refers to.
+    self_type: Option<String>,
+    /// The generic parameters of the items that are being lowered, which types can refer to.
+    generics: Vec<String>,
+    /// The traits that bound the generic parameters in [`generics`](Self::generics).
+    bounds: Bounds,
+    /// Source name -> target name of the locals, innermost scope last.
+    scopes: Vec<HashMap<String, String>>,
+    /// Target name -> type of the locals whose type is known.
+    types: HashMap<String, Type>,
+    /// Target names that can't be used for new bindings.
+    taken: HashSet<String>,
+    /// Target names of the bindings that were declared with `mut`.
+    mutable: HashSet<String>,
+    /// Labels live in their own namespace, every one is unique in the program.
+    labels: HashSet<String>,
+    /// The loops around the current expression, innermost last.
+    loops: Vec<LoopContext>,
+    /// The closures around the current expression, innermost last.
+    closures: Vec<ClosureContext>,
+    /// Whether the current expression is inside a function or closure that `return` can leave.
+    is_in_fn: bool,
+    /// The return type of the function around the current expression,
+    /// closures don't declare one.
+    return_type: Option<Type>,
+    /// Span -> type of the blocks, `if`s and `match`es that were lowered so far.
+    value_types: HashMap<Span, Type>,
+    /// The types of the arms of the `match` that is being lowered, with the span of their value.
+    arm_types: Vec<(Option<Type>, Span)>,
+
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Lowerer<'a> {
+    fn new(options: &'a LowerOptions<'a>) -> Self {
+        Self {
+            options,
+
+            globals: HashMap::new(),
+            enums: HashMap::new(),
+            structs: HashMap::new(),
+            struct_generics: HashMap::new(),
+            struct_bounds: HashMap::new(),
+            fns: HashMap::new(),
+            impls: HashMap::new(),
+            traits: HashMap::new(),
+            implementations: HashMap::new(),
+            self_type: None,
+            generics: Vec::new(),
+            bounds: Bounds::new(),
+            scopes: Vec::new(),
+            types: HashMap::new(),
+            taken: HashSet::new(),
+            mutable: HashSet::new(),
+            labels: HashSet::new(),
+            loops: Vec::new(),
+            closures: Vec::new(),
+            is_in_fn: false,
+            return_type: None,
+            value_types: HashMap::new(),
+            arm_types: Vec::new(),
+
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Reserves a target name that is as close to `name` as possible.
+    fn fresh_name(&mut self, name: &str) -> String {
+        let is_free = |lowerer: &Self, candidate: &str| {
+            !lowerer.taken.contains(candidate) && !lowerer.options.reserved.contains(&candidate)
+        };
+
+        let mut candidate = name.to_owned();
+        let mut counter = 1;
+        while !is_free(self, &candidate) {
+            candidate = format!("{name}_{counter}");
+            counter += 1;
+        }
+
+        self.taken.insert(candidate.clone());
+        candidate
+    }
+
+    fn temp(&mut self) -> String {
+        let mut counter = 0;
+        while self.taken.contains(&format!("_t{counter}")) {
+            counter += 1;
+        }
+        self.fresh_name(&format!("_t{counter}"))
+    }
+
+    fn fresh_label(&mut self, label: Option<&str>) -> String {
+        let mut counter = 0;
+        let mut candidate = label.map_or_else(|| "_l0".to_owned(), ToOwned::to_owned);
+        while self.labels.contains(&candidate) || self.options.reserved.contains(&&*candidate) {
+            candidate = match label {
+                Some(label) => format!("{label}_{}", counter + 1),
+                None => format!("_l{}", counter + 1),
+            };
+            counter += 1;
+        }
+
+        self.labels.insert(candidate.clone());
+        candidate
+    }
+
+    /// Fields live in their own namespace, so they only need to avoid reserved words.
+    fn field_name(&self, name: &str) -> String {
+        if self.options.reserved.contains(&name) {
+            format!("{name}_")
+        } else {
+            name.to_owned()
+        }
+    }
+
+    /// Like [`field_name`](Self::field_name), but targets can rely on no field being called `tag`.
+    fn variant_field_name(&self, name: &str) -> String {
+        match self.field_name(name) {
+            name if name == "tag" => self.field_name("tag_"),
+            name => name,
+        }
+    }
+
+    /// Resolves `Self` to the type of the surrounding `impl` block.
+    fn type_name<'n>(&'n self, name: &'n str) -> &'n str {
+        match &self.self_type {
+            Some(self_type) if name == "Self" => self_type,
+            _ => name,
+        }
+    }
+
+    fn declare(&mut self, name: &str) -> String {
+        let target_name = self.fresh_name(name);
+        // the name could have been used by another function
+        self.types.remove(&target_name);
+        self.scopes
+            .last_mut()
+            .expect("locals can only be declared inside a scope")
+            .insert(name.to_owned(), target_name.clone());
+
+        target_name
+    }
+
+    /// Declares the local that holds the name of the type behind the `dyn` value in `name`,
+    /// with [`LowerOptions::dictionary_passing`].
+    fn declare_implementation(&mut self, name: &str, r#trait: &str) -> String {
+        let target_name = self.fresh_name(&format!("{name}_{trait}"));
+        self.scopes
+            .last_mut()
+            .expect("locals can only be declared inside a scope")
+            .insert(dyn_key(name), target_name.clone());
+
+        target_name
+    }
+
+    /// Remembers where `name` is defined, unless it already is.
+    fn define(&mut self, definitions: &mut HashMap<String, Span>, name: &str, span: Span) {
+        match definitions.get(name) {
+            Some(first) => self.diagnostics.push(
+                Diagnostic::error(format!("`{name}` is defined twice"))
+                    .with_span(span)
+                    .with_note(*first, "first defined here"),
+            ),
+            None => {
+                definitions.insert(name.to_owned(), span);
+            }
+        }
+    }
+
+    /// A local from outside of the closures that are being lowered becomes a capture of them.
+    fn resolve(&mut self, name: &str) -> Option<String> {
+        let Some(depth) = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(name))
+        else {
+            return self.globals.get(name).cloned();
+        };
+        let target = self.scopes[depth][name].clone();
+
+        for closure in &mut self.closures {
+            if closure.depth > depth && !closure.captures.contains(&target) {
+                closure.captures.push(target.clone());
+            }
+        }
+        Some(target)
+    }
+
+    /// The generic parameters of the struct `name`, enums don't have any.
+    fn type_generics(&self, name: &str) -> Vec<String> {
+        self.struct_generics.get(name).cloned().unwrap_or_default()
+    }
+
+    /// The type of `self` inside of the `impl` block of `name`.
+    fn receiver_type(&self, name: &str) -> Type {
+        match self.type_generics(name) {
+            generics if generics.is_empty() => Type::Named(name.to_owned()),
+            generics => Type::Applied {
+                name: name.to_owned(),
+                args: generics.into_iter().map(Type::Param).collect(),
+            },
+        }
+    }
+
+    fn lower_type(&mut self, r#type: &TypeLiteral) -> Type {
+        match r#type {
+            TypeLiteral::Ident(ident)
+                if self.generics.iter().any(|name| name == ident.as_str()) =>
+            {
+                Type::Param(ident.as_str().to_owned())
+            }
+            TypeLiteral::Ident(ident) => Type::Named(self.type_name(ident.as_str()).to_owned()),
+            TypeLiteral::Applied(applied) => {
+                let name = self.type_name(applied.name.as_str()).to_owned();
+                let args: Vec<_> = applied
+                    .args
+                    .iter()
+                    .map(|arg| self.lower_type(arg))
+                    .collect();
+
+                let expected = self.type_generics(&name).len();
+                // a wrong amount of arguments leaves them unknown
+                if expected == 0 {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("`{name}` doesn't take generic arguments"))
+                            .with_span(applied.name.span),
+                    );
+                    Type::Named(name)
+                } else if expected != args.len() {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "`{name}` takes {expected} generic {} but {} {} given",
+                            if expected == 1 {
+                                "argument"
+                            } else {
+                                "arguments"
+                            },
+                            args.len(),
+                            if args.len() == 1 { "was" } else { "were" },
+                        ))
+                        .with_span(applied.name.span),
+                    );
+                    Type::Named(name)
+                } else {
+                    Type::Applied { name, args }
+                }
+            }
+            TypeLiteral::Fn(signature) => Type::Fn {
+                params: signature
+                    .params
+                    .iter()
+                    .map(|param| self.lower_type(param))
+                    .collect(),
+                return_type: Box::new(self.lower_type(&signature.return_type)),
+            },
+            TypeLiteral::Tuple(tuple) => Type::Tuple(
+                tuple
+                    .0
+                    .iter()
+                    .map(|r#type| self.lower_type(r#type))
+                    .collect(),
+            ),
+            TypeLiteral::Array(element) => Type::Array(Box::new(self.lower_type(element))),
+            TypeLiteral::Dyn(name) => {
+                if !self.traits.contains_key(name.as_str()) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("there is no trait `{}`", name.as_str()))
+                            .with_span(name.span),
+                    );
+                }
+                Type::Dyn(name.as_str().to_owned())
+            }
+        }
+    }
+
+    /// Unknown traits are reported and left out.
+    fn lower_bounds(&mut self, generics: &GenericParams) -> Bounds {
+        let mut bounds = Bounds::new();
+        for param in &generics.0 {
+            let mut traits = Vec::new();
+            for r#trait in &param.bounds {
+                if self.traits.contains_key(r#trait.as_str()) {
+                    traits.push(r#trait.as_str().to_owned());
+                } else {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("there is no trait `{}`", r#trait.as_str()))
+                            .with_span(r#trait.span),
+                    );
+                }
+            }
+            if !traits.is_empty() {
+                bounds.insert(param.name.as_str().to_owned(), traits);
+            }
+        }
+        bounds
+    }
+
+    fn lower_struct(&mut self, is_pub: bool, struct_def: &StructDef) -> Struct {
+        let mut definitions = HashMap::new();
+        for field in &struct_def.fields {
+            self.define(&mut definitions, field.ident.as_str(), field.ident.span);
+        }
+        let bounds = self.lower_bounds(&struct_def.generics);
+        self.struct_bounds
+            .insert(struct_def.name.as_str().to_owned(), bounds);
+        let generics = self.type_generics(struct_def.name.as_str());
+        self.generics = generics.clone();
+        let fields: Vec<_> = struct_def
+            .fields
+            .iter()
+            .map(|field| TypedName {
+                name: self.field_name(field.ident.as_str()),
+                r#type: self.lower_type(&field.r#type),
+            })
+            .collect();
+        self.generics = Vec::new();
+
+        self.structs.insert(
+            struct_def.name.as_str().to_owned(),
+            StructInfo {
+                fields: struct_def
+                    .fields
+                    .iter()
+                    .zip(&fields)
+                    .map(|(source, field)| (source.ident.as_str().to_owned(), field.name.clone()))
+                    .collect(),
+                types: fields.iter().map(|field| field.r#type.clone()).collect(),
+            },
+        );
+        Struct {
+            is_pub,
+            name: self.globals[struct_def.name.as_str()].clone(),
+            generics,
+            fields,
+            methods: Vec::new(),
+        }
+    }
+
+    fn lower_enum(&mut self, is_pub: bool, enum_def: &EnumDef) -> Enum {
+        let name = self.globals[enum_def.name.as_str()].clone();
+        let mut info = EnumInfo {
+            name: name.clone(),
+            variants: Vec::new(),
+        };
+        let mut variants = Vec::new();
+
+        for variant in &enum_def.variants {
+            if let VariantFields::Struct(fields) = &variant.fields {
+                let mut definitions = HashMap::new();
+                for field in fields {
+                    self.define(&mut definitions, field.ident.as_str(), field.ident.span);
+                }
+            }
+            let (fields, shape) = match &variant.fields {
+                VariantFields::Unit => (Vec::new(), VariantShape::Unit),
+                VariantFields::Tuple(types) => (
+                    types
+                        .iter()
+                        .enumerate()
+                        .map(|(i, r#type)| TypedName {
+                            name: format!("_{i}"),
+                            r#type: self.lower_type(r#type),
+                        })
+                        .collect(),
+                    VariantShape::Tuple(types.len()),
+                ),
+                VariantFields::Struct(fields) => (
+                    fields
+                        .iter()
+                        .map(|field| TypedName {
+                            name: self.variant_field_name(field.ident.as_str()),
+                            r#type: self.lower_type(&field.r#type),
+                        })
+                        .collect(),
+                    VariantShape::Struct(
+                        fields
+                            .iter()
+                            .map(|field| {
+                                let name = field.ident.as_str();
+                                (name.to_owned(), self.variant_field_name(name))
+                            })
+                            .collect(),
+                    ),
+                ),
+            };
+
+            if info
+                .variants
+                .iter()
+                .any(|existing| existing.source_name == variant.name.as_str())
+            {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "variant `{}` is already defined",
+                        variant.name.as_str()
+                    ))
+                    .with_span(variant.name.span),
+                );
+                continue;
+            }
+
+            let variant_info = VariantInfo {
+                index: info.variants.len(),
+                source_name: variant.name.as_str().to_owned(),
+                name: self.field_name(variant.name.as_str()),
+                shape,
+            };
+            variants.push(Variant {
+                name: variant_info.name.clone(),
+                fields,
+            });
+            info.variants.push(variant_info);
+        }
+
+        self.enums.insert(enum_def.name.as_str().to_owned(), info);
+        Enum {
+            is_pub,
+            name,
+            variants,
+            methods: Vec::new(),
+        }
+    }
+
+    /// Registers the functions of a trait, which every `impl` block for it has to provide.
+    fn declare_trait(&mut self, trait_def: &TraitDef) {
+        let name = trait_def.name.as_str();
+        if self.traits.contains_key(name) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("there already is a trait `{name}`"))
+                    .with_span(trait_def.name.span),
+            );
+            return;
+        }
+
+        // the type that implements the trait is unknown, so `Self` is a parameter
+        self.generics = vec!["Self".to_owned()];
+        let mut fns = HashMap::new();
+        for trait_fn in &trait_def.fns {
+            let fn_name = trait_fn.name.as_str();
+            if fns.contains_key(fn_name) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{name}` already has a function `{fn_name}`"))
+                        .with_span(trait_fn.name.span),
+                );
+                continue;
+            }
+
+            let signature = Signature {
+                generics: Vec::new(),
+                bounds: Bounds::new(),
+                params: trait_fn
+                    .params
+                    .iter()
+                    .map(|param| self.lower_type(&param.r#type))
+                    .collect(),
+                return_type: self.lower_type(&trait_fn.return_type),
+            };
+            fns.insert(
+                fn_name.to_owned(),
+                MethodInfo {
+                    name: self.variant_field_name(fn_name),
+                    has_receiver: trait_fn.receiver.is_some(),
+                    signature,
+                    span: trait_fn.name.span,
+                },
+            );
+        }
+        self.generics = Vec::new();
+
+        self.traits.insert(name.to_owned(), fns);
+    }
+
+    /// Registers the functions of an `impl` block, so they can be called from anywhere.
+    ///
+    /// The functions of an `impl Trait for Type` block have to match the ones of the trait.
+    fn declare_impl(&mut self, is_pub: bool, span: Span, block: &ImplBlock) {
+        if is_pub {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    "an `impl` block can't be `pub`, its functions are as visible as its type",
+                )
+                .with_span(span),
+            );
+        }
+
+        // the names that instances of the type already use
+        let r#type = block.r#type.as_str();
+        let members: HashSet<String> = if let Some(info) = self.structs.get(r#type) {
+            info.fields.iter().map(|(_, name)| name.clone()).collect()
+        } else if let Some(info) = self.enums.get(r#type) {
+            info.variants
+                .iter()
+                .flat_map(|variant| {
+                    let fields = match &variant.shape {
+                        VariantShape::Unit => Vec::new(),
+                        VariantShape::Tuple(count) => {
+                            (0..*count).map(|i| format!("_{i}")).collect()
+                        }
+                        VariantShape::Struct(names) => {
+                            names.iter().map(|(_, name)| name.clone()).collect()
+                        }
+                    };
+                    fields.into_iter().chain([variant.name.clone()])
+                })
+                .collect()
+        } else {
+            self.diagnostics.push(
+                Diagnostic::error(format!("there is no struct or enum `{type}`"))
+                    .with_span(block.r#type.span),
+            );
+            return;
+        };
+
+        // an unknown trait is reported, its functions are still registered
+        let mut trait_fns = None;
+        if let Some(r#trait) = &block.r#trait {
+            let trait_name = r#trait.as_str();
+            if let Some(fns) = self.traits.get(trait_name) {
+                let is_new = self
+                    .implementations
+                    .entry(r#type.to_owned())
+                    .or_default()
+                    .insert(trait_name.to_owned());
+                if !is_new {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("`{type}` already implements `{trait_name}`"))
+                            .with_span(r#trait.span),
+                    );
+                    return;
+                }
+                trait_fns = Some((trait_name, fns.clone()));
+            } else {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("there is no trait `{trait_name}`"))
+                        .with_span(r#trait.span),
+                );
+            }
+        }
+
+        self.generics = self.type_generics(r#type);
+        self.self_type = Some(r#type.to_owned());
+        for fn_def in &block.fns {
+            let fn_def = &fn_def.inner;
+            let name = fn_def.name.as_str();
+            let target_name = self.variant_field_name(name);
+
+            let fns = self.impls.entry(r#type.to_owned()).or_default();
+            let message = if fns.contains_key(name) {
+                format!("`{type}` already has a function `{name}`")
+            } else if members.contains(&target_name) {
+                format!("`{type}` already has a field or variant called `{name}`")
+            } else if let Some((r#trait, trait_fns)) = &trait_fns {
+                let signature = self.lower_signature(fn_def);
+                match trait_fns.get(name) {
+                    None => format!("`{name}` isn't a function of `{trait}`"),
+                    Some(expected)
+                        if expected.has_receiver != fn_def.receiver.is_some()
+                            || !matches_trait_fn(&expected.signature, &signature, r#type) =>
+                    {
+                        format!("`{name}` doesn't match its declaration in `{trait}`")
+                    }
+                    Some(_) => {
+                        self.impls.entry(r#type.to_owned()).or_default().insert(
+                            name.to_owned(),
+                            MethodInfo {
+                                name: target_name,
+                                has_receiver: fn_def.receiver.is_some(),
+                                signature,
+                                span: fn_def.name.span,
+                            },
+                        );
+                        continue;
+                    }
+                }
+            } else {
+                let signature = self.lower_signature(fn_def);
+                self.impls.entry(r#type.to_owned()).or_default().insert(
+                    name.to_owned(),
+                    MethodInfo {
+                        name: target_name,
+                        has_receiver: fn_def.receiver.is_some(),
+                        signature,
+                        span: fn_def.name.span,
+                    },
+                );
+                continue;
+            };
+            self.diagnostics
+                .push(Diagnostic::error(message).with_span(fn_def.name.span));
+        }
+        self.generics = Vec::new();
+        self.self_type = None;
+
+        if let Some((r#trait, trait_fns)) = trait_fns {
+            let mut missing: Vec<_> = trait_fns
+                .keys()
+                .filter(|name| {
+                    !block
+                        .fns
+                        .iter()
+                        .any(|fn_def| fn_def.inner.name.as_str() == *name)
+                })
+                .collect();
+            missing.sort();
+            for name in missing {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`impl {trait} for {type}` is missing `{name}`"))
+                        .with_span(block.r#type.span),
+                );
+            }
+        }
+    }
+
+    /// Lowers the types of a function, which can refer to its own generic parameters
+    /// and the ones that are already in scope.
+    fn lower_signature(&mut self, fn_def: &FnDef) -> Signature {
+        let generics = generic_names(&fn_def.generics);
+        let bounds = self.lower_bounds(&fn_def.generics);
+        let outer = self.generics.len();
+        self.generics.extend(generics.iter().cloned());
+
+        let params = fn_def
+            .params
+            .iter()
+            .map(|param| self.lower_type(&param.r#type))
+            .collect();
+        let return_type = self.lower_type(&fn_def.return_type);
+
+        self.generics.truncate(outer);
+        Signature {
+            generics,
+            bounds,
+            params,
+            return_type,
+        }
+    }
+
+    fn lower_fn(
+        &mut self,
+        is_pub: bool,
+        name: String,
+        fn_def: &FnDef,
+        signature: Signature,
+    ) -> Function {
+        self.scopes.push(HashMap::new());
+        let outer = self.generics.len();
+        self.generics.extend(signature.generics.iter().cloned());
+        let outer_bounds = self.bounds.clone();
+        for param in &signature.generics {
+            self.bounds.remove(param);
+        }
+        self.bounds.extend(signature.bounds.clone());
+
+        let receiver = self.options.receiver;
+        let mut has_mut_receiver = false;
+        if let Some(source_receiver) = &fn_def.receiver {
+            if let Some(self_type) = &self.self_type {
+                let receiver_type = self.receiver_type(self_type);
+                self.types.insert(receiver.to_owned(), receiver_type);
+                self.taken.insert(receiver.to_owned());
+                self.scopes
+                    .last_mut()
+                    .expect("the scope was just pushed")
+                    .insert("self".to_owned(), receiver.to_owned());
+                has_mut_receiver = source_receiver.is_mut;
+            } else {
+                self.diagnostics.push(
+                    Diagnostic::error("only functions in an `impl` block can take `self`")
+                        .with_span(source_receiver.span),
+                );
+            }
+        }
+        if has_mut_receiver {
+            self.mutable.insert(receiver.to_owned());
+        }
+
+        let mut definitions = HashMap::new();
+        for param in &fn_def.params {
+            self.define(&mut definitions, param.ident.as_str(), param.ident.span);
+        }
+        let mut params: Vec<_> = fn_def
+            .params
+            .iter()
+            .zip(signature.params)
+            .map(|(param, r#type)| {
+                let name = self.declare(param.ident.as_str());
+                self.types.insert(name.clone(), r#type.clone());
+                TypedName { name, r#type }
+            })
+            .collect();
+        if self.options.dictionary_passing {
+            // the name of the type that implements each bound
+            for param in &signature.generics {
+                for r#trait in signature.bounds.get(param).into_iter().flatten() {
+                    let name = self.fresh_name(&format!("{param}_{trait}"));
+                    self.scopes
+                        .last_mut()
+                        .expect("the scope was just pushed")
+                        .insert(implementation_key(param, r#trait), name.clone());
+                    params.push(TypedName {
+                        name,
+                        r#type: Type::Named("String".to_owned()),
+                    });
+                }
+            }
+            // and the name of the type behind every `dyn` parameter
+            let dyn_params: Vec<_> = fn_def
+                .params
+                .iter()
+                .zip(&params)
+                .filter_map(|(param, typed)| match &typed.r#type {
+                    Type::Dyn(r#trait) => Some((param.ident.as_str(), r#trait.clone())),
+                    _ => None,
+                })
+                .collect();
+            for (source, r#trait) in dyn_params {
+                params.push(TypedName {
+                    name: self.declare_implementation(source, &r#trait),
+                    r#type: Type::Named("String".to_owned()),
+                });
+            }
+        }
+
+        let mut body = Vec::new();
+        self.is_in_fn = true;
+        self.return_type = Some(signature.return_type.clone());
+        let (value, value_type) = self.lower_scope(&fn_def.body, &mut body);
+        self.is_in_fn = false;
+        self.return_type = None;
+        if !ends_with_jump(&body) {
+            body.push(Stmt::Return(value));
+        }
+
+        // the type isn't known if the body never ends
+        if let Some(found) = value_type {
+            match &fn_def.body.node.expr {
+                Some(expr) => self.check_type(&signature.return_type, &found, expr.span),
+                None if signature.return_type != found => self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "`{}` has to return `{}`, but its body ends without a value",
+                        fn_def.name.as_str(),
+                        signature.return_type
+                    ))
+                    .with_span(fn_def.name.span),
+                ),
+                None => {}
+            }
+        }
+
+        self.scopes.pop();
+        self.generics.truncate(outer);
+        self.bounds = outer_bounds;
+        if has_mut_receiver {
+            self.mutable.remove(receiver);
+        }
+
+        Function {
+            is_pub,
+            name,
+            generics: signature.generics,
+
+            params,
+            return_type: signature.return_type,
+
+            body,
+        }
+    }
+
+    fn lower_global(&mut self, is_pub: bool, name: &Ident, r#let: &Let) -> Global {
+        // locals of the setup live at the module level
+        self.scopes.push(HashMap::new());
+        let mut setup = Vec::new();
+        let value = self.lower_bound_value(&r#let.right, &mut setup);
+        let value = self.owned(&r#let.right, value, r#let.is_mut);
+        self.scopes.pop();
+
+        Global {
+            is_pub,
+            is_mut: r#let.is_mut,
+            name: self.globals[name.as_str()].clone(),
+            r#type: r#let
+                .r#type
+                .as_deref()
+                .map(|r#type| self.lower_type(r#type)),
+
+            setup,
+            value,
+        }
+    }
+
+    /// Lowers a top level `let` whose pattern isn't a single name into a global for every
+    /// name in it. The setup of the first one destructures the value.
+    fn lower_global_pattern(&mut self, is_pub: bool, r#let: &Let) -> Vec<Global> {
+        self.scopes.push(HashMap::new());
+        let mut setup = Vec::new();
+        let bindings = self.destructure(r#let, &mut setup);
+        self.scopes.pop();
+
+        bindings
+            .into_iter()
+            .map(|(name, value)| Global {
+                is_pub,
+                is_mut: r#let.is_mut,
+                name: self.globals[&name].clone(),
+                r#type: None,
+
+                setup: std::mem::take(&mut setup),
+                value,
+            })
+            .collect()
+    }
+
+    fn lower_statement(&mut self, statement: &Spanned<Statement>, out: &mut Vec<Stmt>) {
+        match &statement.node {
+            Statement::Let(r#let) => {
+                let Pattern::Binding(name) = &r#let.pattern.node else {
+                    return self.lower_let_pattern(r#let, out);
+                };
+                // the value can still refer to a shadowed binding of the same name
+                let value = self.lower_bound_value(&r#let.right, out);
+                let value = self.owned(&r#let.right, value, r#let.is_mut);
+                let r#type = match &r#let.r#type {
+                    Some(annotation) => {
+                        let r#type = self.lower_type(annotation);
+                        self.check_value(&r#type, &r#let.right, &mut HashMap::new());
+                        Some(r#type)
+                    }
+                    None => self.infer(&r#let.right),
+                };
+                let implementation = match &r#type {
+                    Some(Type::Dyn(r#trait)) if self.options.dictionary_passing => Some((
+                        r#trait.clone(),
+                        self.dyn_implementation(r#trait, &r#let.right),
+                    )),
+                    _ => None,
+                };
+                let source = name.as_str();
+                let name = self.declare(source);
+                if let Some(r#type) = r#type {
+                    self.types.insert(name.clone(), r#type);
+                }
+                if r#let.is_mut {
+                    self.mutable.insert(name.clone());
+                }
+
+                out.push(Stmt::Let {
+                    is_mut: r#let.is_mut,
+                    name,
+                    value,
+                });
+                if let Some((r#trait, implementation)) = implementation {
+                    out.push(Stmt::Let {
+                        is_mut: r#let.is_mut,
+                        name: self.declare_implementation(source, &r#trait),
+                        value: implementation.unwrap_or(Expr::Unit),
+                    });
+                }
+            }
+
+            // the values of these are `()`
+            Statement::Loop(r#loop) => {
+                self.lower_loop(
+                    r#loop.label.as_ref(),
+                    LoopKind::Loop,
+                    &r#loop.body,
+                    false,
+                    out,
+                );
+            }
+            Statement::While(r#while) => {
+                self.lower_loop(
+                    r#while.label.as_ref(),
+                    LoopKind::While(&r#while.condition),
+                    &r#while.body,
+                    false,
+                    out,
+                );
+            }
+            Statement::For(r#for) => {
+                self.lower_loop(
+                    r#for.label.as_ref(),
+                    LoopKind::For(r#for),
+                    &r#for.body,
+                    false,
+                    out,
+                );
+            }
+            Statement::Assign(assign) => self.lower_assign(assign, out),
+            Statement::Break(r#break) => self.lower_break(r#break, statement.span, out),
+            Statement::Continue(r#continue) => self.lower_continue(r#continue, statement.span, out),
+            Statement::Return(r#return) => self.lower_return(r#return, statement.span, out),
+            Statement::Expression(expression) => {
+                let value = self.lower_expr_at(expression, statement.span, out);
+                if !value.is_atomic() && !ends_with_jump(out) {
+                    out.push(Stmt::Expr(value));
+                }
+            }
+        }
+    }
+
+    /// `mut` bindings, their fields and their elements can be assigned to.
+    fn lower_assign(&mut self, assign: &Assign, out: &mut Vec<Stmt>) {
+        let mut root = &assign.target;
+        loop {
+            root = match &root.node {
+                Expression::FieldAccess(access) => &access.value,
+                Expression::Index(index) => &index.value,
+                _ => break,
+            };
+        }
+        let is_receiver =
+            |lowerer: &Self, name: &str| name == "self" && lowerer.self_type.is_some();
+
+        let message = match &root.node {
+            Expression::Variable(variable) => {
+                let name = variable.0.as_str();
+                match self.resolve(name) {
+                    // the receiver can't be replaced in targets that call it `this`
+                    Some(_) if std::ptr::eq(root, &assign.target) && is_receiver(self, name) => {
+                        Some("`self` can't be assigned to, only its fields can".to_owned())
+                    }
+                    Some(target) if self.mutable.contains(&target) => None,
+                    Some(_) if is_receiver(self, name) => {
+                        Some("`self` isn't mutable, take it as `mut self`".to_owned())
+                    }
+                    Some(_) => Some(format!(
+                        "`{name}` isn't mutable, declare it with `let mut {name}`"
+                    )),
+                    None => Some(format!("`{name}` isn't defined")),
+                }
+            }
+            _ => Some(
+                "only `mut` bindings, their fields and their elements can be assigned to"
+                    .to_owned(),
+            ),
+        };
+        if let Some(message) = message {
+            self.diagnostics
+                .push(Diagnostic::error(message).with_span(root.span));
+        }
+
+        let op = assign.op.map(compound_op);
+        let mut value = self.lower_expr(&assign.value, out);
+        match op {
+            Some(op) => {
+                let (symbol, operands) = operator(op);
+                self.check_operands(
+                    &format!("{symbol}="),
+                    &[&assign.target, &assign.value],
+                    operands,
+                );
+            }
+            None => {
+                value = self.owned(&assign.value, value, true);
+                if let Some(r#type) = self.infer(&assign.target) {
+                    self.check_value(&r#type, &assign.value, &mut HashMap::new());
+                }
+            }
+        }
+        let operand_type = self.operand_type(&assign.target, &assign.value);
+
+        match &assign.target.node {
+            Expression::Variable(variable) => {
+                let name = variable.0.as_str();
+                let name = self.resolve(name).unwrap_or_else(|| name.to_owned());
+                let value = self.assigned_value(
+                    op,
+                    operand_type.clone(),
+                    Expr::Var(name.clone()),
+                    value,
+                    out,
+                );
+                out.push(Stmt::Assign { name, value });
+
+                // the type behind a `dyn` value changes with it
+                if let (Some(Type::Dyn(r#trait)), Some(implementation)) =
+                    (&operand_type, self.resolve(&dyn_key(variable.0.as_str())))
+                {
+                    let value = self.dyn_implementation(r#trait, &assign.value);
+                    out.push(Stmt::Assign {
+                        name: implementation,
+                        value: value.unwrap_or(Expr::Unit),
+                    });
+                }
+            }
+            Expression::FieldAccess(access) => {
+                let Expr::Field {
+                    value: target,
+                    name,
+                } = self.lower_field_access(access, out)
+                else {
+                    unreachable!("a field access is lowered to a field")
+                };
+                let target = self.reused_operand(op, *target, out);
+
+                let current = Expr::Field {
+                    value: Box::new(target.clone()),
+                    name: name.clone(),
+                };
+                let mut value = self.assigned_value(op, operand_type.clone(), current, value, out);
+                if self.options.flatten_operands && !value.is_atomic() {
+                    self.spill(&mut value, out);
+                }
+                out.push(Stmt::AssignField {
+                    target,
+                    name,
+                    value,
+                });
+            }
+            Expression::Index(index) => {
+                let [target, index]: [Expr; 2] = self
+                    .lower_operands([&index.value, &index.index], out)
+                    .try_into()
+                    .expect("an index has two operands");
+                let target = self.reused_operand(op, target, out);
+                let index = self.reused_operand(op, index, out);
+
+                let current = Expr::Index {
+                    value: Box::new(target.clone()),
+                    index: Box::new(index.clone()),
+                };
+                let mut value = self.assigned_value(op, operand_type.clone(), current, value, out);
+                if self.options.flatten_operands && !value.is_atomic() {
+                    self.spill(&mut value, out);
+                }
+                out.push(Stmt::AssignIndex {
+                    target,
+                    index,
+                    value,
+                });
+            }
+            // already reported
+            _ => {}
+        }
+    }
+
+    /// Stores an operand of the target of an assignment in a temporary
+    /// if a compound assignment evaluates it twice.
+    fn reused_operand(
+        &mut self,
+        op: Option<BinOp>,
+        mut operand: Expr,
+        out: &mut Vec<Stmt>,
+    ) -> Expr {
+        if op.is_some() && !operand.is_constant() && !operand.is_atomic() {
+            self.spill(&mut operand, out);
+        }
+        operand
+    }
+
+    /// Combines the `current` value of the target of an assignment with `value`
+    /// if it's a compound assignment.
+    fn assigned_value(
+        &mut self,
+        op: Option<BinOp>,
+        operand_type: Option<Type>,
+        mut current: Expr,
+        mut value: Expr,
+        out: &mut Vec<Stmt>,
+    ) -> Expr {
+        let Some(op) = op else {
+            return value;
+        };
+
+        if self.options.flatten_operands {
+            for operand in [&mut value, &mut current] {
+                if !operand.is_atomic() {
+                    self.spill(operand, out);
+                }
+            }
+        }
+        Expr::Bin {
+            op,
+            left: Box::new(current),
+            right: Box::new(value),
+            operand_type,
+        }
+    }
+
+    /// Lowers the value of a `let` that binds a single name.
+    ///
+    /// A closure is bound directly instead of being stored in a temporary first.
+    fn lower_bound_value(&mut self, value: &Spanned<Expression>, out: &mut Vec<Stmt>) -> Expr {
+        match &value.node {
+            Expression::Closure(closure) => Expr::Closure(Box::new(self.lower_closure(closure))),
+            _ => self.lower_expr(value, out),
+        }
+    }
+
+    fn lower_closure(&mut self, closure: &Closure) -> super::Closure {
+        // `break` and `continue` can't leave the body
+        let loops = std::mem::take(&mut self.loops);
+        self.closures.push(ClosureContext {
+            depth: self.scopes.len(),
+            captures: Vec::new(),
+        });
+        self.scopes.push(HashMap::new());
+
+        let mut definitions = HashMap::new();
+        for param in &closure.params {
+            self.define(&mut definitions, param.ident.as_str(), param.ident.span);
+        }
+        let params = closure
+            .params
+            .iter()
+            .map(|param| {
+                let name = self.declare(param.ident.as_str());
+                if let Some(r#type) = &param.r#type {
+                    let r#type = self.lower_type(r#type);
+                    self.types.insert(name.clone(), r#type);
+                }
+                name
+            })
+            .collect();
+        let mut body = Vec::new();
+        let is_in_fn = std::mem::replace(&mut self.is_in_fn, true);
+        let return_type = self.return_type.take();
+        let value = self.lower_expr(&closure.body, &mut body);
+        let value = self.owned(&closure.body, value, false);
+        self.is_in_fn = is_in_fn;
+        self.return_type = return_type;
+        if !ends_with_jump(&body) {
+            body.push(Stmt::Return(value));
+        }
+
+        self.scopes.pop();
+        let captures = self
+            .closures
+            .pop()
+            .expect("the closure was pushed above")
+            .captures
+            .into_iter()
+            .map(|name| Capture {
+                is_mut: self.mutable.contains(&name),
+                name,
+            })
+            .collect();
+        self.loops = loops;
+
+        super::Closure {
+            params,
+            captures,
+            body,
+        }
+    }
+
+    /// Lowers the statements of `scope` and returns its value with its type,
+    /// which isn't known if the scope is never left at its end.
+    fn lower_scope(&mut self, scope: &CodeScope, out: &mut Vec<Stmt>) -> (Expr, Option<Type>) {
+        self.scopes.push(HashMap::new());
+
+        let start = out.len();
+        for statement in &scope.statements {
+            self.lower_statement(statement, out);
+        }
+        let (value, r#type) = match &scope.expr {
+            Some(expr) => {
+                let value = self.lower_expr(expr, out);
+                (self.owned(expr, value, false), self.infer(expr))
+            }
+            None => (Expr::Unit, Some(Type::Tuple(Vec::new()))),
+        };
+        let r#type = r#type.filter(|_| !diverges(&out[start..]));
+
+        self.scopes.pop();
+        (value, r#type)
+    }
+
+    /// Lowers `expression`, pushing every statement that has to run beforehand to `out`.
+    fn lower_expr(&mut self, expression: &Spanned<Expression>, out: &mut Vec<Stmt>) -> Expr {
+        self.lower_expr_at(&expression.node, expression.span, out)
+    }
+
+    fn lower_expr_at(&mut self, expression: &Expression, span: Span, out: &mut Vec<Stmt>) -> Expr {
+        match expression {
+            Expression::NumLit(num_lit) => Expr::Num(num_lit.0),
+            Expression::StringLit(string_lit) => Expr::Str(string_lit.0.clone()),
+            Expression::Variable(variable) => {
+                let name = variable.0.as_str();
+                Expr::Var(self.resolve(name).unwrap_or_else(|| name.to_owned()))
+            }
+
+            Expression::UnaryExpr(unary_expr) => self.lower_unary_expr(unary_expr, out),
+            Expression::BinExpr(bin_expr) => self.lower_bin_expr(bin_expr, out),
+            Expression::FnCall(fn_call) => self.lower_fn_call(fn_call, out),
+            Expression::FieldAccess(access) => self.lower_field_access(access, out),
+            Expression::TupleIndex(tuple_index) => self.lower_tuple_index(tuple_index, out),
+            Expression::Index(index) => {
+                let [value, index]: [Expr; 2] = self
+                    .lower_operands([&index.value, &index.index], out)
+                    .try_into()
+                    .expect("an index has two operands");
+
+                Expr::Index {
+                    value: Box::new(value),
+                    index: Box::new(index),
+                }
+            }
+            Expression::MethodCall(call) => self.lower_method_call(call, out),
+            Expression::Closure(closure) => {
+                // targets define closures with a statement
+                let mut value = Expr::Closure(Box::new(self.lower_closure(closure)));
+                self.spill(&mut value, out);
+                value
+            }
+            Expression::TupleLiteral(literal) if literal.0.is_empty() => Expr::Unit,
+            Expression::TupleLiteral(literal) => Expr::Tuple(self.lower_values(&literal.0, out)),
+            Expression::ArrayLiteral(literal) => Expr::Array(self.lower_values(&literal.0, out)),
+            Expression::StructLiteral(literal) => self.lower_struct_literal(literal, span, out),
+            Expression::Path(path) => match self.resolve_variant(path, span) {
+                Some((
+                    r#enum,
+                    VariantInfo {
+                        name: variant,
+                        shape: VariantShape::Unit,
+                        ..
+                    },
+                )) => Expr::Variant {
+                    r#enum,
+                    variant,
+                    fields: Vec::new(),
+                },
+                Some((_, variant)) => {
+                    self.wrong_variant_shape(path, &variant.shape, span);
+                    Expr::Unit
+                }
+                None => Expr::Unit,
+            },
+            Expression::CodeScope(scope) => {
+                let (value, r#type) = self.lower_scope(scope, out);
+                self.record_type(span, r#type);
+                value
+            }
+            Expression::If(r#if) => {
+                let result = self.temp();
+                out.push(Stmt::Let {
+                    is_mut: true,
+                    name: result.clone(),
+                    value: Expr::Unit,
+                });
+                let r#type = self.lower_if(r#if, &result, out);
+                self.record_type(span, r#type);
+                Expr::Var(result)
+            }
+            Expression::Match(r#match) => {
+                let (value, r#type) = self.lower_match(r#match, out);
+                self.record_type(span, r#type);
+                value
+            }
+            Expression::Range(_) => {
+                self.diagnostics.push(
+                    Diagnostic::error("ranges can only be iterated over by a `for` loop")
+                        .with_span(span),
+                );
+                Expr::Unit
+            }
+            Expression::Loop(r#loop) => self.lower_loop(
+                r#loop.label.as_ref(),
+                LoopKind::Loop,
+                &r#loop.body,
+                true,
+                out,
+            ),
+            Expression::While(r#while) => self.lower_loop(
+                r#while.label.as_ref(),
+                LoopKind::While(&r#while.condition),
+                &r#while.body,
+                false,
+                out,
+            ),
+            Expression::For(r#for) => self.lower_loop(
+                r#for.label.as_ref(),
+                LoopKind::For(r#for),
+                &r#for.body,
+                false,
+                out,
+            ),
+            // nothing after a jump is evaluated, so its value doesn't matter
+            Expression::Break(r#break) => {
+                self.lower_break(r#break, span, out);
+                Expr::Unit
+            }
+            Expression::Continue(r#continue) => {
+                self.lower_continue(r#continue, span, out);
+                Expr::Unit
+            }
+            Expression::Return(r#return) => {
+                self.lower_return(r#return, span, out);
+                Expr::Unit
+            }
+        }
+    }
+
+    /// Remembers the type of the block, `if` or `match` at `span` for [`Lowerer::infer`].
+    fn record_type(&mut self, span: Span, r#type: Option<Type>) {
+        if let Some(r#type) = r#type {
+            self.value_types.insert(span, r#type);
+        }
+    }
+
+    /// Makes the condition of a statement atomic if operands are flattened.
+    fn condition(&mut self, mut condition: Expr, out: &mut Vec<Stmt>) -> Expr {
+        if self.options.flatten_operands && !condition.is_atomic() {
+            self.spill(&mut condition, out);
+        }
+        condition
+    }
+
+    /// Lowers `r#if` to an [`Stmt::If`] whose branches assign their value to `result`.
+    ///
+    /// `else if` branches become nested [`Stmt::If`]s that assign to the same `result`.
+    /// Returns the type of the value, which both branches have to agree on.
+    fn lower_if(&mut self, r#if: &If, result: &str, out: &mut Vec<Stmt>) -> Option<Type> {
+        let condition = self.lower_expr(&r#if.condition, out);
+        let condition = self.condition(condition, out);
+
+        let mut then = Vec::new();
+        let (value, then_type) = self.lower_scope(&r#if.then, &mut then);
+        assign_result(result, value, &mut then);
+
+        let mut r#else = Vec::new();
+        let r#type = match &r#if.r#else {
+            Some(Spanned {
+                node: Else::If(else_if),
+                span,
+            }) => {
+                let else_type = self.lower_if(else_if, result, &mut r#else);
+                let branches = vec![(then_type, value_span(&r#if.then)), (else_type, *span)];
+                self.check_branches(branches)
+            }
+            Some(Spanned {
+                node: Else::Scope(scope),
+                span,
+            }) => {
+                let (value, else_type) = self.lower_scope(scope, &mut r#else);
+                assign_result(result, value, &mut r#else);
+                let else_span = scope.expr.as_ref().map_or(*span, |expr| expr.span);
+                let branches = vec![(then_type, value_span(&r#if.then)), (else_type, else_span)];
+                self.check_branches(branches)
+            }
+            // without an `else` there is no value
+            None => Some(Type::Tuple(Vec::new())),
+        };
+
+        out.push(Stmt::If {
+            condition,
+            then,
+            r#else,
+        });
+        r#type
+    }
+
+    /// Lowers any kind of loop.
+    ///
+    /// Only a `loop` that `has_result` is left with the value of its `break`.
+    fn lower_loop(
+        &mut self,
+        label: Option<&Spanned<Label>>,
+        kind: LoopKind,
+        body: &CodeScope,
+        has_result: bool,
+        out: &mut Vec<Stmt>,
+    ) -> Expr {
+        let result = has_result.then(|| {
+            let result = self.temp();
+            out.push(Stmt::Let {
+                is_mut: true,
+                name: result.clone(),
+                value: Expr::Unit,
+            });
+            result
+        });
+        let source_label = label.map(|label| label.0.as_str().to_owned());
+        let label = self.fresh_label(source_label.as_deref());
+
+        // the binding of a `for` loop is only visible in its body
+        self.scopes.push(HashMap::new());
+        let mut statements = Vec::new();
+        let header = match kind {
+            LoopKind::Loop => LoopHeader::Loop(None),
+            LoopKind::While(condition) => {
+                let mut setup = Vec::new();
+                let condition = self.lower_expr(condition, &mut setup);
+
+                if setup.is_empty() && (!self.options.flatten_operands || condition.is_atomic()) {
+                    LoopHeader::Loop(Some(condition))
+                } else {
+                    // the statements of the condition have to run before every iteration
+                    statements = setup;
+                    let condition = self.condition(condition, &mut statements);
+                    let negated = Expr::Unary {
+                        op: UnaryOp::Not,
+                        operand: Box::new(condition),
+                    };
+                    let negated = self.condition(negated, &mut statements);
+                    statements.push(Stmt::If {
+                        condition: negated,
+                        then: vec![Stmt::Break {
+                            label: label.clone(),
+                        }],
+                        r#else: Vec::new(),
+                    });
+                    LoopHeader::Loop(None)
+                }
+            }
+            LoopKind::For(r#for) => self.lower_for_header(r#for, out),
+        };
+        // flags are declared right before the loop so they are reset every time it starts
+        let flags_index = out.len();
+
+        self.loops.push(LoopContext {
+            source_label,
+            label: label.clone(),
+            result: result.clone(),
+            breaks_with_value: matches!(kind, LoopKind::Loop),
+
+            break_flag: None,
+            continue_flag: None,
+            forwarded: Vec::new(),
+        });
+        let (value, _) = self.lower_scope(body, &mut statements);
+        if !value.is_atomic() && !ends_with_jump(&statements) {
+            statements.push(Stmt::Expr(value));
+        }
+        let context = self.loops.pop().expect("the loop was pushed above");
+        self.scopes.pop();
+
+        let flags = [context.break_flag, context.continue_flag]
+            .into_iter()
+            .flatten()
+            .map(|flag| Stmt::Let {
+                is_mut: true,
+                name: flag,
+                value: Expr::Bool(false),
+            });
+        out.splice(flags_index..flags_index, flags);
+        out.push(match header {
+            LoopHeader::Loop(condition) => Stmt::Loop {
+                label,
+                condition,
+                body: statements,
+            },
+            LoopHeader::ForRange {
+                binding,
+                start,
+                end,
+                is_inclusive,
+            } => Stmt::ForRange {
+                label,
+                binding,
+                start,
+                end,
+                is_inclusive,
+                body: statements,
+            },
+            LoopHeader::ForEach { binding, iterable } => Stmt::ForEach {
+                label,
+                binding,
+                iterable,
+                body: statements,
+            },
+        });
+
+        for forwarded in context.forwarded {
+            let parent = &self
+                .loops
+                .last()
+                .expect("jumps are only forwarded to outer loops")
+                .label;
+
+            let (flag, then) = match forwarded {
+                Forwarded::Continue { flag, target } if &target == parent => (
+                    flag.clone(),
+                    vec![
+                        Stmt::Assign {
+                            name: flag,
+                            value: Expr::Bool(false),
+                        },
+                        Stmt::Continue { label: target },
+                    ],
+                ),
+                Forwarded::Break { flag, .. } | Forwarded::Continue { flag, .. } => (
+                    flag,
+                    vec![Stmt::Break {
+                        label: parent.clone(),
+                    }],
+                ),
+            };
+            out.push(Stmt::If {
+                condition: Expr::Var(flag),
+                then,
+                r#else: Vec::new(),
+            });
+        }
+
+        result.map_or(Expr::Unit, Expr::Var)
+    }
+
+    /// Evaluates what a `for` loop iterates over and declares its binding.
+    fn lower_for_header(&mut self, r#for: &For, out: &mut Vec<Stmt>) -> LoopHeader {
+        // the binding is declared last, the iterable can't refer to it
+        match &r#for.iterable.node {
+            Expression::Range(range) => {
+                // the targets count differently from a fractional start or end
+                self.check_integer(&range.start, "the start of a range");
+                self.check_integer(&range.end, "the end of a range");
+
+                let start = self.lower_expr(&range.start, out);
+                let start = self.condition(start, out);
+                let mut end = self.lower_expr(&range.end, out);
+                if !end.is_constant() {
+                    self.spill(&mut end, out);
+                }
+
+                let binding = self.declare(r#for.binding.as_str());
+                self.types
+                    .insert(binding.clone(), Type::Named("int".to_owned()));
+
+                LoopHeader::ForRange {
+                    binding,
+                    start,
+                    end,
+                    is_inclusive: range.is_inclusive,
+                }
+            }
+            _ => {
+                let element = match self.infer(&r#for.iterable) {
+                    Some(Type::Array(element)) => Some(*element),
+                    _ => None,
+                };
+                let iterable = self.lower_expr(&r#for.iterable, out);
+                let iterable = self.condition(iterable, out);
+
+                let binding = self.declare(r#for.binding.as_str());
+                if let Some(element) = element {
+                    self.types.insert(binding.clone(), element);
+                }
+                LoopHeader::ForEach { binding, iterable }
+            }
+        }
+    }
+
+    fn lower_break(&mut self, r#break: &Break, span: Span, out: &mut Vec<Stmt>) {
+        let Some(target) = self.jump_target(r#break.label.as_ref(), span, "break") else {
+            return;
+        };
+
+        if let Some(value) = &r#break.value {
+            let lowered = self.lower_expr(value, out);
+            let lowered = self.owned(value, lowered, false);
+            let context = &self.loops[target];
+
+            if !context.breaks_with_value {
+                self.diagnostics.push(
+                    Diagnostic::error("only a `loop` can be left with a value")
+                        .with_span(value.span),
+                );
+            } else if let Some(result) = &context.result {
+                out.push(Stmt::Assign {
+                    name: result.clone(),
+                    value: lowered,
+                });
+            } else if !lowered.is_atomic() {
+                out.push(Stmt::Expr(lowered));
+            }
+        }
+
+        self.jump(target, false, out);
+    }
+
+    fn lower_return(&mut self, r#return: &Return, span: Span, out: &mut Vec<Stmt>) {
+        if !self.is_in_fn {
+            self.diagnostics.push(
+                Diagnostic::error("`return` can only be used inside of a function or closure")
+                    .with_span(span),
+            );
+        }
+
+        let value = match &r#return.value {
+            Some(value) => {
+                let lowered = self.lower_expr(value, out);
+                if let Some(return_type) = self.return_type.clone() {
+                    self.check_value(&return_type, value, &mut HashMap::new());
+                }
+                self.owned(value, lowered, false)
+            }
+            None => {
+                if let Some(return_type) = self.return_type.clone() {
+                    self.check_type(&return_type, &Type::Tuple(Vec::new()), span);
+                }
+                Expr::Unit
+            }
+        };
+        out.push(Stmt::Return(value));
+    }
+
+    fn lower_continue(&mut self, r#continue: &Continue, span: Span, out: &mut Vec<Stmt>) {
+        if let Some(target) = self.jump_target(r#continue.label.as_ref(), span, "continue") {
+            self.jump(target, true, out);
+        }
+    }
+
+    /// Finds the index of the loop a `break` or `continue` refers to.
+    fn jump_target(
+        &mut self,
+        label: Option<&Spanned<Label>>,
+        span: Span,
+        keyword: &str,
+    ) -> Option<usize> {
+        let target = match label {
+            Some(label) => self
+                .loops
+                .iter()
+                .rposition(|context| context.source_label.as_deref() == Some(label.0.as_str())),
+            None => self.loops.len().checked_sub(1),
+        };
+
+        if target.is_none() {
+            self.diagnostics.push(match label {
+                Some(label) => Diagnostic::error(format!(
+                    "there is no loop with the label `'{}` around this `{keyword}`",
+                    label.0.as_str()
+                ))
+                .with_span(label.span),
+                None => Diagnostic::error(format!("`{keyword}` outside of a loop")).with_span(span),
+            });
+        }
+        target
+    }
+
+    fn jump(&mut self, target: usize, is_continue: bool, out: &mut Vec<Stmt>) {
+        let innermost = self.loops.len() - 1;
+
+        if target == innermost || self.options.labeled_jumps {
+            let label = self.loops[target].label.clone();
+            out.push(match is_continue {
+                true => Stmt::Continue { label },
+                false => Stmt::Break { label },
+            });
+            return;
+        }
+
+        // leave the inner loops one after another
+        let flag = match is_continue {
+            true => self.loops[target].continue_flag.clone(),
+            false => self.loops[target].break_flag.clone(),
+        }
+        .unwrap_or_else(|| {
+            let flag = self.temp();
+            match is_continue {
+                true => self.loops[target].continue_flag = Some(flag.clone()),
+                false => self.loops[target].break_flag = Some(flag.clone()),
+            }
+            flag
+        });
+
+        let target_label = self.loops[target].label.clone();
+        let forwarded = match is_continue {
+            true => Forwarded::Continue {
+                flag: flag.clone(),
+                target: target_label,
+            },
+            false => Forwarded::Break {
+                flag: flag.clone(),
+                target: target_label,
+            },
+        };
+        for context in &mut self.loops[target + 1..] {
+            if !context.forwarded.contains(&forwarded) {
+                context.forwarded.push(forwarded.clone());
+            }
+        }
+
+        out.push(Stmt::Assign {
+            name: flag,
+            value: Expr::Bool(true),
+        });
+        out.push(Stmt::Break {
+            label: self.loops[innermost].label.clone(),
+        });
+    }
+
+    fn lower_unary_expr(&mut self, unary_expr: &UnaryExpr, out: &mut Vec<Stmt>) -> Expr {
+        let (op, expression, symbol, operands) = match unary_expr {
+            UnaryExpr::Neg(expr) => (UnaryOp::Neg, &expr.expression, "-", Operands::Number),
+            UnaryExpr::Not(expr) => (UnaryOp::Not, &expr.expression, "!", Operands::Bool),
+        };
+
+        let [operand]: [Expr; 1] = self
+            .lower_operands(std::slice::from_ref(expression), out)
+            .try_into()
+            .expect("a unary expression has one operand");
+        self.check_operands(symbol, &[expression], operands);
+
+        Expr::Unary {
+            op,
+            operand: Box::new(operand),
+        }
+    }
+
+    fn lower_bin_expr(&mut self, bin_expr: &BinExpr, out: &mut Vec<Stmt>) -> Expr {
+        let (op, expressions) = match bin_expr {
+            BinExpr::Or(expr) => {
+                return self.lower_short_circuit(BinOp::Or, &expr.expressions, out)
+            }
+            BinExpr::And(expr) => {
+                return self.lower_short_circuit(BinOp::And, &expr.expressions, out)
+            }
+
+            BinExpr::Equals(expr) => (BinOp::Equals, &expr.expressions),
+            BinExpr::NotEquals(expr) => (BinOp::NotEquals, &expr.expressions),
+            BinExpr::Less(expr) => (BinOp::Less, &expr.expressions),
+            BinExpr::LessEquals(expr) => (BinOp::LessEquals, &expr.expressions),
+            BinExpr::Greater(expr) => (BinOp::Greater, &expr.expressions),
+            BinExpr::GreaterEquals(expr) => (BinOp::GreaterEquals, &expr.expressions),
+
+            BinExpr::BitOr(expr) => (BinOp::BitOr, &expr.expressions),
+            BinExpr::BitXor(expr) => (BinOp::BitXor, &expr.expressions),
+            BinExpr::BitAnd(expr) => (BinOp::BitAnd, &expr.expressions),
+            BinExpr::Shl(expr) => (BinOp::Shl, &expr.expressions),
+            BinExpr::Shr(expr) => (BinOp::Shr, &expr.expressions),
+
+            BinExpr::Add(expr) => (BinOp::Add, &expr.expressions),
+            BinExpr::Sub(expr) => (BinOp::Sub, &expr.expressions),
+            BinExpr::Mul(expr) => (BinOp::Mul, &expr.expressions),
+            BinExpr::Div(expr) => (BinOp::Div, &expr.expressions),
+            BinExpr::Rem(expr) => (BinOp::Rem, &expr.expressions),
+        };
+
+        let [left, right]: [Expr; 2] = self
+            .lower_operands(expressions, out)
+            .try_into()
+            .expect("a binary expression has two operands");
+        let (symbol, operands) = operator(op);
+        self.check_operands(symbol, &[&expressions[0], &expressions[1]], operands);
+        let operand_type = self.operand_type(&expressions[0], &expressions[1]);
+
+        Expr::Bin {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+            operand_type,
+        }
+    }
+
+    /// Lowers `&&` and `||`, whose right operand is only evaluated if the left one
+    /// doesn't decide the result already.
+    ///
+    /// If the right operand needs statements to run, `a && b` becomes
+    /// `let mut t = a; if t { t = b }` so they are skipped as well.
+    fn lower_short_circuit(
+        &mut self,
+        op: BinOp,
+        expressions: &[Spanned<Expression>; 2],
+        out: &mut Vec<Stmt>,
+    ) -> Expr {
+        let left = self.lower_expr(&expressions[0], out);
+        let mut setup = Vec::new();
+        let right = self.lower_expr(&expressions[1], &mut setup);
+        let (symbol, operands) = operator(op);
+        self.check_operands(symbol, &[&expressions[0], &expressions[1]], operands);
+
+        if setup.is_empty() && !self.options.flatten_operands {
+            return Expr::Bin {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+                operand_type: Some(Type::Named("bool".to_owned())),
+            };
+        }
+
+        let result = self.temp();
+        out.push(Stmt::Let {
+            is_mut: true,
+            name: result.clone(),
+            value: left,
+        });
+        setup.push(Stmt::Assign {
+            name: result.clone(),
+            value: right,
+        });
+
+        let mut condition = Expr::Var(result.clone());
+        if op == BinOp::Or {
+            condition = Expr::Unary {
+                op: UnaryOp::Not,
+                operand: Box::new(condition),
+            };
+        }
+        let condition = self.condition(condition, out);
+
+        out.push(Stmt::If {
+            condition,
+            then: setup,
+            r#else: Vec::new(),
+        });
+        Expr::Var(result)
+    }
+
+    fn lower_fn_call(&mut self, fn_call: &FnCall, out: &mut Vec<Stmt>) -> Expr {
+        let [name] = &fn_call.callee.0[..] else {
+            if let [r#type, function] = &fn_call.callee.0[..] {
+                let r#type = self.type_name(r#type.as_str()).to_owned();
+                if let Some(info) = self
+                    .impls
+                    .get(&r#type)
+                    .and_then(|fns| fns.get(function.as_str()))
+                    .cloned()
+                {
+                    return self.lower_associated_call(&r#type, info, fn_call, out);
+                }
+            }
+            let args = self.lower_values(&fn_call.args, out);
+            return self.lower_tuple_variant(&fn_call.callee, args);
+        };
+        let name = name.as_str();
+        let callee = self.resolve(name);
+        let builtin = Builtin::from_name(name);
+        // builtins only read their arguments
+        let mut args = match (&callee, builtin) {
+            (None, Some(_)) => self.lower_operands(&fn_call.args, out),
+            _ => self.lower_values(&fn_call.args, out),
+        };
+        let checked = self.check_call(name, &fn_call.args, fn_call.callee.span);
+
+        match (callee, builtin) {
+            (None, Some(builtin)) => Expr::Builtin { builtin, args },
+            (callee, _) => {
+                if let Some((signature, bindings)) = checked {
+                    args.extend(self.implementations(
+                        &signature,
+                        &bindings,
+                        &fn_call.args,
+                        fn_call.callee.span,
+                    ));
+                }
+                Expr::Call {
+                    callee: callee.unwrap_or_else(|| name.to_owned()),
+                    args,
+                }
+            }
+        }
+    }
+
+    /// `Point::len(p)` is the same as `p.len()`.
+    fn lower_associated_call(
+        &mut self,
+        r#type: &str,
+        info: MethodInfo,
+        fn_call: &FnCall,
+        out: &mut Vec<Stmt>,
+    ) -> Expr {
+        let span = fn_call.callee.span;
+        let params = match (info.has_receiver, &fn_call.args[..]) {
+            (false, args) => args,
+            (true, [_, args @ ..]) => args,
+            (true, []) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "`{}` takes `self`, which has to be passed as the first argument",
+                        fn_call.callee.node
+                    ))
+                    .with_span(span),
+                );
+                return Expr::Unit;
+            }
+        };
+
+        let mut bindings = types::bindings(&info.signature.generics);
+        bindings.extend(
+            self.type_generics(r#type)
+                .into_iter()
+                .map(|param| (param, None)),
+        );
+        // a receiver is changed in place by a method that takes `mut self`
+        let mut args =
+            self.lower_operands_storing(&fn_call.args, usize::from(info.has_receiver), out);
+        let name = fn_call.callee.node.to_string();
+        let bindings = self.check_args(&name, &info.signature, bindings, params, span);
+        if let Some(bindings) = bindings {
+            args.extend(self.implementations(&info.signature, &bindings, params, span));
+        }
+        if !info.has_receiver || self.options.dictionary_passing {
+            return Expr::AssociatedCall {
+                r#type: self.globals[r#type].clone(),
+                function: info.name,
+                args,
+            };
+        }
+
+        let mut args = args.into_iter();
+        let receiver = args.next().expect("the receiver was passed");
+        Expr::MethodCall {
+            receiver: Box::new(receiver),
+            method: info.name,
+            args: args.collect(),
+        }
+    }
+
+    /// The implementations a call passes for the bounds of `signature` and then for its
+    /// `dyn` parameters, if they are passed as arguments with [`LowerOptions::dictionary_passing`].
+    fn implementations(
+        &mut self,
+        signature: &Signature,
+        bindings: &Bindings,
+        args: &[Spanned<Expression>],
+        span: Span,
+    ) -> Vec<Expr> {
+        if !self.options.dictionary_passing {
+            return Vec::new();
+        }
+
+        let mut implementations = Vec::new();
+        for param in &signature.generics {
+            for r#trait in signature.bounds.get(param).into_iter().flatten() {
+                let r#type = bindings.get(param).cloned().flatten();
+                let implementation = self.implementation(param, r#type, r#trait, span);
+                implementations.push(implementation);
+            }
+        }
+        for (param, arg) in signature.params.iter().zip(args) {
+            if let Type::Dyn(r#trait) = param {
+                let implementation = self.dyn_implementation(r#trait, arg);
+                implementations.push(implementation.unwrap_or(Expr::Unit));
+            }
+        }
+        implementations
+    }
+
+    /// The name of the type that implements `r#trait` for `value`, which is used as a `dyn` value.
+    fn dyn_implementation(&mut self, r#trait: &str, value: &Spanned<Expression>) -> Option<Expr> {
+        let r#type = self.infer(value);
+        let message = match &r#type {
+            Some(Type::Named(name) | Type::Applied { name, .. }) => {
+                // other types were reported when checking the value
+                return self.globals.get(name).cloned().map(Expr::Str);
+            }
+            Some(Type::Dyn(name)) if name == r#trait => {
+                if let Expression::Variable(variable) = &value.node {
+                    if let Some(implementation) = self.resolve(&dyn_key(variable.0.as_str())) {
+                        return Some(Expr::Var(implementation));
+                    }
+                }
+                format!("the type behind a `dyn {trait}` isn't known here, it is only passed along with the parameters of functions and `let` bindings")
+            }
+            Some(r#type @ Type::Param(param)) if self.implements(r#type, r#trait) => {
+                if let Some(implementation) = self.resolve(&implementation_key(param, r#trait)) {
+                    return Some(Expr::Var(implementation));
+                }
+                format!("the implementation of `{trait}` for `{param}` isn't known here, only the bounds of functions are passed to them")
+            }
+            Some(_) => return None,
+            None => {
+                format!("the type of a `dyn {trait}` has to be known to pass its implementation")
+            }
+        };
+        self.diagnostics
+            .push(Diagnostic::error(message).with_span(value.span));
+        None
+    }
+
+    /// The name of the type that implements `r#trait` for the generic parameter `param`.
+    fn implementation(
+        &mut self,
+        param: &str,
+        r#type: Option<Type>,
+        r#trait: &str,
+        span: Span,
+    ) -> Expr {
+        let message = match &r#type {
+            Some(Type::Named(name) | Type::Applied { name, .. }) => {
+                // other types were reported when checking the bounds
+                return self.globals.get(name).cloned().map_or(Expr::Unit, Expr::Str);
+            }
+            Some(r#type @ Type::Param(bound)) => {
+                if let Some(implementation) = self.resolve(&implementation_key(bound, r#trait)) {
+                    return Expr::Var(implementation);
+                }
+                if !self.implements(r#type, r#trait) {
+                    return Expr::Unit;
+                }
+                format!("the implementation of `{trait}` for `{bound}` isn't known here, only the bounds of functions are passed to them")
+            }
+            Some(Type::Dyn(name)) if name == r#trait => format!(
+                "a `dyn {trait}` can't be passed as `{param}`, the type that implements `{trait}` has to be known for this target"
+            ),
+            Some(_) => return Expr::Unit,
+            None => format!(
+                "the type of `{param}` has to be known to pass its implementation of `{trait}`"
+            ),
+        };
+        self.diagnostics
+            .push(Diagnostic::error(message).with_span(span));
+        Expr::Unit
+    }
+
+    fn lower_tuple_variant(&mut self, callee: &Spanned<Path>, args: Vec<Expr>) -> Expr {
+        let Some((r#enum, variant)) = self.resolve_variant(callee, callee.span) else {
+            return Expr::Unit;
+        };
+
+        match variant.shape {
+            VariantShape::Tuple(count) if count == args.len() => Expr::Variant {
+                r#enum,
+                variant: variant.name,
+                fields: args
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, value)| FieldValue {
+                        name: format!("_{i}"),
+                        value,
+                    })
+                    .collect(),
+            },
+            VariantShape::Tuple(count) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "`{}` has {} but {} were given",
+                        callee.node,
+                        fields(count),
+                        args.len()
+                    ))
+                    .with_span(callee.span),
+                );
+                Expr::Unit
+            }
+            shape => {
+                self.wrong_variant_shape(callee, &shape, callee.span);
+                Expr::Unit
+            }
+        }
+    }
+
+    fn lower_struct_literal(
+        &mut self,
+        literal: &StructLiteral,
+        span: Span,
+        out: &mut Vec<Stmt>,
+    ) -> Expr {
+        let values = self.lower_values(literal.fields.iter().map(|field| &field.value), out);
+
+        let [name] = &literal.path.0[..] else {
+            let Some((r#enum, variant)) = self.resolve_variant(&literal.path, literal.path.span)
+            else {
+                return Expr::Unit;
+            };
+            let VariantShape::Struct(names) = &variant.shape else {
+                self.wrong_variant_shape(&literal.path, &variant.shape, span);
+                return Expr::Unit;
+            };
+
+            let fields = self.initialize_fields(literal, names, values, span);
+            return Expr::Variant {
+                r#enum,
+                variant: variant.name,
+                fields: fields.into_iter().map(|(_, field)| field).collect(),
+            };
+        };
+
+        let name = self.type_name(name.as_str()).to_owned();
+        let Some(info) = self.structs.get(&name) else {
+            self.diagnostics.push(
+                Diagnostic::error(format!("there is no struct `{name}`"))
+                    .with_span(literal.path.span),
+            );
+            return Expr::Unit;
+        };
+        let names = info.fields.clone();
+        self.check_struct_literal(&name, literal);
+        let mut fields = self.initialize_fields(literal, &names, values, span);
+
+        // the values have to be evaluated in source order
+        if !fields.is_sorted_by_key(|(index, _)| *index) {
+            for (_, field) in &mut fields {
+                if !field.value.is_constant() {
+                    self.spill(&mut field.value, out);
+                }
+            }
+            fields.sort_by_key(|(index, _)| *index);
+        }
+
+        Expr::Struct {
+            name: self.globals[&name].clone(),
+            fields: fields.into_iter().map(|(_, field)| field).collect(),
+        }
+    }
+
+    /// Pairs the values of a struct literal with the target names of `names`,
+    /// and the position of the field in them.
+    fn initialize_fields(
+        &mut self,
+        literal: &StructLiteral,
+        names: &[(String, String)],
+        values: Vec<Expr>,
+        span: Span,
+    ) -> Vec<(usize, FieldValue)> {
+        let mut fields = Vec::new();
+        let mut initialized = HashSet::new();
+        for (field, value) in literal.fields.iter().zip(values) {
+            let name = field.name.as_str();
+
+            let message = match names.iter().position(|(source, _)| source == name) {
+                None => format!("`{}` has no field `{name}`", literal.path.node),
+                Some(_) if !initialized.insert(name) => {
+                    format!("field `{name}` is already initialized")
+                }
+                Some(index) => {
+                    fields.push((
+                        index,
+                        FieldValue {
+                            name: names[index].1.clone(),
+                            value,
+                        },
+                    ));
+                    continue;
+                }
+            };
+            self.diagnostics
+                .push(Diagnostic::error(message).with_span(field.name.span));
+        }
+
+        let missing: Vec<_> = names
+            .iter()
+            .filter(|(source, _)| !initialized.contains(source.as_str()))
+            .map(|(source, _)| format!("`{source}`"))
+            .collect();
+        if !missing.is_empty() {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "missing {} {} in `{}`",
+                    if missing.len() == 1 {
+                        "field"
+                    } else {
+                        "fields"
+                    },
+                    missing.join(", "),
+                    literal.path.node
+                ))
+                .with_span(span),
+            );
+        }
+
+        fields
+    }
+
+    /// Fields are looked up by name, any struct with a field of that name can be accessed.
+    fn lower_field_access(&mut self, access: &FieldAccess, out: &mut Vec<Stmt>) -> Expr {
+        let mut value = self.lower_expr(&access.value, out);
+        if self.options.flatten_operands && !value.is_atomic() {
+            self.spill(&mut value, out);
+        }
+
+        let name = access.field.as_str();
+        let message = match self.infer(&access.value) {
+            Some(Type::Named(r#type) | Type::Applied { name: r#type, .. })
+                if self.structs.contains_key(&r#type) =>
+            {
+                let info = &self.structs[&r#type];
+                let exists = info.fields.iter().any(|(source, _)| source == name);
+                (!exists).then(|| format!("struct `{type}` has no field `{name}`"))
+            }
+            Some(r#type) => Some(format!("`{type}` has no field `{name}`")),
+            // any struct could be the value
+            None => {
+                let exists = self
+                    .structs
+                    .values()
+                    .any(|info| info.fields.iter().any(|(source, _)| source == name));
+                (!exists).then(|| format!("no struct has a field `{name}`"))
+            }
+        };
+        if let Some(message) = message {
+            self.diagnostics
+                .push(Diagnostic::error(message).with_span(access.field.span));
+        }
+
+        Expr::Field {
+            value: Box::new(value),
+            name: self.field_name(name),
+        }
+    }
+
+    fn lower_tuple_index(&mut self, tuple_index: &TupleIndex, out: &mut Vec<Stmt>) -> Expr {
+        let mut value = self.lower_expr(&tuple_index.value, out);
+        if self.options.flatten_operands && !value.is_atomic() {
+            self.spill(&mut value, out);
+        }
+
+        Expr::TupleField {
+            value: Box::new(value),
+            index: *tuple_index.index,
+        }
+    }
+
+    /// Methods are looked up by name like fields, any type with a method of that name can be the receiver.
+    ///
+    /// If the type of the receiver is known, the arguments are checked against the method.
+    /// With [`LowerOptions::dictionary_passing`], that type also decides which function is called.
+    fn lower_method_call(&mut self, call: &MethodCall, out: &mut Vec<Stmt>) -> Expr {
+        let name = call.method.as_str();
+        let span = call.method.span;
+        // a receiver is changed in place by a method that takes `mut self`
+        let mut operands =
+            self.lower_operands_storing(std::iter::once(&call.receiver).chain(&call.args), 1, out);
+
+        let receiver_type = self.infer(&call.receiver);
+        let dispatch = receiver_type
+            .as_ref()
+            .and_then(|receiver| self.dispatch(receiver, name))
+            .map(|(dispatch, info, bindings)| {
+                let bindings = self.check_args(name, &info.signature, bindings, &call.args, span);
+                (dispatch, info, bindings)
+            });
+
+        match (&receiver_type, &dispatch) {
+            // only the traits of a generic parameter or `dyn` value are known to be implemented
+            (Some(r#type @ (Type::Param(_) | Type::Dyn(_))), None) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{type}` has no method `{name}` that takes `self`"))
+                        .with_span(span),
+                );
+            }
+            (_, None) => {
+                let exists = self
+                    .impls
+                    .values()
+                    .any(|fns| fns.get(name).is_some_and(|info| info.has_receiver));
+                if !exists {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "no type has a method `{name}` that takes `self`"
+                        ))
+                        .with_span(span),
+                    );
+                }
+            }
+            (_, Some(_)) => {}
+        }
+
+        if let (true, Some((dispatch, info, Some(bindings)))) =
+            (self.options.dictionary_passing, dispatch)
+        {
+            operands.extend(self.implementations(&info.signature, &bindings, &call.args, span));
+            match dispatch {
+                Dispatch::Type(r#type) => {
+                    return Expr::AssociatedCall {
+                        r#type: self.globals[&r#type].clone(),
+                        function: info.name,
+                        args: operands,
+                    };
+                }
+                Dispatch::Param { param, r#trait } => {
+                    if let Some(implementation) =
+                        self.resolve(&implementation_key(&param, &r#trait))
+                    {
+                        return Expr::TraitCall {
+                            implementation: Box::new(Expr::Var(implementation)),
+                            method: info.name,
+                            args: operands,
+                        };
+                    }
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "the implementation of `{trait}` for `{param}` isn't known here, only the bounds of functions are passed to them"
+                        ))
+                        .with_span(span),
+                    );
+                }
+                Dispatch::Dyn(r#trait) => {
+                    if let Some(implementation) = self.dyn_implementation(&r#trait, &call.receiver)
+                    {
+                        return Expr::TraitCall {
+                            implementation: Box::new(implementation),
+                            method: info.name,
+                            args: operands,
+                        };
+                    }
+                }
+            }
+            return Expr::Unit;
+        }
+
+        let receiver = operands.remove(0);
+        Expr::MethodCall {
+            receiver: Box::new(receiver),
+            method: self.variant_field_name(name),
+            args: operands,
+        }
+    }
+
+    /// Looks up the enum variant a path refers to.
+    fn resolve_variant(&mut self, path: &Path, span: Span) -> Option<(String, VariantInfo)> {
+        let [r#enum, variant] = &path.0[..] else {
+            self.diagnostics.push(
+                Diagnostic::error("a path has to refer to an enum variant, like `Shape::Circle`")
+                    .with_span(span),
+            );
+            return None;
+        };
+
+        let Some(info) = self.enums.get(self.type_name(r#enum.as_str())) else {
+            self.diagnostics.push(
+                Diagnostic::error(format!("unknown enum `{}`", r#enum.as_str()))
+                    .with_span(r#enum.span),
+            );
+            return None;
+        };
+        let Some(variant_info) = info
+            .variants
+            .iter()
+            .find(|info| info.source_name == variant.as_str())
+        else {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "enum `{}` has no variant `{}`",
+                    r#enum.as_str(),
+                    variant.as_str()
+                ))
+                .with_span(variant.span),
+            );
+            return None;
+        };
+
+        Some((info.name.clone(), variant_info.clone()))
+    }
+
+    fn wrong_variant_shape(&mut self, path: &Path, shape: &VariantShape, span: Span) {
+        let usage = match shape {
+            VariantShape::Unit => format!("`{path}`"),
+            VariantShape::Tuple(_) => format!("`{path}(..)`"),
+            VariantShape::Struct(_) => format!("`{path} {{ .. }}`"),
+        };
+
+        self.diagnostics.push(
+            Diagnostic::error(format!("`{path}` has to be constructed like {usage}"))
+                .with_span(span),
+        );
+    }
+
+    /// Lowers expressions that are evaluated from left to right.
+    ///
+    /// If an operand needs statements to run, all operands to its left
+    /// are stored in temporaries first so they are still evaluated before it.
+    fn lower_operands<'e>(
+        &mut self,
+        expressions: impl IntoIterator<Item = &'e Spanned<Expression>>,
+        out: &mut Vec<Stmt>,
+    ) -> Vec<Expr> {
+        self.lower_operands_storing(expressions, usize::MAX, out)
+    }
+
+    /// Lowers operands that are stored, like the arguments of a call or the elements of an
+    /// array. Each one is [`owned`](Self::owned).
+    fn lower_values<'e>(
+        &mut self,
+        expressions: impl IntoIterator<Item = &'e Spanned<Expression>>,
+        out: &mut Vec<Stmt>,
+    ) -> Vec<Expr> {
+        self.lower_operands_storing(expressions, 0, out)
+    }
+
+    /// Lowers operands of which the ones from index `first_stored` on are stored.
+    fn lower_operands_storing<'e>(
+        &mut self,
+        expressions: impl IntoIterator<Item = &'e Spanned<Expression>>,
+        first_stored: usize,
+        out: &mut Vec<Stmt>,
+    ) -> Vec<Expr> {
+        let mut operands: Vec<Expr> = Vec::new();
+
+        for (index, expression) in expressions.into_iter().enumerate() {
+            let mut setup = Vec::new();
+            let mut operand = self.lower_expr(expression, &mut setup);
+            if index >= first_stored {
+                operand = self.owned(expression, operand, false);
+            }
+
+            if !setup.is_empty() {
+                for previous in operands.iter_mut().filter(|expr| !expr.is_constant()) {
+                    self.spill(previous, out);
+                }
+                out.extend(setup);
+            }
+            if self.options.flatten_operands && !operand.is_atomic() {
+                self.spill(&mut operand, out);
+            }
+
+            operands.push(operand);
+        }
+
+        operands
+    }
+
+    /// Copies `value`, which `source` was lowered to, if it could otherwise be changed
+    /// through another place, see [`Expr::Copy`].
+    ///
+    /// That is if it's read out of a `mut` binding or stored `into_mut` one,
+    /// unless it was just created or its type has no parts to change.
+    fn owned(&mut self, source: &Spanned<Expression>, value: Expr, into_mut: bool) -> Expr {
+        let is_new = matches!(
+            value,
+            Expr::Unit
+                | Expr::Bool(_)
+                | Expr::Num(_)
+                | Expr::Str(_)
+                | Expr::Unary { .. }
+                | Expr::Bin { .. }
+                | Expr::IsVariant { .. }
+                | Expr::Tuple(_)
+                | Expr::Array(_)
+                | Expr::Copy(_)
+                | Expr::Closure(_)
+                | Expr::Struct { .. }
+                | Expr::Variant { .. }
+        );
+        let is_unchangeable = self
+            .infer(source)
+            .is_some_and(|r#type| r#type.is_primitive() || matches!(r#type, Type::Fn { .. }));
+        if is_new || is_unchangeable || !(into_mut || self.is_in_mut(source)) {
+            return value;
+        }
+
+        Expr::Copy(Box::new(value))
+    }
+
+    /// Whether `expression` is a `mut` binding or a part of one.
+    fn is_in_mut(&mut self, expression: &Spanned<Expression>) -> bool {
+        match &expression.node {
+            Expression::Variable(variable) => self
+                .resolve(variable.0.as_str())
+                .is_some_and(|target| self.mutable.contains(&target)),
+            Expression::FieldAccess(access) => self.is_in_mut(&access.value),
+            Expression::TupleIndex(tuple_index) => self.is_in_mut(&tuple_index.value),
+            Expression::Index(index) => self.is_in_mut(&index.value),
+            _ => false,
+        }
+    }
+
+    /// Stores `expr` in a temporary and replaces it with a reference to it.
+    fn spill(&mut self, expr: &mut Expr, out: &mut Vec<Stmt>) {
+        let name = self.temp();
+        let value = std::mem::replace(expr, Expr::Var(name.clone()));
+
+        out.push(Stmt::Let {
+            is_mut: false,
+            name,
+            value,
+        });
+    }
+}
+
+/// The value of a branch is not needed if it can't be reached.
+fn assign_result(result: &str, value: Expr, out: &mut Vec<Stmt>) {
+    if !ends_with_jump(out) {
+        out.push(Stmt::Assign {
+            name: result.to_owned(),
+            value,
+        });
+    }
+}
+
+fn ends_with_jump(statements: &[Stmt]) -> bool {
+    matches!(
+        statements.last(),
+        Some(Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Return(_))
+    )
+}
+
+/// Whether running `statements` never gets past their end.
+fn diverges(statements: &[Stmt]) -> bool {
+    match statements.last() {
+        Some(Stmt::Break { .. } | Stmt::Continue { .. } | Stmt::Return(_)) => true,
+        Some(Stmt::If { then, r#else, .. }) => diverges(then) && diverges(r#else),
+        Some(Stmt::Loop {
+            label,
+            condition: None,
+            body,
+        }) => !breaks_to(label, body),
+        _ => false,
+    }
+}
+
+/// Whether a `break` inside of `statements` leaves the loop with `label`.
+fn breaks_to(label: &str, statements: &[Stmt]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Stmt::Break { label: target } => target == label,
+        Stmt::If { then, r#else, .. } => breaks_to(label, then) || breaks_to(label, r#else),
+        Stmt::Loop { body, .. } | Stmt::ForRange { body, .. } | Stmt::ForEach { body, .. } => {
+            breaks_to(label, body)
+        }
+        _ => false,
+    })
+}
+
+/// Where the value of a scope is, or the whole scope if it doesn't have one.
+fn value_span(scope: &Spanned<CodeScope>) -> Span {
+    scope
+        .node
+        .expr
+        .as_ref()
+        .map_or(scope.span, |expr| expr.span)
+}
+
+/// How an operator is written and what its operands can be.
+fn operator(op: BinOp) -> (&'static str, Operands) {
+    match op {
+        BinOp::Or => ("||", Operands::Bool),
+        BinOp::And => ("&&", Operands::Bool),
+
+        BinOp::Equals => ("==", Operands::Any),
+        BinOp::NotEquals => ("!=", Operands::Any),
+        BinOp::Less => ("<", Operands::NumberOrString),
+        BinOp::LessEquals => ("<=", Operands::NumberOrString),
+        BinOp::Greater => (">", Operands::NumberOrString),
+        BinOp::GreaterEquals => (">=", Operands::NumberOrString),
+
+        BinOp::BitOr => ("|", Operands::Int),
+        BinOp::BitXor => ("^", Operands::Int),
+        BinOp::BitAnd => ("&", Operands::Int),
+        BinOp::Shl => ("<<", Operands::Int),
+        BinOp::Shr => (">>", Operands::Int),
+
+        BinOp::Add => ("+", Operands::NumberOrString),
+        BinOp::Sub => ("-", Operands::Number),
+        BinOp::Mul => ("*", Operands::Number),
+        BinOp::Div => ("/", Operands::Number),
+        BinOp::Rem => ("%", Operands::Number),
+    }
+}
+
+fn compound_op(op: CompoundOp) -> BinOp {
+    match op {
+        CompoundOp::BitOr => BinOp::BitOr,
+        CompoundOp::BitXor => BinOp::BitXor,
+        CompoundOp::BitAnd => BinOp::BitAnd,
+        CompoundOp::Shl => BinOp::Shl,
+        CompoundOp::Shr => BinOp::Shr,
+
+        CompoundOp::Add => BinOp::Add,
+        CompoundOp::Sub => BinOp::Sub,
+        CompoundOp::Mul => BinOp::Mul,
+        CompoundOp::Div => BinOp::Div,
+        CompoundOp::Rem => BinOp::Rem,
+    }
+}
+
+/// The source name of the local that holds the implementation of `r#trait` for `param`,
+/// which no binding in the source code can have.
+fn implementation_key(param: &str, r#trait: &str) -> String {
+    format!("{param}: {trait}")
+}
+
+/// Like [`implementation_key`], but for the type of the `dyn` value in the binding `name`.
+fn dyn_key(name: &str) -> String {
+    format!("{name}: dyn")
+}
+
+fn generic_names(generics: &GenericParams) -> Vec<String> {
+    generics
+        .0
+        .iter()
+        .map(|param| param.name.as_str().to_owned())
+        .collect()
+}
+
+/// Whether the function of an `impl` block for `r#type` has the signature of a trait function.
+fn matches_trait_fn(expected: &Signature, signature: &Signature, r#type: &str) -> bool {
+    let bindings = Bindings::from([("Self".to_owned(), Some(Type::Named(r#type.to_owned())))]);
+    let substitute = |r#type| types::substitute(r#type, &bindings);
+
+    signature.generics.is_empty()
+        && signature.params.len() == expected.params.len()
+        && signature
+            .params
+            .iter()
+            .zip(&expected.params)
+            .all(|(param, expected)| Some(param) == substitute(expected).as_ref())
+        && Some(&signature.return_type) == substitute(&expected.return_type).as_ref()
+}
+
+/// `1 field` or `2 fields`.
+fn fields(count: usize) -> String {
+    match count {
+        1 => "1 field".to_owned(),
+        count => format!("{count} fields"),
+    }
+}
+
+#[cfg(test)]
+fn lower_source(source: &str) -> Program {
+    use erebus_parser::Parsable;
+
+    lower(
+        &Ast::parse(source).unwrap(),
+        &LowerOptions {
+            reserved: &["def"],
+            ..LowerOptions::default()
+        },
+    )
+    .unwrap()
+    .0
+}
+
+#[test]
+fn test_lower_shadowing() {
+    let program =
+        lower_source("fn def(x: int) -> int { let x = x; let def = { let x = 2; x }; x }");
+
+    assert_eq!(
+        program.items,
+        vec![Item::Fn(Function {
+            is_pub: false,
+            name: "def_1".to_owned(),
+            generics: Vec::new(),
+
+            params: vec![TypedName {
+                name: "x".to_owned(),
+                r#type: Type::Named("int".to_owned()),
+            }],
+            return_type: Type::Named("int".to_owned()),
+
+            body: vec![
+                Stmt::Let {
+                    is_mut: false,
+                    name: "x_1".to_owned(),
+                    value: Expr::Var("x".to_owned()),
+                },
+                Stmt::Let {
+                    is_mut: false,
+                    name: "x_2".to_owned(),
+                    value: Expr::Num(2_f64),
+                },
+                Stmt::Let {
+                    is_mut: false,
+                    name: "def_2".to_owned(),
+                    value: Expr::Var("x_2".to_owned()),
+                },
+                Stmt::Return(Expr::Var("x_1".to_owned())),
+            ],
+        })]
+    )
+}
+
+#[test]
+fn test_lower_defined_twice() {
+    use erebus_parser::Parsable;
+
+    let ast = Ast::parse(
+        "fn a() -> int { 1 }
+         fn a() -> String { \"a\" }
+         struct P { x: int, x: int }
+         enum P { A { y: int, y: int } }
+         let a = 1;
+         fn f(x: int, x: int) -> int { let g = |y, y| y; x }",
+    )
+    .unwrap();
+    let diagnostics = lower(&ast, &LowerOptions::default()).unwrap_err();
+    let reports: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let first = &diagnostic.notes[0];
+            (
+                diagnostic.message.as_str(),
+                diagnostic.span.clone().unwrap(),
+                first.span.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        reports,
+        [
+            ("`a` is defined twice", 32..33, 3..4),
+            ("`P` is defined twice", 105..106, 70..71),
+            ("`a` is defined twice", 145..146, 3..4),
+            ("`x` is defined twice", 82..83, 74..75),
+            ("`y` is defined twice", 121..122, 113..114),
+            ("`x` is defined twice", 174..175, 166..167),
+            ("`y` is defined twice", 203..204, 200..201),
+        ]
+    );
+}
+
+#[test]
+fn test_lower_evaluation_order() {
+    let program = lower_source("let a = f(1) + { let b = g(); b }; fn f(x: int) -> int { x }");
+
+    assert!(matches!(&program.items[0], Item::Fn(_)));
+    assert_eq!(
+        program.items[1],
+        Item::Global(Global {
+            is_pub: false,
+            is_mut: false,
+            name: "a".to_owned(),
+            r#type: None,
+
+            setup: vec![
+                Stmt::Let {
+                    is_mut: false,
+                    name: "_t0".to_owned(),
+                    value: Expr::Call {
+                        callee: "f".to_owned(),
+                        args: vec![Expr::Num(1_f64)]
+                    },
+                },
+                Stmt::Let {
+                    is_mut: false,
+                    name: "b".to_owned(),
+                    value: Expr::Call {
+                        callee: "g".to_owned(),
+                        args: Vec::new()
+                    },
+                },
+            ],
+            value: Expr::Bin {
+                op: BinOp::Add,
+                left: Box::new(Expr::Var("_t0".to_owned())),
+                right: Box::new(Expr::Var("b".to_owned())),
+                operand_type: Some(Type::Named("int".to_owned())),
+            },
+        })
+    )
+}
+
+#[test]
+fn test_lower_flatten_operands() {
+    let ast = {
+        use erebus_parser::Parsable;
+        Ast::parse("let a = f(1 + 2 * 3, g(4));").unwrap()
+    };
+    let program = lower(
+        &ast,
+        &LowerOptions {
+            flatten_operands: true,
+            ..LowerOptions::default()
+        },
+    )
+    .unwrap()
+    .0;
+
+    let Item::Global(global) = &program.items[0] else {
+        panic!("expected a global")
+    };
+    let temp = |name: &str, value| Stmt::Let {
+        is_mut: false,
+        name: name.to_owned(),
+        value,
+    };
+    let var = |name: &str| Box::new(Expr::Var(name.to_owned()));
+
+    assert_eq!(
+        global.setup,
+        vec![
+            temp(
+                "_t0",
+                Expr::Bin {
+                    op: BinOp::Mul,
+                    left: Box::new(Expr::Num(2_f64)),
+                    right: Box::new(Expr::Num(3_f64)),
+                    operand_type: Some(Type::Named("int".to_owned())),
+                }
+            ),
+            temp(
+                "_t1",
+                Expr::Bin {
+                    op: BinOp::Add,
+                    left: Box::new(Expr::Num(1_f64)),
+                    right: var("_t0"),
+                    operand_type: Some(Type::Named("int".to_owned())),
+                }
+            ),
+            temp(
+                "_t2",
+                Expr::Call {
+                    callee: "g".to_owned(),
+                    args: vec![Expr::Num(4_f64)],
+                }
+            ),
+        ]
+    );
+    assert_eq!(
+        global.value,
+        Expr::Call {
+            callee: "f".to_owned(),
+            args: vec![*var("_t1"), *var("_t2")],
+        }
+    );
+}
+
+#[test]
+fn test_lower_short_circuit() {
+    let program = lower_source("let a = f() || { let b = g(); b }; let c = f() && g();");
+
+    let Item::Global(global) = &program.items[0] else {
+        panic!("expected a global")
+    };
+    let call = |callee: &str| Expr::Call {
+        callee: callee.to_owned(),
+        args: Vec::new(),
+    };
+
+    assert_eq!(
+        global.setup,
+        vec![
+            Stmt::Let {
+                is_mut: true,
+                name: "_t0".to_owned(),
+                value: call("f"),
+            },
+            Stmt::If {
+                condition: Expr::Unary {
+                    op: UnaryOp::Not,
+                    operand: Box::new(Expr::Var("_t0".to_owned())),
+                },
+                then: vec![
+                    Stmt::Let {
+                        is_mut: false,
+                        name: "b".to_owned(),
+                        value: call("g"),
+                    },
+                    Stmt::Assign {
+                        name: "_t0".to_owned(),
+                        value: Expr::Var("b".to_owned()),
+                    },
+                ],
+                r#else: Vec::new(),
+            },
+        ]
+    );
+    assert_eq!(global.value, Expr::Var("_t0".to_owned()));
+
+    // operands without statements keep using the operator of the target
+    assert_eq!(
+        program.items[1],
+        Item::Global(Global {
+            is_pub: false,
+            is_mut: false,
+            name: "c".to_owned(),
+            r#type: None,
+
+            setup: Vec::new(),
+            value: Expr::Bin {
+                op: BinOp::And,
+                left: Box::new(call("f")),
+                right: Box::new(call("g")),
+                operand_type: Some(Type::Named("bool".to_owned())),
+            },
+        })
+    );
+}
+
+#[test]
+fn test_lower_bitwise() {
+    use erebus_parser::Parsable;
+
+    let ast = Ast::parse(
+        "fn f(x: float, n: int) -> int { let mut a = 1.5 | n; a <<= x; id(n) & 1 }
+         fn id(n: int) -> int { n }",
+    )
+    .unwrap();
+    let diagnostics = lower(&ast, &LowerOptions::default()).unwrap_err();
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.span.clone()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (
+                "an operand of `|` has to be an `int`, found `float`",
+                Some(44..47)
+            ),
+            (
+                "an operand of `<<=` has to be an `int`, found `float`",
+                Some(59..60)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_lower_operands() {
+    use erebus_parser::Parsable;
+
+    let ast = Ast::parse(
+        "fn add<T>(a: T, b: T) -> T { a + b }
+         fn f(s: String) -> () {
+             let a = 1 + \"a\";
+             let b = -s;
+             let c = !3;
+             let d = 1 && 2;
+             let e = { 1 } < { 1 > 0 };
+             let h = 7 / 2;
+             for i in 0..h {}
+             let mut n = 1;
+             n += s;
+             n = \"s\";
+         }",
+    )
+    .unwrap();
+    let diagnostics = lower(&ast, &LowerOptions::default()).unwrap_err();
+    let messages: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "an operand of `+` has to be a number or a `String`, found `T`",
+            "an operand of `+` has to be a number or a `String`, found `T`",
+            "expected `int`, found `String`",
+            "the operand of `-` has to be a number, found `String`",
+            "the operand of `!` has to be a `bool`, found `int`",
+            "an operand of `&&` has to be a `bool`, found `int`",
+            "an operand of `&&` has to be a `bool`, found `int`",
+            "an operand of `<` has to be a number or a `String`, found `bool`",
+            // dividing integers gives a `float`
+            "the end of a range has to be an `int`, found `float`",
+            "expected `int`, found `String`",
+            "expected `int`, found `String`",
+        ]
+    );
+}
+
+#[test]
+fn test_lower_value_types() {
+    use erebus_parser::Parsable;
+
+    let ast = Ast::parse(
+        "fn id<T>(x: T) -> T { x }
+         fn same<T>(a: T, b: T) -> T { a }
+         fn f(c: bool) -> () {
+             let a: String = id({ 1 });
+             let d: int = { let s = \"s\"; s };
+             let e = same(if c { 1 } else { 2 }, \"x\");
+             let g = if c { 1 } else if c { \"a\" } else { return; };
+             let h: String = match 1 { 1 => 1, _ => return };
+         }",
+    )
+    .unwrap();
+    let diagnostics = lower(&ast, &LowerOptions::default()).unwrap_err();
+    let messages: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "expected `String`, found `int`",
+            "expected `int`, found `String`",
+            "expected `int`, found `String`",
+            "expected `int`, found `String`",
+            // an arm that returns has no value
+            "expected `String`, found `int`",
+        ]
+    );
+}
+
+#[test]
+fn test_lower_if() {
+    let program = lower_source("let a = if b { 1 } else if c { let d = 2; d } else { 3 };");
+
+    let Item::Global(global) = &program.items[0] else {
+        panic!("expected a global")
+    };
+    let assign = |value| Stmt::Assign {
+        name: "_t0".to_owned(),
+        value,
+    };
+
+    assert_eq!(
+        global.setup,
+        vec![
+            Stmt::Let {
+                is_mut: true,
+                name: "_t0".to_owned(),
+                value: Expr::Unit,
+            },
+            Stmt::If {
+                condition: Expr::Var("b".to_owned()),
+                then: vec![assign(Expr::Num(1_f64))],
+                r#else: vec![Stmt::If {
+                    condition: Expr::Var("c".to_owned()),
+                    then: vec![
+                        Stmt::Let {
+                            is_mut: false,
+                            name: "d".to_owned(),
+                            value: Expr::Num(2_f64),
+                        },
+                        assign(Expr::Var("d".to_owned())),
+                    ],
+                    r#else: vec![assign(Expr::Num(3_f64))],
+                }],
+            },
+        ]
+    );
+    assert_eq!(global.value, Expr::Var("_t0".to_owned()));
+}
+
+#[test]
+fn test_lower_loops() {
+    use erebus_parser::Parsable;
+
+    let setup = |program: Program| {
+        let Some(Item::Global(global)) = program.items.into_iter().next() else {
+            panic!("expected a global")
+        };
+        global.setup
+    };
+    let var = |name: &str| Expr::Var(name.to_owned());
+
+    let program = lower_source("let a = 'outer: loop { while b { break 'outer 1 } };");
+    assert_eq!(
+        setup(program),
+        vec![
+            Stmt::Let {
+                is_mut: true,
+                name: "_t0".to_owned(),
+                value: Expr::Unit,
+            },
+            Stmt::Loop {
+                label: "outer".to_owned(),
+                condition: None,
+                body: vec![Stmt::Loop {
+                    label: "_l0".to_owned(),
+                    condition: Some(var("b")),
+                    body: vec![
+                        Stmt::Assign {
+                            name: "_t0".to_owned(),
+                            value: Expr::Num(1_f64),
+                        },
+                        Stmt::Break {
+                            label: "outer".to_owned(),
+                        },
+                    ],
+                }],
+            },
+        ]
+    );
+
+    // without labeled jumps, a flag is checked after every nested loop
+    let ast = Ast::parse("let a = 'outer: loop { loop { continue 'outer } };").unwrap();
+    let options = LowerOptions {
+        labeled_jumps: false,
+        ..LowerOptions::default()
+    };
+    let set_flag = |value| Stmt::Assign {
+        name: "_t2".to_owned(),
+        value: Expr::Bool(value),
+    };
+    assert_eq!(
+        setup(lower(&ast, &options).unwrap().0)[1..],
+        [
+            Stmt::Let {
+                is_mut: true,
+                name: "_t2".to_owned(),
+                value: Expr::Bool(false),
+            },
+            Stmt::Loop {
+                label: "outer".to_owned(),
+                condition: None,
+                body: vec![
+                    Stmt::Let {
+                        is_mut: true,
+                        name: "_t1".to_owned(),
+                        value: Expr::Unit,
+                    },
+                    Stmt::Loop {
+                        label: "_l0".to_owned(),
+                        condition: None,
+                        body: vec![
+                            set_flag(true),
+                            Stmt::Break {
+                                label: "_l0".to_owned(),
+                            },
+                        ],
+                    },
+                    Stmt::If {
+                        condition: var("_t2"),
+                        then: vec![
+                            set_flag(false),
+                            Stmt::Continue {
+                                label: "outer".to_owned(),
+                            },
+                        ],
+                        r#else: Vec::new(),
+                    },
+                ],
+            },
+        ]
+    );
+
+    let ast = Ast::parse("let a = loop { break 'missing }; let b = while c { break 1 };").unwrap();
+    let diagnostics = lower(&ast, &options).unwrap_err();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].span, Some(21..29));
+    assert_eq!(diagnostics[1].span, Some(57..58));
+}
+
+#[test]
+fn test_lower_for() {
+    let program = lower_source("fn f(n: int) -> () { for n in 0..n { n } }");
+
+    let Item::Fn(function) = &program.items[0] else {
+        panic!("expected a function")
+    };
+    assert_eq!(
+        function.body[..2],
+        [
+            // the end is evaluated once, before the binding shadows it
+            Stmt::Let {
+                is_mut: false,
+                name: "_t0".to_owned(),
+                value: Expr::Var("n".to_owned()),
+            },
+            Stmt::ForRange {
+                label: "_l0".to_owned(),
+                binding: "n_1".to_owned(),
+                start: Expr::Num(0_f64),
+                end: Expr::Var("_t0".to_owned()),
+                is_inclusive: false,
+                body: Vec::new(),
+            },
+        ]
+    );
+
+    let diagnostics = {
+        use erebus_parser::Parsable;
+
+        let ast = Ast::parse(
+            "let a = 1..2; let b = for x in c { break 1 }; let c = for x in 0.5..\"3\" {};",
+        )
+        .unwrap();
+        lower(&ast, &LowerOptions::default()).unwrap_err()
+    };
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.span.clone()))
+        .collect();
+    assert_eq!(
+        errors[2..],
+        [
+            (
+                "the start of a range has to be an `int`, found `float`",
+                Some(63..66)
+            ),
+            (
+                "the end of a range has to be an `int`, found `String`",
+                Some(68..71)
+            ),
+        ]
+    );
+    assert_eq!(errors[0].1, Some(8..12));
+    assert_eq!(errors[1].1, Some(41..42));
+}
+
+#[test]
+fn test_lower_enum() {
+    let program = lower_source(
+        "fn f() -> Shape { Shape::Rect { tag: 1, width: 2 } }
+         enum Shape { Empty, Circle(int), Rect { width: int, tag: int } }",
+    );
+
+    let int = || Type::Named("int".to_owned());
+    assert_eq!(
+        program.items[0],
+        Item::Enum(Enum {
+            is_pub: false,
+            name: "Shape".to_owned(),
+            variants: vec![
+                Variant {
+                    name: "Empty".to_owned(),
+                    fields: Vec::new(),
+                },
+                Variant {
+                    name: "Circle".to_owned(),
+                    fields: vec![TypedName {
+                        name: "_0".to_owned(),
+                        r#type: int(),
+                    }],
+                },
+                Variant {
+                    name: "Rect".to_owned(),
+                    fields: vec![
+                        TypedName {
+                            name: "width".to_owned(),
+                            r#type: int(),
+                        },
+                        TypedName {
+                            name: "tag_".to_owned(),
+                            r#type: int(),
+                        },
+                    ],
+                },
+            ],
+            methods: Vec::new(),
+        })
+    );
+
+    let Item::Fn(function) = &program.items[1] else {
+        panic!("expected a function")
+    };
+    assert_eq!(
+        function.body,
+        [Stmt::Return(Expr::Variant {
+            r#enum: "Shape".to_owned(),
+            variant: "Rect".to_owned(),
+            fields: vec![
+                FieldValue {
+                    name: "tag_".to_owned(),
+                    value: Expr::Num(1_f64),
+                },
+                FieldValue {
+                    name: "width".to_owned(),
+                    value: Expr::Num(2_f64),
+                },
+            ],
+        })]
+    );
+
+    let diagnostics = {
+        use erebus_parser::Parsable;
+
+        let ast = Ast::parse(
+            "enum E { A, B(int), C { x: int }, A }
+             let a = E::B(1, 2);
+             let b = E::C { x: 1, x: 2, y: 3 };
+             let c = E::A();
+             let d = F::A;
+             let e = E::D;
+             let f = E::C {};",
+        )
+        .unwrap();
+        lower(&ast, &LowerOptions::default()).unwrap_err()
+    };
+    let spans: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.span.clone())
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            // the duplicate variant
+            Some(34..35),
+            // too many values
+            Some(59..63),
+            // the duplicate and unknown field
+            Some(105..106),
+            Some(111..112),
+            // a unit variant can't be called
+            Some(140..144),
+            // the unknown enum and variant
+            Some(169..170),
+            Some(199..200),
+            // the missing field
+            Some(223..230),
+        ]
+    );
+}
+
+#[test]
+fn test_lower_struct() {
+    let program = lower_source(
+        "struct Point { x: int, def: int }
+         fn f(a: int) -> int { let mut p = Point { def: f(1), x: a }; p.x = 2; p.def }",
+    );
+
+    let Item::Fn(function) = &program.items[1] else {
+        panic!("expected a function")
+    };
+    let var = |name: &str| Expr::Var(name.to_owned());
+    assert_eq!(
+        function.body,
+        [
+            // the values are evaluated in source order
+            Stmt::Let {
+                is_mut: false,
+                name: "_t0".to_owned(),
+                value: Expr::Call {
+                    callee: "f".to_owned(),
+                    args: vec![Expr::Num(1_f64)],
+                },
+            },
+            Stmt::Let {
+                is_mut: false,
+                name: "_t1".to_owned(),
+                value: var("a"),
+            },
+            Stmt::Let {
+                is_mut: true,
+                name: "p".to_owned(),
+                value: Expr::Struct {
+                    name: "Point".to_owned(),
+                    fields: vec![
+                        FieldValue {
+                            name: "x".to_owned(),
+                            value: var("_t1"),
+                        },
+                        FieldValue {
+                            name: "def_".to_owned(),
+                            value: var("_t0"),
+                        },
+                    ],
+                },
+            },
+            Stmt::AssignField {
+                target: var("p"),
+                name: "x".to_owned(),
+                value: Expr::Num(2_f64),
+            },
+            Stmt::Return(Expr::Field {
+                value: Box::new(var("p")),
+                name: "def_".to_owned(),
+            }),
+        ]
+    );
+
+    let diagnostics = {
+        use erebus_parser::Parsable;
+
+        let ast = Ast::parse(
+            "struct Point { x: int }
+             fn f() -> int {
+                 let p = Point { x: 1 };
+                 p.x = 2;
+                 1 = 2;
+                 let q = Line { x: 1 };
+                 p.y
+             }",
+        )
+        .unwrap();
+        lower(&ast, &LowerOptions::default()).unwrap_err()
+    };
+    let spans: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.span.clone())
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            // `p` isn't mutable
+            Some(111..112),
+            // a literal can't be assigned to
+            Some(137..138),
+            // the unknown struct and field
+            Some(169..173),
+            Some(203..204),
+        ]
+    );
+
+    // fields are looked up in the type of the value
+    let diagnostics = {
+        use erebus_parser::Parsable;
+
+        let ast = Ast::parse(
+            "struct P { x: int }
+             struct Q { y: int }
+             fn f(p: P, n: int) -> int { p.y + n.x }",
+        )
+        .unwrap();
+        lower(&ast, &LowerOptions::default()).unwrap_err()
+    };
+    let messages: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        ["struct `P` has no field `y`", "`int` has no field `x`"]
+    );
+}
+
+#[test]
+fn test_lower_impl() {
+    use super::Method;
+
+    let program = lower_source(
+        "struct Point { x: int }
+         impl Point {
+             fn new(def: int) -> Self { Self { x: def } }
+             fn set(mut self, x: int) -> int { self.x = x; x }
+         }
+         fn f() -> int { Point::new(1).set(2) + Point::set(Point::new(3), 4) }",
+    );
+
+    let var = |name: &str| Expr::Var(name.to_owned());
+    let Item::Struct(r#struct) = &program.items[0] else {
+        panic!("expected a struct")
+    };
+    assert_eq!(
+        r#struct.methods,
+        [
+            Method {
+                has_receiver: false,
+                function: Function {
+                    is_pub: false,
+                    name: "new".to_owned(),
+                    generics: Vec::new(),
+                    params: vec![TypedName {
+                        name: "def_1".to_owned(),
+                        r#type: Type::Named("int".to_owned()),
+                    }],
+                    // `Self` refers to the type of the impl block
+                    return_type: Type::Named("Point".to_owned()),
+                    body: vec![Stmt::Return(Expr::Struct {
+                        name: "Point".to_owned(),
+                        fields: vec![FieldValue {
+                            name: "x".to_owned(),
+                            value: var("def_1"),
+                        }],
+                    })],
+                },
+            },
+            Method {
+                has_receiver: true,
+                function: Function {
+                    is_pub: false,
+                    name: "set".to_owned(),
+                    generics: Vec::new(),
+                    params: vec![TypedName {
+                        name: "x".to_owned(),
+                        r#type: Type::Named("int".to_owned()),
+                    }],
+                    return_type: Type::Named("int".to_owned()),
+                    body: vec![
+                        Stmt::AssignField {
+                            target: var("self"),
+                            name: "x".to_owned(),
+                            value: var("x"),
+                        },
+                        Stmt::Return(var("x")),
+                    ],
+                },
+            },
+        ]
+    );
+
+    let Item::Fn(function) = &program.items[1] else {
+        panic!("expected a function")
+    };
+    let new = |x: f64| Expr::AssociatedCall {
+        r#type: "Point".to_owned(),
+        function: "new".to_owned(),
+        args: vec![Expr::Num(x)],
+    };
+    let set = |receiver: Expr, x: f64| Expr::MethodCall {
+        receiver: Box::new(receiver),
+        method: "set".to_owned(),
+        args: vec![Expr::Num(x)],
+    };
+    assert_eq!(
+        function.body,
+        // calling a method through its type is the same as calling it on the receiver
+        [Stmt::Return(Expr::Bin {
+            op: BinOp::Add,
+            left: Box::new(set(new(1_f64), 2_f64)),
+            right: Box::new(set(new(3_f64), 4_f64)),
+            operand_type: Some(Type::Named("int".to_owned())),
+        })]
+    );
+
+    let diagnostics = {
+        use erebus_parser::Parsable;
+
+        let ast = Ast::parse(
+            "struct Point { x: int }
+             pub impl Point {
+                 fn x(self) -> int { 1 }
+                 fn get(self) -> int { self.x = 1; 1 }
+                 fn get() -> int { 2 }
+             }
+             impl Line { fn new() -> int { 1 } }
+             fn f(self) -> int { Point::get() + 1.len() }",
+        )
+        .unwrap();
+        lower(&ast, &LowerOptions::default()).unwrap_err()
+    };
+    let spans: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.span.clone())
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            // the `pub` impl block
+            Some(37..217),
+            // the function called like a field and the redefinition
+            Some(74..75),
+            Some(170..173),
+            // the unknown type
+            Some(222..226),
+            // `self` isn't mutable
+            Some(134..138),
+            // `self` outside of an impl block
+            Some(271..275),
+            // the missing receiver and the unknown method
+            Some(286..296),
+            Some(303..306),
+        ]
+    );
+}
+
+#[test]
+fn test_lower_closure() {
+    use super::{Capture, Closure};
+
+    let program = lower_source(
+        "fn f(k: int) -> int { let mut n = k; let g = |x| { let h = || x + n; h() }; g(1) }",
+    );
+    let Item::Fn(function) = &program.items[0] else {
+        panic!("expected a function")
+    };
+
+    let var = |name: &str| Expr::Var(name.to_owned());
+    let capture = |name: &str, is_mut| Capture {
+        name: name.to_owned(),
+        is_mut,
+    };
+    let h = Closure {
+        params: Vec::new(),
+        // the parameter of `g` is only captured by `h`
+        captures: vec![capture("x", false), capture("n", true)],
+        body: vec![Stmt::Return(Expr::Bin {
+            op: BinOp::Add,
+            left: Box::new(var("x")),
+            right: Box::new(var("n")),
+            operand_type: Some(Type::Named("int".to_owned())),
+        })],
+    };
+    let g = Closure {
+        params: vec!["x".to_owned()],
+        captures: vec![capture("n", true)],
+        body: vec![
+            Stmt::Let {
+                is_mut: false,
+                name: "h".to_owned(),
+                value: Expr::Closure(Box::new(h)),
+            },
+            Stmt::Return(Expr::Call {
+                callee: "h".to_owned(),
+                args: Vec::new(),
+            }),
+        ],
+    };
+    assert_eq!(
+        function.body,
+        [
+            Stmt::Let {
+                is_mut: true,
+                name: "n".to_owned(),
+                value: var("k"),
+            },
+            Stmt::Let {
+                is_mut: false,
+                name: "g".to_owned(),
+                value: Expr::Closure(Box::new(g)),
+            },
+            Stmt::Return(Expr::Call {
+                callee: "g".to_owned(),
+                args: vec![Expr::Num(1_f64)],
+            }),
+        ]
+    );
+
+    // a closure that isn't bound is stored in a temporary
+    let program = lower_source("fn f(x: int) -> int { g(|| x) + 1 }");
+    let Item::Fn(function) = &program.items[0] else {
+        panic!("expected a function")
+    };
+    assert!(matches!(
+        &function.body[0],
+        Stmt::Let { name, value: Expr::Closure(_), .. } if name == "_t0"
+    ));
+
+    let diagnostics = {
+        use erebus_parser::Parsable;
+
+        let ast = Ast::parse("fn f() -> () { loop { let g = || break; g() } }").unwrap();
+        lower(&ast, &LowerOptions::default()).unwrap_err()
+    };
+    let spans: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.span.clone())
+        .collect();
+    // a closure can't leave the loop around it
+    assert_eq!(spans, [Some(33..38)]);
+}
+
+#[test]
+fn test_lower_return() {
+    let program = lower_source("fn f(x: int) -> () { g(x); x; if x > 1 { return; } g(2); }");
+    let Item::Fn(function) = &program.items[0] else {
+        panic!("expected a function")
+    };
+
+    let call = |arg: Expr| Expr::Call {
+        callee: "g".to_owned(),
+        args: vec![arg],
+    };
+    assert_eq!(
+        function.body,
+        [
+            // an atomic expression statement has no effect
+            Stmt::Expr(call(Expr::Var("x".to_owned()))),
+            Stmt::Let {
+                is_mut: true,
+                name: "_t0".to_owned(),
+                value: Expr::Unit,
+            },
+            Stmt::If {
+                condition: Expr::Bin {
+                    op: BinOp::Greater,
+                    left: Box::new(Expr::Var("x".to_owned())),
+                    right: Box::new(Expr::Num(1_f64)),
+                    operand_type: Some(Type::Named("int".to_owned())),
+                },
+                then: vec![Stmt::Return(Expr::Unit)],
+                r#else: Vec::new(),
+            },
+            Stmt::Expr(call(Expr::Num(2_f64))),
+            // the missing tail expression is `()`
+            Stmt::Return(Expr::Unit),
+        ]
+    );
+
+    // a body that already returns doesn't get another `return`
+    let program = lower_source("fn f() -> int { return 1; }");
+    let Item::Fn(function) = &program.items[0] else {
+        panic!("expected a function")
+    };
+    assert_eq!(function.body, [Stmt::Return(Expr::Num(1_f64))]);
+
+    let diagnostics = {
+        use erebus_parser::Parsable;
+
+        let ast = Ast::parse("let x = { return 1; }; let f = || { return 2; };").unwrap();
+        lower(&ast, &LowerOptions::default()).unwrap_err()
+    };
+    let spans: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.span.clone())
+        .collect();
+    // only the closure can be left with `return`
+    assert_eq!(spans, [Some(10..20)]);
+}
+
+#[test]
+fn test_lower_return_types() {
+    use erebus_parser::Parsable;
+
+    let ast = Ast::parse(
+        "fn h() -> int { let a = 1; }
+         fn f() -> int { return \"s\"; }
+         fn g() -> int { \"t\" }
+         fn k(c: bool) -> int { if c { return 1; } else { return 2; } }
+         fn l() -> int { loop {} }
+         fn m() -> String { let c = || { return 1; }; return; }",
+    )
+    .unwrap();
+    let diagnostics = lower(&ast, &LowerOptions::default()).unwrap_err();
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.span.clone()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (
+                "`h` has to return `int`, but its body ends without a value",
+                Some(3..4)
+            ),
+            ("expected `int`, found `String`", Some(61..64)),
+            ("expected `int`, found `String`", Some(93..96)),
+            // a closure doesn't declare what it returns
+            ("expected `String`, found `()`", Some(260..268)),
+        ]
+    );
+}
+
+#[test]
+fn test_lower_generics() {
+    let program = lower_source(
+        "struct Pair<A, B> { first: A, second: B }
+         fn first<A, B>(pair: Pair<A, B>) -> A { pair.first }",
+    );
+    let [Item::Struct(pair), Item::Fn(function)] = &program.items[..] else {
+        panic!("expected a struct and a function")
+    };
+
+    let param = |name: &str| Type::Param(name.to_owned());
+    assert_eq!(pair.generics, ["A", "B"]);
+    assert_eq!(pair.fields[1].r#type, param("B"));
+    assert_eq!(function.generics, ["A", "B"]);
+    assert_eq!(
+        function.params[0].r#type,
+        Type::Applied {
+            name: "Pair".to_owned(),
+            args: vec![param("A"), param("B")],
+        }
+    );
+    assert_eq!(function.return_type, param("A"));
+
+    let diagnostics = {
+        use erebus_parser::Parsable;
+
+        let ast = Ast::parse(
+            "struct Pair<A, B> { first: A, second: B }
+             fn same<T>(a: T, b: T) -> T { a }
+             fn f<T>(x: T) -> String {
+                 let p: Pair<int, String> = Pair { first: same(1, 2), second: 3 };
+                 let q: Pair<int> = p;
+                 let n: int = same(x, x);
+                 same(\"a\", 1) + same(p.first, p)
+             }",
+        )
+        .unwrap();
+        lower(&ast, &LowerOptions::default()).unwrap_err()
+    };
+    let messages: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "expected `Pair<int, String>`, found `Pair<int, int>`",
+            "`Pair` takes 2 generic arguments but 1 was given",
+            // the parameter of the caller can't be bound by the callee
+            "expected `int`, found `T`",
+            "expected `String`, found `int`",
+            "expected `int`, found `Pair<int, String>`",
+            // the operands of `+` are a `String` and an `int`
+            "expected `String`, found `int`",
+        ]
+    );
+}
+
+#[test]
+fn test_lower_traits() {
+    use erebus_parser::Parsable;
+
+    let source = "trait Show { fn show(self) -> String; }
+         enum Light { Red }
+         impl Show for Light { fn show(self) -> String { \"red\" } }
+         fn show<T: Show>(x: T) -> String { x.show() }
+         fn f() -> String { show(Light::Red) + Light::Red.show() }";
+    let red = || Expr::Variant {
+        r#enum: "Light".to_owned(),
+        variant: "Red".to_owned(),
+        fields: Vec::new(),
+    };
+
+    // the methods are looked up on the receiver
+    let program = lower_source(source);
+    let [Item::Enum(light), Item::Fn(show), Item::Fn(f)] = &program.items[..] else {
+        panic!("expected an enum and two functions")
+    };
+    assert_eq!(light.methods[0].function.name, "show");
+    assert_eq!(show.params.len(), 1);
+    assert_eq!(
+        show.body,
+        [Stmt::Return(Expr::MethodCall {
+            receiver: Box::new(Expr::Var("x".to_owned())),
+            method: "show".to_owned(),
+            args: Vec::new(),
+        })]
+    );
+
+    // the implementations are passed to the functions
+    let ast = Ast::parse(source).unwrap();
+    let (program, _) = lower(
+        &ast,
+        &LowerOptions {
+            dictionary_passing: true,
+            ..LowerOptions::default()
+        },
+    )
+    .unwrap();
+    let [_, Item::Fn(show), Item::Fn(f_resolved)] = &program.items[..] else {
+        panic!("expected an enum and two functions")
+    };
+    assert_eq!(
+        show.params
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect::<Vec<_>>(),
+        ["x", "T_Show"]
+    );
+    assert_eq!(
+        show.body,
+        [Stmt::Return(Expr::TraitCall {
+            implementation: Box::new(Expr::Var("T_Show".to_owned())),
+            method: "show".to_owned(),
+            args: vec![Expr::Var("x".to_owned())],
+        })]
+    );
+    assert_ne!(f.body, f_resolved.body);
+    assert_eq!(
+        f_resolved.body,
+        [Stmt::Return(Expr::Bin {
+            op: BinOp::Add,
+            left: Box::new(Expr::Call {
+                callee: "show".to_owned(),
+                args: vec![red(), Expr::Str("Light".to_owned())],
+            }),
+            right: Box::new(Expr::AssociatedCall {
+                r#type: "Light".to_owned(),
+                function: "show".to_owned(),
+                args: vec![red()],
+            }),
+            operand_type: Some(Type::Named("String".to_owned())),
+        })]
+    );
+
+    let diagnostics = {
+        let ast = Ast::parse(
+            "trait Shape { fn area(self) -> int; fn name() -> String; }
+             trait Shape {}
+             struct P { x: int }
+             struct Q { y: int }
+             impl Shape for P { fn area(self) -> String { \"a\" } fn extra(self) -> int { 1 } }
+             impl Shape for P {}
+             impl Missing for P {}
+             fn f<T: Shape + Nope>(x: T) -> int { x.size() }
+             fn g(shape: dyn Shape) -> int { f(1) + f(shape) }
+             fn h() -> () { let p: dyn Shape = P { x: 1 }; let q: dyn Shape = Q { y: 1 }; }",
+        )
+        .unwrap();
+        lower(
+            &ast,
+            &LowerOptions {
+                dictionary_passing: true,
+                ..LowerOptions::default()
+            },
+        )
+        .unwrap_err()
+    };
+    let messages: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "there already is a trait `Shape`",
+            "`area` doesn't match its declaration in `Shape`",
+            "`extra` isn't a function of `Shape`",
+            "`impl Shape for P` is missing `name`",
+            "`P` already implements `Shape`",
+            "there is no trait `Missing`",
+            "there is no trait `Nope`",
+            "`T` has no method `size` that takes `self`",
+            "`int` doesn't implement `Shape`",
+            "a `dyn Shape` can't be passed as `T`, the type that implements `Shape` has to be known for this target",
+            "expected `dyn Shape`, found `Q`",
+        ]
+    );
+
+    // the type behind a `dyn` value is passed along with it
+    let source = "trait Show { fn show(self) -> String; }
+         enum Light { Red }
+         impl Show for Light { fn show(self) -> String { \"red\" } }
+         fn show(x: dyn Show) -> String { let mut y = x; y = Light::Red; x.show() + y.show() }
+         fn f() -> String { show(Light::Red) }";
+    let options = LowerOptions {
+        dictionary_passing: true,
+        ..LowerOptions::default()
+    };
+    let (program, _) = lower(&Ast::parse(source).unwrap(), &options).unwrap();
+    let [_, Item::Fn(show), Item::Fn(f)] = &program.items[..] else {
+        panic!("expected an enum and two functions")
+    };
+    let var = |name: &str| Expr::Var(name.to_owned());
+    let light = || Expr::Str("Light".to_owned());
+    let show_call = |implementation: &str, receiver: &str| Expr::TraitCall {
+        implementation: Box::new(var(implementation)),
+        method: "show".to_owned(),
+        args: vec![var(receiver)],
+    };
+    assert_eq!(
+        show.params
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect::<Vec<_>>(),
+        ["x", "x_Show"]
+    );
+    assert_eq!(
+        show.body,
+        [
+            Stmt::Let {
+                is_mut: true,
+                name: "y".to_owned(),
+                value: Expr::Copy(Box::new(var("x"))),
+            },
+            Stmt::Let {
+                is_mut: true,
+                name: "y_Show".to_owned(),
+                value: var("x_Show"),
+            },
+            Stmt::Assign {
+                name: "y".to_owned(),
+                value: red(),
+            },
+            Stmt::Assign {
+                name: "y_Show".to_owned(),
+                value: light(),
+            },
+            Stmt::Return(Expr::Bin {
+                op: BinOp::Add,
+                left: Box::new(show_call("x_Show", "x")),
+                right: Box::new(show_call("y_Show", "y")),
+                operand_type: Some(Type::Named("String".to_owned())),
+            }),
+        ]
+    );
+    assert_eq!(
+        f.body,
+        [Stmt::Return(Expr::Call {
+            callee: "show".to_owned(),
+            args: vec![red(), light()],
+        })]
+    );
+
+    let ast = Ast::parse(&format!(
+        "{source} fn g(xs: [dyn Show]) -> String {{ xs[0].show() }}"
+    ))
+    .unwrap();
+    let messages: Vec<_> = lower(&ast, &options)
+        .unwrap_err()
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect();
+    assert_eq!(
+        messages,
+        ["the type behind a `dyn Show` isn't known here, it is only passed along with the parameters of functions and `let` bindings"]
+    );
+}
+
+#[test]
+fn test_lower_assign() {
+    let program = lower_source(
+        "fn f(xs: [int]) -> int { let mut ys = xs; ys[g(1)] += 2; let mut n = 1; n <<= 1; n }",
+    );
+    let Item::Fn(function) = &program.items[0] else {
+        panic!("expected a function")
+    };
+
+    let var = |name: &str| Expr::Var(name.to_owned());
+    assert_eq!(
+        function.body,
+        [
+            Stmt::Let {
+                is_mut: true,
+                name: "ys".to_owned(),
+                value: Expr::Copy(Box::new(var("xs"))),
+            },
+            // the index is only evaluated once
+            Stmt::Let {
+                is_mut: false,
+                name: "_t0".to_owned(),
+                value: Expr::Call {
+                    callee: "g".to_owned(),
+                    args: vec![Expr::Num(1_f64)],
+                },
+            },
+            Stmt::AssignIndex {
+                target: var("ys"),
+                index: var("_t0"),
+                value: Expr::Bin {
+                    op: BinOp::Add,
+                    left: Box::new(Expr::Index {
+                        value: Box::new(var("ys")),
+                        index: Box::new(var("_t0")),
+                    }),
+                    right: Box::new(Expr::Num(2_f64)),
+                    operand_type: Some(Type::Named("int".to_owned())),
+                },
+            },
+            Stmt::Let {
+                is_mut: true,
+                name: "n".to_owned(),
+                value: Expr::Num(1_f64),
+            },
+            Stmt::Assign {
+                name: "n".to_owned(),
+                value: Expr::Bin {
+                    op: BinOp::Shl,
+                    left: Box::new(var("n")),
+                    right: Box::new(Expr::Num(1_f64)),
+                    operand_type: Some(Type::Named("int".to_owned())),
+                },
+            },
+            Stmt::Return(var("n")),
+        ]
+    );
+
+    let diagnostics = {
+        use erebus_parser::Parsable;
+
+        let ast = Ast::parse(
+            "let g = 1;
+             struct P { x: int }
+             impl P { fn set(mut self) -> int { self = P { x: 1 }; 1 } }
+             fn f(xs: [int]) -> int { xs[0] = 1; g = 2; h += 1; f(1) = 2; 1 }",
+        )
+        .unwrap();
+        lower(&ast, &LowerOptions::default()).unwrap_err()
+    };
+    let spans: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.span.clone())
+        .collect();
+    assert_eq!(
+        spans,
+        [
+            // `self` can only be replaced field by field
+            Some(92..96),
+            // the parameter and the global aren't mutable
+            Some(155..157),
+            Some(166..167),
+            // the unknown binding and the call
+            Some(173..174),
+            Some(181..186),
+        ]
+    );
+}
+
+#[test]
+fn test_lower_copy() {
+    let program = lower_source(
+        "fn g(xs: [int]) -> int { 1 }
+         fn f(xs: [int]) -> [int] { let mut ys = [1]; let zs = ys; let n = g(ys) + ys[0]; let ws = [xs, zs]; ys }",
+    );
+    let Item::Fn(function) = &program.items[1] else {
+        panic!("expected a function")
+    };
+
+    let var = |name: &str| Expr::Var(name.to_owned());
+    let copy = |name: &str| Expr::Copy(Box::new(var(name)));
+    assert_eq!(
+        function.body,
+        [
+            Stmt::Let {
+                is_mut: true,
+                name: "ys".to_owned(),
+                value: Expr::Array(vec![Expr::Num(1_f64)]),
+            },
+            Stmt::Let {
+                is_mut: false,
+                name: "zs".to_owned(),
+                value: copy("ys"),
+            },
+            // the element is a number, which can't be changed
+            Stmt::Let {
+                is_mut: false,
+                name: "n".to_owned(),
+                value: Expr::Bin {
+                    op: BinOp::Add,
+                    left: Box::new(Expr::Call {
+                        callee: "g".to_owned(),
+                        args: vec![copy("ys")],
+                    }),
+                    right: Box::new(Expr::Index {
+                        value: Box::new(var("ys")),
+                        index: Box::new(Expr::Num(0_f64)),
+                    }),
+                    operand_type: Some(Type::Named("int".to_owned())),
+                },
+            },
+            // neither binding is `mut`
+            Stmt::Let {
+                is_mut: false,
+                name: "ws".to_owned(),
+                value: Expr::Array(vec![var("xs"), var("zs")]),
+            },
+            Stmt::Return(copy("ys")),
+        ]
+    );
+}
@@ -0,0 +1,954 @@
+//! Lowering of `match` into `if`s and of destructuring `let`s.
+//!
+//! Exhaustiveness and unreachable arms are checked with the usefulness algorithm
+//! from "Warnings for pattern matching" by Luc Maranget.
+
+use std::collections::{HashMap, HashSet};
+
+use erebus_parser::{Ident, Let, Match, MatchArm, Pattern, Span, Spanned};
+
+use crate::Diagnostic;
+
+use super::{
+    super::{BinOp, Expr, Stmt, Type, UnaryOp},
+    assign_result, diverges, Lowerer, VariantInfo, VariantShape,
+};
+
+/// A pattern whose paths are resolved.
+#[derive(Clone)]
+enum Pat {
+    /// Matches everything, optionally binding the value to a source name.
+    Wild(Option<(String, Span)>),
+    /// Matches the values built by `Ctor` whose fields match the patterns.
+    Ctor(Ctor, Vec<Pat>),
+}
+
+/// Something that builds a value.
+#[derive(Clone, PartialEq)]
+enum Ctor {
+    /// The variant at `index` of the enum with the source name `enum`.
+    Variant {
+        r#enum: String,
+        index: usize,
+    },
+    /// The struct with this source name.
+    Struct(String),
+    Tuple(usize),
+    Num(f64),
+    Str(String),
+}
+
+/// What has to be true before an arm runs.
+#[derive(Default)]
+struct ArmChecks {
+    /// Each condition is only evaluated if the ones before it are true,
+    /// right after its statements ran.
+    conditions: Vec<(Vec<Stmt>, Expr)>,
+    /// Statements that have to run before the next condition or the bindings.
+    pending: Vec<Stmt>,
+    /// Source name -> value of every binding.
+    bindings: Vec<(String, Expr)>,
+}
+
+impl Lowerer<'_> {
+    /// Returns the value of the arm that matched with its type, which every arm has to agree on.
+    pub(super) fn lower_match(
+        &mut self,
+        r#match: &Match,
+        out: &mut Vec<Stmt>,
+    ) -> (Expr, Option<Type>) {
+        let mut scrutinee = self.lower_expr(&r#match.scrutinee, out);
+        // every arm reads it again
+        if !scrutinee.is_atomic() {
+            self.spill(&mut scrutinee, out);
+        }
+
+        let errors = self.diagnostics.len();
+        let patterns: Vec<_> = r#match
+            .arms
+            .iter()
+            .map(|arm| self.resolve_pattern(&arm.pattern, &mut HashSet::new()))
+            .collect();
+        // unresolved patterns would lead to misleading diagnostics
+        if self.diagnostics.len() == errors {
+            self.check_arms(r#match, &patterns);
+        }
+
+        let result = self.temp();
+        out.push(Stmt::Let {
+            is_mut: true,
+            name: result.clone(),
+            value: Expr::Unit,
+        });
+
+        let arms: Vec<_> = r#match.arms.iter().zip(patterns).collect();
+        let outer = std::mem::take(&mut self.arm_types);
+        self.lower_arms(&arms, &scrutinee, &result, out);
+        let arm_types = std::mem::replace(&mut self.arm_types, outer);
+
+        (Expr::Var(result), self.check_branches(arm_types))
+    }
+
+    /// Lowers a `let` whose pattern isn't a single name into a binding for every name in it.
+    pub(super) fn lower_let_pattern(&mut self, r#let: &Let, out: &mut Vec<Stmt>) {
+        for (name, value) in self.destructure(r#let, out) {
+            let name = self.declare(&name);
+            if r#let.is_mut {
+                self.mutable.insert(name.clone());
+            }
+
+            out.push(Stmt::Let {
+                is_mut: r#let.is_mut,
+                name,
+                value,
+            });
+        }
+    }
+
+    /// Checks that the pattern of `let` matches every value and returns the value of
+    /// every name in it by its source name, the statements they need are added to `out`.
+    pub(super) fn destructure(&mut self, r#let: &Let, out: &mut Vec<Stmt>) -> Vec<(String, Expr)> {
+        let value = self.lower_expr(&r#let.right, out);
+        let mut value = self.owned(&r#let.right, value, r#let.is_mut);
+
+        let errors = self.diagnostics.len();
+        let pat = self.resolve_pattern(&r#let.pattern, &mut HashSet::new());
+        if self.diagnostics.len() == errors {
+            if let Some(witness) = self.witness(&[vec![pat.clone()]], &[Pat::Wild(None)]) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "refutable pattern in `let`, `{}` isn't covered",
+                        self.display(&witness[0])
+                    ))
+                    .with_span(r#let.pattern.span),
+                );
+            }
+        }
+
+        // every binding reads a part of it
+        if !value.is_atomic() {
+            self.spill(&mut value, out);
+        }
+        let mut checks = ArmChecks::default();
+        self.collect_checks(&pat, value, &mut checks);
+
+        // the pattern matches every value, so only the statements of the conditions are needed
+        for (statements, _) in checks.conditions {
+            out.extend(statements);
+        }
+        out.extend(checks.pending);
+        checks.bindings
+    }
+
+    fn resolve_pattern(&mut self, pattern: &Spanned<Pattern>, bound: &mut HashSet<String>) -> Pat {
+        match &pattern.node {
+            Pattern::Wildcard => Pat::Wild(None),
+            Pattern::Binding(name) => {
+                if !bound.insert(name.as_str().to_owned()) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "`{}` is bound more than once in the same pattern",
+                            name.as_str()
+                        ))
+                        .with_span(pattern.span),
+                    );
+                }
+                Pat::Wild(Some((name.as_str().to_owned(), pattern.span)))
+            }
+            Pattern::NumLit(num_lit) => Pat::Ctor(Ctor::Num(num_lit.0), Vec::new()),
+            Pattern::StringLit(string_lit) => {
+                Pat::Ctor(Ctor::Str(string_lit.0.clone()), Vec::new())
+            }
+            Pattern::Tuple(elements) => Pat::Ctor(
+                Ctor::Tuple(elements.len()),
+                elements
+                    .iter()
+                    .map(|element| self.resolve_pattern(element, bound))
+                    .collect(),
+            ),
+
+            Pattern::Path(path) => match self.resolve_variant(path, pattern.span) {
+                Some((
+                    _,
+                    variant @ VariantInfo {
+                        shape: VariantShape::Unit,
+                        ..
+                    },
+                )) => Pat::Ctor(
+                    variant_ctor(self.type_name(path.0[0].as_str()), &variant),
+                    Vec::new(),
+                ),
+                Some((_, variant)) => {
+                    self.wrong_variant_shape(path, &variant.shape, pattern.span);
+                    Pat::Wild(None)
+                }
+                None => Pat::Wild(None),
+            },
+            Pattern::TupleStruct { path, fields } => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|field| self.resolve_pattern(field, bound))
+                    .collect();
+
+                match self.resolve_variant(path, path.span) {
+                    Some((_, variant)) => match variant.shape {
+                        VariantShape::Tuple(count) if count == fields.len() => Pat::Ctor(
+                            variant_ctor(self.type_name(path.0[0].as_str()), &variant),
+                            fields,
+                        ),
+                        VariantShape::Tuple(count) => {
+                            self.diagnostics.push(
+                                Diagnostic::error(format!(
+                                    "`{}` has {} but the pattern has {}",
+                                    path.node,
+                                    super::fields(count),
+                                    fields.len()
+                                ))
+                                .with_span(pattern.span),
+                            );
+                            Pat::Wild(None)
+                        }
+                        shape => {
+                            self.wrong_variant_shape(path, &shape, pattern.span);
+                            Pat::Wild(None)
+                        }
+                    },
+                    None => Pat::Wild(None),
+                }
+            }
+            Pattern::Struct {
+                path,
+                fields,
+                has_rest,
+            } => {
+                let (ctor, names) = match &path.0[..] {
+                    [name] => match self.structs.get(self.type_name(name.as_str())) {
+                        Some(info) => (
+                            Ctor::Struct(self.type_name(name.as_str()).to_owned()),
+                            info.fields.clone(),
+                        ),
+                        None => {
+                            self.diagnostics.push(
+                                Diagnostic::error(format!("unknown struct `{}`", name.as_str()))
+                                    .with_span(name.span),
+                            );
+                            return Pat::Wild(None);
+                        }
+                    },
+                    _ => match self.resolve_variant(path, path.span) {
+                        Some((_, variant)) => match &variant.shape {
+                            VariantShape::Struct(names) => (
+                                variant_ctor(self.type_name(path.0[0].as_str()), &variant),
+                                names.clone(),
+                            ),
+                            shape => {
+                                self.wrong_variant_shape(path, shape, pattern.span);
+                                return Pat::Wild(None);
+                            }
+                        },
+                        None => return Pat::Wild(None),
+                    },
+                };
+
+                let mut resolved = vec![Pat::Wild(None); names.len()];
+                let mut mentioned = HashSet::new();
+                for field in fields {
+                    let field_pattern = self.resolve_pattern(&field.pattern, bound);
+                    let name = field.name.as_str();
+
+                    let message = match names.iter().position(|(source, _)| source == name) {
+                        None => format!("`{}` has no field `{name}`", path.node),
+                        Some(_) if !mentioned.insert(name) => {
+                            format!("field `{name}` is already matched")
+                        }
+                        Some(index) => {
+                            resolved[index] = field_pattern;
+                            continue;
+                        }
+                    };
+                    self.diagnostics
+                        .push(Diagnostic::error(message).with_span(field.name.span));
+                }
+
+                let missing: Vec<_> = names
+                    .iter()
+                    .filter(|(source, _)| !mentioned.contains(source.as_str()))
+                    .map(|(source, _)| format!("`{source}`"))
+                    .collect();
+                if !missing.is_empty() && !has_rest {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "the pattern doesn't mention {} {}, ignore the rest with `..`",
+                            if missing.len() == 1 {
+                                "field"
+                            } else {
+                                "fields"
+                            },
+                            missing.join(", ")
+                        ))
+                        .with_span(pattern.span),
+                    );
+                }
+
+                Pat::Ctor(ctor, resolved)
+            }
+        }
+    }
+
+    fn check_arms(&mut self, r#match: &Match, patterns: &[Pat]) {
+        let mut matrix = Vec::new();
+
+        for (arm, pattern) in r#match.arms.iter().zip(patterns) {
+            let row = vec![pattern.clone()];
+            if self.witness(&matrix, &row).is_none() {
+                self.diagnostics.push(
+                    Diagnostic::warning("unreachable arm, earlier arms match all of its values")
+                        .with_span(arm.pattern.span),
+                );
+            }
+
+            // a guard can reject any value
+            if arm.guard.is_none() {
+                matrix.push(row);
+            }
+        }
+
+        if let Some(witness) = self.witness(&matrix, &[Pat::Wild(None)]) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "non-exhaustive match, `{}` isn't covered",
+                    self.display(&witness[0])
+                ))
+                .with_span(r#match.scrutinee.span),
+            );
+        }
+    }
+
+    /// Finds values that `row` matches but no row of `matrix` does, one for each column.
+    fn witness(&self, matrix: &[Vec<Pat>], row: &[Pat]) -> Option<Vec<Pat>> {
+        let Some((head, tail)) = row.split_first() else {
+            return matrix.is_empty().then(Vec::new);
+        };
+
+        if let Pat::Ctor(ctor, fields) = head {
+            let row = [fields.as_slice(), tail].concat();
+            return self
+                .witness(&self.specialize(matrix, ctor), &row)
+                .map(|witness| self.rebuild(ctor, witness));
+        }
+
+        let heads: Vec<_> = matrix
+            .iter()
+            .filter_map(|row| match &row[0] {
+                Pat::Ctor(ctor, _) => Some(ctor),
+                Pat::Wild(_) => None,
+            })
+            .collect();
+        let all = heads.first().and_then(|ctor| self.all_ctors(ctor));
+
+        match all {
+            // only values built by one of these can be missing
+            Some(all) if all.iter().all(|ctor| heads.contains(&ctor)) => {
+                all.iter().find_map(|ctor| {
+                    let row = [vec![Pat::Wild(None); self.arity(ctor)], tail.to_vec()].concat();
+                    self.witness(&self.specialize(matrix, ctor), &row)
+                        .map(|witness| self.rebuild(ctor, witness))
+                })
+            }
+            all => {
+                let defaults: Vec<_> = matrix
+                    .iter()
+                    .filter(|row| matches!(row[0], Pat::Wild(_)))
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                let mut witness = self.witness(&defaults, tail)?;
+
+                let missing = all
+                    .filter(|_| !heads.is_empty())
+                    .and_then(|all| all.into_iter().find(|ctor| !heads.contains(&ctor)));
+                witness.insert(
+                    0,
+                    match missing {
+                        Some(ctor) => {
+                            let fields = vec![Pat::Wild(None); self.arity(&ctor)];
+                            Pat::Ctor(ctor, fields)
+                        }
+                        None => Pat::Wild(None),
+                    },
+                );
+                Some(witness)
+            }
+        }
+    }
+
+    /// Keeps the rows that match values built by `ctor`,
+    /// replacing their first column with the patterns of its fields.
+    fn specialize(&self, matrix: &[Vec<Pat>], ctor: &Ctor) -> Vec<Vec<Pat>> {
+        matrix
+            .iter()
+            .filter_map(|row| {
+                let fields = match &row[0] {
+                    Pat::Ctor(head, fields) if head == ctor => fields.clone(),
+                    Pat::Ctor(..) => return None,
+                    Pat::Wild(_) => vec![Pat::Wild(None); self.arity(ctor)],
+                };
+                Some([fields, row[1..].to_vec()].concat())
+            })
+            .collect()
+    }
+
+    /// Undoes [`specialize`](Self::specialize) for a witness.
+    fn rebuild(&self, ctor: &Ctor, mut witness: Vec<Pat>) -> Vec<Pat> {
+        let fields = witness.drain(..self.arity(ctor)).collect();
+        witness.insert(0, Pat::Ctor(ctor.clone(), fields));
+        witness
+    }
+
+    /// Every constructor of the type `ctor` builds, if there are finitely many.
+    fn all_ctors(&self, ctor: &Ctor) -> Option<Vec<Ctor>> {
+        match ctor {
+            Ctor::Variant { r#enum, .. } => Some(
+                (0..self.enums[r#enum].variants.len())
+                    .map(|index| Ctor::Variant {
+                        r#enum: r#enum.clone(),
+                        index,
+                    })
+                    .collect(),
+            ),
+            Ctor::Struct(_) | Ctor::Tuple(_) => Some(vec![ctor.clone()]),
+            Ctor::Num(_) | Ctor::Str(_) => None,
+        }
+    }
+
+    fn arity(&self, ctor: &Ctor) -> usize {
+        match ctor {
+            Ctor::Variant { r#enum, index } => self.enums[r#enum].variants[*index].field_count(),
+            Ctor::Struct(name) => self.structs[name].fields.len(),
+            Ctor::Tuple(count) => *count,
+            Ctor::Num(_) | Ctor::Str(_) => 0,
+        }
+    }
+
+    /// Formats a witness like it would be written in the source code.
+    fn display(&self, pat: &Pat) -> String {
+        let Pat::Ctor(ctor, fields) = pat else {
+            return "_".to_owned();
+        };
+        let fields: Vec<_> = fields.iter().map(|field| self.display(field)).collect();
+        let named = |path: &str, names: &[(String, String)]| {
+            if fields.iter().all(|field| field == "_") {
+                return format!("{path} {{ .. }}");
+            }
+            let fields: Vec<_> = names
+                .iter()
+                .zip(&fields)
+                .map(|((name, _), field)| format!("{name}: {field}"))
+                .collect();
+            format!("{path} {{ {} }}", fields.join(", "))
+        };
+
+        match ctor {
+            Ctor::Variant { r#enum, index } => {
+                let variant = &self.enums[r#enum].variants[*index];
+                let path = format!("{enum}::{}", variant.source_name);
+                match &variant.shape {
+                    VariantShape::Unit => path,
+                    VariantShape::Tuple(_) => format!("{path}({})", fields.join(", ")),
+                    VariantShape::Struct(names) => named(&path, names),
+                }
+            }
+            Ctor::Struct(name) => named(name, &self.structs[name].fields),
+            Ctor::Tuple(1) => format!("({},)", fields[0]),
+            Ctor::Tuple(_) => format!("({})", fields.join(", ")),
+            Ctor::Num(num) => num.to_string(),
+            Ctor::Str(str) => format!("{str:?}"),
+        }
+    }
+
+    /// Lowers the arms into a chain of `if`s.
+    ///
+    /// An arm with a guard or conditions that can't be combined sets a flag
+    /// when it runs, the later arms only run if it isn't set.
+    fn lower_arms(
+        &mut self,
+        arms: &[(&Spanned<MatchArm>, Pat)],
+        scrutinee: &Expr,
+        result: &str,
+        out: &mut Vec<Stmt>,
+    ) {
+        let Some(((arm, pattern), rest)) = arms.split_first() else {
+            return;
+        };
+
+        let mut checks = ArmChecks::default();
+        self.collect_checks(pattern, scrutinee.clone(), &mut checks);
+
+        // without flattening, every condition is pure and can be short circuited
+        let conditions = match self.options.flatten_operands {
+            true => checks.conditions,
+            false => {
+                let mut conditions = checks
+                    .conditions
+                    .into_iter()
+                    .map(|(_, condition)| condition);
+                conditions
+                    .next()
+                    .map(|first| {
+                        let condition = conditions.fold(first, |left, right| Expr::Bin {
+                            op: BinOp::And,
+                            left: Box::new(left),
+                            right: Box::new(right),
+                            operand_type: Some(Type::Named("bool".to_owned())),
+                        });
+                        (Vec::new(), condition)
+                    })
+                    .into_iter()
+                    .collect()
+            }
+        };
+        let (pending, bindings) = (checks.pending, checks.bindings);
+
+        if arm.guard.is_none() && conditions.len() <= 1 {
+            let Some((setup, condition)) = conditions.into_iter().next() else {
+                // the later arms are unreachable
+                self.lower_arm(arm, pending, bindings, None, result, out);
+                return;
+            };
+
+            out.extend(setup);
+            let condition = self.condition(condition, out);
+
+            let mut then = Vec::new();
+            self.lower_arm(arm, pending, bindings, None, result, &mut then);
+            let mut r#else = Vec::new();
+            self.lower_arms(rest, scrutinee, result, &mut r#else);
+
+            out.push(Stmt::If {
+                condition,
+                then,
+                r#else,
+            });
+            return;
+        }
+
+        let matched = self.temp();
+        out.push(Stmt::Let {
+            is_mut: true,
+            name: matched.clone(),
+            value: Expr::Bool(false),
+        });
+        self.lower_conditions(
+            conditions.into_iter(),
+            |lowerer, out| lowerer.lower_arm(arm, pending, bindings, Some(&matched), result, out),
+            out,
+        );
+
+        if !rest.is_empty() {
+            let condition = self.condition(
+                Expr::Unary {
+                    op: UnaryOp::Not,
+                    operand: Box::new(Expr::Var(matched.clone())),
+                },
+                out,
+            );
+            let mut then = Vec::new();
+            self.lower_arms(rest, scrutinee, result, &mut then);
+
+            out.push(Stmt::If {
+                condition,
+                then,
+                r#else: Vec::new(),
+            });
+        }
+    }
+
+    /// Nests an `if` for every condition, with `then` in the innermost one.
+    fn lower_conditions(
+        &mut self,
+        mut conditions: std::vec::IntoIter<(Vec<Stmt>, Expr)>,
+        then: impl FnOnce(&mut Self, &mut Vec<Stmt>),
+        out: &mut Vec<Stmt>,
+    ) {
+        let Some((setup, condition)) = conditions.next() else {
+            return then(self, out);
+        };
+
+        out.extend(setup);
+        let condition = self.condition(condition, out);
+        let mut body = Vec::new();
+        self.lower_conditions(conditions, then, &mut body);
+
+        out.push(Stmt::If {
+            condition,
+            then: body,
+            r#else: Vec::new(),
+        });
+    }
+
+    /// Lowers the bindings, guard and body of an arm whose pattern matched.
+    ///
+    /// `matched` is set if the guard is true.
+    fn lower_arm(
+        &mut self,
+        arm: &MatchArm,
+        pending: Vec<Stmt>,
+        bindings: Vec<(String, Expr)>,
+        matched: Option<&str>,
+        result: &str,
+        out: &mut Vec<Stmt>,
+    ) {
+        self.scopes.push(HashMap::new());
+
+        out.extend(pending);
+        for (name, value) in bindings {
+            let name = self.declare(&name);
+            out.push(Stmt::Let {
+                is_mut: false,
+                name,
+                value,
+            });
+        }
+
+        let mut body = Vec::new();
+        if let Some(matched) = matched {
+            body.push(Stmt::Assign {
+                name: matched.to_owned(),
+                value: Expr::Bool(true),
+            });
+        }
+        let value = self.lower_expr(&arm.body, &mut body);
+        let value = self.owned(&arm.body, value, false);
+        if !diverges(&body) {
+            let r#type = self.infer(&arm.body);
+            self.arm_types.push((r#type, arm.body.span));
+        }
+        assign_result(result, value, &mut body);
+
+        match &arm.guard {
+            Some(guard) => {
+                let condition = self.lower_expr(guard, out);
+                let condition = self.condition(condition, out);
+                out.push(Stmt::If {
+                    condition,
+                    then: body,
+                    r#else: Vec::new(),
+                });
+            }
+            None => out.extend(body),
+        }
+
+        self.scopes.pop();
+    }
+
+    /// Collects what has to be true for `value` to match `pat`.
+    fn collect_checks(&mut self, pat: &Pat, value: Expr, checks: &mut ArmChecks) {
+        let (ctor, fields) = match pat {
+            Pat::Wild(None) => return,
+            Pat::Wild(Some((name, _))) => return checks.bindings.push((name.clone(), value)),
+            Pat::Ctor(ctor, fields) => (ctor, fields),
+        };
+
+        if let Some(condition) = self.ctor_condition(ctor, &value) {
+            checks
+                .conditions
+                .push((std::mem::take(&mut checks.pending), condition));
+        }
+
+        for (index, field) in fields.iter().enumerate() {
+            if matches!(field, Pat::Wild(None)) {
+                continue;
+            }
+
+            let mut access = self.field_access(ctor, &value, index);
+            // the conditions of nested patterns need an atomic operand
+            if self.options.flatten_operands && matches!(field, Pat::Ctor(..)) {
+                self.spill(&mut access, &mut checks.pending);
+            }
+            self.collect_checks(field, access, checks);
+        }
+    }
+
+    /// Whether `value` is built by `ctor`, if there are values of the same type that aren't.
+    fn ctor_condition(&self, ctor: &Ctor, value: &Expr) -> Option<Expr> {
+        let (literal, r#type) = match ctor {
+            Ctor::Variant { r#enum, index } => {
+                let info = &self.enums[r#enum];
+                return Some(Expr::IsVariant {
+                    value: Box::new(value.clone()),
+                    r#enum: info.name.clone(),
+                    variant: info.variants[*index].name.clone(),
+                });
+            }
+            Ctor::Struct(_) | Ctor::Tuple(_) => return None,
+            Ctor::Num(num) if num.fract() == 0.0 => (Expr::Num(*num), "int"),
+            Ctor::Num(num) => (Expr::Num(*num), "float"),
+            Ctor::Str(str) => (Expr::Str(str.clone()), "String"),
+        };
+
+        Some(Expr::Bin {
+            op: BinOp::Equals,
+            left: Box::new(value.clone()),
+            right: Box::new(literal),
+            operand_type: Some(Type::Named(r#type.to_owned())),
+        })
+    }
+
+    fn field_access(&self, ctor: &Ctor, value: &Expr, index: usize) -> Expr {
+        let value = Box::new(value.clone());
+        let name = match ctor {
+            Ctor::Variant {
+                r#enum,
+                index: variant,
+            } => match &self.enums[r#enum].variants[*variant].shape {
+                VariantShape::Struct(names) => names[index].1.clone(),
+                _ => format!("_{index}"),
+            },
+            Ctor::Struct(name) => self.structs[name].fields[index].1.clone(),
+            Ctor::Tuple(_) => return Expr::TupleField { value, index },
+            Ctor::Num(_) | Ctor::Str(_) => unreachable!("literals don't have fields"),
+        };
+
+        Expr::Field { value, name }
+    }
+}
+
+/// The names `pattern` binds, in source order.
+pub(super) fn bound_names(pattern: &Pattern) -> Vec<&Ident> {
+    match pattern {
+        Pattern::Binding(name) => vec![name],
+        Pattern::Tuple(patterns)
+        | Pattern::TupleStruct {
+            fields: patterns, ..
+        } => patterns
+            .iter()
+            .flat_map(|pattern| bound_names(&pattern.node))
+            .collect(),
+        Pattern::Struct { fields, .. } => fields
+            .iter()
+            .flat_map(|field| bound_names(&field.node.pattern.node))
+            .collect(),
+        Pattern::Wildcard | Pattern::NumLit(_) | Pattern::StringLit(_) | Pattern::Path(_) => {
+            Vec::new()
+        }
+    }
+}
+
+fn variant_ctor(r#enum: &str, variant: &VariantInfo) -> Ctor {
+    Ctor::Variant {
+        r#enum: r#enum.to_owned(),
+        index: variant.index,
+    }
+}
+
+#[test]
+fn test_lower_match() {
+    use super::super::{Function, Item};
+    use crate::Severity;
+
+    let program =
+        super::lower_source("fn f(x: int) -> int { match x { 0 => 1, n if n > 5 => n, _ => 2 } }");
+    let Item::Fn(Function { body, .. }) = &program.items[0] else {
+        panic!("expected a function")
+    };
+    let var = |name: &str| Expr::Var(name.to_owned());
+    let assign = |name: &str, value| Stmt::Assign {
+        name: name.to_owned(),
+        value,
+    };
+    assert_eq!(
+        *body,
+        [
+            Stmt::Let {
+                is_mut: true,
+                name: "_t0".to_owned(),
+                value: Expr::Unit,
+            },
+            Stmt::If {
+                condition: Expr::Bin {
+                    op: BinOp::Equals,
+                    left: Box::new(var("x")),
+                    right: Box::new(Expr::Num(0_f64)),
+                    operand_type: Some(Type::Named("int".to_owned())),
+                },
+                then: vec![assign("_t0", Expr::Num(1_f64))],
+                // the guard can fail, so a flag remembers whether the arm matched
+                r#else: vec![
+                    Stmt::Let {
+                        is_mut: true,
+                        name: "_t1".to_owned(),
+                        value: Expr::Bool(false),
+                    },
+                    Stmt::Let {
+                        is_mut: false,
+                        name: "n".to_owned(),
+                        value: var("x"),
+                    },
+                    Stmt::If {
+                        condition: Expr::Bin {
+                            op: BinOp::Greater,
+                            left: Box::new(var("n")),
+                            right: Box::new(Expr::Num(5_f64)),
+                            operand_type: Some(Type::Named("int".to_owned())),
+                        },
+                        then: vec![assign("_t1", Expr::Bool(true)), assign("_t0", var("n"))],
+                        r#else: Vec::new(),
+                    },
+                    Stmt::If {
+                        condition: Expr::Unary {
+                            op: UnaryOp::Not,
+                            operand: Box::new(var("_t1")),
+                        },
+                        then: vec![assign("_t0", Expr::Num(2_f64))],
+                        r#else: Vec::new(),
+                    },
+                ],
+            },
+            Stmt::Return(var("_t0")),
+        ]
+    );
+
+    let lower_diagnostics = |source| {
+        use erebus_parser::{Ast, Parsable};
+
+        let options = super::LowerOptions::default();
+        match super::lower(&Ast::parse(source).unwrap(), &options) {
+            Ok((_, warnings)) => warnings,
+            Err(errors) => errors,
+        }
+    };
+    let spans = |diagnostics: Vec<Diagnostic>| -> Vec<_> {
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.span))
+            .collect()
+    };
+
+    let source = "enum E { A, B(int), C { x: int } }
+                  fn f(e: E) -> int { match e { E::B(_) => 1, _ => 2, E::A => 3 } }";
+    assert_eq!(
+        spans(lower_diagnostics(source)),
+        [(Severity::Warning, Some(105..109))]
+    );
+
+    let source = "enum E { A, B(int), C { x: int } }
+                  fn f(e: E) -> int { match e { E::B(1) => 1, E::C { .. } => 2, E::A => 3 } }
+                  fn g(e: E) -> int { match e { E::B(x, y) => 1, E::C { y } => 2, _ => 3 } }";
+    assert_eq!(
+        spans(lower_diagnostics(source)),
+        [
+            // `E::B(_)` isn't covered
+            (Severity::Error, Some(79..80)),
+            // the wrong number of fields
+            (Severity::Error, Some(177..188)),
+            // the unknown and missing field
+            (Severity::Error, Some(201..202)),
+            (Severity::Error, Some(194..205)),
+        ]
+    );
+}
+
+#[test]
+fn test_lower_let_pattern() {
+    use super::super::{Function, Global, Item};
+    use crate::Severity;
+
+    let program = super::lower_source(
+        "struct P { x: int, y: int }
+         fn f(p: P) -> int { let (a, P { y, .. }) = (p.x, p); a + y }",
+    );
+    let Item::Fn(Function { body, .. }) = &program.items[1] else {
+        panic!("expected a function")
+    };
+    let var = |name: &str| Box::new(Expr::Var(name.to_owned()));
+    let tuple_field = |index| Expr::TupleField {
+        value: var("_t0"),
+        index,
+    };
+    assert_eq!(
+        body[..3],
+        [
+            Stmt::Let {
+                is_mut: false,
+                name: "_t0".to_owned(),
+                value: Expr::Tuple(vec![
+                    Expr::Field {
+                        value: var("p"),
+                        name: "x".to_owned(),
+                    },
+                    *var("p"),
+                ]),
+            },
+            Stmt::Let {
+                is_mut: false,
+                name: "a".to_owned(),
+                value: tuple_field(0),
+            },
+            Stmt::Let {
+                is_mut: false,
+                name: "y".to_owned(),
+                value: Expr::Field {
+                    value: Box::new(tuple_field(1)),
+                    name: "y".to_owned(),
+                },
+            },
+        ]
+    );
+
+    // a top level `let` becomes a global for every name
+    let program = super::lower_source("let mut (a, b) = (1, 2);");
+    let global = |name: &str, setup, index| {
+        Item::Global(Global {
+            is_pub: false,
+            is_mut: true,
+            name: name.to_owned(),
+            r#type: None,
+
+            setup,
+            value: tuple_field(index),
+        })
+    };
+    assert_eq!(
+        program.items,
+        [
+            global(
+                "a",
+                vec![Stmt::Let {
+                    is_mut: false,
+                    name: "_t0".to_owned(),
+                    value: Expr::Tuple(vec![Expr::Num(1_f64), Expr::Num(2_f64)]),
+                }],
+                0
+            ),
+            global("b", Vec::new(), 1),
+        ]
+    );
+
+    let source = "enum E { A, B(int) }
+                  let (_, _) = (1, 2);
+                  fn f(e: E) -> int { let E::B(x) = e; x }";
+    let diagnostics = match super::lower(
+        &erebus_parser::Parsable::parse(source).unwrap(),
+        &super::LowerOptions::default(),
+    ) {
+        Ok(_) => panic!("expected errors"),
+        Err(errors) => errors,
+    };
+    assert_eq!(
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.message, diagnostic.span))
+            .collect::<Vec<_>>(),
+        [
+            (
+                Severity::Error,
+                "a top level `let` has to bind a name".to_owned(),
+                Some(43..50)
+            ),
+            (
+                Severity::Error,
+                "refutable pattern in `let`, `E::A` isn't covered".to_owned(),
+                Some(102..110)
+            ),
+        ]
+    );
+}
@@ -0,0 +1,777 @@
+//! Checking of the values passed to functions, struct literals, annotated `let`s and operators.
+//!
+//! The arguments of generic functions and structs are inferred from these values,
+//! and have to implement the traits that bound their parameters.
+//! A type is only inferred from an expression if its operands make it obvious,
+//! everything else is unknown and accepted anywhere.
+
+use std::{collections::HashMap, fmt};
+
+use erebus_parser::{
+    BinExpr, Expression, FieldInit, FnCall, Span, Spanned, StructLiteral, UnaryExpr,
+};
+
+use crate::Diagnostic;
+
+use super::{super::Type, Lowerer, MethodInfo, StructInfo};
+
+/// The types of a function, which can refer to its generic parameters.
+///
+/// The functions of a trait refer to the type that implements it as the parameter `Self`.
+#[derive(Clone, PartialEq)]
+pub(super) struct Signature {
+    pub generics: Vec<String>,
+    pub bounds: Bounds,
+    pub params: Vec<Type>,
+    pub return_type: Type,
+}
+
+/// Generic parameter -> the argument that was inferred for it so far.
+pub(super) type Bindings = HashMap<String, Option<Type>>;
+
+/// Generic parameter -> the traits its argument has to implement, in the order they are written.
+pub(super) type Bounds = HashMap<String, Vec<String>>;
+
+/// Where the method of a call is found.
+pub(super) enum Dispatch {
+    /// In the `impl` blocks of the struct or enum with this source name.
+    Type(String),
+    /// In a trait that bounds the generic parameter, which a function gets the
+    /// implementation of with [`LowerOptions::dictionary_passing`](super::LowerOptions).
+    Param { param: String, r#trait: String },
+    /// In the trait of a `dyn` value.
+    Dyn(String),
+}
+
+pub(super) fn bindings(generics: &[String]) -> Bindings {
+    generics.iter().map(|name| (name.clone(), None)).collect()
+}
+
+impl Lowerer<'_> {
+    /// The type of `expression`, if it can be inferred.
+    ///
+    /// The types of blocks, `if`s and `match`es are only known once they were lowered.
+    pub(super) fn infer(&self, expression: &Spanned<Expression>) -> Option<Type> {
+        let named = |name: &str| Some(Type::Named(name.to_owned()));
+
+        match &expression.node {
+            Expression::NumLit(num_lit) if num_lit.0.fract() == 0.0 => named("int"),
+            Expression::NumLit(_) => named("float"),
+            Expression::StringLit(_) => named("String"),
+            Expression::Variable(variable) => {
+                let name = variable.0.as_str();
+                match self.local(name) {
+                    Some(target) => self.types.get(target).cloned(),
+                    // a function can be passed around if it isn't generic
+                    None => self
+                        .fns
+                        .get(name)
+                        .filter(|signature| signature.generics.is_empty())
+                        .map(|signature| Type::Fn {
+                            params: signature.params.clone(),
+                            return_type: Box::new(signature.return_type.clone()),
+                        }),
+                }
+            }
+
+            Expression::UnaryExpr(unary_expr) => match &**unary_expr {
+                UnaryExpr::Neg(expr) => self.infer(&expr.expression),
+                UnaryExpr::Not(_) => named("bool"),
+            },
+            Expression::BinExpr(bin_expr) => self.infer_bin_expr(bin_expr),
+            Expression::FnCall(fn_call) => self.infer_fn_call(fn_call),
+            Expression::StructLiteral(literal) => match &literal.path.0[..] {
+                [name] => {
+                    let name = self.type_name(name.as_str());
+                    let info = self.structs.get(name)?;
+                    let generics = &self.struct_generics[name];
+                    let mut bindings = bindings(generics);
+                    for field in &literal.fields {
+                        if let Some((r#type, found)) = self
+                            .field_type(info, field.name.as_str())
+                            .zip(self.infer(&field.value))
+                        {
+                            self.unify(r#type, &found, &mut bindings);
+                        }
+                    }
+                    Some(applied(name, generics, &bindings))
+                }
+                [r#enum, _] => self.enum_type(r#enum.as_str()),
+                _ => None,
+            },
+            Expression::Path(path) => match &path.0[..] {
+                [r#enum, _] => self.enum_type(r#enum.as_str()),
+                _ => None,
+            },
+
+            Expression::FieldAccess(access) => {
+                let (name, args) = match self.infer(&access.value)? {
+                    Type::Named(name) => (name, Vec::new()),
+                    Type::Applied { name, args } => (name, args),
+                    _ => return None,
+                };
+                let info = self.structs.get(&name)?;
+                let r#type = self.field_type(info, access.field.as_str())?;
+
+                // the parameters stay unknown without arguments
+                let generics = &self.struct_generics[&name];
+                let mut bindings = bindings(generics);
+                if args.len() == generics.len() {
+                    for (param, arg) in generics.iter().zip(args) {
+                        bindings.insert(param.clone(), Some(arg));
+                    }
+                }
+                substitute(r#type, &bindings)
+            }
+            Expression::MethodCall(call) => {
+                let receiver = self.infer(&call.receiver)?;
+                let (_, info, mut bindings) = self.dispatch(&receiver, call.method.as_str())?;
+                for (param, arg) in info.signature.params.iter().zip(&call.args) {
+                    if let Some(found) = self.infer(arg) {
+                        self.unify(param, &found, &mut bindings);
+                    }
+                }
+                substitute(&info.signature.return_type, &bindings)
+            }
+            Expression::TupleIndex(tuple_index) => match self.infer(&tuple_index.value)? {
+                Type::Tuple(mut types) if *tuple_index.index < types.len() => {
+                    Some(types.swap_remove(*tuple_index.index))
+                }
+                _ => None,
+            },
+            Expression::Index(index) => match self.infer(&index.value)? {
+                Type::Array(element) => Some(*element),
+                _ => None,
+            },
+            Expression::TupleLiteral(literal) => literal
+                .0
+                .iter()
+                .map(|element| self.infer(element))
+                .collect::<Option<_>>()
+                .map(Type::Tuple),
+            Expression::ArrayLiteral(literal) => literal
+                .0
+                .first()
+                .and_then(|element| self.infer(element))
+                .map(|element| Type::Array(Box::new(element))),
+            // the value of a block can refer to its own locals, so it's only known once it's lowered
+            Expression::CodeScope(_) | Expression::If(_) | Expression::Match(_) => {
+                self.value_types.get(&expression.span).cloned()
+            }
+
+            _ => None,
+        }
+    }
+
+    fn infer_bin_expr(&self, bin_expr: &BinExpr) -> Option<Type> {
+        let named = |name: &str| Some(Type::Named(name.to_owned()));
+        let number = |[left, right]: &[Spanned<Expression>; 2]| match (
+            self.infer(left)?,
+            self.infer(right)?,
+        ) {
+            (Type::Named(left), Type::Named(right)) if is_number(&left) && is_number(&right) => {
+                named(if left == "float" || right == "float" {
+                    "float"
+                } else {
+                    "int"
+                })
+            }
+            _ => None,
+        };
+
+        match bin_expr {
+            BinExpr::Or(_)
+            | BinExpr::And(_)
+            | BinExpr::Equals(_)
+            | BinExpr::NotEquals(_)
+            | BinExpr::Less(_)
+            | BinExpr::LessEquals(_)
+            | BinExpr::Greater(_)
+            | BinExpr::GreaterEquals(_) => named("bool"),
+
+            BinExpr::BitOr(_)
+            | BinExpr::BitXor(_)
+            | BinExpr::BitAnd(_)
+            | BinExpr::Shl(_)
+            | BinExpr::Shr(_) => named("int"),
+
+            // strings can be concatenated
+            BinExpr::Add(expr) => match self.infer(&expr.expressions[0])? {
+                Type::Named(name) if name == "String" => named("String"),
+                _ => number(&expr.expressions),
+            },
+            BinExpr::Sub(expr) => number(&expr.expressions),
+            BinExpr::Mul(expr) => number(&expr.expressions),
+            // dividing integers doesn't round
+            BinExpr::Div(expr) => number(&expr.expressions).and(named("float")),
+            BinExpr::Rem(expr) => number(&expr.expressions),
+        }
+    }
+
+    fn infer_fn_call(&self, fn_call: &FnCall) -> Option<Type> {
+        match &fn_call.callee.0[..] {
+            [name] => {
+                let name = name.as_str();
+                if let Some(signature) = self.signature(name) {
+                    let mut bindings = bindings(&signature.generics);
+                    for (param, arg) in signature.params.iter().zip(&fn_call.args) {
+                        if let Some(found) = self.infer(arg) {
+                            self.unify(param, &found, &mut bindings);
+                        }
+                    }
+                    return substitute(&signature.return_type, &bindings);
+                }
+
+                // a local closure
+                match self.types.get(self.local(name)?)? {
+                    Type::Fn { return_type, .. } => Some(*return_type.clone()),
+                    _ => None,
+                }
+            }
+            [r#type, function] => {
+                let r#type = self.type_name(r#type.as_str());
+                let Some(info) = self
+                    .impls
+                    .get(r#type)
+                    .and_then(|fns| fns.get(function.as_str()))
+                else {
+                    // a tuple variant
+                    return self
+                        .enums
+                        .get(r#type)?
+                        .variants
+                        .iter()
+                        .any(|info| info.source_name == function.as_str())
+                        .then(|| Type::Named(r#type.to_owned()));
+                };
+
+                let mut bindings = bindings(&info.signature.generics);
+                bindings.extend(
+                    self.type_generics(r#type)
+                        .into_iter()
+                        .map(|param| (param, None)),
+                );
+                let args = match info.has_receiver {
+                    true => fn_call.args.get(1..)?,
+                    false => &fn_call.args,
+                };
+                for (param, arg) in info.signature.params.iter().zip(args) {
+                    if let Some(found) = self.infer(arg) {
+                        self.unify(param, &found, &mut bindings);
+                    }
+                }
+                substitute(&info.signature.return_type, &bindings)
+            }
+            _ => None,
+        }
+    }
+
+    /// The target name of a local, if `name` isn't a global.
+    fn local(&self, name: &str) -> Option<&String> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// The signature of the top level function `name`, if it isn't shadowed by a local.
+    pub(super) fn signature(&self, name: &str) -> Option<&Signature> {
+        match self.local(name) {
+            Some(_) => None,
+            None => self.fns.get(name),
+        }
+    }
+
+    fn enum_type(&self, name: &str) -> Option<Type> {
+        let name = self.type_name(name);
+        self.enums
+            .contains_key(name)
+            .then(|| Type::Named(name.to_owned()))
+    }
+
+    fn field_type<'i>(&self, info: &'i StructInfo, name: &str) -> Option<&'i Type> {
+        let index = info.fields.iter().position(|(source, _)| source == name)?;
+        Some(&info.types[index])
+    }
+
+    /// Whether values of type `r#type` implement the trait `r#trait`.
+    pub(super) fn implements(&self, r#type: &Type, r#trait: &str) -> bool {
+        match r#type {
+            Type::Named(name) | Type::Applied { name, .. } => self
+                .implementations
+                .get(name)
+                .is_some_and(|traits| traits.contains(r#trait)),
+            Type::Param(param) => self
+                .bounds
+                .get(param)
+                .is_some_and(|traits| traits.iter().any(|bound| bound == r#trait)),
+            Type::Dyn(name) => name == r#trait,
+            _ => false,
+        }
+    }
+
+    /// Finds the method `name` that takes `self` for a receiver of type `receiver`.
+    ///
+    /// The bindings contain the parameters of the method, and the ones of the receiver's type
+    /// as far as they are known.
+    pub(super) fn dispatch(
+        &self,
+        receiver: &Type,
+        name: &str,
+    ) -> Option<(Dispatch, MethodInfo, Bindings)> {
+        let (dispatch, info) = match receiver {
+            Type::Named(r#type) | Type::Applied { name: r#type, .. } => {
+                let info = self.impls.get(r#type)?.get(name)?;
+                (Dispatch::Type(r#type.clone()), info)
+            }
+            Type::Param(param) => self.bounds.get(param)?.iter().find_map(|r#trait| {
+                let info = self.traits[r#trait].get(name)?;
+                let dispatch = Dispatch::Param {
+                    param: param.clone(),
+                    r#trait: r#trait.clone(),
+                };
+                Some((dispatch, info))
+            })?,
+            Type::Dyn(r#trait) => (
+                Dispatch::Dyn(r#trait.clone()),
+                self.traits.get(r#trait)?.get(name)?,
+            ),
+            _ => return None,
+        };
+        if !info.has_receiver {
+            return None;
+        }
+
+        let mut bindings = bindings(&info.signature.generics);
+        match &dispatch {
+            Dispatch::Type(r#type) => {
+                let generics = self.type_generics(r#type);
+                let args = match receiver {
+                    Type::Applied { args, .. } if args.len() == generics.len() => {
+                        args.iter().cloned().map(Some).collect()
+                    }
+                    _ => vec![None; generics.len()],
+                };
+                bindings.extend(generics.into_iter().zip(args));
+            }
+            Dispatch::Param { .. } | Dispatch::Dyn(_) => {
+                bindings.insert("Self".to_owned(), Some(receiver.clone()));
+            }
+        }
+        Some((dispatch, info.clone(), bindings))
+    }
+
+    /// Checks the arguments of a call to the top level function `name`.
+    pub(super) fn check_call(
+        &mut self,
+        name: &str,
+        args: &[Spanned<Expression>],
+        span: Span,
+    ) -> Option<(Signature, Bindings)> {
+        let signature = self.signature(name)?.clone();
+        let bindings = bindings(&signature.generics);
+        let bindings = self.check_args(name, &signature, bindings, args, span)?;
+        Some((signature, bindings))
+    }
+
+    /// Checks the arguments of a call to the function `name` with `signature`,
+    /// starting with the parameters in `bindings`.
+    ///
+    /// Returns what the parameters were bound to, unless the amount of arguments is wrong.
+    pub(super) fn check_args(
+        &mut self,
+        name: &str,
+        signature: &Signature,
+        mut bindings: Bindings,
+        args: &[Spanned<Expression>],
+        span: Span,
+    ) -> Option<Bindings> {
+        if signature.params.len() != args.len() {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "`{name}` takes {} but {} {} given",
+                    arguments(signature.params.len()),
+                    args.len(),
+                    if args.len() == 1 { "was" } else { "were" },
+                ))
+                .with_span(span),
+            );
+            return None;
+        }
+
+        let mut spans = HashMap::new();
+        for (param, arg) in signature.params.iter().zip(args) {
+            self.check_value(param, arg, &mut bindings);
+            record_spans(&bindings, arg.span, &mut spans);
+        }
+        self.check_bounds(&signature.bounds, &bindings, &spans);
+        Some(bindings)
+    }
+
+    /// Checks the fields of a literal of the struct `name` against each other and their declared types.
+    pub(super) fn check_struct_literal(&mut self, name: &str, literal: &StructLiteral) {
+        let Some(info) = self.structs.get(name) else {
+            return;
+        };
+        let mut bindings = bindings(&self.struct_generics[name]);
+
+        let fields: Vec<(Type, &Spanned<FieldInit>)> = literal
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let r#type = self.field_type(info, field.name.as_str())?;
+                Some((r#type.clone(), field))
+            })
+            .collect();
+        let mut spans = HashMap::new();
+        for (r#type, field) in fields {
+            self.check_value(&r#type, &field.value, &mut bindings);
+            record_spans(&bindings, field.value.span, &mut spans);
+        }
+
+        let bounds = self.struct_bounds.get(name).cloned().unwrap_or_default();
+        self.check_bounds(&bounds, &bindings, &spans);
+    }
+
+    /// Reports the arguments in `bindings` that don't implement the traits of their parameter.
+    ///
+    /// `spans` are the values the arguments were inferred from.
+    fn check_bounds(
+        &mut self,
+        bounds: &Bounds,
+        bindings: &Bindings,
+        spans: &HashMap<String, Span>,
+    ) {
+        let mut params: Vec<_> = bounds.keys().collect();
+        params.sort();
+
+        for param in params {
+            let Some(Some(r#type)) = bindings.get(param) else {
+                continue;
+            };
+            for r#trait in &bounds[param] {
+                if !self.implements(r#type, r#trait) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("`{type}` doesn't implement `{trait}`"))
+                            .with_span(spans[param]),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Reports a mismatch if `value` can't be of type `expected`,
+    /// binding the generic parameters in `bindings` to what it is.
+    pub(super) fn check_value(
+        &mut self,
+        expected: &Type,
+        value: &Spanned<Expression>,
+        bindings: &mut Bindings,
+    ) {
+        let Some(found) = self.infer(value) else {
+            return;
+        };
+        // the error mentions what the parameters were bound to before
+        let before = bindings.clone();
+        if !self.unify(expected, &found, bindings) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "expected `{}`, found `{found}`",
+                    substitute_known(expected, &before)
+                ))
+                .with_span(value.span),
+            );
+        }
+    }
+
+    /// Reports a mismatch if a value of type `found` at `span` can't be used as `expected`.
+    pub(super) fn check_type(&mut self, expected: &Type, found: &Type, span: Span) {
+        if !self.unify(expected, found, &mut Bindings::new()) {
+            self.diagnostics.push(
+                Diagnostic::error(format!("expected `{expected}`, found `{found}`"))
+                    .with_span(span),
+            );
+        }
+    }
+
+    /// The type of a value that comes from one of `branches`, which have to agree on it.
+    ///
+    /// Branches whose type isn't known are accepted, the first known type is what the others
+    /// are checked against.
+    pub(super) fn check_branches(&mut self, branches: Vec<(Option<Type>, Span)>) -> Option<Type> {
+        let mut known = branches
+            .into_iter()
+            .filter_map(|(r#type, span)| Some((r#type?, span)));
+        let (r#type, _) = known.next()?;
+        for (found, span) in known {
+            self.check_type(&r#type, &found, span);
+        }
+        Some(r#type)
+    }
+
+    /// Checks the operands of the operator `symbol` if their types are known.
+    ///
+    /// Every operand has to be one of the types in `operands`,
+    /// and the same type as the ones before it.
+    pub(super) fn check_operands(
+        &mut self,
+        symbol: &str,
+        values: &[&Spanned<Expression>],
+        operands: Operands,
+    ) {
+        let what = match values.len() {
+            1 => "the operand",
+            _ => "an operand",
+        };
+        let mut first = None;
+        for value in values {
+            let Some(found) = self.infer(value) else {
+                continue;
+            };
+            if !operands.accepts(&found) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "{what} of `{symbol}` has to be {operands}, found `{found}`"
+                    ))
+                    .with_span(value.span),
+                );
+            } else if let Some(expected) = &first {
+                self.check_type(expected, &found, value.span);
+            } else {
+                first = Some(found);
+            }
+        }
+    }
+
+    /// The type of both operands of a binary operator, which is a `float`
+    /// if one of them is an `int` and the other one a `float`.
+    pub(super) fn operand_type(
+        &self,
+        left: &Spanned<Expression>,
+        right: &Spanned<Expression>,
+    ) -> Option<Type> {
+        match (self.infer(left), self.infer(right)) {
+            (Some(Type::Named(left)), Some(Type::Named(right)))
+                if is_number(&left) && is_number(&right) && left != right =>
+            {
+                Some(Type::Named("float".to_owned()))
+            }
+            (left, right) => left.or(right),
+        }
+    }
+
+    /// Checks that `value` is an `int` if its type is known, unlike [`Lowerer::check_value`]
+    /// this rejects a `float`. `what` describes the value in the error.
+    pub(super) fn check_integer(&mut self, value: &Spanned<Expression>, what: &str) {
+        match self.infer(value) {
+            Some(Type::Named(name)) if name == "int" => {}
+            Some(found) => self.diagnostics.push(
+                Diagnostic::error(format!("{what} has to be an `int`, found `{found}`"))
+                    .with_span(value.span),
+            ),
+            None => {}
+        }
+    }
+
+    /// Whether a value of type `found` can be used as `expected`.
+    ///
+    /// The parameters in `bindings` can be anything, the first type that is used
+    /// for one of them is what every other use has to be.
+    fn unify(&self, expected: &Type, found: &Type, bindings: &mut Bindings) -> bool {
+        match (expected, found) {
+            (Type::Param(param), found) if bindings.contains_key(param) => {
+                match &bindings[param] {
+                    // the bound type belongs to the caller, its parameters can't be bound again
+                    Some(bound) => self.unify(bound, found, &mut Bindings::new()),
+                    None => {
+                        bindings.insert(param.clone(), Some(found.clone()));
+                        true
+                    }
+                }
+            }
+            (Type::Param(expected), Type::Param(found)) => expected == found,
+            (Type::Dyn(r#trait), found) => self.implements(found, r#trait),
+
+            (Type::Named(expected), Type::Named(found)) => {
+                expected == found || is_number(expected) && is_number(found)
+            }
+            // the arguments of a struct without them are unknown
+            (Type::Named(expected), Type::Applied { name, .. })
+            | (Type::Applied { name, .. }, Type::Named(expected)) => expected == name,
+            (
+                Type::Applied { name, args },
+                Type::Applied {
+                    name: found_name,
+                    args: found_args,
+                },
+            ) => name == found_name && self.unify_all(args, found_args, bindings),
+
+            (
+                Type::Fn {
+                    params,
+                    return_type,
+                },
+                Type::Fn {
+                    params: found_params,
+                    return_type: found_return_type,
+                },
+            ) => {
+                self.unify_all(params, found_params, bindings)
+                    && self.unify(return_type, found_return_type, bindings)
+            }
+            (Type::Tuple(types), Type::Tuple(found)) => self.unify_all(types, found, bindings),
+            (Type::Array(element), Type::Array(found)) => self.unify(element, found, bindings),
+            _ => false,
+        }
+    }
+
+    fn unify_all(&self, expected: &[Type], found: &[Type], bindings: &mut Bindings) -> bool {
+        expected.len() == found.len()
+            && expected
+                .iter()
+                .zip(found)
+                .all(|(expected, found)| self.unify(expected, found, bindings))
+    }
+}
+
+/// Remembers `span` for the parameters that were bound by the value at it.
+fn record_spans(bindings: &Bindings, span: Span, spans: &mut HashMap<String, Span>) {
+    for (param, bound) in bindings {
+        if bound.is_some() {
+            spans.entry(param.clone()).or_insert(span);
+        }
+    }
+}
+
+/// The type of a struct whose generic parameters are bound by `bindings`,
+/// without arguments if not all of them are known.
+fn applied(name: &str, generics: &[String], bindings: &Bindings) -> Type {
+    let args: Option<Vec<Type>> = generics
+        .iter()
+        .map(|param| bindings.get(param).cloned().flatten())
+        .collect();
+
+    match args {
+        Some(args) if !args.is_empty() => Type::Applied {
+            name: name.to_owned(),
+            args,
+        },
+        _ => Type::Named(name.to_owned()),
+    }
+}
+
+/// The types an operator accepts for its operands.
+#[derive(Clone, Copy)]
+pub(super) enum Operands {
+    Any,
+    Bool,
+    Int,
+    Number,
+    /// Strings are concatenated by `+` and compared by their characters.
+    NumberOrString,
+}
+
+impl Operands {
+    fn accepts(self, r#type: &Type) -> bool {
+        let Type::Named(name) = r#type else {
+            return matches!(self, Self::Any);
+        };
+        match self {
+            Self::Any => true,
+            Self::Bool => name == "bool",
+            Self::Int => name == "int",
+            Self::Number => is_number(name),
+            Self::NumberOrString => is_number(name) || name == "String",
+        }
+    }
+}
+
+impl fmt::Display for Operands {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Any => "anything",
+            Self::Bool => "a `bool`",
+            Self::Int => "an `int`",
+            Self::Number => "a number",
+            Self::NumberOrString => "a number or a `String`",
+        })
+    }
+}
+
+/// All numbers are floats at runtime, so `int` and `float` can be used for each other.
+fn is_number(name: &str) -> bool {
+    matches!(name, "int" | "float")
+}
+
+/// Replaces the parameters in `bindings`, fails if any of them isn't bound yet.
+pub(super) fn substitute(r#type: &Type, bindings: &Bindings) -> Option<Type> {
+    let substitute_all = |types: &[Type]| -> Option<Vec<Type>> {
+        types
+            .iter()
+            .map(|r#type| substitute(r#type, bindings))
+            .collect()
+    };
+
+    Some(match r#type {
+        Type::Param(param) => match bindings.get(param) {
+            Some(bound) => bound.clone()?,
+            None => r#type.clone(),
+        },
+        Type::Named(_) | Type::Dyn(_) => r#type.clone(),
+        Type::Applied { name, args } => Type::Applied {
+            name: name.clone(),
+            args: substitute_all(args)?,
+        },
+        Type::Fn {
+            params,
+            return_type,
+        } => Type::Fn {
+            params: substitute_all(params)?,
+            return_type: Box::new(substitute(return_type, bindings)?),
+        },
+        Type::Tuple(types) => Type::Tuple(substitute_all(types)?),
+        Type::Array(element) => Type::Array(Box::new(substitute(element, bindings)?)),
+    })
+}
+
+/// Like [`substitute`], but keeps the parameters that aren't bound yet.
+fn substitute_known(r#type: &Type, bindings: &Bindings) -> Type {
+    let known = bindings
+        .iter()
+        .map(|(param, bound)| {
+            let bound = bound.clone().unwrap_or_else(|| Type::Param(param.clone()));
+            (param.clone(), Some(bound))
+        })
+        .collect();
+    substitute(r#type, &known).expect("every parameter is bound")
+}
+
+fn arguments(count: usize) -> String {
+    match count {
+        1 => "1 argument".to_owned(),
+        count => format!("{count} arguments"),
+    }
+}
+
+/// Types are displayed the way they are written in the source.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |types: &[Type]| {
+            types
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match self {
+            Self::Named(name) | Self::Param(name) => write!(f, "{name}"),
+            Self::Applied { name, args } => write!(f, "{name}<{}>", list(args)),
+            Self::Fn {
+                params,
+                return_type,
+            } => write!(f, "fn({}) -> {return_type}", list(params)),
+            Self::Tuple(types) if types.len() == 1 => write!(f, "({},)", types[0]),
+            Self::Tuple(types) => write!(f, "({})", list(types)),
+            Self::Array(element) => write!(f, "[{element}]"),
+            Self::Dyn(name) => write!(f, "dyn {name}"),
+        }
+    }
+}
@@ -0,0 +1,503 @@
+//! A statement oriented representation of a program, shared by all backends.
+//!
+//! Every expression in here is free of side effects on the control flow:
+//! blocks and other constructs that need statements have already been hoisted
+//! into the surrounding [`Stmt`] lists by [`lower`].
+//! All names are final, meaning shadowed bindings have been renamed and
+//! reserved words of the target have been avoided.
+
+mod lower;
+
+use serde::Serialize;
+
+pub use lower::{lower, LowerOptions};
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Item {
+    Fn(Function),
+    Struct(Struct),
+    Enum(Enum),
+    Global(Global),
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Function {
+    pub is_pub: bool,
+    pub name: String,
+    /// The names of the generic parameters, which [`Type::Param`]s refer to.
+    pub generics: Vec<String>,
+
+    pub params: Vec<TypedName>,
+    pub return_type: Type,
+
+    /// Always ends with a [`Stmt::Return`].
+    pub body: Vec<Stmt>,
+}
+
+/// A function of an `impl` block.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Method {
+    /// Whether the method takes `self`, which is bound to [`LowerOptions::receiver`].
+    ///
+    /// Methods without one are called through their type.
+    pub has_receiver: bool,
+    /// Its name lives in the namespace of the type and never equals the name of a field or variant.
+    pub function: Function,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Struct {
+    pub is_pub: bool,
+    pub name: String,
+    /// The names of the generic parameters, which [`Type::Param`]s refer to.
+    pub generics: Vec<String>,
+    pub fields: Vec<TypedName>,
+    pub methods: Vec<Method>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Enum {
+    pub is_pub: bool,
+    pub name: String,
+    pub variants: Vec<Variant>,
+    /// Shared by all variants.
+    pub methods: Vec<Method>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Variant {
+    pub name: String,
+    /// The fields of a tuple variant are called `_0`, `_1`, ...
+    ///
+    /// No field is called `tag`, so targets can use it to store the variant.
+    pub fields: Vec<TypedName>,
+}
+
+/// A top level `let`.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Global {
+    pub is_pub: bool,
+    pub is_mut: bool,
+    pub name: String,
+    pub r#type: Option<Type>,
+
+    /// Statements that need to run before `value` can be evaluated.
+    pub setup: Vec<Stmt>,
+    pub value: Expr,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TypedName {
+    pub name: String,
+    pub r#type: Type,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Type {
+    /// Either a builtin type like `int` or the name of a struct.
+    Named(String),
+    /// A generic parameter of the surrounding function or struct.
+    Param(String),
+    /// A generic struct with its arguments.
+    Applied {
+        name: String,
+        args: Vec<Type>,
+    },
+    Fn {
+        params: Vec<Type>,
+        return_type: Box<Type>,
+    },
+    Tuple(Vec<Type>),
+    Array(Box<Type>),
+    /// A value of any type that implements the trait.
+    Dyn(String),
+}
+
+impl Type {
+    /// Whether the values of this type are numbers, booleans, strings or `()`,
+    /// which every target can compare with its own `==`.
+    pub fn is_primitive(&self) -> bool {
+        match self {
+            Self::Named(name) => matches!(name.as_str(), "int" | "float" | "bool" | "String"),
+            Self::Tuple(types) => types.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stmt {
+    /// Introduces a new binding.
+    Let {
+        is_mut: bool,
+        name: String,
+        value: Expr,
+    },
+    /// Changes the value of a binding that was declared with `is_mut`.
+    Assign {
+        name: String,
+        value: Expr,
+    },
+    /// Changes the value of the field `name` of the struct `target`.
+    AssignField {
+        target: Expr,
+        name: String,
+        value: Expr,
+    },
+    /// Changes the element at `index` of the array `target`,
+    /// with the same checks as [`Expr::Index`].
+    AssignIndex {
+        target: Expr,
+        index: Expr,
+        value: Expr,
+    },
+    If {
+        condition: Expr,
+        then: Vec<Stmt>,
+        r#else: Vec<Stmt>,
+    },
+    /// Runs `body` as long as `condition` is true, or forever without one.
+    ///
+    /// The label of every loop is unique.
+    Loop {
+        label: String,
+        condition: Option<Expr>,
+        body: Vec<Stmt>,
+    },
+    /// Runs `body` for every whole number from `start` up to `end`, which is bound to `binding`.
+    /// Both `start` and `end` are `int`s.
+    ///
+    /// `end` is [constant](Expr::is_constant) or a temporary, so it can be
+    /// evaluated before every iteration.
+    ForRange {
+        label: String,
+        binding: String,
+        start: Expr,
+        end: Expr,
+        /// Whether `end` is part of the range.
+        is_inclusive: bool,
+        body: Vec<Stmt>,
+    },
+    /// Runs `body` for every element of the collection `iterable`, which is bound to `binding`.
+    ForEach {
+        label: String,
+        binding: String,
+        iterable: Expr,
+        body: Vec<Stmt>,
+    },
+    /// Leaves the loop with `label`.
+    Break {
+        label: String,
+    },
+    /// Skips to the next iteration of the loop with `label`.
+    Continue {
+        label: String,
+    },
+    /// Evaluates an expression for its side effects.
+    Expr(Expr),
+    Return(Expr),
+}
+
+/// How the `break`s and `continue`s inside the body of a loop refer to it.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Jumps {
+    /// A `continue` at any depth.
+    pub r#continue: bool,
+    /// A `break` from inside of another loop.
+    pub nested_break: bool,
+    /// A `continue` from inside of another loop.
+    pub nested_continue: bool,
+}
+
+impl Jumps {
+    /// Finds the jumps to the loop with `label` in its `body`.
+    pub fn to(label: &str, body: &[Stmt]) -> Self {
+        let mut jumps = Self::default();
+        jumps.find(label, body, false);
+        jumps
+    }
+
+    fn find(&mut self, label: &str, statements: &[Stmt], is_nested: bool) {
+        for statement in statements {
+            match statement {
+                Stmt::Break { label: target } if target == label => {
+                    self.nested_break |= is_nested;
+                }
+                Stmt::Continue { label: target } if target == label => {
+                    self.r#continue = true;
+                    self.nested_continue |= is_nested;
+                }
+                Stmt::If { then, r#else, .. } => {
+                    self.find(label, then, is_nested);
+                    self.find(label, r#else, is_nested);
+                }
+                Stmt::Loop { body, .. }
+                | Stmt::ForRange { body, .. }
+                | Stmt::ForEach { body, .. } => self.find(label, body, true),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expr {
+    /// The value of `()`.
+    Unit,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Var(String),
+
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Bin {
+        op: BinOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+        /// The type of both operands, if the lowerer knows it.
+        operand_type: Option<Type>,
+    },
+    Call {
+        callee: String,
+        args: Vec<Expr>,
+    },
+    Builtin {
+        builtin: Builtin,
+        args: Vec<Expr>,
+    },
+    /// Calls the method `method` of whatever type `receiver` has when the program runs.
+    MethodCall {
+        receiver: Box<Expr>,
+        method: String,
+        args: Vec<Expr>,
+    },
+    /// Calls a [`Method`] of `type` that has no receiver.
+    ///
+    /// With [`LowerOptions::dictionary_passing`], methods whose receiver has a known type
+    /// are called like this too, with the receiver as the first argument.
+    AssociatedCall {
+        r#type: String,
+        function: String,
+        args: Vec<Expr>,
+    },
+    /// Calls the method `method` of the type whose name `implementation` evaluates to,
+    /// with the receiver as the first argument.
+    ///
+    /// Only appears with [`LowerOptions::dictionary_passing`].
+    TraitCall {
+        implementation: Box<Expr>,
+        method: String,
+        args: Vec<Expr>,
+    },
+    /// Reads a field of a struct or enum variant.
+    Field {
+        value: Box<Expr>,
+        name: String,
+    },
+    /// Reads an element of a tuple, starting at 0.
+    TupleField {
+        value: Box<Expr>,
+        index: usize,
+    },
+    /// Reads the element at `index` of an array, starting at 0.
+    ///
+    /// An index that isn't a whole number inside of the array stops the program with an error.
+    Index {
+        value: Box<Expr>,
+        index: Box<Expr>,
+    },
+    /// Whether `value` is the `variant` of `enum`.
+    IsVariant {
+        value: Box<Expr>,
+        r#enum: String,
+        variant: String,
+    },
+    /// Constructs a tuple with at least one element, `()` is [`Expr::Unit`].
+    Tuple(Vec<Expr>),
+    Array(Vec<Expr>),
+    /// A deep copy of an array, tuple, struct or enum variant.
+    ///
+    /// These are values, so changing one through a place is never seen through another:
+    /// they are copied when they are stored in a `mut` binding or read out of one.
+    /// Closures inside of them are shared, and a method that takes `mut self`
+    /// changes its receiver in place.
+    Copy(Box<Expr>),
+    /// Only appears as the value of a [`Stmt::Let`] or a [`Global`].
+    Closure(Box<Closure>),
+    /// Constructs a [`Struct`].
+    Struct {
+        name: String,
+        /// In the order they are declared in.
+        fields: Vec<FieldValue>,
+    },
+    /// Constructs a variant of an [`Enum`].
+    Variant {
+        r#enum: String,
+        variant: String,
+        /// In the order they are written in the source code.
+        fields: Vec<FieldValue>,
+    },
+}
+
+impl Expr {
+    /// Whether evaluating this expression later yields the same value
+    /// as evaluating it now.
+    pub fn is_constant(&self) -> bool {
+        match self {
+            Self::Unit | Self::Bool(_) | Self::Num(_) | Self::Str(_) => true,
+            Self::Variant { fields, .. } => fields.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Whether this expression doesn't contain any other expressions.
+    pub fn is_atomic(&self) -> bool {
+        match self {
+            Self::Unit | Self::Num(_) | Self::Str(_) | Self::Var(_) => true,
+            Self::Variant { fields, .. } => fields.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+/// A function value that can use the locals around it.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Closure {
+    pub params: Vec<String>,
+    /// The locals from outside of the closure that `body` uses.
+    ///
+    /// A capture refers to the binding itself, so assignments to it are seen on both sides.
+    pub captures: Vec<Capture>,
+    /// Always ends with a [`Stmt::Return`].
+    pub body: Vec<Stmt>,
+}
+
+impl Closure {
+    /// The captures that `body` assigns a new value to.
+    pub fn assigned_captures(&self) -> impl Iterator<Item = &Capture> {
+        self.captures
+            .iter()
+            .filter(|capture| is_assigned(&capture.name, &self.body))
+    }
+}
+
+/// Whether `statements` give the binding `name` a new value.
+///
+/// The bodies of closures aren't searched, they are functions of their own.
+pub fn is_assigned(name: &str, statements: &[Stmt]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Stmt::Assign { name: target, .. } => target == name,
+        Stmt::If { then, r#else, .. } => is_assigned(name, then) || is_assigned(name, r#else),
+        Stmt::Loop { body, .. } | Stmt::ForRange { body, .. } | Stmt::ForEach { body, .. } => {
+            is_assigned(name, body)
+        }
+        _ => false,
+    })
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Capture {
+    pub name: String,
+    /// Whether the binding was declared with `is_mut`.
+    pub is_mut: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct FieldValue {
+    pub name: String,
+    pub value: Expr,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnaryOp {
+    /// Negates a number.
+    Neg,
+    /// Inverts a boolean.
+    Not,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinOp {
+    /// Only evaluates `right` if `left` is false.
+    Or,
+    /// Only evaluates `right` if `left` is true.
+    And,
+
+    /// Compares the values structurally, like a derived `PartialEq` in Rust.
+    /// Closures are only equal to themselves.
+    Equals,
+    NotEquals,
+    Less,
+    LessEquals,
+    Greater,
+    GreaterEquals,
+
+    /// The bitwise operators work on 32 bit signed integers, like in JavaScript.
+    ///
+    /// Both operands are converted by truncating them towards zero and wrapping
+    /// them into the range of an `i32`, `NaN` and the infinities become 0.
+    /// The shifts only use the lowest 5 bits of `right` and `>>` keeps the sign.
+    /// The result is an `i32` as well, so `1 << 31` is `-2147483648`.
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shl,
+    Shr,
+
+    Add,
+    Sub,
+    Mul,
+    /// Divides like an `f64`, so dividing by zero gives an infinity or `NaN`.
+    Div,
+    /// The remainder of a truncating division, so it has the sign of `left`.
+    /// Taking the remainder of a division by zero gives `NaN`.
+    Rem,
+}
+
+/// Functions every backend has to provide.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Builtin {
+    /// Writes its arguments separated by spaces, followed by a newline.
+    ///
+    /// Every target writes values the same way:
+    /// - numbers like JavaScript's `String(number)`, with the fewest digits that read back as
+    ///   the same `f64`: without an exponent from `0.000001` up to below `1e21`, like `3`, `0.1`
+    ///   and `12345678901234568`, and with one otherwise, like `1e-7` and `1.5e+21`
+    /// - `NaN`, `inf` and `-inf` for the special numbers
+    /// - `true` and `false` for booleans
+    /// - `()` for the unit value
+    /// - strings without quotes, unless they are inside of another value, like `"a"`,
+    ///   where `"` and `\` are escaped with a `\`
+    /// - tuples and arrays like `[1, "a"]`, most targets can't tell them apart
+    /// - structs like `Point { x: 1, y: 2 }` and enum variants like `Empty`, `Circle(1)` and
+    ///   `Rect { width: 2, height: 3 }`, with their fields in declaration order
+    /// - closures as `<fn>`
+    ///
+    /// Types and fields are written with their target names, which only differ from
+    /// the source for reserved words.
+    Print,
+}
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "print" => Some(Self::Print),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Turns an [`Ast`] into code of another language.
+//!
+//! Every target language is a [`Backend`]. The built-in ones are registered in
+//! [`Backends::builtin`], others can be added with [`Backends::register`].
+//! Most of them implement [`IrBackend`] to generate code from the lowered [`ir::Program`].
+
+mod backends;
+pub mod ir;
+mod writer;
+
+use std::{ops::Range, path::PathBuf};
+
+use erebus_parser::{Ast, Span};
+use serde::Deserialize;
+
+pub use backends::{
+    AstBackend, BashBackend, ExternalBackend, JsBackend, LuaBackend, PythonBackend,
+};
+use ir::{LowerOptions, Program};
+pub use writer::{quote, CodeWriter};
+
+pub trait Backend {
+    /// Name used to select this backend, e.g. `--emit python`.
+    fn name(&self) -> &str;
+
+    /// Generates the code of `module`.
+    fn compile(&self, module: &str, ast: &Ast) -> Output;
+}
+
+/// A [`Backend`] that generates code from the lowered [`Program`] instead of the [`Ast`].
+pub trait IrBackend {
+    /// Name used to select this backend, e.g. `--emit python`.
+    fn name(&self) -> &str;
+
+    /// How the [`Ast`] is lowered before being passed to [`IrBackend::emit`].
+    fn lower_options(&self) -> LowerOptions<'_> {
+        LowerOptions::default()
+    }
+
+    /// Generates the code of `module` from the lowered program.
+    fn emit(&self, module: &str, program: &Program) -> Output;
+}
+
+impl<T: IrBackend> Backend for T {
+    fn name(&self) -> &str {
+        IrBackend::name(self)
+    }
+
+    fn compile(&self, module: &str, ast: &Ast) -> Output {
+        match ir::lower(ast, &self.lower_options()) {
+            Ok((program, warnings)) => {
+                let mut output = self.emit(module, &program);
+                output.diagnostics.splice(0..0, warnings);
+                output
+            }
+            Err(diagnostics) => Output {
+                files: Vec::new(),
+                diagnostics,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct Output {
+    pub files: Vec<OutputFile>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Output {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct OutputFile {
+    /// Relative to the output directory.
+    pub path: PathBuf,
+    pub content: String,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The byte range of the source code this is about.
+    #[serde(default)]
+    pub span: Option<Range<usize>>,
+    /// Other places in the source code that are involved, like an earlier definition.
+    #[serde(default)]
+    pub notes: Vec<Note>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Note {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            span: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        Self {
+            span: Some(span.into_range()),
+            ..self
+        }
+    }
+
+    pub fn with_note(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.notes.push(Note {
+            span: span.into_range(),
+            message: message.into(),
+        });
+        self
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// The backends that can be selected by their [name](Backend::name).
+#[derive(Default)]
+pub struct Backends {
+    backends: Vec<Box<dyn Backend>>,
+}
+
+impl Backends {
+    pub fn builtin() -> Self {
+        let mut backends = Self::default();
+
+        backends.register(AstBackend);
+        backends.register(PythonBackend);
+        backends.register(JsBackend);
+        backends.register(LuaBackend);
+        backends.register(BashBackend);
+
+        backends
+    }
+
+    /// Adds `backend`, replacing an existing one with the same name.
+    pub fn register(&mut self, backend: impl Backend + 'static) {
+        self.backends
+            .retain(|existing| existing.name() != backend.name());
+        self.backends.push(Box::new(backend));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Backend> {
+        self.backends
+            .iter()
+            .find(|backend| backend.name() == name)
+            .map(|backend| backend.as_ref())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.backends.iter().map(|backend| backend.name())
+    }
+}
+
+#[test]
+fn test_backends() {
+    use erebus_parser::Parsable;
+
+    struct Custom;
+
+    impl IrBackend for Custom {
+        fn name(&self) -> &str {
+            "python"
+        }
+
+        fn emit(&self, _module: &str, _program: &Program) -> Output {
+            Output {
+                files: Vec::new(),
+                diagnostics: vec![Diagnostic::error("custom")],
+            }
+        }
+    }
+
+    let mut backends = Backends::builtin();
+    assert_eq!(
+        backends.names().collect::<Vec<_>>(),
+        ["ast", "python", "js", "lua", "bash"]
+    );
+
+    backends.register(Custom);
+    let output = backends
+        .get("python")
+        .unwrap()
+        .compile("test", &Ast::parse("").unwrap());
+    assert!(output.has_errors());
+    assert!(backends.get("missing").is_none());
+}
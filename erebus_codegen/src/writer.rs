@@ -0,0 +1,167 @@
+/// Accumulates lines of source code with the correct indentation.
+pub struct CodeWriter {
+    output: String,
+
+    indent: &'static str,
+    level: usize,
+}
+
+impl CodeWriter {
+    pub fn new(indent: &'static str) -> Self {
+        Self {
+            output: String::new(),
+
+            indent,
+            level: 0,
+        }
+    }
+
+    pub fn line(&mut self, line: impl AsRef<str>) {
+        for _ in 0..self.level {
+            self.output.push_str(self.indent);
+        }
+        self.output.push_str(line.as_ref());
+        self.output.push('\n');
+    }
+
+    /// Separates two blocks of code, does nothing at the start of the output.
+    pub fn blank_line(&mut self) {
+        if !self.output.is_empty() {
+            self.output.push('\n');
+        }
+    }
+
+    pub fn indent(&mut self) {
+        self.level += 1;
+    }
+
+    pub fn dedent(&mut self) {
+        self.level = self
+            .level
+            .checked_sub(1)
+            .expect("dedented more often than indented");
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+}
+
+/// Quotes `string` with C-style escapes, which most targets understand.
+pub fn quote(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+
+    for char in string.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            char if char.is_ascii_control() => quoted.push_str(&format!("\\x{:02x}", char as u8)),
+            char => quoted.push(char),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[test]
+fn test_quote() {
+    assert_eq!(quote("plain"), "\"plain\"");
+    assert_eq!(quote("\"\\\n\u{7}ä"), r#""\"\\\n\x07ä""#);
+}
+
+/// Runs `source` with `interpreter` and returns its stdout.
+///
+/// Returns [`None`] if the interpreter isn't installed, so the end-to-end tests
+/// don't fail on machines without it.
+#[cfg(test)]
+pub fn run_with(interpreter: &str, extension: &str, source: &str) -> Option<String> {
+    let output = run_output(interpreter, extension, source)?;
+
+    assert!(
+        output.status.success(),
+        "{interpreter} failed:\n{}\n{source}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Some(String::from_utf8(output.stdout).unwrap())
+}
+
+/// Like [`run_with`], but the program has to fail. Returns its stderr.
+#[cfg(test)]
+pub fn run_failing_with(interpreter: &str, extension: &str, source: &str) -> Option<String> {
+    let output = run_output(interpreter, extension, source)?;
+
+    assert!(
+        !output.status.success(),
+        "{interpreter} succeeded:\n{}\n{source}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    Some(String::from_utf8(output.stderr).unwrap())
+}
+
+#[cfg(test)]
+fn run_output(interpreter: &str, extension: &str, source: &str) -> Option<std::process::Output> {
+    use std::{io::ErrorKind, process::Command};
+
+    let path = std::env::temp_dir().join(format!(
+        "erebus_test_{}_{:?}.{extension}",
+        std::process::id(),
+        std::thread::current().id(),
+    ));
+    std::fs::write(&path, source).unwrap();
+
+    let output = match Command::new(interpreter).arg(&path).output() {
+        Ok(output) => output,
+        Err(err) if err.kind() == ErrorKind::NotFound => return None,
+        Err(err) => panic!("failed to run {interpreter}: {err}"),
+    };
+    std::fs::remove_file(&path).unwrap();
+
+    Some(output)
+}
+
+/// Compiles `source` with `backend` and returns the content of the only file.
+#[cfg(test)]
+pub fn compile_source(backend: &dyn crate::Backend, source: &str) -> String {
+    use erebus_parser::{Ast, Parsable};
+
+    let mut output = backend.compile("test", &Ast::parse(source).unwrap());
+    assert!(!output.has_errors(), "{:?}", output.diagnostics);
+    assert_eq!(output.files.len(), 1);
+
+    output.files.remove(0).content
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    use crate::backends::{BashBackend, JsBackend, LuaBackend, PythonBackend};
+
+    let backends: [(&dyn crate::Backend, &str, &str); 4] = [
+        (&PythonBackend, "python3", "py"),
+        (&JsBackend, "node", "mjs"),
+        (&LuaBackend, "lua", "lua"),
+        (&BashBackend, "bash", "sh"),
+    ];
+
+    for index in ["3", "-1", "0.5"] {
+        let read = format!("fn main() -> () {{ let xs = [1, 2, 3]; print(xs[{index}]) }}");
+        let write =
+            format!("fn main() -> () {{ let mut xs = [1, 2, 3]; xs[{index}] = 4; print(xs[0]) }}");
+
+        for (backend, interpreter, extension) in backends {
+            for source in [&read, &write] {
+                let code = compile_source(backend, source);
+                if let Some(stderr) = run_failing_with(interpreter, extension, &code) {
+                    assert!(
+                        stderr.contains("is out of bounds for length 3"),
+                        "{interpreter} failed differently:\n{stderr}"
+                    );
+                }
+            }
+        }
+    }
+}
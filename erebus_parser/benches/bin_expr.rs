@@ -0,0 +1,34 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use erebus_parser::{Expression, Parsable};
+
+/// Builds an expression like `1 * 2 - 3 / 4 == 5 && 6 + ...` with `operands` operands.
+///
+/// Comparisons can't be chained, so every one of them is joined with `&&`.
+fn long_expression(operands: usize) -> String {
+    const OPERATORS: [&str; 6] = ["+", "*", "-", "/", "==", "&&"];
+
+    let mut expression = "1".to_owned();
+    for i in 1..operands {
+        expression.push_str(&format!(" {} {}", OPERATORS[i % OPERATORS.len()], i + 1));
+    }
+    expression
+}
+
+fn bench_long_expressions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bin_expr");
+
+    for operands in [10, 100, 1_000] {
+        let expression = long_expression(operands);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(operands),
+            &expression,
+            |b, expression| b.iter(|| Expression::parse(black_box(expression)).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_long_expressions);
+criterion_main!(benches);
@@ -1,17 +1,97 @@
-use chumsky::{IterParser, Parser};
+use chumsky::{recovery::via_parser, IterParser, Parser};
 
-use super::{parsable::ParsableParser, statement::TopLevelStatement, Parsable};
+use super::{
+    parsable::ParsableParser, recovery::skip_top_level_statement, span::Spanned,
+    statement::TopLevelStatement, Parsable,
+};
 
 #[derive(Debug, PartialEq)]
 pub struct Ast {
-    statements: Vec<TopLevelStatement>,
+    pub statements: Vec<Spanned<TopLevelStatement>>,
 }
 
 impl Parsable for Ast {
     fn parser<'src>() -> impl ParsableParser<'src, Self> {
-        TopLevelStatement::parser()
+        Spanned::<TopLevelStatement>::parser()
+            .map(Some)
+            // broken statements are left out of the ast, their errors are still reported
+            .recover_with(via_parser(skip_top_level_statement().map(|()| None)))
+            .padded()
             .repeated()
-            .collect()
-            .map(|statements| Self { statements })
+            .collect::<Vec<_>>()
+            .map(|statements| Self {
+                statements: statements.into_iter().flatten().collect(),
+            })
     }
 }
+
+#[test]
+fn test_ast_recovery() {
+    use crate::statement::RawTopLevelStatement;
+
+    let (ast, errors) = Ast::parse(
+        "fn a() -> int { let x = ; let y = 2; y }
+         struct S { a: int, b: 1, c: String }
+         let broken = ;
+         fn b() -> int { 1 }
+         }",
+    )
+    .into_output_errors();
+    let ast = ast.unwrap();
+
+    assert_eq!(errors.len(), 4);
+    assert_eq!(ast.statements.len(), 3);
+
+    let RawTopLevelStatement::FnDef(a) = &ast.statements[0].inner else {
+        panic!("expected a function")
+    };
+    assert_eq!(a.body.statements.len(), 1);
+
+    let RawTopLevelStatement::StructDef(s) = &ast.statements[1].inner else {
+        panic!("expected a struct")
+    };
+    assert_eq!(s.fields.len(), 2);
+}
+
+#[test]
+fn test_ast_recovery_after_cascade() {
+    let source = "struct S { a: int, b: 1, c: String }
+         fn a() -> int {
+             if 1 > { 2 } else { 3 }
+         }
+         fn c() -> int { let x = ; x }
+         let broken = ;
+         fn d() -> int { 1 }";
+    let (ast, errors) = Ast::parse(source).into_output_errors();
+
+    // every broken statement is reported, not only the first ones before the cascade
+    let errors = errors
+        .iter()
+        .map(|error| {
+            let expected = error
+                .expected()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            (
+                &source[error.span().start..],
+                error.found().copied(),
+                expected,
+            )
+        })
+        .map(|(rest, found, expected)| (rest.lines().next().unwrap(), found, expected))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            ("1, c: String }", Some('1'), vec!["type".to_string()]),
+            (
+                "else { 3 }",
+                Some('e'),
+                vec!["operator".to_string(), "'{'".to_string()]
+            ),
+            ("; x }", Some(';'), vec!["expression".to_string()]),
+            (";", Some(';'), vec!["expression".to_string()]),
+        ]
+    );
+    assert_eq!(ast.unwrap().statements.len(), 4);
+}
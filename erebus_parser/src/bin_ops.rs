@@ -1,145 +1,235 @@
-use std::fmt::Debug;
-use std::marker::PhantomData;
+use std::{fmt::Debug, marker::PhantomData};
 
-use chumsky::{prelude::choice, Parser};
+use chumsky::{error::Rich, prelude::choice, IterParser, Parser};
 
 use super::{
     expr::Expression,
-    parsable::{Parsable, ParsableParser},
-    syntax_elements::{AddExpr, DivExpr, EqualsExpr, MulExpr, SubExpr},
+    parsable::{Parsable, ParsableParser, ParserError},
+    span::Spanned,
+    syntax_elements::{
+        AddExpr, AndExpr, BitAndExpr, BitOrExpr, BitXorExpr, DivExpr, EqualsExpr,
+        GreaterEqualsExpr, GreaterExpr, LessEqualsExpr, LessExpr, MulExpr, NotEqualsExpr, OrExpr,
+        RemExpr, ShlExpr, ShrExpr, SubExpr,
+    },
 };
 
 pub type Precedence = u8;
 
+/// How a chain of operators with the same precedence is grouped.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a < b < c` is an error
+    NonAssociative,
+}
+
 pub trait HasPrecedence {
     const PRECEDENCE: Precedence;
+    const ASSOCIATIVITY: Associativity;
 }
 
+/// Builds the expression of an operator from its operands.
+type BinExprBuilder = fn([Spanned<Expression>; 2]) -> BinExpr;
+
 #[derive(Debug, PartialEq)]
 pub struct GenericBinOp<OP: Parsable + HasPrecedence> {
     op: PhantomData<OP>,
-    expressions: [Expression; 2],
+    pub expressions: [Spanned<Expression>; 2],
 }
 
 impl<OP: Parsable + HasPrecedence + Debug> GenericBinOp<OP> {
-    pub fn new(expressions: [Expression; 2]) -> Self {
+    const PRECEDENCE: Precedence = OP::PRECEDENCE;
+    const ASSOCIATIVITY: Associativity = OP::ASSOCIATIVITY;
+
+    pub fn new(expressions: [Spanned<Expression>; 2]) -> Self {
         Self {
             op: PhantomData {},
             expressions,
         }
     }
 
-    fn maybe_parser_with_precedence_and_parser<'src>(
-        current_precedence: Precedence,
-        // this parser must be of `current_precedence`
-        expression_parser: impl ParsableParser<'src, Expression>,
-    ) -> Option<impl ParsableParser<'src, Self>> {
-        if OP::PRECEDENCE >= current_precedence {
-            Some(
-                // We're not allowed to search for an expression with the same operator to the left.
-                // This prevents infinite recursion.
-                Expression::parser_with_precedence(OP::PRECEDENCE + 1)
-                    .then_ignore(OP::parser())
-                    // It's not possible to cause infinite recursion on the right side.
-                    .then(expression_parser)
-                    .map(|(expression0, expression1)| Self::new([expression0, expression1])),
-            )
-        } else {
-            None
-        }
+    fn operator_parser<'src>() -> impl ParsableParser<'src, OP> {
+        OP::parser()
     }
 }
 
 #[derive(Debug, PartialEq)]
 pub enum BinExpr {
+    Or(OrExpr),
+    And(AndExpr),
+
     Equals(EqualsExpr),
+    NotEquals(NotEqualsExpr),
+    Less(LessExpr),
+    LessEquals(LessEqualsExpr),
+    Greater(GreaterExpr),
+    GreaterEquals(GreaterEqualsExpr),
+
+    BitOr(BitOrExpr),
+    BitXor(BitXorExpr),
+    BitAnd(BitAndExpr),
+    Shl(ShlExpr),
+    Shr(ShrExpr),
 
     Add(AddExpr),
     Sub(SubExpr),
     Mul(MulExpr),
     Div(DivExpr),
+    Rem(RemExpr),
 }
 
 impl BinExpr {
-    pub fn parser_with_precedence_and_parser<'src>(
-        current_precedence: Precedence,
-        // this parser must be of `current_precedence`
-        expression_parser: impl ParsableParser<'src, Expression> + 'src,
-    ) -> impl ParsableParser<'src, Self> {
-        macro_rules! parse_as_bin_expr {
+    /// Parses an expression consisting of operands separated by binary operators.
+    ///
+    /// Every precedence level parses a chain of the next higher level, which is
+    /// folded from the left. This parses each operand exactly once.
+    /// Chains of non-associative operators are reported but still folded,
+    /// so parsing can continue.
+    pub fn parser_with<'src>(
+        // parses everything that binds tighter than a binary operator
+        operand_parser: impl ParsableParser<'src, Expression> + 'src,
+    ) -> impl ParsableParser<'src, Spanned<Expression>> {
+        macro_rules! operator {
             ($expr_name:ident) => {
-                $expr_name::maybe_parser_with_precedence_and_parser(
-                    current_precedence,
-                    expression_parser.clone(),
+                (
+                    $expr_name::PRECEDENCE,
+                    $expr_name::ASSOCIATIVITY,
+                    $expr_name::operator_parser()
+                        .to((|expressions| $expr_name::new(expressions).into_bin_expr())
+                            as BinExprBuilder)
+                        .boxed(),
                 )
-                .map(|parser| {
-                    parser
-                        .map(|expressions| $expr_name::into_bin_expr(expressions))
-                        .boxed()
-                })
             };
         }
 
-        let parsers: Vec<_> = [
-            // The operators with the lowest precedence need to be parsed first
-            // Precedence: 1
-            parse_as_bin_expr!(EqualsExpr),
-            // Precedence: 2
-            parse_as_bin_expr!(AddExpr),
-            parse_as_bin_expr!(SubExpr),
-            // Precedence: 3
-            parse_as_bin_expr!(MulExpr),
-            parse_as_bin_expr!(DivExpr),
-        ]
-        .into_iter()
-        .flatten()
-        .collect();
-
-        choice(parsers).boxed()
+        // operators that are a prefix of another one of the same precedence come last
+        let operators = [
+            operator!(OrExpr),
+            operator!(AndExpr),
+            operator!(EqualsExpr),
+            operator!(NotEqualsExpr),
+            operator!(LessEqualsExpr),
+            operator!(LessExpr),
+            operator!(GreaterEqualsExpr),
+            operator!(GreaterExpr),
+            operator!(BitOrExpr),
+            operator!(BitXorExpr),
+            operator!(BitAndExpr),
+            operator!(ShlExpr),
+            operator!(ShrExpr),
+            operator!(AddExpr),
+            operator!(SubExpr),
+            operator!(MulExpr),
+            operator!(DivExpr),
+            operator!(RemExpr),
+        ];
+
+        let mut precedences: Vec<_> = operators
+            .iter()
+            .map(|(precedence, associativity, _)| (*precedence, *associativity))
+            .collect();
+        precedences.sort_unstable_by_key(|(precedence, _)| *precedence);
+        precedences.dedup();
+        debug_assert!(
+            precedences
+                .windows(2)
+                .all(|levels| levels[0].0 != levels[1].0),
+            "operators of the same precedence need the same associativity"
+        );
+
+        let mut expression_parser = Spanned::parser_with(operand_parser).boxed();
+        // the operators with the highest precedence bind the tightest
+        for (precedence, associativity) in precedences.into_iter().rev() {
+            let operator_parser = choice(
+                operators
+                    .iter()
+                    .filter(|(operator_precedence, ..)| *operator_precedence == precedence)
+                    .map(|(.., parser)| parser.clone())
+                    .collect::<Vec<_>>(),
+            )
+            .labelled("operator");
+            let chain_parser = operator_parser.then(expression_parser.clone()).repeated();
+
+            expression_parser = match associativity {
+                Associativity::Left => expression_parser
+                    .foldl_with(chain_parser, |left, (build, right), extra| {
+                        Spanned::new(
+                            Expression::BinExpr(Box::new(build([left, right]))),
+                            extra.span(),
+                        )
+                    })
+                    .boxed(),
+                Associativity::NonAssociative => expression_parser
+                    .then(chain_parser.collect::<Vec<_>>())
+                    .validate(|(first, chain), extra, emitter| {
+                        if chain.len() > 1 {
+                            emitter.emit(Rich::custom(
+                                extra.span(),
+                                "comparison operators cannot be chained, \
+                                 combine the comparisons with `&&` instead",
+                            ))
+                        }
+
+                        chain.into_iter().fold(first, |left, (build, right)| {
+                            let span = (left.span.start..right.span.end).into();
+                            Spanned::new(Expression::BinExpr(Box::new(build([left, right]))), span)
+                        })
+                    })
+                    .boxed(),
+            };
+        }
+
+        expression_parser
     }
 }
 
 impl Parsable for BinExpr {
     fn parser<'src>() -> impl ParsableParser<'src, Self> {
-        Self::parser_with_precedence_and_parser(
-            Precedence::MIN,
-            Expression::parser_with_precedence(Precedence::MIN),
-        )
+        Expression::parser().try_map(|expression, span| match expression {
+            Expression::BinExpr(bin_expr) => Ok(*bin_expr),
+            _ => Err(ParserError::custom(span, "expected a binary expression")),
+        })
     }
 }
 
 #[test]
 fn test_bin_expr() {
-    use crate::literals::NumLit;
+    use crate::{
+        expr::FnCall,
+        ident::{Ident, Path},
+    };
 
     assert_eq!(
         BinExpr::parse("1 + 1").unwrap(),
         BinExpr::Add(AddExpr::new([
-            Expression::NumLit(NumLit(1_f64)),
-            Expression::NumLit(NumLit(1_f64))
+            Expression::num(1_f64).into(),
+            Expression::num(1_f64).into()
         ]))
     );
     assert_eq!(
         BinExpr::parse("1 + 2 * 3 / 4").unwrap(),
         BinExpr::Add(AddExpr::new([
-            Expression::NumLit(NumLit(1_f64)),
-            MulExpr::as_expr([
-                Expression::NumLit(NumLit(2_f64)),
-                DivExpr::as_expr([
-                    Expression::NumLit(NumLit(3_f64)),
-                    Expression::NumLit(NumLit(4_f64))
-                ])
+            Expression::num(1_f64).into(),
+            DivExpr::as_expr([
+                MulExpr::as_expr([Expression::num(2_f64), Expression::num(3_f64)]),
+                Expression::num(4_f64)
             ])
+            .into()
         ]))
     );
     assert_eq!(
         BinExpr::parse("3 + 2 - 1").unwrap(),
-        BinExpr::Add(AddExpr::new([
-            Expression::NumLit(NumLit(3_f64)),
-            SubExpr::as_expr([
-                Expression::NumLit(NumLit(2_f64)),
-                Expression::NumLit(NumLit(1_f64)),
-            ])
+        BinExpr::Sub(SubExpr::new([
+            AddExpr::as_expr([Expression::num(3_f64), Expression::num(2_f64),]).into(),
+            Expression::num(1_f64).into()
+        ]))
+    );
+    assert_eq!(
+        BinExpr::parse("8 / 4 / 2").unwrap(),
+        BinExpr::Div(DivExpr::new([
+            DivExpr::as_expr([Expression::num(8_f64), Expression::num(4_f64),]).into(),
+            Expression::num(2_f64).into()
         ]))
     );
     assert_eq!(
@@ -147,19 +237,89 @@ fn test_bin_expr() {
         Expression::parse("1 * 2 + 3 * 4 + 5 == 6").unwrap(),
         EqualsExpr::as_expr([
             AddExpr::as_expr([
-                MulExpr::as_expr([
-                    Expression::NumLit(NumLit(1_f64)),
-                    Expression::NumLit(NumLit(2_f64)),
-                ]),
                 AddExpr::as_expr([
-                    MulExpr::as_expr([
-                        Expression::NumLit(NumLit(3_f64)),
-                        Expression::NumLit(NumLit(4_f64)),
-                    ]),
-                    Expression::NumLit(NumLit(5_f64))
+                    MulExpr::as_expr([Expression::num(1_f64), Expression::num(2_f64),]),
+                    MulExpr::as_expr([Expression::num(3_f64), Expression::num(4_f64),]),
+                ]),
+                Expression::num(5_f64)
+            ]),
+            Expression::num(6_f64)
+        ])
+    );
+    assert_eq!(
+        // operands can contain operators of any precedence
+        Expression::parse("sub(3, 2 - 1) * 2").unwrap(),
+        MulExpr::as_expr([
+            Expression::FnCall(FnCall {
+                callee: Path::from(Spanned::from(Ident::from_str("sub"))).into(),
+                args: vec![
+                    Expression::num(3_f64).into(),
+                    SubExpr::as_expr([Expression::num(2_f64), Expression::num(1_f64),]).into()
+                ]
+            }),
+            Expression::num(2_f64)
+        ])
+    );
+
+    assert!(BinExpr::is_err("1"));
+    assert!(BinExpr::is_err("1 +"));
+}
+
+#[test]
+fn test_bin_expr_operators() {
+    assert_eq!(
+        Expression::parse("1 || 2 && 3 != 4 | 5 ^ 6 & 7 << 8 + 9 % 10").unwrap(),
+        OrExpr::as_expr([
+            Expression::num(1_f64),
+            AndExpr::as_expr([
+                Expression::num(2_f64),
+                NotEqualsExpr::as_expr([
+                    Expression::num(3_f64),
+                    BitOrExpr::as_expr([
+                        Expression::num(4_f64),
+                        BitXorExpr::as_expr([
+                            Expression::num(5_f64),
+                            BitAndExpr::as_expr([
+                                Expression::num(6_f64),
+                                ShlExpr::as_expr([
+                                    Expression::num(7_f64),
+                                    AddExpr::as_expr([
+                                        Expression::num(8_f64),
+                                        RemExpr::as_expr([
+                                            Expression::num(9_f64),
+                                            Expression::num(10_f64)
+                                        ])
+                                    ])
+                                ])
+                            ])
+                        ])
+                    ])
                 ])
+            ])
+        ])
+    );
+    assert_eq!(
+        // longer operators aren't split up
+        Expression::parse("1 <= 2 >> 3 || 4 >= 5").unwrap(),
+        OrExpr::as_expr([
+            LessEqualsExpr::as_expr([
+                Expression::num(1_f64),
+                ShrExpr::as_expr([Expression::num(2_f64), Expression::num(3_f64)])
             ]),
-            Expression::NumLit(NumLit(6_f64))
+            GreaterEqualsExpr::as_expr([Expression::num(4_f64), Expression::num(5_f64)])
         ])
     );
+    assert_eq!(
+        Expression::parse("(1 < 2) == (3 > 4)").unwrap(),
+        EqualsExpr::as_expr([
+            LessExpr::as_expr([Expression::num(1_f64), Expression::num(2_f64)]),
+            GreaterExpr::as_expr([Expression::num(3_f64), Expression::num(4_f64)])
+        ])
+    );
+
+    let errors = Expression::parse("1 < 2 < 3").into_errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span().into_range(), 0..9);
+    assert!(errors[0].to_string().contains("cannot be chained"));
+    assert!(BinExpr::is_err("1 == 2 != 3"));
 }
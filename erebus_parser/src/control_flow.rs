@@ -0,0 +1,479 @@
+use chumsky::{
+    prelude::{choice, empty, recursive},
+    IterParser, Parser,
+};
+
+use super::{
+    expr::{CodeScope, Expression},
+    ident::Ident,
+    parsable::{Parsable, ParsableParser, ParserError},
+    pattern::Pattern,
+    span::Spanned,
+    syntax_elements::{
+        Apostrophe, BreakKeyword, Colon, Comma, ContinueKeyword, ElseKeyword, FatArrow, ForKeyword,
+        IfKeyword, InKeyword, LCurly, LoopKeyword, MatchKeyword, RCurly, ReturnKeyword,
+        WhileKeyword,
+    },
+};
+
+/// `if condition { ... } else { ... }`, which evaluates to the value of the taken branch.
+///
+/// Without an `else` branch the value is `()`.
+#[derive(Debug, PartialEq)]
+pub struct If {
+    pub condition: Spanned<Expression>,
+    pub then: Spanned<CodeScope>,
+    pub r#else: Option<Spanned<Else>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Else {
+    /// `else if ...`
+    If(Box<If>),
+    Scope(CodeScope),
+}
+
+impl If {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression> + 'src,
+        condition_parser: impl ParsableParser<'src, Expression> + 'src,
+    ) -> impl ParsableParser<'src, Self> {
+        recursive(|if_parser| {
+            let scope_parser = CodeScope::parser_with(expression_parser);
+
+            IfKeyword::parser()
+                .padded()
+                .ignore_then(Spanned::parser_with(condition_parser))
+                .then(Spanned::parser_with(scope_parser.clone()))
+                .then(
+                    ElseKeyword::parser()
+                        .padded()
+                        .ignore_then(Spanned::parser_with(choice((
+                            if_parser.map(|r#if| Else::If(Box::new(r#if))),
+                            scope_parser.map(Else::Scope),
+                        ))))
+                        .or_not(),
+                )
+                .map(|((condition, then), r#else)| Self {
+                    condition,
+                    then,
+                    r#else,
+                })
+        })
+    }
+}
+
+impl Parsable for If {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser(), Expression::condition_parser())
+    }
+}
+
+#[test]
+fn test_if() {
+    use crate::syntax_elements::{EqualsExpr, LessExpr};
+
+    assert_eq!(
+        If::parse("if a { 1 }").unwrap(),
+        If {
+            condition: Expression::variable("a").into(),
+            then: CodeScope::of(Expression::num(1_f64)).into(),
+            r#else: None,
+        }
+    );
+    assert_eq!(
+        If::parse("if a == 1 { 1 } else if a < 3 { 2 } else { 3 }").unwrap(),
+        If {
+            condition: EqualsExpr::as_expr([Expression::variable("a"), Expression::num(1_f64)])
+                .into(),
+            then: CodeScope::of(Expression::num(1_f64)).into(),
+            r#else: Some(
+                Else::If(Box::new(If {
+                    condition: LessExpr::as_expr([
+                        Expression::variable("a"),
+                        Expression::num(3_f64)
+                    ])
+                    .into(),
+                    then: CodeScope::of(Expression::num(2_f64)).into(),
+                    r#else: Some(Else::Scope(CodeScope::of(Expression::num(3_f64))).into()),
+                }))
+                .into()
+            ),
+        }
+    );
+
+    // `if` is an expression
+    assert!(!Expression::is_err("1 + if a { 2 } else { 3 }"));
+    assert!(If::is_err("if a { 1 } else"));
+    assert!(If::is_err("if { 1 }"));
+    assert!(If::is_err("iffy { 1 }"));
+}
+
+/// `match value { pattern => ..., }`, which evaluates to the value of the first arm
+/// whose pattern matches.
+#[derive(Debug, PartialEq)]
+pub struct Match {
+    pub scrutinee: Spanned<Expression>,
+    pub arms: Vec<Spanned<MatchArm>>,
+}
+
+/// `pattern if guard => body`
+#[derive(Debug, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Spanned<Pattern>,
+    pub guard: Option<Spanned<Expression>>,
+    pub body: Spanned<Expression>,
+}
+
+impl Match {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression> + 'src,
+        condition_parser: impl ParsableParser<'src, Expression> + 'src,
+    ) -> impl ParsableParser<'src, Self> {
+        let arm_parser = Spanned::<Pattern>::parser()
+            .padded()
+            .then(
+                IfKeyword::parser()
+                    .padded()
+                    .ignore_then(Spanned::parser_with(expression_parser.clone()))
+                    .or_not(),
+            )
+            .then_ignore(FatArrow::parser())
+            .then(Spanned::parser_with(expression_parser))
+            .map(|((pattern, guard), body)| MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+
+        MatchKeyword::parser()
+            .padded()
+            .ignore_then(Spanned::parser_with(condition_parser))
+            .then_ignore(LCurly::parser())
+            .then(
+                Spanned::parser_with(arm_parser)
+                    .then(choice((
+                        Comma::parser().to(true),
+                        RCurly::parser().rewind().to(true),
+                        empty().to(false),
+                    )))
+                    // like in rust, the comma is optional after a block
+                    .validate(|(arm, is_terminated), _, emitter| {
+                        if !is_terminated && !arm.body.is_block_like() {
+                            emitter.emit(ParserError::custom(
+                                arm.body.span,
+                                "expected `,` after the arm",
+                            ))
+                        }
+                        arm
+                    })
+                    .repeated()
+                    .collect(),
+            )
+            .then_ignore(RCurly::parser())
+            .map(|(scrutinee, arms)| Self { scrutinee, arms })
+    }
+}
+
+impl Parsable for Match {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser(), Expression::condition_parser())
+    }
+}
+
+#[test]
+fn test_match() {
+    use crate::{literals::NumLit, syntax_elements::GreaterExpr};
+
+    assert_eq!(
+        Match::parse("match x { 1 => 2, n if n > 2 => { n } _ => 0 }").unwrap(),
+        Match {
+            scrutinee: Expression::variable("x").into(),
+            arms: vec![
+                MatchArm {
+                    pattern: Pattern::NumLit(NumLit(1_f64)).into(),
+                    guard: None,
+                    body: Expression::num(2_f64).into(),
+                }
+                .into(),
+                MatchArm {
+                    pattern: Pattern::Binding(Ident::from_str("n")).into(),
+                    guard: Some(
+                        GreaterExpr::as_expr([Expression::variable("n"), Expression::num(2_f64)])
+                            .into()
+                    ),
+                    body: Expression::CodeScope(Box::new(CodeScope::of(Expression::variable("n"))))
+                        .into(),
+                }
+                .into(),
+                MatchArm {
+                    pattern: Pattern::Wildcard.into(),
+                    guard: None,
+                    body: Expression::num(0_f64).into(),
+                }
+                .into()
+            ],
+        }
+    );
+    assert_eq!(
+        Match::parse("match x {}").unwrap(),
+        Match {
+            scrutinee: Expression::variable("x").into(),
+            arms: Vec::new(),
+        }
+    );
+
+    assert!(!Expression::is_err("1 + match x { _ => 2 }"));
+    assert!(Match::is_err("match x { 1 => 2 _ => 3 }"));
+    assert!(Match::is_err("match x { 1 = 2 }"));
+    assert!(Match::is_err("matches x { _ => 1 }"));
+}
+
+/// The name of a loop, written as `'name`.
+#[derive(Debug, PartialEq)]
+pub struct Label(pub Ident);
+
+impl Label {
+    /// Parses the optional `'name:` in front of a loop.
+    fn prefix_parser<'src>() -> impl ParsableParser<'src, Option<Spanned<Self>>> {
+        Spanned::<Self>::parser()
+            .then_ignore(Colon::parser())
+            .or_not()
+    }
+}
+
+impl Parsable for Label {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Apostrophe::parser().ignore_then(Ident::parser()).map(Self)
+    }
+}
+
+/// `loop { ... }`, which evaluates to the value given to the `break` that leaves it.
+#[derive(Debug, PartialEq)]
+pub struct Loop {
+    pub label: Option<Spanned<Label>>,
+    pub body: Spanned<CodeScope>,
+}
+
+impl Loop {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression> + 'src,
+    ) -> impl ParsableParser<'src, Self> {
+        Label::prefix_parser()
+            .then_ignore(LoopKeyword::parser())
+            .then(Spanned::parser_with(CodeScope::parser_with(
+                expression_parser,
+            )))
+            .map(|(label, body)| Self { label, body })
+    }
+}
+
+impl Parsable for Loop {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
+    }
+}
+
+/// `while condition { ... }`, which evaluates to `()`.
+#[derive(Debug, PartialEq)]
+pub struct While {
+    pub label: Option<Spanned<Label>>,
+    pub condition: Spanned<Expression>,
+    pub body: Spanned<CodeScope>,
+}
+
+impl While {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression> + 'src,
+        condition_parser: impl ParsableParser<'src, Expression> + 'src,
+    ) -> impl ParsableParser<'src, Self> {
+        Label::prefix_parser()
+            .then_ignore(WhileKeyword::parser().padded())
+            .then(Spanned::parser_with(condition_parser))
+            .then(Spanned::parser_with(CodeScope::parser_with(
+                expression_parser,
+            )))
+            .map(|((label, condition), body)| Self {
+                label,
+                condition,
+                body,
+            })
+    }
+}
+
+impl Parsable for While {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser(), Expression::condition_parser())
+    }
+}
+
+/// `for name in iterable { ... }`, which evaluates to `()`.
+///
+/// `iterable` is either a [`Range`](crate::Range) of numbers or a collection.
+#[derive(Debug, PartialEq)]
+pub struct For {
+    pub label: Option<Spanned<Label>>,
+    pub binding: Spanned<Ident>,
+    pub iterable: Spanned<Expression>,
+    pub body: Spanned<CodeScope>,
+}
+
+impl For {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression> + 'src,
+        condition_parser: impl ParsableParser<'src, Expression> + 'src,
+    ) -> impl ParsableParser<'src, Self> {
+        Label::prefix_parser()
+            .then_ignore(ForKeyword::parser().padded())
+            .then(Spanned::<Ident>::parser())
+            .then_ignore(InKeyword::parser().padded())
+            .then(Spanned::parser_with(condition_parser))
+            .then(Spanned::parser_with(CodeScope::parser_with(
+                expression_parser,
+            )))
+            .map(|(((label, binding), iterable), body)| Self {
+                label,
+                binding,
+                iterable,
+                body,
+            })
+    }
+}
+
+impl Parsable for For {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser(), Expression::condition_parser())
+    }
+}
+
+/// Leaves the innermost loop or the one with `label`.
+///
+/// Only a `loop` can be left with a value.
+#[derive(Debug, PartialEq)]
+pub struct Break {
+    pub label: Option<Spanned<Label>>,
+    pub value: Option<Spanned<Expression>>,
+}
+
+impl Break {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression>,
+    ) -> impl ParsableParser<'src, Self> {
+        BreakKeyword::parser()
+            .padded()
+            .ignore_then(Spanned::<Label>::parser().padded().or_not())
+            .then(Spanned::parser_with(expression_parser).or_not())
+            .map(|(label, value)| Self { label, value })
+    }
+}
+
+impl Parsable for Break {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
+    }
+}
+
+/// Skips to the next iteration of the innermost loop or the one with `label`.
+#[derive(Debug, PartialEq)]
+pub struct Continue {
+    pub label: Option<Spanned<Label>>,
+}
+
+impl Parsable for Continue {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        ContinueKeyword::parser()
+            .padded()
+            .ignore_then(Spanned::<Label>::parser().padded().or_not())
+            .map(|label| Self { label })
+    }
+}
+
+/// Leaves the surrounding function or closure, `return` alone returns `()`.
+#[derive(Debug, PartialEq)]
+pub struct Return {
+    pub value: Option<Spanned<Expression>>,
+}
+
+impl Return {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression>,
+    ) -> impl ParsableParser<'src, Self> {
+        ReturnKeyword::parser()
+            .padded()
+            .ignore_then(Spanned::parser_with(expression_parser).or_not())
+            .map(|value| Self { value })
+    }
+}
+
+impl Parsable for Return {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
+    }
+}
+
+#[test]
+fn test_return() {
+    assert_eq!(
+        Return::parse("return 1").unwrap(),
+        Return {
+            value: Some(Expression::num(1_f64).into())
+        }
+    );
+    assert_eq!(Return::parse("return").unwrap(), Return { value: None });
+    assert!(Return::is_err("returned"));
+}
+
+#[test]
+fn test_loops() {
+    use crate::range::Range;
+
+    let label = |name| Some(Label(Ident::from_str(name)).into());
+
+    assert_eq!(
+        Loop::parse("'outer: loop { break 'outer 1 }").unwrap(),
+        Loop {
+            label: label("outer"),
+            body: CodeScope::of(Expression::Break(Box::new(Break {
+                label: label("outer"),
+                value: Some(Expression::num(1_f64).into()),
+            })))
+            .into(),
+        }
+    );
+    assert_eq!(
+        While::parse("while running { continue }").unwrap(),
+        While {
+            label: None,
+            condition: Expression::variable("running").into(),
+            body: CodeScope::of(Expression::Continue(Continue { label: None })).into(),
+        }
+    );
+    assert_eq!(
+        Break::parse("break").unwrap(),
+        Break {
+            label: None,
+            value: None,
+        }
+    );
+
+    assert_eq!(
+        For::parse("'rows: for row in 1..=3 { row }").unwrap(),
+        For {
+            label: label("rows"),
+            binding: Ident::from_str("row").into(),
+            iterable: Expression::Range(Box::new(Range {
+                start: Expression::num(1_f64).into(),
+                end: Expression::num(3_f64).into(),
+                is_inclusive: true,
+            }))
+            .into(),
+            body: CodeScope::of(Expression::variable("row")).into(),
+        }
+    );
+
+    assert!(Loop::is_err("'outer loop { 1 }"));
+    assert!(For::is_err("for x in { 1 }"));
+    assert!(For::is_err("for x inxs { 1 }"));
+    assert!(Loop::is_err("loopy { 1 }"));
+    assert!(Continue::is_err("continue 1"));
+}
@@ -1,48 +1,92 @@
 use chumsky::{
-    prelude::{choice, recursive},
+    extra,
+    prelude::{choice, Recursive},
+    recovery::via_parser,
+    recursive::Indirect,
+    text::int,
     IterParser, Parser,
 };
 use enum_dispatch::enum_dispatch;
 
 use crate::{
-    ident::Ident,
+    ident::{Ident, IdentWithOptionalType, Path},
     literals::{NumLit, StringLit},
 };
 
 use super::{
-    bin_ops::{BinExpr, Precedence},
-    parsable::{Parsable, ParsableParser},
+    bin_ops::BinExpr,
+    control_flow::{Break, Continue, For, If, Loop, Match, Return, While},
+    parsable::{Parsable, ParsableParser, ParserError, ParserInput},
+    range::Range,
+    recovery::skip_statement,
+    span::Spanned,
     statement::Statement,
-    syntax_elements::{Comma, LCurly, LParen, RCurly, RParen},
+    syntax_elements::{
+        Colon, Comma, Dot, LBracket, LCurly, LParen, Pipe, RBracket, RCurly, RParen,
+    },
+    unary_ops::UnaryExpr,
 };
 
 /// Block of Code. Used in if's, matches, fn bodies, ...
 #[derive(Debug, PartialEq)]
 pub struct CodeScope {
-    pub statements: Vec<Statement>,
-    pub expr: Expression,
+    pub statements: Vec<Spanned<Statement>>,
+    /// The value of the scope, `()` without one.
+    pub expr: Option<Spanned<Expression>>,
 }
 
-impl Parsable for CodeScope {
-    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+impl CodeScope {
+    /// A scope without statements that evaluates to `expr`.
+    #[cfg(test)]
+    pub fn of(expr: Expression) -> Self {
+        Self {
+            statements: Vec::new(),
+            expr: Some(expr.into()),
+        }
+    }
+
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression> + 'src,
+    ) -> impl ParsableParser<'src, Self> {
         LCurly::parser()
-            .ignore_then(Statement::parser().repeated().collect())
-            .then(Expression::parser())
+            .ignore_then(
+                Spanned::parser_with(Statement::parser_with(expression_parser.clone()))
+                    .map(Some)
+                    .recover_with(via_parser(
+                        // the final expression of the scope isn't a broken statement
+                        expression_parser
+                            .clone()
+                            .padded()
+                            .then(RCurly::parser())
+                            .not()
+                            .ignore_then(skip_statement())
+                            .map(|()| None),
+                    ))
+                    .repeated()
+                    .collect::<Vec<_>>(),
+            )
+            .then(Spanned::parser_with(expression_parser).or_not())
             .then_ignore(RCurly::parser())
-            .map(|(statements, expr)| Self { statements, expr })
+            .map(|(statements, expr)| Self {
+                statements: statements.into_iter().flatten().collect(),
+                expr,
+            })
+    }
+}
+
+impl Parsable for CodeScope {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
     }
 }
 
 #[test]
 fn test_scope() {
-    use crate::statement::Let;
+    use crate::{pattern::Pattern, statement::Let};
 
     assert_eq!(
         CodeScope::parse("{ 1 }").unwrap(),
-        CodeScope {
-            statements: Vec::new(),
-            expr: Expression::NumLit(NumLit(1_f64))
-        }
+        CodeScope::of(Expression::num(1_f64))
     );
 
     assert_eq!(
@@ -57,29 +101,58 @@ fn test_scope() {
             statements: vec![Statement::Let(Let {
                 is_mut: true,
 
-                left: Ident::from_str("test").into(),
-                right: Expression::StringLit(StringLit("Statement".to_owned()))
-            })],
-            expr: Expression::StringLit(StringLit("TestStatement".to_owned()))
+                pattern: Pattern::Binding(Ident::from_str("test")).into(),
+                r#type: None,
+                right: Expression::StringLit(StringLit("Statement".to_owned())).into()
+            })
+            .into()],
+            expr: Some(Expression::StringLit(StringLit("TestStatement".to_owned())).into())
         }
     );
+
+    assert_eq!(
+        Expression::parse("{ let a = 1; { a } }").unwrap(),
+        Expression::CodeScope(Box::new(CodeScope {
+            statements: vec![Statement::Let(Let {
+                is_mut: false,
+
+                pattern: Pattern::Binding(Ident::from_str("a")).into(),
+                r#type: None,
+                right: Expression::num(1_f64).into()
+            })
+            .into()],
+            expr: Some(
+                Expression::CodeScope(Box::new(CodeScope::of(Expression::variable("a")))).into()
+            )
+        }))
+    );
+
+    let scope = CodeScope::parse("{ f(); return; }").unwrap();
+    assert_eq!(scope.statements.len(), 2);
+    assert_eq!(scope.expr, None);
+    assert_eq!(CodeScope::parse("{}").unwrap().expr, None);
 }
 
+/// Calls a function or constructs a tuple variant, like `Shape::Circle(1)`.
 #[derive(Debug, PartialEq)]
 pub struct FnCall {
-    fn_name: Ident,
-    args: Vec<Expression>,
+    pub callee: Spanned<Path>,
+    pub args: Vec<Spanned<Expression>>,
 }
 
 impl FnCall {
     fn parser_with<'src>(
         existing_parser: impl ParsableParser<'src, Expression>,
     ) -> impl ParsableParser<'src, Self> {
-        Ident::parser()
+        Spanned::<Path>::parser()
             .then_ignore(LParen::parser())
-            .then(existing_parser.separated_by(Comma::parser()).collect())
+            .then(
+                Spanned::parser_with(existing_parser)
+                    .separated_by(Comma::parser())
+                    .collect(),
+            )
             .then_ignore(RParen::parser())
-            .map(|(fn_name, args)| Self { fn_name, args })
+            .map(|(callee, args)| Self { callee, args })
     }
 }
 
@@ -94,10 +167,528 @@ fn test_fn_call() {
     assert_eq!(
         FnCall::parse("simple_test(123)").unwrap(),
         FnCall {
-            fn_name: Ident::from_str("simple_test"),
-            args: vec![Expression::NumLit(NumLit(123_f64))]
+            callee: Path::from(Spanned::from(Ident::from_str("simple_test"))).into(),
+            args: vec![Expression::num(123_f64).into()]
+        }
+    );
+    assert_eq!(
+        FnCall::parse("Shape::Circle()").unwrap(),
+        FnCall {
+            callee: Path(vec![
+                Ident::from_str("Shape").into(),
+                Ident::from_str("Circle").into()
+            ])
+            .into(),
+            args: Vec::new(),
+        }
+    );
+}
+
+/// `(a, b)`, a tuple with one element needs a trailing comma like `(a,)`.
+///
+/// `()` is the unit value.
+#[derive(Debug, PartialEq)]
+pub struct TupleLiteral(pub Vec<Spanned<Expression>>);
+
+impl TupleLiteral {
+    fn parser_with<'src>(
+        existing_parser: impl ParsableParser<'src, Expression>,
+    ) -> impl ParsableParser<'src, Self> {
+        Spanned::parser_with(existing_parser.clone())
+            .then_ignore(Comma::parser())
+            .repeated()
+            .collect::<Vec<_>>()
+            .then(Spanned::parser_with(existing_parser).or_not())
+            .delimited_by(LParen::parser(), RParen::parser())
+            .try_map(|(mut elements, last), span| match last {
+                Some(_) if elements.is_empty() => Err(ParserError::custom(
+                    span,
+                    "a tuple with one element needs a trailing comma",
+                )),
+                Some(last) => {
+                    elements.push(last);
+                    Ok(Self(elements))
+                }
+                None => Ok(Self(elements)),
+            })
+    }
+}
+
+impl Parsable for TupleLiteral {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
+    }
+}
+
+#[test]
+fn test_tuple_literal() {
+    assert_eq!(
+        TupleLiteral::parse("( 1 , 2 )").unwrap(),
+        TupleLiteral(vec![
+            Spanned::from(Expression::num(1_f64)),
+            Spanned::from(Expression::num(2_f64))
+        ])
+    );
+    assert_eq!(
+        TupleLiteral::parse("(1,)").unwrap(),
+        TupleLiteral(vec![Spanned::from(Expression::num(1_f64))])
+    );
+    assert_eq!(TupleLiteral::parse("()").unwrap(), TupleLiteral(Vec::new()));
+    assert_eq!(Expression::parse("(1)").unwrap(), Expression::num(1_f64));
+
+    assert!(TupleLiteral::is_err("(1)"));
+    assert!(TupleLiteral::is_err("(,)"));
+    assert!(TupleLiteral::is_err("(1,,)"));
+}
+
+/// `[1, 2, 3]`
+#[derive(Debug, PartialEq)]
+pub struct ArrayLiteral(pub Vec<Spanned<Expression>>);
+
+impl ArrayLiteral {
+    fn parser_with<'src>(
+        existing_parser: impl ParsableParser<'src, Expression>,
+    ) -> impl ParsableParser<'src, Self> {
+        Spanned::parser_with(existing_parser)
+            .separated_by(Comma::parser())
+            .allow_trailing()
+            .collect()
+            .delimited_by(LBracket::parser(), RBracket::parser())
+            .map(Self)
+    }
+}
+
+impl Parsable for ArrayLiteral {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
+    }
+}
+
+#[test]
+fn test_array_literal() {
+    assert_eq!(
+        ArrayLiteral::parse("[ 1, 2 , ]").unwrap(),
+        ArrayLiteral(vec![
+            Spanned::from(Expression::num(1_f64)),
+            Spanned::from(Expression::num(2_f64))
+        ])
+    );
+    assert_eq!(ArrayLiteral::parse("[]").unwrap(), ArrayLiteral(Vec::new()));
+    assert_eq!(
+        ArrayLiteral::parse("[[1]]").unwrap(),
+        ArrayLiteral(vec![Expression::ArrayLiteral(ArrayLiteral(vec![
+            Spanned::from(Expression::num(1_f64))
+        ]))
+        .into()])
+    );
+
+    assert!(ArrayLiteral::is_err("[,]"));
+    assert!(ArrayLiteral::is_err("[1 2]"));
+}
+
+/// `|a: int, b| a + b`, a function value that can use the bindings around it.
+#[derive(Debug, PartialEq)]
+pub struct Closure {
+    pub params: Vec<Spanned<IdentWithOptionalType>>,
+    pub body: Spanned<Expression>,
+}
+
+impl Closure {
+    fn parser_with<'src>(
+        existing_parser: impl ParsableParser<'src, Expression>,
+    ) -> impl ParsableParser<'src, Self> {
+        // `||` is two pipes around an empty parameter list
+        Spanned::<IdentWithOptionalType>::parser()
+            .padded()
+            .separated_by(Comma::parser())
+            .allow_trailing()
+            .collect()
+            .delimited_by(Pipe::parser(), Pipe::parser())
+            .then(Spanned::parser_with(existing_parser))
+            .map(|(params, body)| Self { params, body })
+    }
+}
+
+impl Parsable for Closure {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
+    }
+}
+
+#[test]
+fn test_closure() {
+    use crate::{r#type::TypeLiteral, syntax_elements::AddExpr};
+
+    assert_eq!(
+        Closure::parse("|a: int, b| a + b").unwrap(),
+        Closure {
+            params: vec![
+                IdentWithOptionalType {
+                    ident: Ident::from_str("a").into(),
+                    r#type: Some(TypeLiteral::Ident(Ident::from_str("int")).into()),
+                }
+                .into(),
+                IdentWithOptionalType::from(Spanned::from(Ident::from_str("b"))).into(),
+            ],
+            body: Expression::BinExpr(Box::new(
+                AddExpr::new([
+                    Spanned::from(Expression::variable("a")),
+                    Spanned::from(Expression::variable("b"))
+                ])
+                .into_bin_expr()
+            ))
+            .into(),
         }
-    )
+    );
+    assert_eq!(
+        Closure::parse("|| 1").unwrap(),
+        Closure {
+            params: Vec::new(),
+            body: Expression::num(1_f64).into(),
+        }
+    );
+    assert!(!Expression::is_err("apply(|x| x, 2)"));
+
+    assert!(Closure::is_err("|a b| a"));
+    assert!(Closure::is_err("|a|"));
+}
+
+/// `name: value` inside of a [`StructLiteral`].
+///
+/// The shorthand `name` is parsed as `name: name`.
+#[derive(Debug, PartialEq)]
+pub struct FieldInit {
+    pub name: Spanned<Ident>,
+    pub value: Spanned<Expression>,
+}
+
+/// Constructs a struct or a struct variant, like `Point { x: 1, y }`
+/// or `Shape::Rect { width: 1, height: 2 }`.
+#[derive(Debug, PartialEq)]
+pub struct StructLiteral {
+    pub path: Spanned<Path>,
+    pub fields: Vec<Spanned<FieldInit>>,
+}
+
+impl StructLiteral {
+    fn parser_with<'src>(
+        existing_parser: impl ParsableParser<'src, Expression>,
+    ) -> impl ParsableParser<'src, Self> {
+        let field_parser = Spanned::<Ident>::parser()
+            .then(
+                Colon::parser()
+                    .ignore_then(Spanned::parser_with(existing_parser))
+                    .or_not(),
+            )
+            .map(|(name, value)| FieldInit {
+                value: value.unwrap_or_else(|| {
+                    Spanned::new(Expression::Variable(Variable(name.node.clone())), name.span)
+                }),
+                name,
+            });
+
+        Spanned::<Path>::parser()
+            .then_ignore(LCurly::parser())
+            .then(
+                Spanned::parser_with(field_parser)
+                    .separated_by(Comma::parser())
+                    .allow_trailing()
+                    .collect(),
+            )
+            .then_ignore(RCurly::parser())
+            .map(|(path, fields)| Self { path, fields })
+    }
+}
+
+impl Parsable for StructLiteral {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
+    }
+}
+
+#[test]
+fn test_struct_literal() {
+    assert_eq!(
+        StructLiteral::parse("Shape::Rect { width: 1, height: 2, }").unwrap(),
+        StructLiteral {
+            path: Path(vec![
+                Ident::from_str("Shape").into(),
+                Ident::from_str("Rect").into()
+            ])
+            .into(),
+            fields: vec![
+                FieldInit {
+                    name: Ident::from_str("width").into(),
+                    value: Expression::num(1_f64).into(),
+                }
+                .into(),
+                FieldInit {
+                    name: Ident::from_str("height").into(),
+                    value: Expression::num(2_f64).into(),
+                }
+                .into()
+            ],
+        }
+    );
+
+    assert_eq!(
+        StructLiteral::parse("Point { x, y: 2 }").unwrap(),
+        StructLiteral {
+            path: Path::from(Spanned::from(Ident::from_str("Point"))).into(),
+            fields: vec![
+                FieldInit {
+                    name: Ident::from_str("x").into(),
+                    value: Expression::variable("x").into(),
+                }
+                .into(),
+                FieldInit {
+                    name: Ident::from_str("y").into(),
+                    value: Expression::num(2_f64).into(),
+                }
+                .into()
+            ],
+        }
+    );
+
+    assert!(StructLiteral::is_err("Shape::Rect { width: 1 height: 2 }"));
+    assert!(StructLiteral::is_err("Point { x: }"));
+}
+
+/// Reads a field of a struct, like `point.x`.
+#[derive(Debug, PartialEq)]
+pub struct FieldAccess {
+    pub value: Spanned<Expression>,
+    pub field: Spanned<Ident>,
+}
+
+/// What can follow an operand.
+enum Postfix {
+    Field(Spanned<Ident>),
+    TupleIndex(Spanned<usize>),
+    MethodCall(Spanned<Ident>, Vec<Spanned<Expression>>),
+    Index(Spanned<Expression>),
+}
+
+/// Parses an operand followed by any amount of field accesses, tuple indexing, method calls
+/// and indexing.
+///
+/// They bind tighter than every prefix operator.
+fn postfix_parser<'src>(
+    operand_parser: impl ParsableParser<'src, Expression>,
+    expression_parser: impl ParsableParser<'src, Expression>,
+) -> impl ParsableParser<'src, Expression> {
+    let args_parser = Spanned::parser_with(expression_parser.clone())
+        .separated_by(Comma::parser())
+        .collect()
+        .delimited_by(LParen::parser(), RParen::parser());
+
+    let index_parser = int(10).try_map(|index: &str, span| {
+        index
+            .parse()
+            .map_err(|_| ParserError::custom(span, "the index is too large"))
+    });
+    let dot_postfix_parser = choice((
+        Spanned::parser_with(index_parser).map(Postfix::TupleIndex),
+        Spanned::<Ident>::parser()
+            .then(args_parser.or_not())
+            .map(|(name, args)| match args {
+                Some(args) => Postfix::MethodCall(name, args),
+                None => Postfix::Field(name),
+            }),
+    ));
+
+    Spanned::parser_with(operand_parser)
+        .foldl_with(
+            choice((
+                Dot::parser().padded().ignore_then(dot_postfix_parser),
+                Spanned::parser_with(expression_parser)
+                    .delimited_by(LBracket::parser(), RBracket::parser())
+                    .map(Postfix::Index),
+            ))
+            .labelled("operator")
+            .repeated(),
+            |value, postfix, extra| {
+                let expression = match postfix {
+                    Postfix::Field(field) => {
+                        Expression::FieldAccess(Box::new(FieldAccess { value, field }))
+                    }
+                    Postfix::TupleIndex(index) => {
+                        Expression::TupleIndex(Box::new(TupleIndex { value, index }))
+                    }
+                    Postfix::MethodCall(method, args) => {
+                        Expression::MethodCall(Box::new(MethodCall {
+                            receiver: value,
+                            method,
+                            args,
+                        }))
+                    }
+                    Postfix::Index(index) => Expression::Index(Box::new(Index { value, index })),
+                };
+                Spanned::new(expression, extra.span())
+            },
+        )
+        .map(|expression| expression.node)
+}
+
+impl Parsable for FieldAccess {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Expression::parser().try_map(|expression, span| match expression {
+            Expression::FieldAccess(field_access) => Ok(*field_access),
+            _ => Err(ParserError::custom(span, "expected a field access")),
+        })
+    }
+}
+
+#[test]
+fn test_field_access() {
+    use crate::syntax_elements::{AddExpr, NegExpr};
+
+    let field = |value, name| {
+        Expression::FieldAccess(Box::new(FieldAccess {
+            value: Spanned::from(value),
+            field: Ident::from_str(name).into(),
+        }))
+    };
+
+    assert_eq!(
+        Expression::parse("line.start . x").unwrap(),
+        field(field(Expression::variable("line"), "start"), "x")
+    );
+    assert_eq!(
+        Expression::parse("-a.x + 1.5").unwrap(),
+        AddExpr::as_expr([
+            NegExpr::as_expr(field(Expression::variable("a"), "x")),
+            Expression::num(1.5),
+        ])
+    );
+    assert!(!Expression::is_err("Point { x: 1 }.x"));
+    assert!(Expression::is_err("a."));
+    assert!(Expression::is_err("a.1x"));
+}
+
+/// Reads an element of a tuple, like `pair.0`.
+#[derive(Debug, PartialEq)]
+pub struct TupleIndex {
+    pub value: Spanned<Expression>,
+    pub index: Spanned<usize>,
+}
+
+impl Parsable for TupleIndex {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Expression::parser().try_map(|expression, span| match expression {
+            Expression::TupleIndex(tuple_index) => Ok(*tuple_index),
+            _ => Err(ParserError::custom(span, "expected a tuple index")),
+        })
+    }
+}
+
+#[test]
+fn test_tuple_index() {
+    let index = |value, index: usize| {
+        Expression::TupleIndex(Box::new(TupleIndex {
+            value: Spanned::from(value),
+            index: index.into(),
+        }))
+    };
+
+    assert_eq!(
+        Expression::parse("pair.1.0").unwrap(),
+        index(index(Expression::variable("pair"), 1), 0)
+    );
+    assert!(matches!(
+        Expression::parse("(1, (2, 3)).1.0").unwrap(),
+        Expression::TupleIndex(_)
+    ));
+    assert!(Expression::is_err("pair.-1"));
+    assert!(Expression::is_err("pair.99999999999999999999999"));
+}
+
+/// Reads an element of an array, like `xs[i]`.
+#[derive(Debug, PartialEq)]
+pub struct Index {
+    pub value: Spanned<Expression>,
+    pub index: Spanned<Expression>,
+}
+
+impl Parsable for Index {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Expression::parser().try_map(|expression, span| match expression {
+            Expression::Index(index) => Ok(*index),
+            _ => Err(ParserError::custom(span, "expected an index")),
+        })
+    }
+}
+
+#[test]
+fn test_index() {
+    use crate::syntax_elements::{AddExpr, NegExpr};
+
+    let index = |value, index| {
+        Expression::Index(Box::new(Index {
+            value: Spanned::from(value),
+            index: Spanned::from(index),
+        }))
+    };
+
+    assert_eq!(
+        Expression::parse("-xs[i + 1][0]").unwrap(),
+        NegExpr::as_expr(index(
+            index(
+                Expression::variable("xs"),
+                AddExpr::as_expr([Expression::variable("i"), Expression::num(1_f64)])
+            ),
+            Expression::num(0_f64)
+        ))
+    );
+    assert!(matches!(
+        Expression::parse("[1, 2][0].x").unwrap(),
+        Expression::FieldAccess(_)
+    ));
+    assert!(Expression::is_err("xs[]"));
+    assert!(Expression::is_err("xs[1"));
+}
+
+/// Calls a function of an `impl` block with `receiver` as `self`, like `point.len()`.
+#[derive(Debug, PartialEq)]
+pub struct MethodCall {
+    pub receiver: Spanned<Expression>,
+    pub method: Spanned<Ident>,
+    pub args: Vec<Spanned<Expression>>,
+}
+
+impl Parsable for MethodCall {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Expression::parser().try_map(|expression, span| match expression {
+            Expression::MethodCall(method_call) => Ok(*method_call),
+            _ => Err(ParserError::custom(span, "expected a method call")),
+        })
+    }
+}
+
+#[test]
+fn test_method_call() {
+    assert_eq!(
+        MethodCall::parse("point.scale(2, b).len()").unwrap(),
+        MethodCall {
+            receiver: Expression::MethodCall(Box::new(MethodCall {
+                receiver: Expression::variable("point").into(),
+                method: Ident::from_str("scale").into(),
+                args: vec![
+                    Expression::num(2_f64).into(),
+                    Expression::variable("b").into()
+                ],
+            }))
+            .into(),
+            method: Ident::from_str("len").into(),
+            args: Vec::new(),
+        }
+    );
+    assert!(matches!(
+        Expression::parse("line.start.len()").unwrap(),
+        Expression::MethodCall(call) if matches!(call.receiver.node, Expression::FieldAccess(_))
+    ));
+    assert!(Expression::is_err("point.len(1,,)"));
+    assert!(Expression::is_err("point.len("));
 }
 
 #[derive(Debug, PartialEq)]
@@ -120,12 +711,31 @@ fn test_variable() {
 
 /// An expression that has a value/can return something
 #[enum_dispatch]
-#[non_exhaustive]
 #[derive(Debug, PartialEq)]
 pub enum Expression {
     BinExpr(Box<BinExpr>),
+    UnaryExpr(Box<UnaryExpr>),
+    CodeScope(Box<CodeScope>),
+    If(Box<If>),
+    Match(Box<Match>),
+    Range(Box<Range>),
+    Loop(Box<Loop>),
+    While(Box<While>),
+    For(Box<For>),
+    Break(Box<Break>),
+    Continue,
+    Return(Box<Return>),
+    FieldAccess(Box<FieldAccess>),
+    TupleIndex(Box<TupleIndex>),
+    MethodCall(Box<MethodCall>),
+    Index(Box<Index>),
+    Closure(Box<Closure>),
 
     FnCall,
+    StructLiteral,
+    TupleLiteral,
+    ArrayLiteral,
+    Path,
     Variable,
 
     NumLit,
@@ -134,27 +744,128 @@ pub enum Expression {
 }
 
 impl Expression {
-    pub fn parser_with_precedence<'src>(precedence: Precedence) -> impl ParsableParser<'src, Self> {
-        recursive(|expr| {
-            choice((
-                // The longest possible expression needs to be parsed first.
-                // Since BinExpr can start with an expression it needs to be first.
-                BinExpr::parser_with_precedence_and_parser(precedence, expr.clone())
-                    .map(|bin_expr| Self::BinExpr(Box::new(bin_expr))),
-                // a `FnCall` needs to be parsed first because they both start
-                // with an Ident but a `FnCall` is longer.
-                FnCall::parser_with(expr.clone()).map(Self::FnCall),
-                Variable::parser().map(Self::Variable),
-                // self contained expressions do not need a specific order
-                NumLit::parser().map(Self::NumLit),
-                StringLit::parser().map(Self::StringLit),
-            ))
-        })
+    /// Whether this ends with a `}`, which makes the separator after it optional.
+    pub fn is_block_like(&self) -> bool {
+        matches!(
+            self,
+            Self::CodeScope(_)
+                | Self::If(_)
+                | Self::Match(_)
+                | Self::Loop(_)
+                | Self::While(_)
+                | Self::For(_)
+        )
+    }
+
+    #[cfg(test)]
+    pub fn num(num: f64) -> Self {
+        Self::NumLit(NumLit(num))
+    }
+
+    #[cfg(test)]
+    pub fn variable(name: &str) -> Self {
+        Self::Variable(Variable(Ident::from_str(name)))
+    }
+}
+
+type RecursiveParser<'src, T> =
+    Recursive<Indirect<'src, 'src, ParserInput<'src>, T, extra::Err<ParserError<'src>>>>;
+
+impl Expression {
+    /// Parses an expression that is directly followed by a block, like the condition of an `if`.
+    ///
+    /// Struct literals have to be put in parentheses there, otherwise `if a { b }` would be ambiguous.
+    pub fn condition_parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parsers().1
+    }
+
+    /// Returns an expression and a [condition](Self::condition_parser) parser,
+    /// which both contain the other one.
+    fn parsers<'src>() -> (RecursiveParser<'src, Self>, RecursiveParser<'src, Self>) {
+        let mut expression_parser = Recursive::declare();
+        let mut condition_parser = Recursive::declare();
+
+        expression_parser.define(Self::parser_from(
+            expression_parser.clone(),
+            condition_parser.clone(),
+            true,
+        ));
+        condition_parser.define(Self::parser_from(
+            expression_parser.clone(),
+            condition_parser.clone(),
+            false,
+        ));
+
+        (expression_parser, condition_parser)
+    }
+
+    fn parser_from<'src>(
+        expr: RecursiveParser<'src, Self>,
+        condition: RecursiveParser<'src, Self>,
+        allow_struct_literal: bool,
+    ) -> impl ParsableParser<'src, Self> {
+        let operand_parser = choice((
+            // grouping only affects the structure of the ast
+            expr.clone()
+                .delimited_by(LParen::parser(), RParen::parser()),
+            TupleLiteral::parser_with(expr.clone()).map(Self::TupleLiteral),
+            Closure::parser_with(expr.clone()).map(|closure| Self::Closure(Box::new(closure))),
+            ArrayLiteral::parser_with(expr.clone()).map(Self::ArrayLiteral),
+            // keywords would be parsed as a `Variable` otherwise
+            If::parser_with(expr.clone(), condition.clone()).map(|r#if| Self::If(Box::new(r#if))),
+            Match::parser_with(expr.clone(), condition.clone())
+                .map(|r#match| Self::Match(Box::new(r#match))),
+            Loop::parser_with(expr.clone()).map(|r#loop| Self::Loop(Box::new(r#loop))),
+            While::parser_with(expr.clone(), condition.clone())
+                .map(|r#while| Self::While(Box::new(r#while))),
+            For::parser_with(expr.clone(), condition).map(|r#for| Self::For(Box::new(r#for))),
+            Break::parser_with(expr.clone()).map(|r#break| Self::Break(Box::new(r#break))),
+            Continue::parser().map(Self::Continue),
+            Return::parser_with(expr.clone()).map(|r#return| Self::Return(Box::new(r#return))),
+            // a `FnCall` needs to be parsed first because they both start
+            // with an Ident but a `FnCall` is longer.
+            FnCall::parser_with(expr.clone()).map(Self::FnCall),
+            StructLiteral::parser_with(expr.clone()).try_map(move |literal, span| {
+                if allow_struct_literal {
+                    Ok(Self::StructLiteral(literal))
+                } else {
+                    Err(ParserError::custom(
+                        span,
+                        "a struct literal has to be put in parentheses here",
+                    ))
+                }
+            }),
+            Path::qualified_parser().map(Self::Path),
+            Variable::parser().map(Self::Variable),
+            // self contained expressions do not need a specific order
+            NumLit::parser().map(Self::NumLit),
+            StringLit::parser().map(Self::StringLit),
+            CodeScope::parser_with(expr.clone()).map(|scope| Self::CodeScope(Box::new(scope))),
+        ))
+        // instead of listing the first character of every operand
+        .labelled("expression");
+
+        Range::parser_with(BinExpr::parser_with(UnaryExpr::parser_with(
+            postfix_parser(operand_parser, expr),
+        )))
+        .map(|expression| expression.node)
+        .labelled("expression")
     }
 }
 
 impl Parsable for Expression {
     fn parser<'src>() -> impl ParsableParser<'src, Self> {
-        Self::parser_with_precedence(Precedence::MIN)
+        Self::parsers().0
     }
 }
+
+#[test]
+fn test_condition() {
+    use crate::control_flow::If;
+
+    let r#if = If::parse("if a { b }").unwrap();
+    assert_eq!(r#if.condition.node, Expression::variable("a"));
+    assert!(!If::is_err("if (Point { x: 1 }).x == a { b }"));
+    assert!(!If::is_err("if a == b.c { Point { x: 1 } }"));
+    assert!(If::is_err("if Point { x: 1 }.x == a { b }"));
+}
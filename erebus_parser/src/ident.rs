@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use chumsky::{
     prelude::{choice, just},
     text::ident,
@@ -5,15 +7,30 @@ use chumsky::{
 };
 
 use super::{
-    parsable::{Parsable, ParsableParser},
+    parsable::{Parsable, ParsableParser, ParserError},
     r#type::TypeLiteral,
+    span::Spanned,
+    syntax_elements::PathSeparator,
 };
 
-#[derive(Debug, PartialEq, Eq)]
+/// Words that can't be used as an [`Ident`].
+///
+/// `self` isn't one of them, it's a variable inside methods.
+const KEYWORDS: &[&str] = &[
+    "break", "continue", "dyn", "else", "enum", "fn", "for", "if", "impl", "in", "let", "loop",
+    "match", "mut", "pub", "return", "struct", "trait", "while",
+];
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Ident(String);
 
 impl Ident {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
     #[cfg(test)]
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(str: &str) -> Self {
         Ident(str.to_owned())
     }
@@ -27,10 +44,18 @@ impl Parsable for Ident {
             .repeated()
             .collect::<String>()
             .then(ident())
-            .map(|(mut underscores, str)| {
+            .try_map(|(mut underscores, str), span| {
                 underscores.push_str(str);
-                Self(underscores)
+                if KEYWORDS.contains(&underscores.as_str()) {
+                    return Err(ParserError::custom(
+                        span,
+                        format!("`{underscores}` is a keyword"),
+                    ));
+                }
+                Ok(Self(underscores))
             })
+            // besides the message, this keeps `ident` from dropping errors of earlier alternatives
+            .labelled("identifier")
     }
 }
 
@@ -43,22 +68,91 @@ fn test_ident() {
     // assert_eq!(Ident::parse("hyphen-var").unwrap(), Ident("hyphen".into()));
     assert!(Ident::is_err("1starts_number"));
     assert!(Ident::is_err(" starts_space123"));
+    assert!(Ident::is_err("let"));
+    assert!(Ident::is_err("match"));
+    assert_eq!(Ident::parse("letter").unwrap(), Ident("letter".into()));
+    assert_eq!(Ident::parse("self").unwrap(), Ident("self".into()));
+}
+
+/// Names separated by `::`, like `Shape::Circle`.
+#[derive(Debug, PartialEq)]
+pub struct Path(pub Vec<Spanned<Ident>>);
+
+impl Path {
+    /// Parses a path with at least two segments, a single name is a [`Variable`](crate::Variable).
+    pub fn qualified_parser<'src>() -> impl ParsableParser<'src, Self> {
+        Spanned::<Ident>::parser()
+            .then(
+                PathSeparator::parser()
+                    .ignore_then(Spanned::<Ident>::parser())
+                    .repeated()
+                    .at_least(1)
+                    .collect::<Vec<_>>(),
+            )
+            .map(|(first, rest)| Self([first].into_iter().chain(rest).collect()))
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("::")?;
+            }
+            f.write_str(segment.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Spanned<Ident>> for Path {
+    fn from(ident: Spanned<Ident>) -> Self {
+        Self(vec![ident])
+    }
+}
+
+impl Parsable for Path {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Spanned::<Ident>::parser()
+            .separated_by(PathSeparator::parser())
+            .at_least(1)
+            .collect()
+            .map(Self)
+    }
+}
+
+#[test]
+fn test_path() {
+    assert_eq!(
+        Path::parse("Shape :: Circle").unwrap(),
+        Path(vec![
+            Ident::from_str("Shape").into(),
+            Ident::from_str("Circle").into()
+        ])
+    );
+    assert_eq!(
+        Path::parse("circle").unwrap(),
+        Path(vec![Ident::from_str("circle").into()])
+    );
+    assert!(Path::qualified_parser().parse("circle").has_errors());
+    assert!(Path::is_err("Shape::"));
+    assert!(Path::is_err("Shape:Circle"));
 }
 
 #[derive(Debug, PartialEq)]
 pub struct IdentWithType {
-    pub ident: Ident,
-    pub r#type: TypeLiteral,
+    pub ident: Spanned<Ident>,
+    pub r#type: Spanned<TypeLiteral>,
 }
 
 impl IdentWithType {
     pub fn parser_with<'src>(
         existing_parser: impl ParsableParser<'src, TypeLiteral>,
     ) -> impl ParsableParser<'src, Self> {
-        Ident::parser()
+        Spanned::<Ident>::parser()
             .then_ignore(just(":"))
             .padded()
-            .then(existing_parser)
+            .then(Spanned::parser_with(existing_parser))
             .map(|(ident, r#type)| Self { ident, r#type })
     }
 }
@@ -74,15 +168,15 @@ fn test_ident_with_type() {
     assert_eq!(
         IdentWithType::parse("test: String").unwrap(),
         IdentWithType {
-            ident: Ident::from_str("test"),
-            r#type: TypeLiteral::Ident(Ident::from_str("String")),
+            ident: Ident::from_str("test").into(),
+            r#type: TypeLiteral::Ident(Ident::from_str("String")).into(),
         }
     );
     assert_eq!(
         IdentWithType::parse("test2: \n_String").unwrap(),
         IdentWithType {
-            ident: Ident::from_str("test2"),
-            r#type: TypeLiteral::Ident(Ident::from_str("_String")),
+            ident: Ident::from_str("test2").into(),
+            r#type: TypeLiteral::Ident(Ident::from_str("_String")).into(),
         }
     );
     assert!(IdentWithType::is_err("test3 : String"))
@@ -90,12 +184,12 @@ fn test_ident_with_type() {
 
 #[derive(Debug, PartialEq)]
 pub struct IdentWithOptionalType {
-    pub ident: Ident,
-    pub r#type: Option<TypeLiteral>,
+    pub ident: Spanned<Ident>,
+    pub r#type: Option<Spanned<TypeLiteral>>,
 }
 
-impl From<Ident> for IdentWithOptionalType {
-    fn from(ident: Ident) -> Self {
+impl From<Spanned<Ident>> for IdentWithOptionalType {
+    fn from(ident: Spanned<Ident>) -> Self {
         Self {
             ident,
             r#type: None,
@@ -116,7 +210,7 @@ impl Parsable for IdentWithOptionalType {
     fn parser<'src>() -> impl ParsableParser<'src, Self> {
         choice((
             IdentWithType::parser().map(Self::from),
-            Ident::parser().map(Self::from),
+            Spanned::<Ident>::parser().map(Self::from),
         ))
     }
 }
@@ -126,15 +220,15 @@ fn test_ident_with_optional_type() {
     assert_eq!(
         IdentWithOptionalType::parse("test").unwrap(),
         IdentWithOptionalType {
-            ident: Ident::from_str("test"),
+            ident: Ident::from_str("test").into(),
             r#type: None
         }
     );
     assert_eq!(
         IdentWithOptionalType::parse("str: \n\tString").unwrap(),
         IdentWithOptionalType {
-            ident: Ident::from_str("str"),
-            r#type: Some(TypeLiteral::Ident(Ident::from_str("String")))
+            ident: Ident::from_str("str").into(),
+            r#type: Some(TypeLiteral::Ident(Ident::from_str("String")).into())
         }
     )
 }
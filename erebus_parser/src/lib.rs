@@ -1,12 +1,40 @@
 mod ast;
 mod bin_ops;
+mod control_flow;
 mod expr;
 mod ident;
 mod literals;
 mod parsable;
+mod pattern;
+mod range;
+mod recovery;
+mod span;
 mod statement;
 mod syntax_elements;
 mod r#type;
+mod unary_ops;
 
 pub use ast::Ast;
+pub use bin_ops::{BinExpr, GenericBinOp};
+pub use control_flow::{
+    Break, Continue, Else, For, If, Label, Loop, Match, MatchArm, Return, While,
+};
+pub use expr::{
+    ArrayLiteral, Closure, CodeScope, Expression, FieldAccess, FieldInit, FnCall, Index,
+    MethodCall, StructLiteral, TupleIndex, TupleLiteral, Variable,
+};
+pub use ident::{Ident, IdentWithOptionalType, IdentWithType, Path};
+pub use literals::{NumLit, StringLit};
 pub use parsable::Parsable;
+pub use pattern::{FieldPattern, Pattern};
+pub use r#type::{
+    AppliedType, FnSignatureType, GenericParam, GenericParams, TupleType, TypeLiteral,
+};
+pub use range::Range;
+pub use span::{Span, Spanned};
+pub use statement::{
+    Assign, CompoundOp, EnumDef, FnDef, ImplBlock, Let, MaybePublic, RawTopLevelStatement,
+    Receiver, Statement, StructDef, TopLevelStatement, TraitDef, TraitFn, VariantDef,
+    VariantFields,
+};
+pub use unary_ops::{GenericUnaryOp, UnaryExpr};
@@ -52,10 +52,16 @@ impl NumLit {
     ) -> impl ParsableParser<'src, Self> {
         just(symbol)
             .ignored()
-            // ignore leading zeros
-            .then_ignore(just('0').repeated())
+            // ignore leading zeros, except for the last digit of `0`
+            .then_ignore(just('0').then(text::digits(BASE).rewind()).repeated())
             .then(text::int(BASE))
-            .then(Dot::parser().ignored().then(text::int(BASE)).or_not())
+            // the fractional part can start with zeros
+            .then(
+                Dot::parser()
+                    .ignored()
+                    .then(text::digits(BASE).to_slice())
+                    .or_not(),
+            )
             .try_map(|((_, int), fractional), span| {
                 if fractional.is_some() && BASE > 10 {
                     // 0x0.dead_beef() is ambiguous
@@ -81,6 +87,8 @@ impl Parsable for NumLit {
             Self::raw_base_parser::<8>("0o"),
             Self::raw_base_parser::<10>(""),
         ))
+        // besides the message, this keeps `repeated` from dropping errors of earlier alternatives
+        .labelled("number")
     }
 }
 
@@ -88,6 +96,10 @@ impl Parsable for NumLit {
 fn test_num_literal() {
     assert_eq!(NumLit::parse("1234.4321").unwrap(), NumLit(1234.4321));
     assert_eq!(NumLit::parse("000743.6400").unwrap(), NumLit(743.64));
+    assert_eq!(NumLit::parse("0").unwrap(), NumLit(0_f64));
+    assert_eq!(NumLit::parse("00").unwrap(), NumLit(0_f64));
+    assert_eq!(NumLit::parse("0.05").unwrap(), NumLit(0.05));
+    assert_eq!(NumLit::parse("0x0").unwrap(), NumLit(0_f64));
 
     assert_eq!(NumLit::parse("0xFF3B").unwrap(), NumLit(0xFF3B as f64));
     assert_eq!(
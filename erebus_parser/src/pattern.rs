@@ -0,0 +1,191 @@
+use chumsky::{
+    prelude::{choice, just, recursive},
+    IterParser, Parser,
+};
+
+use super::{
+    ident::{Ident, Path},
+    literals::{NumLit, StringLit},
+    parsable::{Parsable, ParsableParser, ParserError},
+    span::Spanned,
+    syntax_elements::{Colon, Comma, LCurly, LParen, RCurly, RParen, RangeOp, Underscore},
+};
+
+/// The shape a value is compared against in a `match` arm.
+#[derive(Debug, PartialEq)]
+pub enum Pattern {
+    /// `_`, which matches everything.
+    Wildcard,
+    /// `name`, which matches everything and binds the value to `name`.
+    Binding(Ident),
+    /// `1` or `-2.5`
+    NumLit(NumLit),
+    StringLit(StringLit),
+    /// `(a, _)`
+    Tuple(Vec<Spanned<Pattern>>),
+    /// A unit variant like `Shape::Empty`.
+    Path(Path),
+    /// A tuple variant like `Shape::Circle(radius)`.
+    TupleStruct {
+        path: Spanned<Path>,
+        fields: Vec<Spanned<Pattern>>,
+    },
+    /// A struct or struct variant like `Shape::Rect { width, height: 2, .. }`.
+    Struct {
+        path: Spanned<Path>,
+        fields: Vec<Spanned<FieldPattern>>,
+        /// Whether the fields that aren't mentioned are ignored with `..`.
+        has_rest: bool,
+    },
+}
+
+/// `name: pattern` inside of a struct pattern.
+///
+/// The shorthand `name` is parsed as `name: name`.
+#[derive(Debug, PartialEq)]
+pub struct FieldPattern {
+    pub name: Spanned<Ident>,
+    pub pattern: Spanned<Pattern>,
+}
+
+impl Parsable for Pattern {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        recursive(|pattern| {
+            let element_parser = Spanned::parser_with(pattern).padded();
+            let elements_parser = element_parser
+                .clone()
+                .separated_by(Comma::parser())
+                .allow_trailing()
+                .collect::<Vec<_>>()
+                .delimited_by(LParen::parser(), RParen::parser());
+
+            let field_parser = Spanned::<Ident>::parser()
+                .then(Colon::parser().ignore_then(element_parser.clone()).or_not())
+                .map(|(name, pattern)| FieldPattern {
+                    pattern: pattern.unwrap_or_else(|| {
+                        Spanned::new(Self::Binding(name.node.clone()), name.span)
+                    }),
+                    name,
+                });
+            let fields_parser = choice((
+                RangeOp::parser().map(|_| None),
+                Spanned::parser_with(field_parser).padded().map(Some),
+            ))
+            .separated_by(Comma::parser())
+            .allow_trailing()
+            .collect::<Vec<_>>()
+            .delimited_by(LCurly::parser(), RCurly::parser())
+            .try_map(|mut fields, span| {
+                let has_rest = fields.last().is_some_and(Option::is_none);
+                if has_rest {
+                    fields.pop();
+                }
+
+                fields
+                    .into_iter()
+                    .collect::<Option<Vec<_>>>()
+                    .map(|fields| (fields, has_rest))
+                    .ok_or_else(|| ParserError::custom(span, "`..` has to be the last field"))
+            });
+
+            choice((
+                Underscore::parser().map(|_| Self::Wildcard),
+                just('-')
+                    .padded()
+                    .ignore_then(NumLit::parser())
+                    .map(|num_lit| Self::NumLit(NumLit(-num_lit.0))),
+                NumLit::parser().map(Self::NumLit),
+                StringLit::parser().map(Self::StringLit),
+                // `(pattern)` only groups, a tuple with one element needs a trailing comma
+                element_parser
+                    .delimited_by(LParen::parser(), RParen::parser())
+                    .map(|pattern| pattern.node),
+                elements_parser.clone().map(Self::Tuple),
+                Spanned::<Path>::parser()
+                    .then(elements_parser)
+                    .map(|(path, fields)| Self::TupleStruct { path, fields }),
+                Spanned::<Path>::parser()
+                    .then(fields_parser)
+                    .map(|(path, (fields, has_rest))| Self::Struct {
+                        path,
+                        fields,
+                        has_rest,
+                    }),
+                Path::qualified_parser().map(Self::Path),
+                Ident::parser().map(Self::Binding),
+            ))
+            .labelled("pattern")
+        })
+    }
+}
+
+#[test]
+fn test_pattern() {
+    let binding = |name| Spanned::from(Pattern::Binding(Ident::from_str(name)));
+    let path = |segments: &[&str]| {
+        Path(
+            segments
+                .iter()
+                .map(|segment| Ident::from_str(segment).into())
+                .collect(),
+        )
+    };
+
+    assert_eq!(Pattern::parse("_").unwrap(), Pattern::Wildcard);
+    assert_eq!(Pattern::parse("_x").unwrap(), *binding("_x"));
+    assert_eq!(
+        Pattern::parse("- 2.5").unwrap(),
+        Pattern::NumLit(NumLit(-2.5))
+    );
+    assert_eq!(
+        Pattern::parse("( a ,_ )").unwrap(),
+        Pattern::Tuple(vec![binding("a"), Pattern::Wildcard.into()])
+    );
+    assert_eq!(Pattern::parse("(a)").unwrap(), *binding("a"));
+    assert_eq!(
+        Pattern::parse("(a,)").unwrap(),
+        Pattern::Tuple(vec![binding("a")])
+    );
+    assert_eq!(
+        Pattern::parse("Shape::Empty").unwrap(),
+        Pattern::Path(path(&["Shape", "Empty"]))
+    );
+    assert_eq!(
+        Pattern::parse("Shape::Circle(\"r\")").unwrap(),
+        Pattern::TupleStruct {
+            path: path(&["Shape", "Circle"]).into(),
+            fields: vec![Pattern::StringLit(StringLit("r".to_owned())).into()],
+        }
+    );
+    assert_eq!(
+        Pattern::parse("Shape::Rect { width, height: 2, .. }").unwrap(),
+        Pattern::Struct {
+            path: path(&["Shape", "Rect"]).into(),
+            fields: vec![
+                FieldPattern {
+                    name: Ident::from_str("width").into(),
+                    pattern: binding("width"),
+                }
+                .into(),
+                FieldPattern {
+                    name: Ident::from_str("height").into(),
+                    pattern: Pattern::NumLit(NumLit(2_f64)).into(),
+                }
+                .into()
+            ],
+            has_rest: true,
+        }
+    );
+    assert_eq!(
+        Pattern::parse("Point {}").unwrap(),
+        Pattern::Struct {
+            path: path(&["Point"]).into(),
+            fields: Vec::new(),
+            has_rest: false,
+        }
+    );
+
+    assert!(Pattern::is_err("Point { .., x }"));
+    assert!(Pattern::is_err("Shape::"));
+    assert!(Pattern::is_err("1 + 2"));
+}
@@ -0,0 +1,105 @@
+use chumsky::{prelude::choice, Parser};
+
+use super::{
+    expr::Expression,
+    parsable::{Parsable, ParsableParser, ParserError},
+    span::Spanned,
+    syntax_elements::{RangeInclusiveOp, RangeOp},
+};
+
+/// `start..end` or `start..=end`, the numbers a `for` loop iterates over.
+#[derive(Debug, PartialEq)]
+pub struct Range {
+    pub start: Spanned<Expression>,
+    pub end: Spanned<Expression>,
+    /// Whether `end` is part of the range.
+    pub is_inclusive: bool,
+}
+
+impl Range {
+    /// Parses an operand that is optionally followed by a range operator and another operand.
+    ///
+    /// Ranges bind weaker than every binary operator and can't be chained.
+    pub fn parser_with<'src>(
+        operand_parser: impl ParsableParser<'src, Spanned<Expression>>,
+    ) -> impl ParsableParser<'src, Spanned<Expression>> {
+        operand_parser
+            .clone()
+            .then(
+                // `..=` starts with `..`
+                choice((
+                    RangeInclusiveOp::parser().to(true),
+                    RangeOp::parser().to(false),
+                ))
+                .labelled("operator")
+                .then(operand_parser)
+                .or_not(),
+            )
+            .map_with(|(start, end), extra| match end {
+                Some((is_inclusive, end)) => Spanned::new(
+                    Expression::Range(Box::new(Self {
+                        start,
+                        end,
+                        is_inclusive,
+                    })),
+                    extra.span(),
+                ),
+                None => start,
+            })
+    }
+}
+
+impl Parsable for Range {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Expression::parser().try_map(|expression, span| match expression {
+            Expression::Range(range) => Ok(*range),
+            _ => Err(ParserError::custom(span, "expected a range")),
+        })
+    }
+}
+
+#[test]
+fn test_range() {
+    use crate::syntax_elements::{AddExpr, LessExpr};
+
+    assert_eq!(
+        Range::parse("0..n + 1").unwrap(),
+        Range {
+            start: Expression::num(0_f64).into(),
+            end: AddExpr::as_expr([Expression::variable("n"), Expression::num(1_f64)]).into(),
+            is_inclusive: false,
+        }
+    );
+    assert_eq!(
+        Range::parse("1..=2").unwrap(),
+        Range {
+            start: Expression::num(1_f64).into(),
+            end: Expression::num(2_f64).into(),
+            is_inclusive: true,
+        }
+    );
+    // a number directly in front of `..` has no fractional part
+    assert_eq!(
+        Range::parse("1.5..2").unwrap(),
+        Range {
+            start: Expression::num(1.5).into(),
+            end: Expression::num(2_f64).into(),
+            is_inclusive: false,
+        }
+    );
+    assert_eq!(
+        Expression::parse("(1..2) < 3").unwrap(),
+        LessExpr::as_expr([
+            Expression::Range(Box::new(Range {
+                start: Expression::num(1_f64).into(),
+                end: Expression::num(2_f64).into(),
+                is_inclusive: false,
+            })),
+            Expression::num(3_f64)
+        ])
+    );
+
+    assert!(Range::is_err("1.."));
+    assert!(Range::is_err("..2"));
+    assert!(Expression::is_err("1..2..3"));
+}
@@ -0,0 +1,177 @@
+//! Parsers that skip over broken code so parsing can continue after an error.
+
+use chumsky::{
+    prelude::{choice, end, just, none_of, recursive},
+    text, Parser,
+};
+
+use super::{
+    literals::StringLit,
+    parsable::{Parsable, ParsableParser},
+    syntax_elements::ElseKeyword,
+};
+
+/// Keywords that start an item, which is never part of the broken code before it.
+const ITEM_KEYWORDS: &[&str] = &["enum", "fn", "impl", "pub", "struct", "trait"];
+
+/// Keywords that start an item or a statement.
+const STATEMENT_KEYWORDS: &[&str] = &["enum", "fn", "impl", "let", "pub", "struct", "trait"];
+
+/// One of `keywords` followed by whitespace, so `fn f` matches but the type `fn(int)` doesn't.
+fn keyword<'src>(keywords: &'static [&'static str]) -> impl ParsableParser<'src, ()> {
+    text::ident()
+        .filter(move |ident: &&str| keywords.contains(ident))
+        .then(text::whitespace().at_least(1))
+        .ignored()
+}
+
+/// Skips a string, a whole word or any other character except `;`, `{` and `}`.
+fn token<'src>() -> impl ParsableParser<'src, ()> {
+    choice((
+        // braces inside of strings don't count
+        StringLit::parser().ignored(),
+        // keywords are only recognized at the start of a word
+        text::ident().ignored(),
+        none_of(";{}").ignored(),
+    ))
+}
+
+/// Skips a `{ ... }` block including all blocks nested in it.
+fn balanced_block<'src>() -> impl ParsableParser<'src, ()> {
+    recursive(|block| {
+        just('{')
+            .then(choice((block, just(';').ignored(), token())).repeated())
+            .then(just('}'))
+            .ignored()
+    })
+}
+
+/// Like [`balanced_block`], but a block that is never closed ends in front of the next item,
+/// instead of taking the rest of the file with it.
+fn block<'src>() -> impl ParsableParser<'src, ()> {
+    recursive(|block| {
+        let unclosed = just('{')
+            .then(
+                choice((
+                    block,
+                    just(';').ignored(),
+                    keyword(ITEM_KEYWORDS).not().ignore_then(token()),
+                ))
+                .repeated(),
+            )
+            .then(choice((
+                just('}').ignored(),
+                keyword(ITEM_KEYWORDS).rewind().ignored(),
+                end(),
+            )));
+        choice((balanced_block(), unclosed.ignored()))
+    })
+}
+
+/// Skips broken code up to and including the next `;` or block,
+/// or up to the next statement, item or `}` that ends the surrounding scope.
+///
+/// At least the first word is skipped, even if it is a keyword like `let`.
+fn skip<'src>() -> impl ParsableParser<'src, ()> {
+    recursive(|skip| {
+        let terminator = choice((
+            just(';').ignored(),
+            block()
+                .then(choice((
+                    // an `if` goes on after its first block
+                    ElseKeyword::parser().padded().ignore_then(skip).ignored(),
+                    just(';').padded().or_not().ignored(),
+                )))
+                .ignored(),
+        ));
+
+        choice((
+            token()
+                .then(
+                    keyword(STATEMENT_KEYWORDS)
+                        .not()
+                        .ignore_then(token())
+                        .repeated(),
+                )
+                .then(choice((
+                    terminator.clone(),
+                    // these are left for the parser of the next statement or the scope
+                    choice((keyword(STATEMENT_KEYWORDS), just('}').ignored(), end()))
+                        .rewind()
+                        .ignored(),
+                )))
+                .ignored(),
+            terminator,
+        ))
+        // so it never succeeds by only skipping whitespace
+        .padded()
+    })
+}
+
+/// Skips a broken statement inside of a scope.
+///
+/// An item isn't skipped, since it can only follow a scope whose `}` is missing.
+pub fn skip_statement<'src>() -> impl ParsableParser<'src, ()> {
+    keyword(ITEM_KEYWORDS).padded().not().ignore_then(skip())
+}
+
+/// Skips a broken item, like a method in an `impl` block.
+pub fn skip_item<'src>() -> impl ParsableParser<'src, ()> {
+    skip()
+}
+
+/// Like [`skip_item`], but also skips a stray `}`.
+pub fn skip_top_level_statement<'src>() -> impl ParsableParser<'src, ()> {
+    choice((skip_item(), just('}').padded().ignored()))
+}
+
+#[test]
+fn test_skip() {
+    use chumsky::{prelude::any, text::ident};
+
+    let skip_then_ident = || skip_statement().ignore_then(ident().padded());
+
+    assert_eq!(
+        skip_then_ident().parse("a = 1; next").into_result(),
+        Ok("next")
+    );
+    assert_eq!(
+        skip_item()
+            .ignore_then(ident().padded())
+            .parse("fn f() { \"}\" { } } next")
+            .into_result(),
+        Ok("next")
+    );
+    // the next statement starts at a keyword, but not in the middle of a word
+    assert_eq!(
+        skip_statement()
+            .ignore_then(ident())
+            .then_ignore(any().repeated())
+            .parse("let x = a_let + let y = 1;")
+            .into_result(),
+        Ok("let")
+    );
+    assert_eq!(
+        skip_statement()
+            .then_ignore(just('}'))
+            .parse("1 + }")
+            .into_result(),
+        Ok(())
+    );
+    // an unclosed block ends at the next item
+    assert_eq!(
+        skip_item()
+            .ignore_then(ident())
+            .then_ignore(any().repeated())
+            .parse("struct S { a: int\nfn f() { 1 }")
+            .into_result(),
+        Ok("fn")
+    );
+    assert_eq!(
+        skip_statement()
+            .ignore_then(ident().padded())
+            .parse("let f: fn(int) -> int = { 1 } next")
+            .into_result(),
+        Ok("next")
+    );
+}
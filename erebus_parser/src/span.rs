@@ -0,0 +1,94 @@
+use std::{
+    fmt::{self, Debug},
+    ops::{Deref, DerefMut},
+};
+
+use chumsky::span::SimpleSpan;
+
+use super::parsable::{Parsable, ParsableParser};
+
+/// Byte range of a node in the parsed source.
+pub type Span = SimpleSpan<usize>;
+
+/// A node together with the location it was parsed from.
+///
+/// The span is ignored when comparing nodes,
+/// so the same code formatted differently still results in equal ASTs.
+#[derive(Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Self { node, span }
+    }
+
+    pub fn parser_with<'src>(
+        node_parser: impl ParsableParser<'src, T>,
+    ) -> impl ParsableParser<'src, Self> {
+        node_parser.map_with(|node, extra| Self::new(node, extra.span()))
+    }
+}
+
+impl<T: Debug> Debug for Spanned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.node.fmt(f)?;
+        write!(f, " @ {:?}", self.span)
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+impl<T> DerefMut for Spanned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.node
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T: Eq> Eq for Spanned<T> {}
+
+/// Only used to construct expected ASTs, since their spans are never compared.
+#[cfg(test)]
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Self {
+        Self::new(node, Span::splat(0))
+    }
+}
+
+impl<T: Parsable> Parsable for Spanned<T> {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(T::parser())
+    }
+}
+
+#[test]
+fn test_spanned() {
+    use crate::{expr::Expression, statement::Let};
+
+    let r#let = Let::parse("let a = 1 + 23").unwrap();
+    assert_eq!(r#let.pattern.span, Span::new(4, 5));
+    assert_eq!(r#let.right.span, Span::new(8, 14));
+
+    let Expression::BinExpr(bin_expr) = &r#let.right.node else {
+        panic!("expected a binary expression")
+    };
+    let crate::BinExpr::Add(add) = &**bin_expr else {
+        panic!("expected an addition")
+    };
+    assert_eq!(add.expressions[1].span, Span::new(12, 14));
+    assert_eq!(add.expressions[1], Spanned::from(Expression::num(23_f64)));
+}
@@ -1,30 +1,40 @@
 use std::fmt::Debug;
 
-use chumsky::{prelude::choice, text::whitespace, IterParser, Parser};
+use chumsky::{
+    prelude::{choice, just, none_of},
+    recovery::via_parser,
+    text::whitespace,
+    IterParser, Parser,
+};
 use enum_dispatch::enum_dispatch;
 
 use crate::ident::{Ident, IdentWithType};
 
 use super::{
+    control_flow::{Break, Continue, For, Loop, Return, While},
     expr::{CodeScope, Expression},
-    ident::IdentWithOptionalType,
-    parsable::{Parsable, ParsableParser},
-    r#type::TypeLiteral,
+    parsable::{Parsable, ParsableParser, ParserError},
+    pattern::Pattern,
+    r#type::{GenericParams, TypeLiteral},
+    recovery::skip_item,
+    span::Spanned,
     syntax_elements::{
-        AssignmentOp, Comma, FnKeyword, LCurly, LParen, LetKeyword, MutModifier, PubModifier,
-        RCurly, RParen, ReturnTypeOp, Semicolon, StructKeyword,
+        AssignmentOp, Colon, Comma, EnumKeyword, FnKeyword, ForKeyword, ImplKeyword, LCurly,
+        LParen, LetKeyword, MutModifier, PubModifier, RCurly, RParen, ReturnTypeOp, SelfKeyword,
+        Semicolon, StructKeyword, TraitKeyword,
     },
 };
 
 #[derive(Debug, PartialEq)]
 pub struct MaybePublic<T> {
-    is_pub: bool,
-    inner: T,
+    pub is_pub: bool,
+    pub inner: T,
 }
 
 impl<T: Parsable> Parsable for MaybePublic<T> {
     fn parser<'src>() -> impl ParsableParser<'src, Self> {
         PubModifier::parser()
+            .padded()
             .or_not()
             .then(T::parser())
             .map(|(pub_modifier, inner)| Self {
@@ -34,41 +44,59 @@ impl<T: Parsable> Parsable for MaybePublic<T> {
     }
 }
 
+/// `let (x, y): (int, int) = pair`
 #[derive(Debug, PartialEq)]
 pub struct Let {
     pub is_mut: bool,
 
-    pub left: IdentWithOptionalType,
-    pub right: Expression,
+    /// Patterns that don't match every value are rejected when lowering.
+    pub pattern: Spanned<Pattern>,
+    pub r#type: Option<Spanned<TypeLiteral>>,
+    pub right: Spanned<Expression>,
 }
 
-impl Parsable for Let {
-    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+impl Let {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression>,
+    ) -> impl ParsableParser<'src, Self> {
         LetKeyword::parser()
             .then(MutModifier::parser().padded().or_not())
-            .then(IdentWithOptionalType::parser().padded())
+            .then(Spanned::<Pattern>::parser().padded())
+            .then(
+                Colon::parser()
+                    .ignore_then(Spanned::<TypeLiteral>::parser().padded())
+                    .or_not(),
+            )
             .then_ignore(AssignmentOp::parser())
-            .then(Expression::parser())
-            .map(|(((_, mut_modifier), left), right)| Self {
+            .then(Spanned::parser_with(expression_parser))
+            .map(|((((_, mut_modifier), pattern), r#type), right)| Self {
                 is_mut: mut_modifier.is_some(),
 
-                left,
+                pattern,
+                r#type,
                 right,
             })
     }
 }
 
+impl Parsable for Let {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
+    }
+}
+
 #[test]
 fn test_let() {
-    use crate::literals::{NumLit, StringLit};
+    use crate::{ident::Path, literals::StringLit, pattern::FieldPattern};
 
     assert_eq!(
         Let::parse("let _test = 123").unwrap(),
         Let {
             is_mut: false,
 
-            left: Ident::from_str("_test").into(),
-            right: Expression::NumLit(NumLit(123_f64)),
+            pattern: Pattern::Binding(Ident::from_str("_test")).into(),
+            r#type: None,
+            right: Expression::num(123_f64).into(),
         }
     );
     assert_eq!(
@@ -76,28 +104,205 @@ fn test_let() {
         Let {
             is_mut: true,
 
-            left: IdentWithType {
-                ident: Ident::from_str("o"),
-                r#type: TypeLiteral::Ident(Ident::from_str("String")),
-            }
+            pattern: Pattern::Binding(Ident::from_str("o")).into(),
+            r#type: Some(TypeLiteral::Ident(Ident::from_str("String")).into()),
+            right: Expression::StringLit(StringLit("helloTest".to_string())).into(),
+        }
+    );
+    assert_eq!(
+        Let::parse("let (x, Point { y, .. }) = pair").unwrap(),
+        Let {
+            is_mut: false,
+
+            pattern: Pattern::Tuple(vec![
+                Pattern::Binding(Ident::from_str("x")).into(),
+                Pattern::Struct {
+                    path: Path(vec![Ident::from_str("Point").into()]).into(),
+                    fields: vec![FieldPattern {
+                        name: Ident::from_str("y").into(),
+                        pattern: Pattern::Binding(Ident::from_str("y")).into(),
+                    }
+                    .into()],
+                    has_rest: true,
+                }
+                .into(),
+            ])
             .into(),
-            right: Expression::StringLit(StringLit("helloTest".to_string())),
+            r#type: None,
+            right: Expression::variable("pair").into(),
         }
     );
 
     assert!(Let::is_err("letmut a = 321"));
-    assert!(Let::is_err("let mut 1 = 321"));
+    assert!(Let::is_err("let (a = 321"));
+    assert!(Let::is_err("let a: = 321"));
     assert!(Let::is_err("let mut a == 321"));
 }
 
+/// `target = value` or `target += value`, which changes a `mut` binding,
+/// one of its fields or an element of it.
+#[derive(Debug, PartialEq)]
+pub struct Assign {
+    /// Which expressions can be assigned to is checked when lowering.
+    pub target: Spanned<Expression>,
+    /// The operator of a compound assignment, which combines the old value with `value`.
+    pub op: Option<CompoundOp>,
+    pub value: Spanned<Expression>,
+}
+
+impl Assign {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression>,
+    ) -> impl ParsableParser<'src, Self> {
+        Spanned::parser_with(expression_parser.clone())
+            .then(Self::operator_parser(expression_parser))
+            .map(|(target, (op, value))| Self { target, op, value })
+    }
+
+    /// Parses everything after the target.
+    fn operator_parser<'src>(
+        expression_parser: impl ParsableParser<'src, Expression>,
+    ) -> impl ParsableParser<'src, (Option<CompoundOp>, Spanned<Expression>)> {
+        choice((
+            AssignmentOp::parser().to(None),
+            CompoundOp::parser().map(Some),
+        ))
+        .then(Spanned::parser_with(expression_parser))
+    }
+}
+
+/// The binary operators that can be combined with an assignment, like `+=`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CompoundOp {
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shl,
+    Shr,
+
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Parsable for CompoundOp {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        choice((
+            just("|=").to(Self::BitOr),
+            just("^=").to(Self::BitXor),
+            just("&=").to(Self::BitAnd),
+            just("<<=").to(Self::Shl),
+            just(">>=").to(Self::Shr),
+            just("+=").to(Self::Add),
+            just("-=").to(Self::Sub),
+            just("*=").to(Self::Mul),
+            just("/=").to(Self::Div),
+            just("%=").to(Self::Rem),
+        ))
+        .padded()
+    }
+}
+
+impl Parsable for Assign {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Self::parser_with(Expression::parser())
+    }
+}
+
+#[test]
+fn test_assign() {
+    use crate::expr::{FieldAccess, Index};
+
+    assert_eq!(
+        Assign::parse("point.x = 1").unwrap(),
+        Assign {
+            target: Expression::FieldAccess(Box::new(FieldAccess {
+                value: Expression::variable("point").into(),
+                field: Ident::from_str("x").into(),
+            }))
+            .into(),
+            op: None,
+            value: Expression::num(1_f64).into(),
+        }
+    );
+    assert_eq!(
+        Assign::parse("xs[i] <<= 2").unwrap(),
+        Assign {
+            target: Expression::Index(Box::new(Index {
+                value: Expression::variable("xs").into(),
+                index: Expression::variable("i").into(),
+            }))
+            .into(),
+            op: Some(CompoundOp::Shl),
+            value: Expression::num(2_f64).into(),
+        }
+    );
+    assert_eq!(Assign::parse("x -= -1").unwrap().op, Some(CompoundOp::Sub));
+    assert!(Assign::is_err("point.x == 1"));
+    assert!(Assign::is_err("x + = 1"));
+    assert!(Assign::is_err("point.x ="));
+}
+
+/// The `self` or `mut self` in front of the parameters of a method.
+#[derive(Debug, PartialEq)]
+pub struct Receiver {
+    pub is_mut: bool,
+}
+
+impl Parsable for Receiver {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        MutModifier::parser()
+            .then_ignore(whitespace())
+            .or_not()
+            .then_ignore(SelfKeyword::parser())
+            .map(|mut_modifier| Self {
+                is_mut: mut_modifier.is_some(),
+            })
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct FnDef {
-    name: Ident,
+    pub name: Spanned<Ident>,
+    pub generics: GenericParams,
+
+    /// Only functions inside of an [`ImplBlock`] can take `self`, which is checked when lowering.
+    pub receiver: Option<Spanned<Receiver>>,
+    pub params: Vec<Spanned<IdentWithType>>,
+    pub return_type: Spanned<TypeLiteral>,
 
-    params: Vec<IdentWithType>,
-    return_type: TypeLiteral,
+    pub body: Spanned<CodeScope>,
+}
 
-    body: CodeScope,
+/// The receiver, parameters and return type of a function, from `(` up to the body.
+fn signature_parser<'src>() -> impl ParsableParser<
+    'src,
+    (
+        Option<Spanned<Receiver>>,
+        Vec<Spanned<IdentWithType>>,
+        Spanned<TypeLiteral>,
+    ),
+> {
+    LParen::parser()
+        .ignore_then(
+            Spanned::<Receiver>::parser()
+                .then_ignore(choice((
+                    Comma::parser().ignored(),
+                    RParen::parser().rewind().ignored(),
+                )))
+                .or_not(),
+        )
+        .then(
+            Spanned::<IdentWithType>::parser()
+                .separated_by(Comma::parser())
+                .collect(),
+        )
+        .then_ignore(RParen::parser())
+        .then_ignore(ReturnTypeOp::parser().padded())
+        .then(Spanned::<TypeLiteral>::parser())
+        .map(|((receiver, params), return_type)| (receiver, params, return_type))
 }
 
 impl Parsable for FnDef {
@@ -105,25 +310,22 @@ impl Parsable for FnDef {
         FnKeyword::parser()
             .ignored()
             .then_ignore(whitespace())
-            .ignore_then(Ident::parser())
-            .then_ignore(LParen::parser())
-            .then(
-                IdentWithType::parser()
-                    .separated_by(Comma::parser())
-                    .collect(),
+            .ignore_then(Spanned::<Ident>::parser())
+            .then(GenericParams::parser().or_not())
+            .then(signature_parser())
+            .then(Spanned::<CodeScope>::parser())
+            .map(
+                |(((name, generics), (receiver, params, return_type)), body)| Self {
+                    name,
+                    generics: generics.unwrap_or_default(),
+
+                    receiver,
+                    params,
+                    return_type,
+
+                    body,
+                },
             )
-            .then_ignore(RParen::parser())
-            .then_ignore(ReturnTypeOp::parser().padded())
-            .then(TypeLiteral::parser())
-            .then(CodeScope::parser())
-            .map(|(((name, params), return_type), body)| Self {
-                name,
-
-                params,
-                return_type,
-
-                body,
-            })
     }
 }
 
@@ -134,41 +336,69 @@ fn test_fn() {
     assert_eq!(
         FnDef::parse("fn basic_test_fn(arg1: int) -> String { \"test\" }").unwrap(),
         FnDef {
-            name: Ident::from_str("basic_test_fn"),
+            name: Ident::from_str("basic_test_fn").into(),
+            generics: GenericParams::default(),
 
+            receiver: None,
             params: vec![IdentWithType {
-                ident: Ident::from_str("arg1"),
-                r#type: TypeLiteral::Ident(Ident::from_str("int")),
-            }],
-            return_type: TypeLiteral::Ident(Ident::from_str("String")),
-
-            body: CodeScope {
-                statements: Vec::new(),
-                expr: Expression::StringLit(StringLit("test".to_owned()))
+                ident: Ident::from_str("arg1").into(),
+                r#type: TypeLiteral::Ident(Ident::from_str("int")).into(),
             }
+            .into()],
+            return_type: TypeLiteral::Ident(Ident::from_str("String")).into(),
+
+            body: CodeScope::of(Expression::StringLit(StringLit("test".to_owned()))).into()
         }
-    )
+    );
+
+    let method = FnDef::parse("fn len(mut self) -> int { 1 }").unwrap();
+    assert_eq!(method.receiver, Some(Receiver { is_mut: true }.into()));
+    assert!(method.params.is_empty());
+    let method = FnDef::parse("fn scale(self, by: int) -> int { by }").unwrap();
+    assert_eq!(method.receiver, Some(Receiver { is_mut: false }.into()));
+    assert_eq!(method.params.len(), 1);
+
+    let generic = FnDef::parse("fn id<T>(x: T) -> T { x }").unwrap();
+    assert_eq!(generic.generics.0[0].name, Ident::from_str("T").into());
+    assert_eq!(
+        generic.return_type.node,
+        TypeLiteral::Ident(Ident::from_str("T"))
+    );
+
+    assert!(FnDef::is_err("fn scale(by: int, self) -> int { by }"));
+    assert!(FnDef::is_err("fn len(selfish) -> int { 1 }"));
 }
 
 #[derive(Debug, PartialEq)]
 pub struct StructDef {
-    name: Ident,
-    fields: Vec<IdentWithType>,
+    pub name: Spanned<Ident>,
+    pub generics: GenericParams,
+    pub fields: Vec<Spanned<IdentWithType>>,
 }
 
 impl Parsable for StructDef {
     fn parser<'src>() -> impl ParsableParser<'src, Self> {
         StructKeyword::parser()
-            .ignore_then(Ident::parser().padded())
+            .ignore_then(Spanned::<Ident>::parser().padded())
+            .then(GenericParams::parser().or_not())
             .then_ignore(LCurly::parser())
             .then(
-                IdentWithType::parser()
+                Spanned::<IdentWithType>::parser()
+                    .map(Some)
+                    // skip a broken field up to the next one
+                    .recover_with(via_parser(
+                        none_of(",;{}").repeated().at_least(1).map(|()| None),
+                    ))
                     .separated_by(Comma::parser())
                     .allow_trailing()
-                    .collect(),
+                    .collect::<Vec<_>>(),
             )
             .then_ignore(RCurly::parser())
-            .map(|(name, fields)| Self { name, fields })
+            .map(|((name, generics), fields)| Self {
+                name,
+                generics: generics.unwrap_or_default(),
+                fields: fields.into_iter().flatten().collect(),
+            })
     }
 }
 
@@ -177,19 +407,300 @@ fn test_struct_def() {
     assert_eq!(
         StructDef::parse("struct SimpleTest { a: int, b: String, }").unwrap(),
         StructDef {
-            name: Ident::from_str("SimpleTest"),
+            name: Ident::from_str("SimpleTest").into(),
+            generics: GenericParams::default(),
             fields: vec![
                 IdentWithType {
-                    ident: Ident::from_str("a"),
-                    r#type: TypeLiteral::Ident(Ident::from_str("int")),
-                },
+                    ident: Ident::from_str("a").into(),
+                    r#type: TypeLiteral::Ident(Ident::from_str("int")).into(),
+                }
+                .into(),
                 IdentWithType {
-                    ident: Ident::from_str("b"),
-                    r#type: TypeLiteral::Ident(Ident::from_str("String")),
+                    ident: Ident::from_str("b").into(),
+                    r#type: TypeLiteral::Ident(Ident::from_str("String")).into(),
                 }
+                .into()
             ]
         }
-    )
+    );
+
+    let generic = StructDef::parse("struct Pair<A, B> { a: A, b: B }").unwrap();
+    assert_eq!(generic.generics.0.len(), 2);
+    assert_eq!(generic.fields.len(), 2);
+}
+
+/// The data a single enum variant carries.
+#[derive(Debug, PartialEq)]
+pub enum VariantFields {
+    /// `Empty`
+    Unit,
+    /// `Circle(int)`
+    Tuple(Vec<Spanned<TypeLiteral>>),
+    /// `Rect { width: int, height: int }`
+    Struct(Vec<Spanned<IdentWithType>>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VariantDef {
+    pub name: Spanned<Ident>,
+    pub fields: VariantFields,
+}
+
+impl Parsable for VariantDef {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        let tuple_parser = Spanned::<TypeLiteral>::parser()
+            .separated_by(Comma::parser())
+            .allow_trailing()
+            .collect()
+            .delimited_by(LParen::parser(), RParen::parser())
+            .map(VariantFields::Tuple);
+
+        let struct_parser = Spanned::<IdentWithType>::parser()
+            .separated_by(Comma::parser())
+            .allow_trailing()
+            .collect()
+            .delimited_by(LCurly::parser(), RCurly::parser())
+            .map(VariantFields::Struct);
+
+        Spanned::<Ident>::parser()
+            .then(
+                choice((tuple_parser, struct_parser))
+                    .or_not()
+                    .map(|fields| fields.unwrap_or(VariantFields::Unit)),
+            )
+            .map(|(name, fields)| Self { name, fields })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EnumDef {
+    pub name: Spanned<Ident>,
+    pub variants: Vec<Spanned<VariantDef>>,
+}
+
+impl Parsable for EnumDef {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        EnumKeyword::parser()
+            .ignore_then(Spanned::<Ident>::parser().padded())
+            .then_ignore(LCurly::parser())
+            .then(
+                Spanned::<VariantDef>::parser()
+                    .padded()
+                    .map(Some)
+                    // skip a broken variant up to the next one
+                    .recover_with(via_parser(
+                        none_of(",;{}").repeated().at_least(1).map(|()| None),
+                    ))
+                    .separated_by(Comma::parser())
+                    .allow_trailing()
+                    .collect::<Vec<_>>(),
+            )
+            .then_ignore(RCurly::parser())
+            .map(|(name, variants)| Self {
+                name,
+                variants: variants.into_iter().flatten().collect(),
+            })
+    }
+}
+
+#[test]
+fn test_enum_def() {
+    let int = || TypeLiteral::Ident(Ident::from_str("int"));
+
+    assert_eq!(
+        EnumDef::parse("enum Shape { Empty, Circle(int), Rect { width: int, height: int }, }")
+            .unwrap(),
+        EnumDef {
+            name: Ident::from_str("Shape").into(),
+            variants: vec![
+                VariantDef {
+                    name: Ident::from_str("Empty").into(),
+                    fields: VariantFields::Unit,
+                }
+                .into(),
+                VariantDef {
+                    name: Ident::from_str("Circle").into(),
+                    fields: VariantFields::Tuple(vec![int().into()]),
+                }
+                .into(),
+                VariantDef {
+                    name: Ident::from_str("Rect").into(),
+                    fields: VariantFields::Struct(vec![
+                        IdentWithType {
+                            ident: Ident::from_str("width").into(),
+                            r#type: int().into(),
+                        }
+                        .into(),
+                        IdentWithType {
+                            ident: Ident::from_str("height").into(),
+                            r#type: int().into(),
+                        }
+                        .into()
+                    ]),
+                }
+                .into()
+            ]
+        }
+    );
+    assert_eq!(
+        EnumDef::parse("enum Never {}").unwrap(),
+        EnumDef {
+            name: Ident::from_str("Never").into(),
+            variants: Vec::new(),
+        }
+    );
+
+    assert!(EnumDef::is_err("enum Shape { Circle(int }"));
+    assert!(EnumDef::is_err("enum { Circle }"));
+}
+
+/// A function of a [`TraitDef`], which only has a signature.
+#[derive(Debug, PartialEq)]
+pub struct TraitFn {
+    pub name: Spanned<Ident>,
+    pub receiver: Option<Spanned<Receiver>>,
+    pub params: Vec<Spanned<IdentWithType>>,
+    pub return_type: Spanned<TypeLiteral>,
+}
+
+impl Parsable for TraitFn {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        FnKeyword::parser()
+            .ignored()
+            .then_ignore(whitespace())
+            .ignore_then(Spanned::<Ident>::parser())
+            .then(signature_parser())
+            .then_ignore(Semicolon::parser())
+            .map(|(name, (receiver, params, return_type))| Self {
+                name,
+                receiver,
+                params,
+                return_type,
+            })
+    }
+}
+
+/// `trait Show { fn show(self) -> String; }`, the functions a type has to provide to implement it.
+#[derive(Debug, PartialEq)]
+pub struct TraitDef {
+    pub name: Spanned<Ident>,
+    pub fns: Vec<Spanned<TraitFn>>,
+}
+
+impl Parsable for TraitDef {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        TraitKeyword::parser()
+            .ignore_then(Spanned::<Ident>::parser().padded())
+            .then_ignore(LCurly::parser())
+            .then(
+                Spanned::<TraitFn>::parser()
+                    .map(Some)
+                    // a broken function is left out, the ones after it are still parsed
+                    .recover_with(via_parser(skip_item().map(|()| None)))
+                    .padded()
+                    .repeated()
+                    .collect::<Vec<_>>(),
+            )
+            .then_ignore(RCurly::parser())
+            .map(|(name, fns)| Self {
+                name,
+                fns: fns.into_iter().flatten().collect(),
+            })
+    }
+}
+
+#[test]
+fn test_trait_def() {
+    let def =
+        TraitDef::parse("trait Shape { fn area(self) -> int; fn name() -> String; }").unwrap();
+    assert_eq!(def.name.node, Ident::from_str("Shape"));
+    assert_eq!(def.fns.len(), 2);
+    assert_eq!(def.fns[0].receiver, Some(Receiver { is_mut: false }.into()));
+    assert_eq!(def.fns[1].name.node, Ident::from_str("name"));
+    assert_eq!(def.fns[1].receiver, None);
+
+    assert_eq!(TraitDef::parse("trait Marker {}").unwrap().fns, Vec::new());
+
+    assert!(TraitDef::is_err("trait Shape { fn area(self) -> int }"));
+    assert!(TraitDef::is_err(
+        "trait Shape { fn area(self) -> int { 1 } }"
+    ));
+    assert!(TraitDef::is_err("trait { fn area(self) -> int; }"));
+}
+
+/// `impl Point { ... }`, which defines functions that belong to a struct or an enum.
+///
+/// With `impl Show for Point { ... }` they implement the trait `Show`.
+#[derive(Debug, PartialEq)]
+pub struct ImplBlock {
+    pub r#trait: Option<Spanned<Ident>>,
+    pub r#type: Spanned<Ident>,
+    pub fns: Vec<Spanned<MaybePublic<FnDef>>>,
+}
+
+impl Parsable for ImplBlock {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        ImplKeyword::parser()
+            .ignore_then(Spanned::<Ident>::parser().padded())
+            .then(
+                ForKeyword::parser()
+                    .ignore_then(Spanned::<Ident>::parser().padded())
+                    .or_not(),
+            )
+            .then_ignore(LCurly::parser())
+            .then(
+                Spanned::<MaybePublic<FnDef>>::parser()
+                    .map(Some)
+                    // a broken function is left out, the ones after it are still parsed
+                    .recover_with(via_parser(skip_item().map(|()| None)))
+                    .padded()
+                    .repeated()
+                    .collect::<Vec<_>>(),
+            )
+            .then_ignore(RCurly::parser())
+            .map(|((name, r#type), fns)| {
+                // the first name is the trait if a type follows it
+                let (r#trait, r#type) = match r#type {
+                    Some(r#type) => (Some(name), r#type),
+                    None => (None, name),
+                };
+                Self {
+                    r#trait,
+                    r#type,
+                    fns: fns.into_iter().flatten().collect(),
+                }
+            })
+    }
+}
+
+#[test]
+fn test_impl_block() {
+    let block =
+        ImplBlock::parse("impl Point { fn new() -> Point { a } pub fn len(self) -> int { 1 } }")
+            .unwrap();
+    assert_eq!(block.r#trait, None);
+    assert_eq!(block.r#type.node, Ident::from_str("Point"));
+    assert_eq!(block.fns.len(), 2);
+    assert!(!block.fns[0].is_pub);
+    assert!(block.fns[1].is_pub);
+    assert_eq!(block.fns[1].inner.name.node, Ident::from_str("len"));
+
+    assert_eq!(ImplBlock::parse("impl Never {}").unwrap().fns, Vec::new());
+
+    let block = ImplBlock::parse("impl Show for Point { fn show(self) -> String { s } }").unwrap();
+    assert_eq!(block.r#trait, Some(Ident::from_str("Show").into()));
+    assert_eq!(block.r#type.node, Ident::from_str("Point"));
+    assert_eq!(block.fns.len(), 1);
+
+    let (block, errors) =
+        ImplBlock::parse("impl Point { fn broken( -> int { 1 } fn ok() -> int { 2 } }")
+            .into_output_errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(block.unwrap().fns.len(), 1);
+
+    assert!(ImplBlock::is_err("impl { fn a() -> int { 1 } }"));
+    assert!(ImplBlock::is_err("impl Point { let a = 1; }"));
+    assert!(ImplBlock::is_err("impl Show for { fn a() -> int { 1 } }"));
 }
 
 // TODO test
@@ -202,6 +713,9 @@ pub enum RawTopLevelStatement {
     Let,
     FnDef,
     StructDef,
+    EnumDef,
+    TraitDef,
+    ImplBlock,
 }
 
 impl Parsable for RawTopLevelStatement {
@@ -212,25 +726,93 @@ impl Parsable for RawTopLevelStatement {
                 .map(Self::Let),
             FnDef::parser().map(Self::FnDef),
             StructDef::parser().map(Self::StructDef),
+            EnumDef::parser().map(Self::EnumDef),
+            TraitDef::parser().map(Self::TraitDef),
+            ImplBlock::parser().map(Self::ImplBlock),
         ))
-        .padded()
     }
 }
 
 /// Something that cannot return a value.
 ///
-/// Always delimited with a semicolon.
+/// Delimited with a semicolon, which is optional after expressions that end with a block.
 #[enum_dispatch]
 #[derive(Debug, PartialEq)]
 pub enum Statement {
     Let,
+    Assign,
+
+    Loop,
+    While,
+    For,
+    Break,
+    Continue,
+    Return,
+    /// An expression that is only evaluated for its side effects, like `f();`.
+    Expression,
+}
+
+/// What follows the expression at the start of a statement.
+enum StatementEnd {
+    Assign(Option<CompoundOp>, Spanned<Expression>),
+    Semicolon,
+    /// Only allowed after an expression that ends with a block.
+    Nothing,
+}
+
+impl Statement {
+    pub fn parser_with<'src>(
+        expression_parser: impl ParsableParser<'src, Expression> + 'src,
+    ) -> impl ParsableParser<'src, Self> {
+        // the target of an assignment is an expression, so both are parsed together
+        let expression_statement = Spanned::parser_with(expression_parser.clone())
+            .then(choice((
+                Assign::operator_parser(expression_parser.clone())
+                    .then_ignore(Semicolon::parser())
+                    .map(|(op, value)| StatementEnd::Assign(op, value)),
+                Semicolon::parser().map(|_| StatementEnd::Semicolon),
+                // an expression at the end of a scope is its value
+                RCurly::parser().not().map(|()| StatementEnd::Nothing),
+            )))
+            .try_map(|(expression, end), span| match end {
+                StatementEnd::Assign(op, value) => Ok(Self::Assign(Assign {
+                    target: expression,
+                    op,
+                    value,
+                })),
+                StatementEnd::Semicolon => Ok(Self::from_expression(expression.node)),
+                StatementEnd::Nothing if expression.node.is_block_like() => {
+                    Ok(Self::from_expression(expression.node))
+                }
+                StatementEnd::Nothing => Err(ParserError::custom(span, "expected `;`")),
+            });
+
+        choice((
+            Let::parser_with(expression_parser)
+                .then_ignore(Semicolon::parser())
+                .map(Self::Let),
+            // errors inside of a `let` are reported there, instead of where the expression failed
+            LetKeyword::parser().not().ignore_then(expression_statement),
+        ))
+    }
+
+    /// Statements that are also expressions keep their own variant.
+    fn from_expression(expression: Expression) -> Self {
+        match expression {
+            Expression::Loop(r#loop) => Self::Loop(*r#loop),
+            Expression::While(r#while) => Self::While(*r#while),
+            Expression::For(r#for) => Self::For(*r#for),
+            Expression::Break(r#break) => Self::Break(*r#break),
+            Expression::Continue(r#continue) => Self::Continue(r#continue),
+            Expression::Return(r#return) => Self::Return(*r#return),
+            expression => Self::Expression(expression),
+        }
+    }
 }
 
 impl Parsable for Statement {
     fn parser<'src>() -> impl ParsableParser<'src, Self> {
-        Let::parser()
-            .then_ignore(Semicolon::parser())
-            .map(Self::Let)
+        Self::parser_with(Expression::parser())
     }
 }
 
@@ -242,10 +824,81 @@ fn test_statement() {
         Statement::parse("let var = \"simple_let\";").unwrap(),
         Statement::Let(Let {
             is_mut: false,
-            left: Ident::from_str("var").into(),
-            right: Expression::StringLit(StringLit("simple_let".to_owned()))
+            pattern: Pattern::Binding(Ident::from_str("var")).into(),
+            r#type: None,
+            right: Expression::StringLit(StringLit("simple_let".to_owned())).into()
         })
     );
 
+    let scope =
+        CodeScope::parse("{ while a { break } for i in 1..2 { i } 'l: loop { break 'l 1 } }")
+            .unwrap();
+    assert!(matches!(
+        scope.statements[..],
+        [
+            Spanned {
+                node: Statement::While(_),
+                ..
+            },
+            Spanned {
+                node: Statement::For(_),
+                ..
+            }
+        ]
+    ));
+    assert!(matches!(
+        scope.expr.map(|expr| expr.node),
+        Some(Expression::Loop(_))
+    ));
+
+    let scope = CodeScope::parse("{ f(); if a { b() } x += 1; return 2; }").unwrap();
+    assert!(matches!(
+        scope.statements[..],
+        [
+            Spanned {
+                node: Statement::Expression(Expression::FnCall(_)),
+                ..
+            },
+            Spanned {
+                node: Statement::Expression(Expression::If(_)),
+                ..
+            },
+            Spanned {
+                node: Statement::Assign(_),
+                ..
+            },
+            Spanned {
+                node: Statement::Return(_),
+                ..
+            }
+        ]
+    ));
+    // a block at the end of a scope is its value
+    let scope = CodeScope::parse("{ f(); if a { b() } }").unwrap();
+    assert_eq!(scope.statements.len(), 1);
+    assert!(matches!(
+        scope.expr.map(|expr| expr.node),
+        Some(Expression::If(_))
+    ));
+    assert!(matches!(
+        Statement::parse("break 'outer;").into_output(),
+        Some(Statement::Break(_))
+    ));
+
     assert!(Statement::is_err("let missing_semicolon = 1"));
+    assert!(Statement::is_err("continue"));
+    assert!(Statement::is_err("f() g();"));
+
+    // keywords aren't variables
+    assert!(CodeScope::is_err("{ let = 3; 1 }"));
+    let errors = Statement::parse("let x = ;").into_errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span().into_range(), 8..9);
+    for keyword in [
+        "return", "break", "continue", "loop", "while", "for", "match", "if",
+    ] {
+        assert!(CodeScope::is_err(&format!(
+            "{{ let {keyword} = 1; print({keyword}); }}"
+        )));
+    }
 }
@@ -1,8 +1,14 @@
-use chumsky::{prelude::just, text::keyword, Parser};
+use chumsky::{
+    error::Error,
+    prelude::{any, just},
+    util::MaybeRef,
+    Parser,
+};
 
 use super::{
-    bin_ops::{BinExpr, GenericBinOp, HasPrecedence, Precedence},
-    parsable::{Parsable, ParsableParser},
+    bin_ops::{Associativity, BinExpr, GenericBinOp, HasPrecedence, Precedence},
+    parsable::{Parsable, ParsableParser, ParserError, ParserInput},
+    unary_ops::{GenericUnaryOp, UnaryExpr},
 };
 
 macro_rules! generate_parsable {
@@ -18,9 +24,24 @@ macro_rules! generate_parsable {
     };
 }
 
+// `text::keyword` would hide errors that occurred further into the input
+// when it's used as an alternative, so the keyword is matched manually.
+// The whole word is compared, so that a word like `fn` fails at its start
+// with the keyword as the expected token, instead of in the middle of `for`.
 macro_rules! generate_keyword_parsable {
     ($ident:ident, $str_repr:literal) => {
-        generate_parsable! {$ident, keyword($str_repr)}
+        generate_parsable! {$ident, any()
+        .then(any().filter(|c: &char| c.is_ascii_alphanumeric() || *c == '_').repeated())
+        .to_slice()
+        .try_map(|word: &str, span| match word == $str_repr {
+            true => Ok(()),
+            false => Err(<ParserError as Error<ParserInput>>::expected_found(
+                [],
+                word.chars().next().map(MaybeRef::Val),
+                span,
+            )),
+        })
+        .labelled(concat!("`", $str_repr, "`"))}
     };
 }
 
@@ -38,6 +59,9 @@ use super::expr::Expression;
 
 macro_rules! generate_binary_operator_parsable {
     ($op_name:ident, $expr_name:ident, $enum_variant:ident, $precedence:literal, $str_repr:literal) => {
+        generate_binary_operator_parsable! {$op_name, $expr_name, $enum_variant, $precedence, $str_repr, Left}
+    };
+    ($op_name:ident, $expr_name:ident, $enum_variant:ident, $precedence:literal, $str_repr:literal, $associativity:ident) => {
         pub type $expr_name = GenericBinOp<$op_name>;
         generate_operator_parsable! {$op_name, $str_repr, padded}
 
@@ -48,39 +72,110 @@ macro_rules! generate_binary_operator_parsable {
 
             #[cfg(test)]
             pub fn as_expr(expressions: [Expression; 2]) -> Expression {
-                Expression::BinExpr(Box::new(Self::into_bin_expr(Self::new(expressions))))
+                Expression::BinExpr(Box::new(Self::into_bin_expr(Self::new(
+                    expressions.map(Into::into),
+                ))))
             }
         }
 
         impl HasPrecedence for $op_name {
             const PRECEDENCE: Precedence = $precedence;
+            const ASSOCIATIVITY: Associativity = Associativity::$associativity;
+        }
+    };
+}
+
+macro_rules! generate_unary_operator_parsable {
+    ($op_name:ident, $expr_name:ident, $enum_variant:ident, $str_repr:literal) => {
+        pub type $expr_name = GenericUnaryOp<$op_name>;
+        generate_operator_parsable! {$op_name, $str_repr, padded}
+
+        impl $expr_name {
+            pub fn into_unary_expr(self) -> UnaryExpr {
+                UnaryExpr::$enum_variant(self)
+            }
+
+            #[cfg(test)]
+            pub fn as_expr(expression: Expression) -> Expression {
+                Expression::UnaryExpr(Box::new(Self::into_unary_expr(Self::new(
+                    expression.into(),
+                ))))
+            }
         }
     };
 }
 
-generate_operator_parsable! {MacroCallOp, '!'}
 generate_operator_parsable! {AssignmentOp, '=', padded}
 generate_operator_parsable! {ReturnTypeOp, "->", padded}
 
 generate_operator_parsable! {Dot, '.'}
+generate_operator_parsable! {Colon, ':', padded}
+generate_operator_parsable! {PathSeparator, "::", padded}
+generate_operator_parsable! {Apostrophe, '\''}
 generate_operator_parsable! {Comma, ',', padded}
 generate_operator_parsable! {Semicolon, ';', padded}
-
-// Precedence needs to start at 1, because 0 can parse all Expressions.
-generate_binary_operator_parsable! {EqualsOp, EqualsExpr, Equals, 1, "=="}
-generate_binary_operator_parsable! {AddOp, AddExpr, Add, 2, '+'}
-generate_binary_operator_parsable! {SubOp, SubExpr, Sub, 2, '-'}
-generate_binary_operator_parsable! {MulOp, MulExpr, Mul, 3, '*'}
-generate_binary_operator_parsable! {DivOp, DivExpr, Div, 3, '/'}
+generate_operator_parsable! {RangeOp, "..", padded}
+generate_operator_parsable! {RangeInclusiveOp, "..=", padded}
+generate_operator_parsable! {FatArrow, "=>", padded}
+generate_operator_parsable! {Pipe, '|', padded}
+generate_operator_parsable! {LAngle, '<', padded}
+generate_operator_parsable! {RAngle, '>', padded}
+
+// Operators with a higher precedence bind tighter.
+// Within a precedence level, operators that are a prefix of another one come last.
+generate_binary_operator_parsable! {OrOp, OrExpr, Or, 1, "||"}
+generate_binary_operator_parsable! {AndOp, AndExpr, And, 2, "&&"}
+
+generate_binary_operator_parsable! {EqualsOp, EqualsExpr, Equals, 3, "==", NonAssociative}
+generate_binary_operator_parsable! {NotEqualsOp, NotEqualsExpr, NotEquals, 3, "!=", NonAssociative}
+generate_binary_operator_parsable! {LessEqualsOp, LessEqualsExpr, LessEquals, 3, "<=", NonAssociative}
+generate_binary_operator_parsable! {LessOp, LessExpr, Less, 3, '<', NonAssociative}
+generate_binary_operator_parsable! {GreaterEqualsOp, GreaterEqualsExpr, GreaterEquals, 3, ">=", NonAssociative}
+generate_binary_operator_parsable! {GreaterOp, GreaterExpr, Greater, 3, '>', NonAssociative}
+
+generate_binary_operator_parsable! {BitOrOp, BitOrExpr, BitOr, 4, '|'}
+generate_binary_operator_parsable! {BitXorOp, BitXorExpr, BitXor, 5, '^'}
+generate_binary_operator_parsable! {BitAndOp, BitAndExpr, BitAnd, 6, '&'}
+generate_binary_operator_parsable! {ShlOp, ShlExpr, Shl, 7, "<<"}
+generate_binary_operator_parsable! {ShrOp, ShrExpr, Shr, 7, ">>"}
+
+generate_binary_operator_parsable! {AddOp, AddExpr, Add, 8, '+'}
+generate_binary_operator_parsable! {SubOp, SubExpr, Sub, 8, '-'}
+generate_binary_operator_parsable! {MulOp, MulExpr, Mul, 9, '*'}
+generate_binary_operator_parsable! {DivOp, DivExpr, Div, 9, '/'}
+generate_binary_operator_parsable! {RemOp, RemExpr, Rem, 9, '%'}
+
+// Prefix operators bind tighter than all binary operators.
+generate_unary_operator_parsable! {NegOp, NegExpr, Neg, '-'}
+generate_unary_operator_parsable! {NotOp, NotExpr, Not, '!'}
 
 generate_operator_parsable! {LCurly, '{', padded}
 generate_operator_parsable! {RCurly, '}', padded}
 generate_operator_parsable! {LParen, '(', padded}
 generate_operator_parsable! {RParen, ')', padded}
+generate_operator_parsable! {LBracket, '[', padded}
+generate_operator_parsable! {RBracket, ']', padded}
 
 generate_keyword_parsable! {MutModifier, "mut"}
 generate_keyword_parsable! {PubModifier, "pub"}
 
 generate_keyword_parsable! {LetKeyword, "let"}
 generate_keyword_parsable! {StructKeyword, "struct"}
+generate_keyword_parsable! {EnumKeyword, "enum"}
 generate_keyword_parsable! {FnKeyword, "fn"}
+generate_keyword_parsable! {ImplKeyword, "impl"}
+generate_keyword_parsable! {SelfKeyword, "self"}
+generate_keyword_parsable! {TraitKeyword, "trait"}
+generate_keyword_parsable! {DynKeyword, "dyn"}
+
+generate_keyword_parsable! {IfKeyword, "if"}
+generate_keyword_parsable! {ElseKeyword, "else"}
+generate_keyword_parsable! {LoopKeyword, "loop"}
+generate_keyword_parsable! {WhileKeyword, "while"}
+generate_keyword_parsable! {ForKeyword, "for"}
+generate_keyword_parsable! {InKeyword, "in"}
+generate_keyword_parsable! {BreakKeyword, "break"}
+generate_keyword_parsable! {ContinueKeyword, "continue"}
+generate_keyword_parsable! {ReturnKeyword, "return"}
+generate_keyword_parsable! {MatchKeyword, "match"}
+generate_keyword_parsable! {Underscore, "_"}
@@ -6,14 +6,18 @@ use chumsky::{
 use super::{
     ident::Ident,
     parsable::ParsableParser,
-    syntax_elements::{Comma, FnKeyword, LParen, RParen, ReturnTypeOp},
+    span::Spanned,
+    syntax_elements::{
+        AddOp, Colon, Comma, DynKeyword, FnKeyword, LAngle, LBracket, LParen, RAngle, RBracket,
+        RParen, ReturnTypeOp,
+    },
     Parsable,
 };
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct FnSignatureType {
-    params: Vec<TypeLiteral>,
-    return_type: TypeLiteral,
+    pub params: Vec<Spanned<TypeLiteral>>,
+    pub return_type: Spanned<TypeLiteral>,
 }
 
 impl FnSignatureType {
@@ -23,10 +27,16 @@ impl FnSignatureType {
         FnKeyword::parser()
             .ignored()
             .then(TupleType::parser_with(type_parser.clone()))
-            .then(ReturnTypeOp::parser().ignore_then(type_parser).or_not())
-            .map(|((_, params), return_type)| Self {
+            .then(
+                ReturnTypeOp::parser()
+                    .ignore_then(Spanned::parser_with(type_parser))
+                    .or_not(),
+            )
+            .map_with(|((_, params), return_type), extra| Self {
                 params: params.0,
-                return_type: return_type.unwrap_or_default(),
+                // a missing return type is attributed to the whole signature
+                return_type: return_type
+                    .unwrap_or_else(|| Spanned::new(TypeLiteral::default(), extra.span())),
             })
     }
 }
@@ -43,13 +53,13 @@ fn test_fn_signature_type() {
         FnSignatureType::parse("fn()").unwrap(),
         FnSignatureType {
             params: Vec::new(),
-            return_type: TypeLiteral::Tuple(TupleType::UNIT),
+            return_type: TypeLiteral::Tuple(TupleType::UNIT).into(),
         }
     )
 }
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct TupleType(pub Vec<TypeLiteral>);
+pub struct TupleType(pub Vec<Spanned<TypeLiteral>>);
 
 impl TupleType {
     pub const UNIT: Self = Self(Vec::new());
@@ -59,7 +69,7 @@ impl TupleType {
     ) -> impl ParsableParser<'src, Self> {
         LParen::parser()
             .ignore_then(
-                type_parser
+                Spanned::parser_with(type_parser)
                     .separated_by(Comma::parser())
                     .allow_trailing()
                     .collect(),
@@ -75,8 +85,9 @@ fn test_tuple_type() {
     assert_eq!(
         TupleType::parse("((()))").unwrap(),
         TupleType(vec![TypeLiteral::Tuple(TupleType(vec![
-            TypeLiteral::Tuple(TupleType::UNIT)
-        ]))])
+            TypeLiteral::Tuple(TupleType::UNIT).into()
+        ]))
+        .into()])
     );
 
     assert!(TupleType::is_err("(,)"))
@@ -88,14 +99,90 @@ impl Parsable for TupleType {
     }
 }
 
+/// A generic parameter with the traits its arguments have to implement, like `T: Show + Eq`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GenericParam {
+    pub name: Spanned<Ident>,
+    pub bounds: Vec<Spanned<Ident>>,
+}
+
+impl Parsable for GenericParam {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Spanned::<Ident>::parser()
+            .then(
+                Colon::parser()
+                    .ignore_then(
+                        Spanned::<Ident>::parser()
+                            .separated_by(AddOp::parser())
+                            .at_least(1)
+                            .collect(),
+                    )
+                    .or_not(),
+            )
+            .map(|(name, bounds)| Self {
+                name,
+                bounds: bounds.unwrap_or_default(),
+            })
+    }
+}
+
+/// The generic parameters of a `fn` or `struct`, like `<A, B: Show>`.
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct GenericParams(pub Vec<GenericParam>);
+
+impl Parsable for GenericParams {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        GenericParam::parser()
+            .separated_by(Comma::parser())
+            .allow_trailing()
+            .at_least(1)
+            .collect()
+            .delimited_by(LAngle::parser(), RAngle::parser())
+            .map(Self)
+    }
+}
+
+#[test]
+fn test_generic_params() {
+    let generics = GenericParams::parse("<A, B: Show + Eq,>").unwrap();
+    assert_eq!(
+        generics
+            .0
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect::<Vec<_>>(),
+        ["A", "B"]
+    );
+    assert!(generics.0[0].bounds.is_empty());
+    assert_eq!(
+        generics.0[1].bounds,
+        [Ident::from_str("Show").into(), Ident::from_str("Eq").into()]
+    );
+
+    assert!(GenericParams::is_err("<>"));
+    assert!(GenericParams::is_err("<A B>"));
+    assert!(GenericParams::is_err("<A:>"));
+    assert!(GenericParams::is_err("<A: Show +>"));
+}
+
+/// A generic struct with its arguments, like `Pair<int, String>`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AppliedType {
+    pub name: Spanned<Ident>,
+    pub args: Vec<Spanned<TypeLiteral>>,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum TypeLiteral {
     /// Either refers to a struct or to a generic parameter
     Ident(Ident),
+    Applied(Box<AppliedType>),
     Fn(Box<FnSignatureType>),
     Tuple(TupleType),
-    // TODO array & tuple literals // recursive type. This can indirectly have generics
-    // TODO trait obj // this can also have generics
+    /// `[T]`, an array with any amount of elements.
+    Array(Box<Spanned<TypeLiteral>>),
+    /// `dyn Show`, a value of any type that implements the trait.
+    Dyn(Spanned<Ident>),
 }
 
 impl Default for TypeLiteral {
@@ -109,10 +196,30 @@ impl Parsable for TypeLiteral {
         recursive(|type_parser| {
             choice((
                 FnSignatureType::parser_with(type_parser.clone()).map(|t| Self::Fn(Box::new(t))),
-                TupleType::parser_with(type_parser).map(Self::Tuple),
+                TupleType::parser_with(type_parser.clone()).map(Self::Tuple),
+                Spanned::parser_with(type_parser.clone())
+                    .delimited_by(LBracket::parser(), RBracket::parser())
+                    .map(|element| Self::Array(Box::new(element))),
+                DynKeyword::parser()
+                    .ignore_then(Spanned::<Ident>::parser().padded())
+                    .map(Self::Dyn),
                 // Ident must be parsed last because e.g. fn could be considered a keyword
-                Ident::parser().map(Self::Ident),
+                Spanned::<Ident>::parser()
+                    .then(
+                        Spanned::parser_with(type_parser)
+                            .separated_by(Comma::parser())
+                            .allow_trailing()
+                            .at_least(1)
+                            .collect()
+                            .delimited_by(LAngle::parser(), RAngle::parser())
+                            .or_not(),
+                    )
+                    .map(|(name, args)| match args {
+                        Some(args) => Self::Applied(Box::new(AppliedType { name, args })),
+                        None => Self::Ident(name.node),
+                    }),
             ))
+            .labelled("type")
         })
     }
 }
@@ -126,18 +233,62 @@ fn test_type() {
     assert_eq!(
         TypeLiteral::parse("fn(int,) -> String").unwrap(),
         TypeLiteral::Fn(Box::new(FnSignatureType {
-            params: vec![TypeLiteral::Ident(Ident::from_str("int"))],
-            return_type: TypeLiteral::Ident(Ident::from_str("String"))
+            params: vec![TypeLiteral::Ident(Ident::from_str("int")).into()],
+            return_type: TypeLiteral::Ident(Ident::from_str("String")).into()
         }))
     );
     assert_eq!(
         TypeLiteral::parse("(String, (int, T))").unwrap(),
         TypeLiteral::Tuple(TupleType(vec![
-            TypeLiteral::Ident(Ident::from_str("String")),
+            TypeLiteral::Ident(Ident::from_str("String")).into(),
             TypeLiteral::Tuple(TupleType(vec![
-                TypeLiteral::Ident(Ident::from_str("int")),
-                TypeLiteral::Ident(Ident::from_str("T")),
+                TypeLiteral::Ident(Ident::from_str("int")).into(),
+                TypeLiteral::Ident(Ident::from_str("T")).into(),
             ]))
+            .into()
         ]))
-    )
+    );
+    assert_eq!(
+        TypeLiteral::parse("[[int]]").unwrap(),
+        TypeLiteral::Array(Box::new(
+            TypeLiteral::Array(Box::new(TypeLiteral::Ident(Ident::from_str("int")).into())).into()
+        ))
+    );
+
+    assert_eq!(
+        TypeLiteral::parse("Pair<int, Pair<[T], String>>").unwrap(),
+        TypeLiteral::Applied(Box::new(AppliedType {
+            name: Ident::from_str("Pair").into(),
+            args: vec![
+                TypeLiteral::Ident(Ident::from_str("int")).into(),
+                TypeLiteral::Applied(Box::new(AppliedType {
+                    name: Ident::from_str("Pair").into(),
+                    args: vec![
+                        TypeLiteral::Array(Box::new(
+                            TypeLiteral::Ident(Ident::from_str("T")).into()
+                        ))
+                        .into(),
+                        TypeLiteral::Ident(Ident::from_str("String")).into(),
+                    ],
+                }))
+                .into(),
+            ],
+        }))
+    );
+
+    assert_eq!(
+        TypeLiteral::parse("[dyn Show]").unwrap(),
+        TypeLiteral::Array(Box::new(
+            TypeLiteral::Dyn(Ident::from_str("Show").into()).into()
+        ))
+    );
+    // a type that starts with `dyn` is still a name
+    assert_eq!(
+        TypeLiteral::parse("dynamic").unwrap(),
+        TypeLiteral::Ident(Ident::from_str("dynamic"))
+    );
+
+    assert!(TypeLiteral::is_err("[int, int]"));
+    assert!(TypeLiteral::is_err("Pair<>"));
+    assert!(TypeLiteral::is_err("[]"));
 }
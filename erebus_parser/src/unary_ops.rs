@@ -0,0 +1,119 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use chumsky::{
+    prelude::{choice, recursive},
+    Parser,
+};
+
+use super::{
+    expr::Expression,
+    parsable::{Parsable, ParsableParser, ParserError},
+    span::Spanned,
+    syntax_elements::{NegExpr, NotExpr},
+};
+
+/// Builds the expression of an operator from its operand.
+type UnaryExprBuilder = fn(Spanned<Expression>) -> UnaryExpr;
+
+/// A prefix operator applied to a single expression.
+#[derive(Debug, PartialEq)]
+pub struct GenericUnaryOp<OP: Parsable> {
+    op: PhantomData<OP>,
+    pub expression: Spanned<Expression>,
+}
+
+impl<OP: Parsable + Debug> GenericUnaryOp<OP> {
+    pub fn new(expression: Spanned<Expression>) -> Self {
+        Self {
+            op: PhantomData {},
+            expression,
+        }
+    }
+
+    fn operator_parser<'src>() -> impl ParsableParser<'src, OP> {
+        OP::parser()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UnaryExpr {
+    Neg(NegExpr),
+    Not(NotExpr),
+}
+
+impl UnaryExpr {
+    /// Parses any amount of prefix operators followed by an operand.
+    ///
+    /// Prefix operators bind tighter than every binary operator.
+    pub fn parser_with<'src>(
+        operand_parser: impl ParsableParser<'src, Expression> + 'src,
+    ) -> impl ParsableParser<'src, Expression> {
+        macro_rules! operator {
+            ($expr_name:ident) => {
+                $expr_name::operator_parser()
+                    .to((|expression| $expr_name::new(expression).into_unary_expr())
+                        as UnaryExprBuilder)
+            };
+        }
+
+        recursive(|unary_parser| {
+            choice((operator!(NegExpr), operator!(NotExpr)))
+                .then(Spanned::parser_with(unary_parser))
+                .map(|(build, expression)| Expression::UnaryExpr(Box::new(build(expression))))
+                .or(operand_parser)
+                .labelled("expression")
+        })
+    }
+}
+
+impl Parsable for UnaryExpr {
+    fn parser<'src>() -> impl ParsableParser<'src, Self> {
+        Expression::parser().try_map(|expression, span| match expression {
+            Expression::UnaryExpr(unary_expr) => Ok(*unary_expr),
+            _ => Err(ParserError::custom(span, "expected a unary expression")),
+        })
+    }
+}
+
+#[test]
+fn test_unary_expr() {
+    use crate::syntax_elements::{AddExpr, MulExpr, SubExpr};
+
+    assert_eq!(
+        UnaryExpr::parse("-1").unwrap(),
+        UnaryExpr::Neg(NegExpr::new(Expression::num(1_f64).into()))
+    );
+    assert_eq!(
+        UnaryExpr::parse("!-done").unwrap(),
+        UnaryExpr::Not(NotExpr::new(
+            NegExpr::as_expr(Expression::variable("done")).into()
+        ))
+    );
+    assert_eq!(
+        Expression::parse("-2 * 3").unwrap(),
+        MulExpr::as_expr([
+            NegExpr::as_expr(Expression::num(2_f64)),
+            Expression::num(3_f64),
+        ])
+    );
+    assert_eq!(
+        Expression::parse("-(1 + 2) * (3)").unwrap(),
+        MulExpr::as_expr([
+            NegExpr::as_expr(AddExpr::as_expr([
+                Expression::num(1_f64),
+                Expression::num(2_f64),
+            ])),
+            Expression::num(3_f64),
+        ])
+    );
+    assert_eq!(
+        Expression::parse("1 - -2").unwrap(),
+        SubExpr::as_expr([
+            Expression::num(1_f64),
+            NegExpr::as_expr(Expression::num(2_f64)),
+        ])
+    );
+
+    assert!(UnaryExpr::is_err("1"));
+    assert!(UnaryExpr::is_err("!"));
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use clap::{Parser, ValueEnum};
+use clap::Parser;
 
 /// TODO programming language
 #[derive(Parser, Debug)]
@@ -10,14 +10,12 @@ pub struct Args {
     #[arg(short, long)]
     pub input_file: PathBuf,
 
-    /// Type of output to emit
+    /// Directory to write the emitted files to. Defaults to stdout
     #[arg(short, long)]
-    #[arg(value_enum, default_value_t=Emit::default())]
-    pub emit: Emit,
-}
+    pub out_dir: Option<PathBuf>,
 
-#[derive(Default, ValueEnum, Clone, Debug)]
-pub enum Emit {
-    #[default]
-    Ast,
+    /// Name of the backend used to emit the output,
+    /// or `external:<path>` to run an executable as the backend
+    #[arg(short, long, default_value = "ast")]
+    pub emit: String,
 }
@@ -0,0 +1,132 @@
+//! The command line interface of erebus.
+//!
+//! Binaries that bring their own [`Backend`](erebus_codegen::Backend)s can
+//! [register](Backends::register) them and call [`run`].
+
+use std::{fs, path::Path};
+
+use ariadne::{sources, Color, Label, Report, ReportKind};
+use clap::{error::ErrorKind, CommandFactory, Parser as ClapParser};
+
+use args::Args;
+use erebus_codegen::{Backend, Backends, ExternalBackend, Severity};
+use erebus_parser::{Ast, Parsable};
+
+mod args;
+
+fn failed_compiling(input_file: &Path, error_amount: usize) -> ! {
+    assert!(error_amount > 0);
+
+    panic!(
+        "failed compiling \"{}\" due to {}.",
+        input_file.display(),
+        match error_amount {
+            1 => "1 error".to_owned(),
+            x => format!("{x} errors."),
+        },
+    )
+}
+
+pub fn run(backends: &Backends) {
+    let args = Args::parse();
+    let external = ExternalBackend::from_name(&args.emit);
+    let backend = match &external {
+        Some(external) => Some(external as &dyn Backend),
+        None => backends.get(&args.emit),
+    };
+    let Some(backend) = backend else {
+        Args::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!(
+                    "unknown backend \"{}\", available: {}, external:<path>",
+                    args.emit,
+                    backends.names().collect::<Vec<_>>().join(", ")
+                ),
+            )
+            .exit()
+    };
+
+    let input_content = fs::read_to_string(&args.input_file)
+        .unwrap_or_else(|_| panic!("failed to read {:#?}", &args.input_file));
+    let filename = args.input_file.display().to_string();
+
+    // the parser recovers from errors, so all of them can be reported at once
+    let (ast, errors) = Ast::parse(&input_content).into_output_errors();
+    let ast = match ast {
+        Some(ast) if errors.is_empty() => ast,
+        _ => {
+            for err in &errors {
+                Report::build(ReportKind::Error, filename.clone(), err.span().start)
+                    .with_message(err.to_string())
+                    .with_label(
+                        Label::new((filename.clone(), err.span().into_range()))
+                            .with_message(err.reason().to_string())
+                            .with_color(Color::Red),
+                    )
+                    .finish()
+                    .eprint(sources([(filename.clone(), input_content.clone())]))
+                    .unwrap()
+            }
+
+            failed_compiling(&args.input_file, errors.len())
+        }
+    };
+
+    let module = args
+        .input_file
+        .file_stem()
+        .map_or("main".into(), |stem| stem.to_string_lossy());
+    let output = backend.compile(&module, &ast);
+
+    for diagnostic in &output.diagnostics {
+        let kind = match diagnostic.severity {
+            Severity::Error => ReportKind::Error,
+            Severity::Warning => ReportKind::Warning,
+        };
+
+        let offset = diagnostic.span.as_ref().map_or(0, |span| span.start);
+        let mut report =
+            Report::<(String, std::ops::Range<usize>)>::build(kind, filename.clone(), offset)
+                .with_message(&diagnostic.message);
+        if let Some(span) = &diagnostic.span {
+            report = report.with_label(Label::new((filename.clone(), span.clone())).with_color(
+                match kind {
+                    ReportKind::Warning => Color::Yellow,
+                    _ => Color::Red,
+                },
+            ));
+        }
+        for note in &diagnostic.notes {
+            report = report.with_label(
+                Label::new((filename.clone(), note.span.clone()))
+                    .with_message(&note.message)
+                    .with_color(Color::Blue),
+            );
+        }
+
+        report
+            .finish()
+            .eprint(sources([(filename.clone(), input_content.clone())]))
+            .unwrap()
+    }
+    if output.has_errors() {
+        let error_amount = output
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .count();
+        failed_compiling(&args.input_file, error_amount)
+    }
+
+    for file in output.files {
+        match &args.out_dir {
+            Some(out_dir) => {
+                let path = out_dir.join(&file.path);
+                fs::write(&path, file.content)
+                    .unwrap_or_else(|_| panic!("failed to write {:#?}", path))
+            }
+            None => print!("{}", file.content),
+        }
+    }
+}